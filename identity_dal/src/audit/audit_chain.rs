@@ -0,0 +1,28 @@
+use serde::{Serialize, Deserialize};
+
+/**
+ * Function that hashes the content of an audit event into the hash that links it in the chain.
+ */
+pub type ChainHashFunction = fn(&[u8]) -> String;
+
+/**
+ * Result of walking the hash chain of the audit log.
+ *
+ * Attributes:
+ * * checked: amount of events that were checked before the walk ended
+ * * last_id and last_hash: id and hash of the last event that was intact, None for an empty log
+ * * broken_at: id of the first event that has been changed or whose previous event has been changed or removed
+ * * reason: why the link is broken
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct ChainVerification {
+    pub checked : usize,
+    pub last_id : Option<u64>,
+    pub last_hash : Option<String>,
+    pub broken_at : Option<u64>,
+    pub reason : Option<String>
+}
+
+impl ChainVerification {
+    pub fn is_intact(&self) -> bool { self.broken_at.is_none() }
+}
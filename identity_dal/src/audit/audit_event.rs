@@ -0,0 +1,184 @@
+use serde::{Serialize, Deserialize};
+use std::convert::From;
+use chrono::Utc;
+
+/**
+ * Kind of security relevant event that is recorded in the audit log.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEventType {
+    Login,
+    PasswordChange,
+    PasswordReset,
+    EmailChange,
+    FlagChange,
+    StatusChange,
+    AdminAction,
+    Deletion,
+    IdentityLink
+}
+
+impl AuditEventType {
+    /**
+     * Returns the snake case name of the event type.
+     */
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            AuditEventType::Login => "login",
+            AuditEventType::PasswordChange => "password_change",
+            AuditEventType::PasswordReset => "password_reset",
+            AuditEventType::EmailChange => "email_change",
+            AuditEventType::FlagChange => "flag_change",
+            AuditEventType::StatusChange => "status_change",
+            AuditEventType::AdminAction => "admin_action",
+            AuditEventType::Deletion => "deletion",
+            AuditEventType::IdentityLink => "identity_link"
+        }
+    }
+
+    /**
+     * Returns the event type with the snake case name, None for an unknown name.
+     */
+    pub fn parse(name : &str) -> Option<AuditEventType> {
+        match name.trim().to_lowercase().as_str() {
+            "login" => Some(AuditEventType::Login),
+            "password_change" => Some(AuditEventType::PasswordChange),
+            "password_reset" => Some(AuditEventType::PasswordReset),
+            "email_change" => Some(AuditEventType::EmailChange),
+            "flag_change" => Some(AuditEventType::FlagChange),
+            "status_change" => Some(AuditEventType::StatusChange),
+            "admin_action" => Some(AuditEventType::AdminAction),
+            "deletion" => Some(AuditEventType::Deletion),
+            "identity_link" => Some(AuditEventType::IdentityLink),
+            _ => None
+        }
+    }
+}
+
+/**
+ * Outcome of the action of an audit event, a failure keeps why it failed.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    Failure(String)
+}
+
+/**
+ * AuditEvent is a security relevant action that has been recorded, events are never changed once they are recorded.
+ *
+ * Attributes:
+ * * id: monotonic id given when the event is recorded
+ * * event_type: kind of action
+ * * action: name of the action, e.g. the route or function that was used
+ * * actor: id of the user or client that did the action, None when unknown like with a failed login
+ * * target: id or email of the user the action was done on
+ * * timestamp: unix timestamp of when the action happened
+ * * ip: address of the client that did the request
+ * * outcome: whether the action succeeded
+ * * previous_hash: hash of the event that was recorded before this one, empty for the first event
+ * * hash: hash of this event together with the previous hash, so an edited or removed event breaks the chain
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AuditEvent {
+    id : u64,
+    event_type : AuditEventType,
+    action : String,
+    actor : Option<String>,
+    target : Option<String>,
+    timestamp : i64,
+    ip : Option<String>,
+    outcome : AuditOutcome,
+    previous_hash : String,
+    hash : String
+}
+
+impl From<&sled::IVec> for AuditEvent {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to an AuditEvent struct.")
+    }
+}
+
+impl From<&AuditEvent> for sled::IVec {
+    fn from(item : &AuditEvent) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert AuditEvent struct to bytes"))
+    }
+}
+
+impl AuditEvent {
+    /**
+     * Returns an event that happened now, it gets its id once it is recorded.
+     */
+    pub fn new(event_type : AuditEventType, action : &str, outcome : AuditOutcome) -> Self {
+        AuditEvent {
+            id : 0,
+            event_type,
+            action : action.to_owned(),
+            actor : None,
+            target : None,
+            timestamp : Utc::now().timestamp(),
+            ip : None,
+            outcome,
+            previous_hash : String::new(),
+            hash : String::new()
+        }
+    }
+
+    pub fn with_actor(mut self, actor : Option<&str>) -> Self {
+        self.actor = actor.map(str::to_owned);
+        self
+    }
+
+    pub fn with_target(mut self, target : Option<&str>) -> Self {
+        self.target = target.map(str::to_owned);
+        self
+    }
+
+    pub fn with_ip(mut self, ip : Option<&str>) -> Self {
+        self.ip = ip.map(str::to_owned);
+        self
+    }
+
+    /**
+     * Links the event to the chain: it gets its id, the hash of the previous event and its own hash made with the hash function.
+     */
+    pub(crate) fn link(&mut self, id : u64, previous_hash : &str, hash_function : fn(&[u8]) -> String) {
+        self.id = id;
+        self.previous_hash = previous_hash.to_owned();
+        self.hash = String::new();
+        self.hash = hash_function(&self.chain_content());
+    }
+
+    /**
+     * Returns the bytes the hash of the event is made of, that is the whole event without its own hash.
+     */
+    pub fn chain_content(&self) -> Vec<u8> {
+        let mut content = self.clone();
+        content.hash = String::new();
+        serde_cbor::to_vec(&content).expect("Could not convert AuditEvent struct to bytes")
+    }
+
+    pub fn get_id(&self) -> u64 { self.id }
+
+    pub fn get_event_type(&self) -> AuditEventType { self.event_type }
+
+    pub fn get_action(&self) -> &str { &self.action }
+
+    pub fn get_actor(&self) -> Option<&str> { self.actor.as_deref() }
+
+    pub fn get_target(&self) -> Option<&str> { self.target.as_deref() }
+
+    pub fn get_timestamp(&self) -> i64 { self.timestamp }
+
+    pub fn get_ip(&self) -> Option<&str> { self.ip.as_deref() }
+
+    pub fn get_outcome(&self) -> &AuditOutcome { &self.outcome }
+
+    pub fn is_success(&self) -> bool { self.outcome == AuditOutcome::Success }
+
+    pub fn get_previous_hash(&self) -> &str { &self.previous_hash }
+
+    pub fn get_hash(&self) -> &str { &self.hash }
+}
@@ -0,0 +1,2 @@
+pub mod audit_event;
+pub mod audit_chain;
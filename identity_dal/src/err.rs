@@ -32,6 +32,54 @@ pub enum IdentityError {
     SmtpDomainNotGood,
     CouldNotSendEmail,
     FlagIsEmpty,
+    ClientNotFound,
+    ClientCannotBeAdded,
+    ClientIsDisabled,
+    RedirectUriIsInvalid,
+    InvalidRequest(String),
+    InvalidClient,
+    InvalidGrant,
+    InvalidScope,
+    UnauthorizedClient,
+    UnsupportedGrantType,
+    UnsupportedResponseType,
+    AccessDenied,
+    InsufficientScope,
+    AuthorizationPending,
+    SlowDown,
+    ExpiredToken,
+    DeviceCodeNotFound,
+    ProviderNotFound,
+    FederationFailed(String),
+    UpstreamEmailNotVerified,
+    IdentityIsAlreadyLinked,
+    LinkedIdentityNotFound,
+    LastLoginMethod,
+    ServiceProviderNotFound,
+    ServiceProviderMetadataIsInvalid(String),
+    SamlRequestIsInvalid(String),
+    DirectoryIsUnavailable(String),
+    GroupNotFound,
+    GroupNameIsAlreadyTaken,
+    InvalidFilter(String),
+    MagicLinkIsInvalid,
+    MagicLinkIsDisabled,
+    InvitationNotFound,
+    InvitationIsNotPending,
+    EmailIsAlreadyInvited,
+    RegistrationIsDisabled,
+    RegistrationIsInviteOnly,
+    EmailDomainIsNotAllowed,
+    EmailIsAwaitingApproval,
+    RegistrationNotFound,
+    UserIsSuspended,
+    UserIsDeactivated,
+    UserIsPending,
+    UserIsNotRestorable,
+    UserIsDeleted,
+    AuditLogIsBroken(u64),
+    MailTemplateNotFound(String),
+    MailTemplateIsInvalid(String),
     CustomError(String)
 }
 
@@ -68,6 +116,54 @@ impl fmt::Display for IdentityError {
             IdentityError::SmtpDomainNotGood => write!(f,"Stmp domain is not good"),
             IdentityError::CouldNotSendEmail => write!(f,"Could not send the email throught the smtp transport"),
             IdentityError::FlagIsEmpty => write!(f,"Flag can't be empty"),
+            IdentityError::ClientNotFound => write!(f,"Client cannot be found"),
+            IdentityError::ClientCannotBeAdded => write!(f,"Client cannot be added"),
+            IdentityError::ClientIsDisabled => write!(f,"Client has been disabled"),
+            IdentityError::RedirectUriIsInvalid => write!(f,"Redirect uri is not registered for this client"),
+            IdentityError::InvalidRequest(e) => write!(f,"Invalid request: {}",e),
+            IdentityError::InvalidClient => write!(f,"Client authentication failed"),
+            IdentityError::InvalidGrant => write!(f,"Authorization grant is invalid, expired or already used"),
+            IdentityError::InvalidScope => write!(f,"Requested scope is invalid"),
+            IdentityError::UnauthorizedClient => write!(f,"Client is not authorized to use this grant"),
+            IdentityError::UnsupportedGrantType => write!(f,"Grant type is not supported"),
+            IdentityError::UnsupportedResponseType => write!(f,"Response type is not supported"),
+            IdentityError::AccessDenied => write!(f,"Access has been denied"),
+            IdentityError::InsufficientScope => write!(f,"Token doesn't have the scope needed for this request"),
+            IdentityError::AuthorizationPending => write!(f,"The user hasn't approved the device yet"),
+            IdentityError::SlowDown => write!(f,"The device is polling too fast"),
+            IdentityError::ExpiredToken => write!(f,"The device code has expired"),
+            IdentityError::DeviceCodeNotFound => write!(f,"User code is not found or has expired"),
+            IdentityError::ProviderNotFound => write!(f,"Identity provider is not found"),
+            IdentityError::FederationFailed(e) => write!(f,"Login with the identity provider failed: {}",e),
+            IdentityError::UpstreamEmailNotVerified => write!(f,"The identity provider didn't verify the email of the user"),
+            IdentityError::IdentityIsAlreadyLinked => write!(f,"The identity is already linked to an user"),
+            IdentityError::LinkedIdentityNotFound => write!(f,"Linked identity is not found"),
+            IdentityError::LastLoginMethod => write!(f,"The last way to log in can't be removed"),
+            IdentityError::ServiceProviderNotFound => write!(f,"SAML service provider is not found"),
+            IdentityError::ServiceProviderMetadataIsInvalid(e) => write!(f,"The metadata of the SAML service provider is not valid: {}",e),
+            IdentityError::SamlRequestIsInvalid(e) => write!(f,"The SAML request is not valid: {}",e),
+            IdentityError::DirectoryIsUnavailable(e) => write!(f,"The LDAP directory can't be used: {}",e),
+            IdentityError::GroupNotFound => write!(f,"Group is not found"),
+            IdentityError::GroupNameIsAlreadyTaken => write!(f,"The name of the group is already taken"),
+            IdentityError::InvalidFilter(e) => write!(f,"The filter is not valid: {}",e),
+            IdentityError::MagicLinkIsInvalid => write!(f,"The magic link is not valid or has expired"),
+            IdentityError::MagicLinkIsDisabled => write!(f,"Logging in with a magic link is not enabled"),
+            IdentityError::InvitationNotFound => write!(f,"Invitation is not found"),
+            IdentityError::InvitationIsNotPending => write!(f,"The invitation has already been accepted, revoked or has expired"),
+            IdentityError::EmailIsAlreadyInvited => write!(f,"There is already a pending invitation for the email"),
+            IdentityError::RegistrationIsDisabled => write!(f,"Registration is disabled"),
+            IdentityError::RegistrationIsInviteOnly => write!(f,"Registration is only possible with an invitation"),
+            IdentityError::EmailDomainIsNotAllowed => write!(f,"Registration is not allowed with the domain of the email"),
+            IdentityError::EmailIsAwaitingApproval => write!(f,"A registration with the email is already waiting for approval"),
+            IdentityError::RegistrationNotFound => write!(f,"Registration is not found"),
+            IdentityError::UserIsSuspended => write!(f,"The account has been suspended"),
+            IdentityError::UserIsDeactivated => write!(f,"The account has been deactivated"),
+            IdentityError::UserIsPending => write!(f,"The account can't be used yet"),
+            IdentityError::UserIsNotRestorable => write!(f,"The account can't be reactivated anymore"),
+            IdentityError::UserIsDeleted => write!(f,"The account has been deleted"),
+            IdentityError::AuditLogIsBroken(id) => write!(f,"The audit log has been changed at event {}",id),
+            IdentityError::MailTemplateNotFound(e) => write!(f,"Mail template is not found: {}",e),
+            IdentityError::MailTemplateIsInvalid(e) => write!(f,"Mail template is not valid: {}",e),
             IdentityError::CustomError(e) => write!(f,"{}",e)
         }
     }
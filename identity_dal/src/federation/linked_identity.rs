@@ -0,0 +1,68 @@
+use serde::{Serialize, Deserialize};
+use std::convert::From;
+use chrono::Utc;
+
+/**
+ * LinkedIdentity is an account at an external identity provider that can be used to log in as an user.
+ *
+ * Attributes:
+ * * provider: name of the configured identity provider
+ * * subject: id of the user at the identity provider
+ * * user_id: id of the local user the identity is linked to
+ * * email: email the identity provider gave when the identity was linked
+ * * linked_at: unix timestamp of when the identity was linked
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LinkedIdentity {
+    provider : String,
+    subject : String,
+    user_id : String,
+    email : String,
+    linked_at : i64
+}
+
+impl From<&sled::IVec> for LinkedIdentity {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to a LinkedIdentity struct.")
+    }
+}
+
+impl From<&LinkedIdentity> for sled::IVec {
+    fn from(item : &LinkedIdentity) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert LinkedIdentity struct to bytes"))
+    }
+}
+
+impl LinkedIdentity {
+    pub fn new(provider : &str, subject : &str, user_id : &str, email : &str) -> Self {
+        LinkedIdentity {
+            provider : provider.to_owned(),
+            subject : subject.to_owned(),
+            user_id : user_id.to_owned(),
+            email : email.to_owned(),
+            linked_at : Utc::now().timestamp()
+        }
+    }
+
+    /**
+     * Key of the identity in the sled tree, the provider and subject together are unique.
+     */
+    pub fn get_key(&self) -> String { identity_key(&self.provider, &self.subject) }
+
+    pub fn get_provider(&self) -> &str { &self.provider }
+
+    pub fn get_subject(&self) -> &str { &self.subject }
+
+    pub fn get_user_id(&self) -> &str { &self.user_id }
+
+    pub fn get_email(&self) -> &str { &self.email }
+
+    pub fn get_linked_at(&self) -> i64 { self.linked_at }
+}
+
+/**
+ * Returns the key under which the identity of a subject at a provider is kept.
+ */
+pub fn identity_key(provider : &str, subject : &str) -> String {
+    format!("{}\u{0}{}", provider, subject)
+}
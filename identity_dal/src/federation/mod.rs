@@ -0,0 +1,2 @@
+pub mod linked_identity;
+pub mod pending_login;
\ No newline at end of file
@@ -0,0 +1,74 @@
+use serde::{Serialize, Deserialize};
+use std::convert::From;
+use chrono::{Duration, Utc};
+use crate::util::get_hash;
+
+/**
+ * PendingLogin is kept while the user logs in at an external identity provider, it is looked up again with the state the provider sends back.
+ *
+ * Attributes:
+ * * state: random value sent to the provider and returned in the callback, also the key in the sled tree
+ * * provider: name of the identity provider the user was sent to
+ * * nonce: value that has to be repeated in the id token of the provider
+ * * code_verifier: PKCE verifier of the challenge sent to the provider
+ * * expires_at: unix timestamp after which the login can't be finished
+ * * link_user_id: user that is logged in and wants to link the identity, empty for a normal login
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PendingLogin {
+    state : String,
+    provider : String,
+    nonce : String,
+    code_verifier : String,
+    expires_at : i64,
+    #[serde(default)]
+    link_user_id : Option<String>
+}
+
+impl From<&sled::IVec> for PendingLogin {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to a PendingLogin struct.")
+    }
+}
+
+impl From<&PendingLogin> for sled::IVec {
+    fn from(item : &PendingLogin) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert PendingLogin struct to bytes"))
+    }
+}
+
+impl PendingLogin {
+    /**
+     * Returns a new pending login with a random state, nonce and code verifier that expires after the given amount of seconds.
+     */
+    pub fn new(provider : &str, lifetime : i64) -> Self {
+        PendingLogin {
+            state : get_hash(32),
+            provider : provider.to_owned(),
+            nonce : get_hash(32),
+            code_verifier : get_hash(64),
+            expires_at : (Utc::now() + Duration::seconds(lifetime)).timestamp(),
+            link_user_id : None
+        }
+    }
+
+    /**
+     * Marks the login as one of a logged in user that links the identity to his account.
+     */
+    pub fn for_user(mut self, user_id : &str) -> Self {
+        self.link_user_id = Some(user_id.to_owned());
+        self
+    }
+
+    pub fn is_expired(&self) -> bool { Utc::now().timestamp() > self.expires_at }
+
+    pub fn get_state(&self) -> &str { &self.state }
+
+    pub fn get_provider(&self) -> &str { &self.provider }
+
+    pub fn get_nonce(&self) -> &str { &self.nonce }
+
+    pub fn get_code_verifier(&self) -> &str { &self.code_verifier }
+
+    pub fn get_link_user_id(&self) -> Option<&str> { self.link_user_id.as_deref() }
+}
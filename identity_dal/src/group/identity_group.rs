@@ -0,0 +1,106 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeSet;
+use std::convert::From;
+use chrono::Utc;
+
+/**
+ * IdentityGroup is a named group of users. The name of the group is given to its members as a flag.
+ *
+ * Attributes:
+ * * id: unique id of the group
+ * * display_name: unique name of the group, it is the flag of the members
+ * * external_id: id the group has in the system that provisions it
+ * * members: ids of the users in the group
+ * * created_at: unix timestamp of when the group was made
+ * * updated_at: unix timestamp of the last change of the group
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IdentityGroup {
+    id : String,
+    display_name : String,
+    external_id : Option<String>,
+    members : BTreeSet<String>,
+    created_at : i64,
+    updated_at : i64
+}
+
+impl From<&sled::IVec> for IdentityGroup {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to an IdentityGroup struct.")
+    }
+}
+
+impl From<&IdentityGroup> for sled::IVec {
+    fn from(item : &IdentityGroup) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert IdentityGroup struct to bytes"))
+    }
+}
+
+impl IdentityGroup {
+    pub fn new(id : &str, display_name : &str) -> Self {
+        let now = Utc::now().timestamp();
+        IdentityGroup {
+            id : id.to_owned(),
+            display_name : display_name.to_owned(),
+            external_id : None,
+            members : BTreeSet::new(),
+            created_at : now,
+            updated_at : now
+        }
+    }
+
+    pub fn get_id(&self) -> &str { &self.id }
+
+    pub fn get_display_name(&self) -> &str { &self.display_name }
+
+    pub fn get_external_id(&self) -> Option<&str> { self.external_id.as_deref() }
+
+    pub fn get_members(&self) -> &BTreeSet<String> { &self.members }
+
+    pub fn get_created_at(&self) -> i64 { self.created_at }
+
+    pub fn get_updated_at(&self) -> i64 { self.updated_at }
+
+    pub fn has_member(&self, user_id : &str) -> bool { self.members.contains(user_id) }
+
+    pub fn set_display_name(&mut self, display_name : &str) {
+        self.display_name = display_name.to_owned();
+        self.touch();
+    }
+
+    pub fn set_external_id(&mut self, external_id : Option<&str>) {
+        self.external_id = external_id.map(str::to_owned);
+        self.touch();
+    }
+
+    pub fn set_members(&mut self, members : BTreeSet<String>) {
+        self.members = members;
+        self.touch();
+    }
+
+    /**
+     * Adds an user to the group, returns true if he wasn't a member yet.
+     */
+    pub fn add_member(&mut self, user_id : &str) -> bool {
+        let added = self.members.insert(user_id.to_owned());
+        if added {
+            self.touch();
+        }
+        added
+    }
+
+    /**
+     * Removes an user from the group, returns true if he was a member.
+     */
+    pub fn remove_member(&mut self, user_id : &str) -> bool {
+        let removed = self.members.remove(user_id);
+        if removed {
+            self.touch();
+        }
+        removed
+    }
+
+    fn touch(&mut self) {
+        self.updated_at = Utc::now().timestamp();
+    }
+}
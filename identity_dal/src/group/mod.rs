@@ -0,0 +1 @@
+pub mod identity_group;
\ No newline at end of file
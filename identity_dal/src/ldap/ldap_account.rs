@@ -0,0 +1,52 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeSet;
+use std::convert::From;
+use chrono::Utc;
+
+/**
+ * LdapAccount is the entry in an LDAP directory an user logs in with, it remembers what has been synced into the user.
+ *
+ * Attributes:
+ * * user_id: id of the local user
+ * * dn: distinguished name of the entry in the directory
+ * * groups: groups of the entry that have been added as flags to the user at the last sync
+ * * synced_at: unix timestamp of the last sync
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LdapAccount {
+    user_id : String,
+    dn : String,
+    groups : BTreeSet<String>,
+    synced_at : i64
+}
+
+impl From<&sled::IVec> for LdapAccount {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to a LdapAccount struct.")
+    }
+}
+
+impl From<&LdapAccount> for sled::IVec {
+    fn from(item : &LdapAccount) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert LdapAccount struct to bytes"))
+    }
+}
+
+impl LdapAccount {
+    pub fn new(user_id : &str, dn : &str, groups : BTreeSet<String>) -> Self {
+        LdapAccount {
+            user_id : user_id.to_owned(),
+            dn : dn.to_owned(),
+            groups,
+            synced_at : Utc::now().timestamp()
+        }
+    }
+
+    pub fn get_user_id(&self) -> &str { &self.user_id }
+
+    pub fn get_dn(&self) -> &str { &self.dn }
+
+    pub fn get_groups(&self) -> &BTreeSet<String> { &self.groups }
+
+    pub fn get_synced_at(&self) -> i64 { self.synced_at }
+}
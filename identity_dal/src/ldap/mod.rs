@@ -0,0 +1 @@
+pub mod ldap_account;
\ No newline at end of file
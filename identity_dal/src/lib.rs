@@ -1,6 +1,17 @@
 pub mod repo;
 pub mod traits;
 pub mod user;
+pub mod oauth;
+pub mod federation;
+pub mod saml;
+pub mod ldap;
+pub mod group;
+pub mod scim;
+pub mod passwordless;
+pub mod onboarding;
+pub mod audit;
+pub mod webhook;
+pub mod mail;
 pub mod util;
 pub mod err;
 
@@ -0,0 +1,193 @@
+use serde::{Serialize, Deserialize};
+use std::convert::From;
+use chrono::Utc;
+
+/**
+ * State of a mail in the outbox. A pending mail is tried again until it is sent or it has used all its attempts, then it has failed and stays in the outbox as a dead letter until an admin retries it.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum MailStatus {
+    Pending,
+    Sent,
+    Failed
+}
+
+impl MailStatus {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            MailStatus::Pending => "pending",
+            MailStatus::Sent => "sent",
+            MailStatus::Failed => "failed"
+        }
+    }
+
+    pub fn parse(name : &str) -> Option<MailStatus> {
+        match name {
+            "pending" => Some(MailStatus::Pending),
+            "sent" => Some(MailStatus::Sent),
+            "failed" => Some(MailStatus::Failed),
+            _ => None
+        }
+    }
+}
+
+/**
+ * One attempt to send a mail, the error is None when the mail was sent.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MailAttempt {
+    attempted_at : i64,
+    error : Option<String>
+}
+
+impl MailAttempt {
+    pub fn success() -> Self {
+        MailAttempt { attempted_at : Utc::now().timestamp(), error : None }
+    }
+
+    pub fn failure(error : &str) -> Self {
+        MailAttempt { attempted_at : Utc::now().timestamp(), error : Some(error.to_owned()) }
+    }
+
+    pub fn get_attempted_at(&self) -> i64 { self.attempted_at }
+
+    pub fn get_error(&self) -> Option<&str> { self.error.as_deref() }
+
+    pub fn is_success(&self) -> bool { self.error.is_none() }
+}
+
+/**
+ * Image that is sent within the html part of a mail.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MailImage {
+    pub content_id : String,
+    pub content_type : String,
+    pub data : Vec<u8>
+}
+
+/**
+ * What is sent: the recipient, the subject, the text and html parts, the inline images and the extra headers.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct MailContent {
+    pub recipient : String,
+    pub alias : String,
+    pub subject : String,
+    pub text : String,
+    pub html : Option<String>,
+    pub images : Vec<MailImage>,
+    pub reply_to : Option<String>,
+    pub headers : Vec<(String, String)>
+}
+
+/**
+ * Outbox mail is a mail waiting in the outbox to be sent, it keeps a log of every attempt.
+ *
+ * Attributes:
+ * * id: monotonic id, given by the store when the mail is queued
+ * * content: the mail that is sent, the same mail is sent on every attempt
+ * * status: pending until it is sent or has failed
+ * * attempts: log of the attempts, the oldest first
+ * * next_attempt_at: unix timestamp from which a pending mail is tried again
+ * * created_at: unix timestamp of when the mail was queued
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OutboxMail {
+    id : u64,
+    content : MailContent,
+    status : MailStatus,
+    attempts : Vec<MailAttempt>,
+    next_attempt_at : i64,
+    created_at : i64
+}
+
+impl From<&sled::IVec> for OutboxMail {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to a OutboxMail struct.")
+    }
+}
+
+impl From<&OutboxMail> for sled::IVec {
+    fn from(item : &OutboxMail) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert OutboxMail struct to bytes"))
+    }
+}
+
+impl OutboxMail {
+    /**
+     * Returns a new pending mail that is due right away.
+     */
+    pub fn new(content : MailContent) -> Self {
+        let now = Utc::now().timestamp();
+        OutboxMail {
+            id : 0,
+            content,
+            status : MailStatus::Pending,
+            attempts : Vec::new(),
+            next_attempt_at : now,
+            created_at : now
+        }
+    }
+
+    pub fn get_id(&self) -> u64 { self.id }
+
+    pub(crate) fn set_id(&mut self, id : u64) { self.id = id; }
+
+    pub fn get_content(&self) -> &MailContent { &self.content }
+
+    pub fn get_status(&self) -> &MailStatus { &self.status }
+
+    pub fn get_attempts(&self) -> &[MailAttempt] { &self.attempts }
+
+    pub fn get_next_attempt_at(&self) -> i64 { self.next_attempt_at }
+
+    pub fn get_created_at(&self) -> i64 { self.created_at }
+
+    /**
+     * Returns true if the mail is pending and its next attempt is due.
+     */
+    pub fn is_due(&self, now : i64) -> bool {
+        self.status == MailStatus::Pending && self.next_attempt_at <= now
+    }
+
+    /**
+     * Logs an attempt. A failed attempt is tried again after a delay that doubles with every attempt, starting from the backoff, until the maximum of attempts is reached.
+     */
+    pub fn record_attempt(&mut self, attempt : MailAttempt, max_attempts : usize, backoff : i64) {
+        let now = attempt.get_attempted_at();
+        self.status = if attempt.is_success() {
+            MailStatus::Sent
+        } else if self.attempts.len() + 1 >= max_attempts {
+            MailStatus::Failed
+        } else {
+            MailStatus::Pending
+        };
+        let exponent = self.attempts.len().min(30) as u32;
+        self.next_attempt_at = now.saturating_add(backoff.saturating_mul(2i64.saturating_pow(exponent)));
+        self.attempts.push(attempt);
+    }
+
+    /**
+     * Makes the mail pending and due right away. The log of the earlier attempts is kept.
+     */
+    pub fn retry(&mut self) {
+        self.status = MailStatus::Pending;
+        self.next_attempt_at = Utc::now().timestamp();
+    }
+}
+
+#[test]
+fn test_mail_backoff() {
+    let mut mail = OutboxMail::new(MailContent { recipient : "jane@corp.be".to_owned(), subject : "Welcome".to_owned(), ..MailContent::default() });
+    mail.record_attempt(MailAttempt::failure("connection refused"), 2, 60);
+    assert_eq!((mail.get_status(), mail.get_next_attempt_at() - mail.get_attempts()[0].get_attempted_at()), (&MailStatus::Pending, 60));
+    mail.record_attempt(MailAttempt::failure("connection refused"), 2, 60);
+    assert_eq!(mail.get_status(), &MailStatus::Failed);
+    assert!(!mail.is_due(i64::MAX));
+
+    mail.retry();
+    assert!(mail.is_due(mail.get_next_attempt_at()));
+    mail.record_attempt(MailAttempt::success(), 2, 60);
+    assert_eq!((mail.get_status(), mail.get_attempts().len()), (&MailStatus::Sent, 3));
+}
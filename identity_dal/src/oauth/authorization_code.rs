@@ -0,0 +1,90 @@
+use serde::{Serialize, Deserialize};
+use std::convert::From;
+use chrono::{Duration, Utc};
+use crate::util::get_hash;
+
+/**
+ * AuthorizationCode is the short lived, single-use code that a client exchanges for a token.
+ *
+ * Attributes:
+ * * code: the code itself, also the key in the sled tree
+ * * client_id: client to which the code was issued
+ * * user_id: user that authorized the client
+ * * redirect_uri: redirect uri used in the authorization request, it has to be repeated when exchanging
+ * * scope: space delimited scopes that were granted
+ * * code_challenge: PKCE S256 challenge the code verifier has to match
+ * * auth_time: unix timestamp of when the user authenticated
+ * * expires_at: unix timestamp after which the code can't be used
+ * * nonce: value of the OpenID Connect authentication request that has to be repeated in the id token
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AuthorizationCode {
+    code : String,
+    client_id : String,
+    user_id : String,
+    redirect_uri : String,
+    scope : String,
+    code_challenge : String,
+    auth_time : i64,
+    expires_at : i64,
+    #[serde(default)]
+    nonce : Option<String>
+}
+
+impl From<&sled::IVec> for AuthorizationCode {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to an AuthorizationCode struct.")
+    }
+}
+
+impl From<&AuthorizationCode> for sled::IVec {
+    fn from(item : &AuthorizationCode) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert AuthorizationCode struct to bytes"))
+    }
+}
+
+impl AuthorizationCode {
+    /**
+     * Returns a new authorization code that expires after the given amount of seconds.
+     */
+    pub fn new(client_id : &str, user_id : &str, redirect_uri : &str, scope : &str, code_challenge : &str, lifetime : i64) -> Self {
+        let now = Utc::now();
+        AuthorizationCode {
+            code : get_hash(40),
+            client_id : client_id.to_owned(),
+            user_id : user_id.to_owned(),
+            redirect_uri : redirect_uri.to_owned(),
+            scope : scope.to_owned(),
+            code_challenge : code_challenge.to_owned(),
+            auth_time : now.timestamp(),
+            expires_at : (now + Duration::seconds(lifetime)).timestamp(),
+            nonce : None
+        }
+    }
+
+    /**
+     * Sets the nonce of the OpenID Connect authentication request on the code.
+     */
+    pub fn with_nonce(mut self, nonce : Option<&str>) -> Self {
+        self.nonce = nonce.map(String::from);
+        self
+    }
+
+    pub fn is_expired(&self) -> bool { Utc::now().timestamp() > self.expires_at }
+
+    pub fn get_code(&self) -> &str { &self.code }
+
+    pub fn get_client_id(&self) -> &str { &self.client_id }
+
+    pub fn get_user_id(&self) -> &str { &self.user_id }
+
+    pub fn get_redirect_uri(&self) -> &str { &self.redirect_uri }
+
+    pub fn get_scope(&self) -> &str { &self.scope }
+
+    pub fn get_code_challenge(&self) -> &str { &self.code_challenge }
+
+    pub fn get_auth_time(&self) -> i64 { self.auth_time }
+
+    pub fn get_nonce(&self) -> Option<&str> { self.nonce.as_deref() }
+}
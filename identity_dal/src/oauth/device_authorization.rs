@@ -0,0 +1,128 @@
+use serde::{Serialize, Deserialize};
+use std::convert::From;
+use chrono::{Duration, Utc};
+use crate::util::{ get_hash, get_user_code };
+
+/**
+ * Decision of the user about a device authorization.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DeviceStatus {
+    Pending,
+    Approved { user_id : String, auth_time : i64 },
+    Denied
+}
+
+/**
+ * DeviceAuthorization is a pending device authorization request. The device polls with the device code while the user approves the request on another device with the user code.
+ *
+ * Attributes:
+ * * device_code: code the device polls the token endpoint with, also the key in the sled tree
+ * * user_code: short code the user types over, kept without separator
+ * * client_id: client that asked the authorization
+ * * scope: space delimited scopes that were asked
+ * * expires_at: unix timestamp after which the codes can't be used
+ * * interval: minimum amount of seconds between two polls
+ * * last_polled_at: unix timestamp of the last poll of the device
+ * * status: pending until the user approves or denies the request
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceAuthorization {
+    device_code : String,
+    user_code : String,
+    client_id : String,
+    scope : String,
+    expires_at : i64,
+    interval : i64,
+    last_polled_at : i64,
+    status : DeviceStatus
+}
+
+impl From<&sled::IVec> for DeviceAuthorization {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to a DeviceAuthorization struct.")
+    }
+}
+
+impl From<&DeviceAuthorization> for sled::IVec {
+    fn from(item : &DeviceAuthorization) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert DeviceAuthorization struct to bytes"))
+    }
+}
+
+/**
+ * Brings a user code as it was typed over back to the form in which it is kept: uppercase and without separators.
+ */
+pub fn normalize_user_code(user_code : &str) -> String {
+    user_code.chars().filter(|c| c.is_ascii_alphanumeric()).map(|c| c.to_ascii_uppercase()).collect()
+}
+
+impl DeviceAuthorization {
+    /**
+     * Returns a new pending device authorization that expires after the given amount of seconds.
+     */
+    pub fn new(client_id : &str, scope : &str, lifetime : i64, interval : i64) -> Self {
+        DeviceAuthorization {
+            device_code : get_hash(40),
+            user_code : get_user_code(8),
+            client_id : client_id.to_owned(),
+            scope : scope.to_owned(),
+            expires_at : (Utc::now() + Duration::seconds(lifetime)).timestamp(),
+            interval,
+            last_polled_at : 0,
+            status : DeviceStatus::Pending
+        }
+    }
+
+    /**
+     * Registers a poll of the device. Returns false when the device polled before the interval had passed, in that case the interval is raised by 5 seconds as RFC 8628 asks.
+     */
+    pub fn poll(&mut self) -> bool {
+        let now = Utc::now().timestamp();
+        let in_time = now - self.last_polled_at >= self.interval;
+        if !in_time {
+            self.interval += 5;
+        }
+        self.last_polled_at = now;
+        in_time
+    }
+
+    pub fn approve(&mut self, user_id : &str) {
+        self.status = DeviceStatus::Approved { user_id : user_id.to_owned(), auth_time : Utc::now().timestamp() };
+    }
+
+    pub fn deny(&mut self) { self.status = DeviceStatus::Denied; }
+
+    pub fn is_expired(&self) -> bool { Utc::now().timestamp() > self.expires_at }
+
+    pub fn get_device_code(&self) -> &str { &self.device_code }
+
+    pub fn get_user_code(&self) -> &str { &self.user_code }
+
+    /**
+     * Returns the user code as it is shown to the user, with a dash in the middle.
+     */
+    pub fn get_display_user_code(&self) -> String {
+        let middle = self.user_code.len() / 2;
+        format!("{}-{}", &self.user_code[..middle], &self.user_code[middle..])
+    }
+
+    pub fn get_client_id(&self) -> &str { &self.client_id }
+
+    pub fn get_scope(&self) -> &str { &self.scope }
+
+    pub fn get_expires_at(&self) -> i64 { self.expires_at }
+
+    pub fn get_interval(&self) -> i64 { self.interval }
+
+    pub fn get_status(&self) -> &DeviceStatus { &self.status }
+}
+
+#[test]
+fn test_device_poll_interval() {
+    let mut device = DeviceAuthorization::new("cli", "openid", 600, 5);
+    assert!(device.poll());
+    assert!(!device.poll());
+    assert_eq!(device.get_interval(), 10);
+    assert_eq!(normalize_user_code(&device.get_display_user_code().to_lowercase()), device.get_user_code());
+}
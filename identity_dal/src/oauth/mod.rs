@@ -0,0 +1,4 @@
+pub mod oauth_client;
+pub mod authorization_code;
+pub mod refresh_token;
+pub mod device_authorization;
\ No newline at end of file
@@ -0,0 +1,221 @@
+use serde::{Serialize, Deserialize};
+use std::convert::From;
+use std::collections::BTreeSet;
+use argon2::Config;
+use chrono::Utc;
+use crate::err::IdentityError;
+use crate::util::get_hash;
+
+/**
+ * Type of an OAuth client. A confidential client can keep a secret (backend apps), a public client can't (SPA's, mobile apps).
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClientType {
+    Confidential,
+    Public
+}
+
+/**
+ * OAuthClient represents an application that is registered to request tokens of users.
+ *
+ * Attributes:
+ * * client_id: unique identification of the client
+ * * client_name: name shown to the user on the consent page
+ * * redirect_uris: the only uri's an authorization response can be sent to
+ * * client_type: confidential or public
+ * * hashed_secret: hash of the client secret, empty for public clients
+ * * secret_stamp: salt used for the hashing of the secret
+ * * created_at: unix timestamp of the registration
+ * * grant_types: grants the client may use at the token endpoint
+ * * allowed_scopes: scopes the client may ask for, with every grant
+ * * disabled: a disabled client can't get new tokens and its tokens aren't accepted anymore
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OAuthClient {
+    client_id : String,
+    client_name : String,
+    redirect_uris : Vec<String>,
+    client_type : ClientType,
+    hashed_secret : String,
+    secret_stamp : String,
+    created_at : i64,
+    #[serde(default = "default_grant_types")]
+    grant_types : BTreeSet<String>,
+    #[serde(default)]
+    allowed_scopes : BTreeSet<String>,
+    #[serde(default)]
+    disabled : bool
+}
+
+/**
+ * Grant that every client registered before grant types were introduced could use.
+ */
+fn default_grant_types() -> BTreeSet<String> {
+    vec![GRANT_AUTHORIZATION_CODE.to_owned()].into_iter().collect()
+}
+
+pub static GRANT_AUTHORIZATION_CODE : &str = "authorization_code";
+
+pub static GRANT_CLIENT_CREDENTIALS : &str = "client_credentials";
+
+pub static GRANT_DEVICE_CODE : &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+impl From<&sled::IVec> for OAuthClient {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to an OAuthClient struct.")
+    }
+}
+
+impl From<&OAuthClient> for sled::IVec {
+    fn from(item : &OAuthClient) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert OAuthClient struct to bytes"))
+    }
+}
+
+impl OAuthClient {
+    /**
+     * Returns a new client together with its secret in plain text. The plain secret is only available here, only its hash is kept. For public clients the secret is None.
+     *
+     * Returns an error when:
+     * * client name is empty
+     * * one of the redirect uri's isn't an absolute uri
+     */
+    pub fn new_client(client_name : &str, redirect_uris : Vec<String>, client_type : ClientType)
+    -> Result<(OAuthClient, Option<String>), IdentityError> {
+        if client_name.is_empty() {
+            return Err(IdentityError::InvalidRequest("Client name cannot be empty".to_owned()))
+        }
+        if !redirect_uris.iter().all(|uri| is_absolute_uri(uri)) {
+            return Err(IdentityError::RedirectUriIsInvalid)
+        }
+        let mut client = OAuthClient {
+            client_id : get_hash(24),
+            client_name : client_name.to_owned(),
+            redirect_uris,
+            client_type,
+            hashed_secret : String::new(),
+            secret_stamp : String::new(),
+            created_at : Utc::now().timestamp(),
+            grant_types : default_grant_types(),
+            allowed_scopes : BTreeSet::new(),
+            disabled : false
+        };
+        let secret = match client_type {
+            ClientType::Confidential => Some(client.new_secret()?),
+            ClientType::Public => None
+        };
+        info!("A client has been made. id: {}", &client.client_id);
+        Ok((client, secret))
+    }
+
+    /**
+     * Sets the grants the client may use. Public clients can't keep a secret, so they are refused the client credentials grant.
+     */
+    pub fn set_grant_types(&mut self, grant_types : BTreeSet<String>) -> Result<(), IdentityError> {
+        if let Some(grant) = grant_types.iter().find(|grant| !is_known_grant(grant)) {
+            return Err(IdentityError::InvalidRequest(format!("Grant type {} is not supported", grant)))
+        }
+        if !self.is_confidential() && grant_types.contains(GRANT_CLIENT_CREDENTIALS) {
+            return Err(IdentityError::UnauthorizedClient)
+        }
+        self.grant_types = grant_types;
+        Ok(())
+    }
+
+    /**
+     * Generates a new secret for the client, only the hash is kept and the plain secret is returned.
+     */
+    pub fn new_secret(&mut self) -> Result<String, IdentityError> {
+        let secret = get_hash(48);
+        let stamp = get_hash(8);
+        self.hashed_secret = match argon2::hash_encoded(secret.as_bytes(), stamp.as_bytes(), &Config::default()) {
+            Ok(hash) => hash,
+            Err(_) => return Err(IdentityError::PasswordCannotBeMade)
+        };
+        self.secret_stamp = stamp;
+        Ok(secret)
+    }
+
+    /**
+     * Checks if the given secret is that of the client, public clients have no secret so this is always false for them.
+     */
+    pub fn check_secret(&self, secret : &str) -> bool {
+        if secret.is_empty() || self.hashed_secret.is_empty() {
+            return false
+        }
+        argon2::verify_encoded(&self.hashed_secret, secret.as_bytes()).unwrap_or(false)
+    }
+
+    /**
+     * Returns true if the given uri is exactly one of the registered redirect uri's.
+     */
+    pub fn is_redirect_uri_registered(&self, redirect_uri : &str) -> bool {
+        self.redirect_uris.iter().any(|uri| uri == redirect_uri)
+    }
+
+    pub fn get_client_id(&self) -> &str { &self.client_id }
+
+    pub fn get_client_name(&self) -> &str { &self.client_name }
+
+    pub fn get_redirect_uris(&self) -> &[String] { &self.redirect_uris }
+
+    pub fn get_client_type(&self) -> ClientType { self.client_type }
+
+    pub fn is_confidential(&self) -> bool { self.client_type == ClientType::Confidential }
+
+    pub fn get_created_at(&self) -> i64 { self.created_at }
+
+    pub fn get_grant_types(&self) -> &BTreeSet<String> { &self.grant_types }
+
+    pub fn is_grant_type_allowed(&self, grant_type : &str) -> bool { self.grant_types.contains(grant_type) }
+
+    pub fn get_allowed_scopes(&self) -> &BTreeSet<String> { &self.allowed_scopes }
+
+    pub fn set_allowed_scopes(&mut self, scopes : BTreeSet<String>) { self.allowed_scopes = scopes; }
+
+    pub fn is_disabled(&self) -> bool { self.disabled }
+
+    pub fn set_disabled(&mut self, disabled : bool) { self.disabled = disabled; }
+}
+
+fn is_known_grant(grant : &str) -> bool {
+    grant == GRANT_AUTHORIZATION_CODE || grant == GRANT_CLIENT_CREDENTIALS || grant == GRANT_DEVICE_CODE
+}
+
+/**
+ * Redirect uri's need to be absolute and can't contain a fragment.
+ */
+fn is_absolute_uri(uri : &str) -> bool {
+    match uri.find("://") {
+        Some(index) => index > 0 && uri.len() > index + 3 && !uri.contains('#'),
+        None => false
+    }
+}
+
+#[test]
+fn test_client_secret() {
+    let (client, secret) = OAuthClient::new_client("app", vec!["https://app.be/callback".to_owned()], ClientType::Confidential).unwrap();
+    let secret = secret.unwrap();
+    assert!(client.check_secret(&secret));
+    assert!(!client.check_secret("wrong"));
+    assert!(client.is_redirect_uri_registered("https://app.be/callback"));
+    assert!(!client.is_redirect_uri_registered("https://app.be/callback/other"));
+
+    let (public, secret) = OAuthClient::new_client("spa", vec!["http://localhost:3000".to_owned()], ClientType::Public).unwrap();
+    assert!(secret.is_none());
+    assert!(!public.check_secret(""));
+    assert!(OAuthClient::new_client("spa", vec!["/relative".to_owned()], ClientType::Public).is_err());
+}
+
+#[test]
+fn test_client_grant_types() {
+    let (mut client, _) = OAuthClient::new_client("service", Vec::new(), ClientType::Confidential).unwrap();
+    assert!(client.is_grant_type_allowed(GRANT_AUTHORIZATION_CODE));
+    client.set_grant_types(vec![GRANT_CLIENT_CREDENTIALS.to_owned()].into_iter().collect()).unwrap();
+    assert!(client.is_grant_type_allowed(GRANT_CLIENT_CREDENTIALS));
+    assert!(!client.is_grant_type_allowed(GRANT_AUTHORIZATION_CODE));
+    assert!(client.set_grant_types(vec!["password".to_owned()].into_iter().collect()).is_err());
+
+    let (mut public, _) = OAuthClient::new_client("spa", Vec::new(), ClientType::Public).unwrap();
+    assert!(public.set_grant_types(vec![GRANT_CLIENT_CREDENTIALS.to_owned()].into_iter().collect()).is_err());
+}
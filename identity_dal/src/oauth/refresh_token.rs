@@ -0,0 +1,75 @@
+use serde::{Serialize, Deserialize};
+use std::convert::From;
+use chrono::{Duration, Utc};
+use super::oauth_client::GRANT_AUTHORIZATION_CODE;
+
+/**
+ * RefreshToken is the long lived grant a client gets together with an access token for an authorization code or a device code, it can be exchanged for a new access token. The token itself is never kept, it is stored under a hash of it.
+ *
+ * Attributes:
+ * * client_id: client to which the token was issued
+ * * user_id: user that authorized the client
+ * * scope: space delimited scopes that were granted
+ * * grant_type: grant the token was first issued with, the client needs it to refresh
+ * * auth_time: unix timestamp of when the user authenticated
+ * * expires_at: unix timestamp after which the token can't be used
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RefreshToken {
+    client_id : String,
+    user_id : String,
+    scope : String,
+    #[serde(default = "default_grant_type")]
+    grant_type : String,
+    auth_time : i64,
+    expires_at : i64
+}
+
+/**
+ * Grant of the refresh tokens that were issued before the grant was kept, only the authorization code grant issued them.
+ */
+fn default_grant_type() -> String {
+    GRANT_AUTHORIZATION_CODE.to_owned()
+}
+
+impl From<&sled::IVec> for RefreshToken {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to a RefreshToken struct.")
+    }
+}
+
+impl From<&RefreshToken> for sled::IVec {
+    fn from(item : &RefreshToken) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert RefreshToken struct to bytes"))
+    }
+}
+
+impl RefreshToken {
+    /**
+     * Returns a new refresh token that expires after the given amount of seconds.
+     */
+    pub fn new(client_id : &str, user_id : &str, scope : &str, grant_type : &str, auth_time : i64, lifetime : i64) -> Self {
+        RefreshToken {
+            client_id : client_id.to_owned(),
+            user_id : user_id.to_owned(),
+            scope : scope.to_owned(),
+            grant_type : grant_type.to_owned(),
+            auth_time,
+            expires_at : (Utc::now() + Duration::seconds(lifetime)).timestamp()
+        }
+    }
+
+    pub fn is_expired(&self) -> bool { Utc::now().timestamp() > self.expires_at }
+
+    pub fn get_client_id(&self) -> &str { &self.client_id }
+
+    pub fn get_user_id(&self) -> &str { &self.user_id }
+
+    pub fn get_scope(&self) -> &str { &self.scope }
+
+    pub fn get_grant_type(&self) -> &str { &self.grant_type }
+
+    pub fn get_auth_time(&self) -> i64 { self.auth_time }
+
+    pub fn get_expires_at(&self) -> i64 { self.expires_at }
+}
@@ -0,0 +1,144 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeSet;
+use std::convert::From;
+use chrono::{Duration, Utc};
+
+/**
+ * State of an invitation, an invitation that is still pending after it expires is shown as expired.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum InvitationStatus {
+    Pending,
+    Accepted { user_id : String, accepted_at : i64 },
+    Revoked { revoked_at : i64 }
+}
+
+/**
+ * Invitation is an invitation for an email to make an account, the invitee chooses his own password when he accepts it. The token of the invitation link is never kept, only a hash of it.
+ *
+ * Attributes:
+ * * id: id of the invitation
+ * * email: email that is invited, the account gets this email
+ * * flags: flags the account gets
+ * * group_ids: groups the account becomes a member of
+ * * invited_by: id of the user that made the invitation, the admin for invitations of the admin or an admin client
+ * * token_hash: hash of the token of the last link that was sent, earlier links can't be used anymore
+ * * created_at: unix timestamp of when the invitation was made
+ * * sent_at: unix timestamp of when the last link was sent
+ * * expires_at: unix timestamp after which the invitation can't be accepted
+ * * status: pending until it is accepted or revoked
+ * * locale: locale the invitation is mailed in and the account gets, the default locale when it has none
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Invitation {
+    id : String,
+    email : String,
+    flags : BTreeSet<String>,
+    group_ids : BTreeSet<String>,
+    invited_by : String,
+    token_hash : String,
+    created_at : i64,
+    sent_at : i64,
+    expires_at : i64,
+    status : InvitationStatus,
+    #[serde(default)]
+    locale : Option<String>
+}
+
+impl From<&sled::IVec> for Invitation {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to an Invitation struct.")
+    }
+}
+
+impl From<&Invitation> for sled::IVec {
+    fn from(item : &Invitation) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert Invitation struct to bytes"))
+    }
+}
+
+impl Invitation {
+    /**
+     * Returns a new pending invitation whose link expires after the given amount of seconds.
+     */
+    pub fn new(id : &str, email : &str, flags : BTreeSet<String>, group_ids : BTreeSet<String>, invited_by : &str, token_hash : &str, lifetime : i64) -> Self {
+        let now = Utc::now();
+        Invitation {
+            id : id.to_owned(),
+            email : email.to_owned(),
+            flags,
+            group_ids,
+            invited_by : invited_by.to_owned(),
+            token_hash : token_hash.to_owned(),
+            created_at : now.timestamp(),
+            sent_at : now.timestamp(),
+            expires_at : (now + Duration::seconds(lifetime)).timestamp(),
+            status : InvitationStatus::Pending,
+            locale : None
+        }
+    }
+
+    /**
+     * Returns the invitation with the locale, an empty locale is the same as none.
+     */
+    pub fn with_locale(mut self, locale : Option<&str>) -> Self {
+        self.locale = locale.map(str::trim).filter(|locale| !locale.is_empty()).map(str::to_owned);
+        self
+    }
+
+    pub fn get_id(&self) -> &str { &self.id }
+
+    pub fn get_email(&self) -> &str { &self.email }
+
+    pub fn get_flags(&self) -> &BTreeSet<String> { &self.flags }
+
+    pub fn get_group_ids(&self) -> &BTreeSet<String> { &self.group_ids }
+
+    pub fn get_invited_by(&self) -> &str { &self.invited_by }
+
+    pub fn get_token_hash(&self) -> &str { &self.token_hash }
+
+    pub fn get_created_at(&self) -> i64 { self.created_at }
+
+    pub fn get_sent_at(&self) -> i64 { self.sent_at }
+
+    pub fn get_expires_at(&self) -> i64 { self.expires_at }
+
+    pub fn get_status(&self) -> &InvitationStatus { &self.status }
+
+    pub fn get_locale(&self) -> Option<&str> { self.locale.as_deref() }
+
+    pub fn is_expired(&self) -> bool { Utc::now().timestamp() > self.expires_at }
+
+    /**
+     * Returns true when the invitation can still be accepted.
+     */
+    pub fn is_pending(&self) -> bool {
+        self.status == InvitationStatus::Pending && !self.is_expired()
+    }
+
+    /**
+     * Replaces the token of the invitation by that of a newly sent link and gives it a new expiration.
+     */
+    pub fn renew(&mut self, token_hash : &str, lifetime : i64) {
+        let now = Utc::now();
+        self.token_hash = token_hash.to_owned();
+        self.sent_at = now.timestamp();
+        self.expires_at = (now + Duration::seconds(lifetime)).timestamp();
+    }
+
+    /**
+     * Lets the invitation expire right away.
+     */
+    pub fn expire(&mut self) {
+        self.expires_at = Utc::now().timestamp() - 1;
+    }
+
+    pub fn accept(&mut self, user_id : &str) {
+        self.status = InvitationStatus::Accepted { user_id : user_id.to_owned(), accepted_at : Utc::now().timestamp() };
+    }
+
+    pub fn revoke(&mut self) {
+        self.status = InvitationStatus::Revoked { revoked_at : Utc::now().timestamp() };
+    }
+}
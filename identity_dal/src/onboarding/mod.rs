@@ -0,0 +1,2 @@
+pub mod invitation;
+pub mod pending_registration;
\ No newline at end of file
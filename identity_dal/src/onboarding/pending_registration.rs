@@ -0,0 +1,50 @@
+use serde::{Serialize, Deserialize};
+use std::convert::From;
+use chrono::Utc;
+use crate::user::identity_user::IdentityUser;
+use crate::traits::t_user::UserTrait;
+
+/**
+ * PendingRegistration is a registration that waits for the approval of the admin, the user is only added once it is approved.
+ *
+ * Attributes:
+ * * user: user as he registered, with his hashed password
+ * * requested_at: unix timestamp of when the user registered
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PendingRegistration {
+    user : IdentityUser,
+    requested_at : i64
+}
+
+impl From<&sled::IVec> for PendingRegistration {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to a PendingRegistration struct.")
+    }
+}
+
+impl From<&PendingRegistration> for sled::IVec {
+    fn from(item : &PendingRegistration) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert PendingRegistration struct to bytes"))
+    }
+}
+
+impl PendingRegistration {
+    pub fn new(user : IdentityUser) -> Self {
+        PendingRegistration {
+            user,
+            requested_at : Utc::now().timestamp()
+        }
+    }
+
+    /**
+     * The id of the registration is the id the user gets.
+     */
+    pub fn get_id(&self) -> &str { self.user.get_id() }
+
+    pub fn get_user(&self) -> &IdentityUser { &self.user }
+
+    pub fn get_requested_at(&self) -> i64 { self.requested_at }
+
+    pub fn into_user(self) -> IdentityUser { self.user }
+}
@@ -0,0 +1,51 @@
+use serde::{Serialize, Deserialize};
+use std::convert::From;
+use chrono::{Duration, Utc};
+
+/**
+ * MagicLink is a single use link that is mailed to an user so he can log in without his password. The token of the link is never kept, it is stored under a hash of it.
+ *
+ * Attributes:
+ * * user_id: user that can log in with the link
+ * * security_stamp: security stamp of the user when the link was made, the link can't be used anymore once the stamp changes
+ * * expires_at: unix timestamp after which the link can't be used
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MagicLink {
+    user_id : String,
+    security_stamp : String,
+    expires_at : i64
+}
+
+impl From<&sled::IVec> for MagicLink {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to a MagicLink struct.")
+    }
+}
+
+impl From<&MagicLink> for sled::IVec {
+    fn from(item : &MagicLink) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert MagicLink struct to bytes"))
+    }
+}
+
+impl MagicLink {
+    /**
+     * Returns a new link that expires after the given amount of seconds.
+     */
+    pub fn new(user_id : &str, security_stamp : &str, lifetime : i64) -> Self {
+        MagicLink {
+            user_id : user_id.to_owned(),
+            security_stamp : security_stamp.to_owned(),
+            expires_at : (Utc::now() + Duration::seconds(lifetime)).timestamp()
+        }
+    }
+
+    pub fn is_expired(&self) -> bool { Utc::now().timestamp() > self.expires_at }
+
+    pub fn get_user_id(&self) -> &str { &self.user_id }
+
+    pub fn get_security_stamp(&self) -> &str { &self.security_stamp }
+
+    pub fn get_expires_at(&self) -> i64 { self.expires_at }
+}
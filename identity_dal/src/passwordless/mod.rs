@@ -0,0 +1 @@
+pub mod magic_link;
\ No newline at end of file
@@ -0,0 +1,167 @@
+use crate::audit::audit_event::{ AuditEvent, AuditEventType };
+use crate::audit::audit_chain::{ ChainHashFunction, ChainVerification };
+use crate::err::IdentityError;
+use super::user_config::UserConfig;
+use sled::{ Db, Tree };
+use std::sync::Mutex;
+
+lazy_static! {
+    /**
+     * Events are appended one at a time, otherwise two events could be linked to the same previous event.
+     */
+    static ref APPEND_LOCK : Mutex<()> = Mutex::new(());
+}
+
+/**
+ * Name of the sled tree in which the audit events are kept.
+ */
+pub static AUDIT_TREE : &str = "audit_log";
+
+/**
+ * Audit store represents the append only tree within the sled database where the audit events are kept. Events are stored under their big endian id, so the tree is ordered by when they were recorded. Every event holds the hash of the event before it, so the log can be verified to be unchanged.
+ */
+#[derive(Clone)]
+pub struct AuditStore {
+    db : Db,
+    pub audit_db_tree : Tree
+}
+
+/**
+ * Conditions the returned audit events have to meet, a condition that is None isn't checked.
+ *
+ * * user: id or email of the user the action was done on
+ * * actor: id of the user or client that did the action
+ * * event_type: kind of action
+ * * from and to: unix timestamps between which the event happened, both included
+ * * limit: maximum amount of events, the newest are returned
+ */
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    pub user : Option<String>,
+    pub actor : Option<String>,
+    pub event_type : Option<AuditEventType>,
+    pub from : Option<i64>,
+    pub to : Option<i64>,
+    pub limit : Option<usize>
+}
+
+impl AuditQuery {
+    pub fn matches(&self, event : &AuditEvent) -> bool {
+        self.user.as_deref().is_none_or(|user| event.get_target() == Some(user))
+        && self.actor.as_deref().is_none_or(|actor| event.get_actor() == Some(actor))
+        && self.event_type.is_none_or(|event_type| event.get_event_type() == event_type)
+        && self.from.is_none_or(|from| event.get_timestamp() >= from)
+        && self.to.is_none_or(|to| event.get_timestamp() <= to)
+    }
+}
+
+impl AuditStore {
+    /**
+     * Return the audit tree on the database of the given config.
+     */
+    pub fn new_db(config : UserConfig) -> AuditStore {
+        match config.get_db().open_tree(AUDIT_TREE) {
+            Ok(tree) => AuditStore { db : config.get_db().clone(), audit_db_tree : tree },
+            Err(_) => panic!("Could not open the tree {}", AUDIT_TREE)
+        }
+    }
+
+    /**
+     * Records an event under a new monotonic id and links it to the last event with the hash function, events can't be changed or removed afterwards.
+     */
+    pub fn append(&self, mut event : AuditEvent, hash_function : ChainHashFunction) -> Result<AuditEvent, IdentityError> {
+        let _lock = APPEND_LOCK.lock().map_err(|_| IdentityError::CustomError("The audit log is locked".to_owned()))?;
+        let id = self.db.generate_id().map_err(|_| IdentityError::CustomError("No id could be generated for the audit event".to_owned()))?;
+        let previous_hash = self.get_last_event().map(|last| last.get_hash().to_owned()).unwrap_or_default();
+        event.link(id, &previous_hash, hash_function);
+        match self.audit_db_tree.insert(id.to_be_bytes(), &event) {
+            Ok(_) => Ok(event),
+            Err(_) => Err(IdentityError::CustomError("The audit event could not be stored".to_owned()))
+        }
+    }
+
+    /**
+     * Returns the event that was recorded last.
+     */
+    pub fn get_last_event(&self) -> Option<AuditEvent> {
+        match self.audit_db_tree.last() {
+            Ok(Some((_, value))) => Some(AuditEvent::from(&value)),
+            _ => None
+        }
+    }
+
+    /**
+     * Walks the chain from the first event on and stops at the first event whose link is broken: its hash doesn't match its content, or its previous hash isn't that of the event before it.
+     */
+    pub fn verify_chain(&self, hash_function : ChainHashFunction) -> ChainVerification {
+        let mut verification = ChainVerification::default();
+        for (key, value) in self.audit_db_tree.iter().filter_map(|entry| entry.ok()) {
+            let event = AuditEvent::from(&value);
+            let previous_hash = verification.last_hash.clone().unwrap_or_default();
+            let reason = if key.as_ref() != event.get_id().to_be_bytes() {
+                Some("the event is not stored under its id")
+            } else if event.get_previous_hash() != previous_hash {
+                Some("the previous event has been changed or removed")
+            } else if hash_function(&event.chain_content()) != event.get_hash() {
+                Some("the event has been changed")
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                verification.broken_at = Some(event.get_id());
+                verification.reason = Some(reason.to_owned());
+                return verification
+            }
+            verification.checked += 1;
+            verification.last_id = Some(event.get_id());
+            verification.last_hash = Some(event.get_hash().to_owned());
+        }
+        verification
+    }
+
+    /**
+     * Returns the events that match the query, the newest first.
+     */
+    pub fn query(&self, query : &AuditQuery) -> Vec<AuditEvent> {
+        self.audit_db_tree.iter()
+        .rev()
+        .filter_map(|entry| entry.ok())
+        .map(|(_, value)| AuditEvent::from(&value))
+        .filter(|event| query.matches(event))
+        .take(query.limit.unwrap_or(usize::MAX))
+        .collect()
+    }
+}
+
+#[test]
+fn test_audit_query() {
+    use crate::audit::audit_event::AuditOutcome;
+
+    let store = AuditStore::new_db(UserConfig::new_config("", "", 100000));
+    let hash = |content : &[u8]| format!("{:x}", content.iter().fold(0u64, |hash, byte| hash.wrapping_mul(31).wrapping_add(*byte as u64)));
+    let failed = store.append(AuditEvent::new(AuditEventType::Login, "login", AuditOutcome::Failure("Password is not right".to_owned())).with_target(Some("jane@corp.be")), hash).unwrap();
+    let changed = store.append(AuditEvent::new(AuditEventType::PasswordChange, "change_password", AuditOutcome::Success).with_actor(Some("jane")).with_target(Some("jane")), hash).unwrap();
+    store.append(AuditEvent::new(AuditEventType::AdminAction, "suspend_user", AuditOutcome::Success).with_actor(Some("admin")).with_target(Some("jane")), hash).unwrap();
+    assert!(failed.get_id() < changed.get_id());
+
+    let query = |query : AuditQuery| store.query(&query).iter().map(AuditEvent::get_action).map(str::to_owned).collect::<Vec<String>>();
+    assert_eq!(query(AuditQuery::default()), vec!["suspend_user", "change_password", "login"]);
+    assert_eq!(query(AuditQuery { user : Some("jane".to_owned()), ..AuditQuery::default() }), vec!["suspend_user", "change_password"]);
+    assert_eq!(query(AuditQuery { actor : Some("jane".to_owned()), ..AuditQuery::default() }), vec!["change_password"]);
+    assert_eq!(query(AuditQuery { event_type : AuditEventType::parse("login"), ..AuditQuery::default() }), vec!["login"]);
+    assert_eq!(query(AuditQuery { limit : Some(1), ..AuditQuery::default() }), vec!["suspend_user"]);
+    assert!(query(AuditQuery { from : Some(changed.get_timestamp() + 10), ..AuditQuery::default() }).is_empty());
+
+    let verification = store.verify_chain(hash);
+    assert!(verification.is_intact());
+    assert_eq!((verification.checked, verification.last_id), (3, store.get_last_event().map(|event| event.get_id())));
+    assert_eq!(changed.get_previous_hash(), failed.get_hash());
+
+    let mut edited = changed.clone();
+    edited.link(changed.get_id(), changed.get_previous_hash(), hash);
+    store.audit_db_tree.insert(changed.get_id().to_be_bytes(), &edited.with_actor(Some("admin"))).unwrap();
+    let verification = store.verify_chain(hash);
+    assert_eq!((verification.checked, verification.broken_at), (1, Some(changed.get_id())));
+    store.audit_db_tree.remove(changed.get_id().to_be_bytes()).unwrap();
+    assert_eq!(store.verify_chain(hash).reason.as_deref(), Some("the previous event has been changed or removed"));
+}
@@ -0,0 +1,159 @@
+use crate::user::user_change::{ UserChange, UserChangeKind };
+use crate::err::IdentityError;
+use super::user_config::UserConfig;
+use super::user_repo::UserStore;
+use sled::{ Db, Event, Subscriber, Tree };
+
+/**
+ * Suffix of the name of the sled tree in which the changes of a user tree are kept.
+ */
+pub static CHANGE_TREE_SUFFIX : &str = "_changes";
+
+/**
+ * Suffix of the name of the sled tree with the ids of the users the change feed has seen created.
+ */
+pub static KNOWN_TREE_SUFFIX : &str = "_changes_known";
+
+/**
+ * Change feed store represents the trees within the sled database where the changes of the user records are kept. Changes are stored under their big endian sequence, so the feed is ordered and can be read from any sequence on. The ids of the users that exist according to the feed are kept to tell a created user from an updated one.
+ */
+#[derive(Clone)]
+pub struct ChangeFeedStore {
+    db : Db,
+    pub change_db_tree : Tree,
+    pub known_db_tree : Tree
+}
+
+impl ChangeFeedStore {
+    /**
+     * Return the change feed trees of the user tree of the given config.
+     */
+    pub fn new_db(config : UserConfig) -> ChangeFeedStore {
+        let open = |name : &str| match config.get_db().open_tree(name) {
+            Ok(tree) => tree,
+            Err(_) => panic!("Could not open the tree {}", name)
+        };
+        ChangeFeedStore {
+            db : config.get_db().clone(),
+            change_db_tree : open(&format!("{}{}", config.get_tree(), CHANGE_TREE_SUFFIX)),
+            known_db_tree : open(&format!("{}{}", config.get_tree(), KNOWN_TREE_SUFFIX))
+        }
+    }
+
+    /**
+     * Appends a change under a new sequence and returns it. Sequences start at 1, so a cursor of 0 is before every change.
+     */
+    fn append(&self, kind : UserChangeKind, user_id : &str) -> Result<UserChange, IdentityError> {
+        let mut change = UserChange::new(kind, user_id);
+        let id = self.db.generate_id().map_err(|_| IdentityError::CustomError("No sequence could be generated for the user change".to_owned()))?;
+        change.set_sequence(id + 1);
+        match self.change_db_tree.insert(change.get_sequence().to_be_bytes(), &change) {
+            Ok(_) => Ok(change),
+            Err(_) => Err(IdentityError::CustomError("The user change could not be stored".to_owned()))
+        }
+    }
+
+    /**
+     * Records an insert in the user tree as a created or an updated user, and a removal as a deleted user. A removal of an user the feed doesn't know is ignored.
+     */
+    pub fn record_event(&self, event : &Event) -> Result<Option<UserChange>, IdentityError> {
+        let user_id = String::from_utf8_lossy(event.key()).into_owned();
+        let is_known = self.known_db_tree.contains_key(event.key()).unwrap_or(false);
+        let kind = match event {
+            Event::Insert { .. } if is_known => UserChangeKind::Updated,
+            Event::Insert { .. } => UserChangeKind::Created,
+            Event::Remove { .. } if is_known => UserChangeKind::Deleted,
+            Event::Remove { .. } => return Ok(None)
+        };
+        let change = self.append(kind, &user_id)?;
+        let known = match kind {
+            UserChangeKind::Deleted => self.known_db_tree.remove(event.key()).map(|_| ()),
+            _ => self.known_db_tree.insert(event.key(), vec![]).map(|_| ())
+        };
+        known.map_err(|_| IdentityError::CustomError("The users known to the change feed could not be updated".to_owned()))?;
+        Ok(Some(change))
+    }
+
+    /**
+     * Brings the feed up to date with the user tree: users it doesn't know yet are recorded as created and users that are gone as deleted. Returns the amount of recorded changes.
+     */
+    pub fn backfill(&self, users : &UserStore) -> Result<usize, IdentityError> {
+        let mut events : Vec<Event> = users.user_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|(key, _)| !self.known_db_tree.contains_key(key).unwrap_or(true))
+        .map(|(key, value)| Event::Insert { key, value })
+        .collect();
+        events.extend(self.known_db_tree.iter().keys()
+        .filter_map(|key| key.ok())
+        .filter(|key| !users.user_db_tree.contains_key(key).unwrap_or(true))
+        .map(|key| Event::Remove { key }));
+        for event in &events {
+            self.record_event(event)?;
+        }
+        Ok(events.len())
+    }
+
+    /**
+     * Returns the changes after the cursor, the oldest first. A cursor of 0 reads the feed from the start.
+     */
+    pub fn get_changes_after(&self, cursor : u64, limit : usize) -> Vec<UserChange> {
+        self.change_db_tree.range(cursor.saturating_add(1).to_be_bytes()..)
+        .values()
+        .filter_map(|value| value.ok())
+        .map(|value| UserChange::from(&value))
+        .take(limit)
+        .collect()
+    }
+
+    /**
+     * Returns the sequence of the last change, 0 for an empty feed.
+     */
+    pub fn get_last_sequence(&self) -> u64 {
+        match self.change_db_tree.last() {
+            Ok(Some((_, value))) => UserChange::from(&value).get_sequence(),
+            _ => 0
+        }
+    }
+
+    /**
+     * Returns a subscriber that is woken up by every change that is recorded, to wait for new changes.
+     */
+    pub fn watch_changes(&self) -> Subscriber {
+        self.change_db_tree.watch_prefix(vec![])
+    }
+}
+
+#[test]
+fn test_change_feed() {
+    use crate::traits::t_user::UserTrait;
+    use crate::traits::t_user_manager::UserStoreTrait;
+    use crate::user::identity_user::IdentityUser;
+    use std::time::Duration;
+
+    let config = UserConfig::new_config("", "person", 100000);
+    let (users, feed) = (UserStore::new_db(config.clone()), ChangeFeedStore::new_db(config));
+    let mut john = users.add_user(IdentityUser::new_user("john@corp.be", "john", "Passw0rd!").unwrap()).unwrap();
+    assert_eq!(feed.backfill(&users).unwrap(), 1);
+    assert_eq!(feed.backfill(&users).unwrap(), 0);
+
+    let mut subscriber = users.user_db_tree.watch_prefix(vec![]);
+    let jane = users.add_user(IdentityUser::new_user("jane@corp.be", "jane", "Passw0rd!").unwrap()).unwrap();
+    john.set_user_name("johnny");
+    users.update_user(john.get_id(), &john).unwrap();
+    users.delete_user(jane.get_id()).unwrap();
+    for _ in 0..3 {
+        feed.record_event(&subscriber.next_timeout(Duration::from_secs(1)).unwrap()).unwrap();
+    }
+
+    let kinds = |changes : Vec<UserChange>| changes.iter().map(|change| (change.get_kind(), change.get_user_id().to_owned())).collect::<Vec<(UserChangeKind, String)>>();
+    let changes = kinds(feed.get_changes_after(0, 10));
+    assert_eq!(changes, vec![
+        (UserChangeKind::Created, john.get_id().to_owned()),
+        (UserChangeKind::Created, jane.get_id().to_owned()),
+        (UserChangeKind::Updated, john.get_id().to_owned()),
+        (UserChangeKind::Deleted, jane.get_id().to_owned())
+    ]);
+    let second = feed.get_changes_after(0, 2)[1].get_sequence();
+    assert_eq!(kinds(feed.get_changes_after(second, 10)), changes[2..].to_vec());
+    assert!(feed.get_changes_after(feed.get_last_sequence(), 10).is_empty());
+}
@@ -0,0 +1,75 @@
+use crate::oauth::oauth_client::OAuthClient;
+use crate::err::IdentityError;
+use super::user_config::UserConfig;
+use sled::Tree;
+
+/**
+ * Name of the sled tree in which the OAuth clients are kept.
+ */
+pub static CLIENT_TREE : &str = "oauth_client";
+
+/**
+ * Client store represents the tree within the sled database where registered OAuth clients are kept.
+ */
+#[derive(Clone)]
+pub struct ClientStore {
+    pub client_db_tree : Tree
+}
+
+impl ClientStore {
+    /**
+     * Return the client tree on the database of the given config.
+     */
+    pub fn new_db(config : UserConfig) -> ClientStore {
+        match config.get_db().open_tree(CLIENT_TREE) {
+            Ok(tree) => ClientStore{ client_db_tree : tree },
+            Err(_) => panic!("Could not open the tree {}", CLIENT_TREE)
+        }
+    }
+
+    /**
+     * Adds a client to the sled database, an error is returned when the client id is already taken.
+     */
+    pub fn add_client(&self, client : OAuthClient) -> Result<OAuthClient, IdentityError> {
+        if self.get_client(client.get_client_id()).is_some() {
+            return Err(IdentityError::ClientCannotBeAdded)
+        }
+        match self.client_db_tree.insert(client.get_client_id(), &client) {
+            Ok(_) => Ok(client),
+            Err(_) => Err(IdentityError::ClientCannotBeAdded)
+        }
+    }
+
+    /**
+     * Returns a client based on its id, if none has the id a None is returned.
+     */
+    pub fn get_client(&self, client_id : &str) -> Option<OAuthClient> {
+        match self.client_db_tree.get(client_id) {
+            Ok(Some(client)) => Some(OAuthClient::from(&client)),
+            _ => None
+        }
+    }
+
+    /**
+     * Overwrites the stored client with the given one, an error is returned when the client doesn't exist.
+     */
+    pub fn update_client(&self, client : &OAuthClient) -> Result<(), IdentityError> {
+        if self.get_client(client.get_client_id()).is_none() {
+            return Err(IdentityError::ClientNotFound)
+        }
+        match self.client_db_tree.insert(client.get_client_id(), client) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError("Client could not be updated".to_owned()))
+        }
+    }
+
+    /**
+     * Returns a collection of all registered clients.
+     */
+    pub fn get_all_clients(&self) -> Vec<OAuthClient> {
+        self.client_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .map(|(_, client)| OAuthClient::from(&client))
+        .collect()
+    }
+}
@@ -0,0 +1,80 @@
+use crate::oauth::authorization_code::AuthorizationCode;
+use crate::err::IdentityError;
+use super::user_config::UserConfig;
+use sled::Tree;
+
+/**
+ * Name of the sled tree in which the authorization codes are kept.
+ */
+pub static CODE_TREE : &str = "oauth_code";
+
+/**
+ * Code store represents the tree within the sled database where the pending authorization codes are kept.
+ */
+#[derive(Clone)]
+pub struct CodeStore {
+    pub code_db_tree : Tree
+}
+
+impl CodeStore {
+    /**
+     * Return the authorization code tree on the database of the given config.
+     */
+    pub fn new_db(config : UserConfig) -> CodeStore {
+        match config.get_db().open_tree(CODE_TREE) {
+            Ok(tree) => CodeStore{ code_db_tree : tree },
+            Err(_) => panic!("Could not open the tree {}", CODE_TREE)
+        }
+    }
+
+    /**
+     * Stores an authorization code so it can later be exchanged.
+     */
+    pub fn add_code(&self, code : &AuthorizationCode) -> Result<(), IdentityError> {
+        match self.code_db_tree.insert(code.get_code(), code) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError("Authorization code could not be stored".to_owned()))
+        }
+    }
+
+    /**
+     * Removes the code from the database and returns it. Because the code is removed in the same operation, a code can only be taken once. Expired codes are also removed but never returned.
+     */
+    pub fn take_code(&self, code : &str) -> Option<AuthorizationCode> {
+        match self.code_db_tree.remove(code) {
+            Ok(Some(value)) => {
+                let code = AuthorizationCode::from(&value);
+                if code.is_expired() {
+                    warn!("An expired authorization code has been used.");
+                    return None
+                }
+                Some(code)
+            },
+            _ => None
+        }
+    }
+
+    /**
+     * Removes all the codes that have expired and returns how many were removed.
+     */
+    pub fn clean_expired_codes(&self) -> usize {
+        self.code_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|(_, value)| AuthorizationCode::from(value).is_expired())
+        .filter(|(key, _)| matches!(self.code_db_tree.remove(key), Ok(Some(_))))
+        .count()
+    }
+}
+
+#[test]
+fn test_code_single_use() {
+    let store = CodeStore::new_db(UserConfig::new_config("","",100000));
+    let code = AuthorizationCode::new("client", "user", "https://app.be/cb", "openid", "challenge", 60);
+    store.add_code(&code).unwrap();
+    assert_eq!(store.take_code(code.get_code()), Some(code.clone()));
+    assert_eq!(store.take_code(code.get_code()), None);
+
+    let expired = AuthorizationCode::new("client", "user", "https://app.be/cb", "openid", "challenge", -1);
+    store.add_code(&expired).unwrap();
+    assert_eq!(store.take_code(expired.get_code()), None);
+}
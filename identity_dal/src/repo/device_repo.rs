@@ -0,0 +1,122 @@
+use crate::oauth::device_authorization::{ DeviceAuthorization, normalize_user_code };
+use crate::err::IdentityError;
+use super::user_config::UserConfig;
+use sled::Tree;
+
+/**
+ * Name of the sled tree in which the pending device authorizations are kept.
+ */
+pub static DEVICE_TREE : &str = "oauth_device_code";
+
+/**
+ * Name of the sled tree that maps user codes on device codes.
+ */
+pub static USER_CODE_TREE : &str = "oauth_user_code";
+
+/**
+ * Device store represents the trees within the sled database where the pending device authorizations are kept, they can be looked up by device code and by user code.
+ */
+#[derive(Clone)]
+pub struct DeviceStore {
+    pub device_db_tree : Tree,
+    pub user_code_db_tree : Tree
+}
+
+impl DeviceStore {
+    /**
+     * Return the device trees on the database of the given config.
+     */
+    pub fn new_db(config : UserConfig) -> DeviceStore {
+        let open = |name : &str| match config.get_db().open_tree(name) {
+            Ok(tree) => tree,
+            Err(_) => panic!("Could not open the tree {}", name)
+        };
+        DeviceStore {
+            device_db_tree : open(DEVICE_TREE),
+            user_code_db_tree : open(USER_CODE_TREE)
+        }
+    }
+
+    /**
+     * Stores a new device authorization, an error is returned when its user code is already in use.
+     */
+    pub fn add_device(&self, device : &DeviceAuthorization) -> Result<(), IdentityError> {
+        match self.user_code_db_tree.compare_and_swap(device.get_user_code(), None as Option<&[u8]>, Some(device.get_device_code())) {
+            Ok(Ok(_)) => {},
+            _ => return Err(IdentityError::CustomError("User code is already in use".to_owned()))
+        }
+        match self.device_db_tree.insert(device.get_device_code(), device) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError("Device authorization could not be stored".to_owned()))
+        }
+    }
+
+    /**
+     * Returns the device authorization of the device code.
+     */
+    pub fn get_device(&self, device_code : &str) -> Option<DeviceAuthorization> {
+        match self.device_db_tree.get(device_code) {
+            Ok(Some(value)) => Some(DeviceAuthorization::from(&value)),
+            _ => None
+        }
+    }
+
+    /**
+     * Returns the device authorization of a user code as the user typed it over, expired authorizations are never returned.
+     */
+    pub fn get_device_by_user_code(&self, user_code : &str) -> Option<DeviceAuthorization> {
+        match self.user_code_db_tree.get(normalize_user_code(user_code)) {
+            Ok(Some(device_code)) => self.get_device(&String::from_utf8_lossy(&device_code))
+                .filter(|device| !device.is_expired()),
+            _ => None
+        }
+    }
+
+    /**
+     * Overwrites the stored device authorization with the given one.
+     */
+    pub fn update_device(&self, device : &DeviceAuthorization) -> Result<(), IdentityError> {
+        match self.device_db_tree.insert(device.get_device_code(), device) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError("Device authorization could not be updated".to_owned()))
+        }
+    }
+
+    /**
+     * Removes the device authorization and its user code, the removed authorization is returned. Because it is removed in one operation, an approved authorization can only be exchanged once.
+     */
+    pub fn take_device(&self, device_code : &str) -> Option<DeviceAuthorization> {
+        match self.device_db_tree.remove(device_code) {
+            Ok(Some(value)) => {
+                let device = DeviceAuthorization::from(&value);
+                let _ = self.user_code_db_tree.remove(device.get_user_code());
+                Some(device)
+            },
+            _ => None
+        }
+    }
+
+    /**
+     * Removes all the device authorizations that have expired and returns how many were removed.
+     */
+    pub fn clean_expired_devices(&self) -> usize {
+        self.device_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .map(|(_, value)| DeviceAuthorization::from(&value))
+        .filter(|device| device.is_expired())
+        .filter(|device| self.take_device(device.get_device_code()).is_some())
+        .count()
+    }
+}
+
+#[test]
+fn test_device_lookup() {
+    let store = DeviceStore::new_db(UserConfig::new_config("","",100000));
+    let device = DeviceAuthorization::new("cli", "openid", 600, 5);
+    store.add_device(&device).unwrap();
+    let found = store.get_device_by_user_code(&device.get_display_user_code().to_lowercase()).unwrap();
+    assert_eq!(found, device);
+    assert_eq!(store.take_device(device.get_device_code()), Some(device.clone()));
+    assert_eq!(store.get_device_by_user_code(device.get_user_code()), None);
+    assert_eq!(store.take_device(device.get_device_code()), None);
+}
@@ -0,0 +1,107 @@
+use crate::federation::linked_identity::{ LinkedIdentity, identity_key };
+use crate::federation::pending_login::PendingLogin;
+use crate::err::IdentityError;
+use super::user_config::UserConfig;
+use sled::Tree;
+
+/**
+ * Name of the sled tree in which the identities of external identity providers are kept.
+ */
+pub static LINKED_IDENTITY_TREE : &str = "linked_identity";
+
+/**
+ * Name of the sled tree in which the logins at external identity providers are kept until they are finished.
+ */
+pub static PENDING_LOGIN_TREE : &str = "federation_state";
+
+/**
+ * Federation store represents the trees within the sled database where the linked external identities and the pending logins at external identity providers are kept.
+ */
+#[derive(Clone)]
+pub struct FederationStore {
+    pub identity_db_tree : Tree,
+    pub pending_db_tree : Tree
+}
+
+impl FederationStore {
+    /**
+     * Return the federation trees on the database of the given config.
+     */
+    pub fn new_db(config : UserConfig) -> FederationStore {
+        let open = |name : &str| match config.get_db().open_tree(name) {
+            Ok(tree) => tree,
+            Err(_) => panic!("Could not open the tree {}", name)
+        };
+        FederationStore {
+            identity_db_tree : open(LINKED_IDENTITY_TREE),
+            pending_db_tree : open(PENDING_LOGIN_TREE)
+        }
+    }
+
+    /**
+     * Links an external identity to an user, an error is returned when the identity is already linked.
+     */
+    pub fn add_linked_identity(&self, identity : &LinkedIdentity) -> Result<(), IdentityError> {
+        match self.identity_db_tree.compare_and_swap(identity.get_key(), None as Option<&[u8]>, Some(identity)) {
+            Ok(Ok(_)) => Ok(()),
+            _ => Err(IdentityError::IdentityIsAlreadyLinked)
+        }
+    }
+
+    /**
+     * Returns the linked identity of a subject at a provider.
+     */
+    pub fn get_linked_identity(&self, provider : &str, subject : &str) -> Option<LinkedIdentity> {
+        match self.identity_db_tree.get(identity_key(provider, subject)) {
+            Ok(Some(value)) => Some(LinkedIdentity::from(&value)),
+            _ => None
+        }
+    }
+
+    /**
+     * Returns all external identities linked to an user.
+     */
+    pub fn get_linked_identities_of_user(&self, user_id : &str) -> Vec<LinkedIdentity> {
+        self.identity_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .map(|(_, value)| LinkedIdentity::from(&value))
+        .filter(|identity| identity.get_user_id() == user_id)
+        .collect()
+    }
+
+    /**
+     * Removes the linked identity of a subject at a provider, returns true if it existed.
+     */
+    pub fn remove_linked_identity(&self, provider : &str, subject : &str) -> bool {
+        matches!(self.identity_db_tree.remove(identity_key(provider, subject)), Ok(Some(_)))
+    }
+
+    /**
+     * Removes all external identities linked to an user, returns how many were removed.
+     */
+    pub fn remove_linked_identities_of_user(&self, user_id : &str) -> usize {
+        self.get_linked_identities_of_user(user_id).iter()
+        .filter(|identity| self.remove_linked_identity(identity.get_provider(), identity.get_subject()))
+        .count()
+    }
+
+    /**
+     * Stores a login that has been started at an external identity provider.
+     */
+    pub fn add_pending_login(&self, login : &PendingLogin) -> Result<(), IdentityError> {
+        match self.pending_db_tree.insert(login.get_state(), login) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError("The login could not be stored".to_owned()))
+        }
+    }
+
+    /**
+     * Removes the pending login of a state and returns it, so a state can only be used once. Expired logins are never returned.
+     */
+    pub fn take_pending_login(&self, state : &str) -> Option<PendingLogin> {
+        match self.pending_db_tree.remove(state) {
+            Ok(Some(value)) => Some(PendingLogin::from(&value)).filter(|login| !login.is_expired()),
+            _ => None
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::group::identity_group::IdentityGroup;
+use crate::err::IdentityError;
+use super::user_config::UserConfig;
+use sled::Tree;
+
+/**
+ * Name of the sled tree in which the groups are kept.
+ */
+pub static GROUP_TREE : &str = "identity_group";
+
+/**
+ * Group store represents the tree within the sled database where the groups of users are kept, they are kept under their id.
+ */
+#[derive(Clone)]
+pub struct GroupStore {
+    pub group_db_tree : Tree
+}
+
+impl GroupStore {
+    /**
+     * Return the group tree on the database of the given config.
+     */
+    pub fn new_db(config : UserConfig) -> GroupStore {
+        match config.get_db().open_tree(GROUP_TREE) {
+            Ok(tree) => GroupStore{ group_db_tree : tree },
+            Err(_) => panic!("Could not open the tree {}", GROUP_TREE)
+        }
+    }
+
+    /**
+     * Returns true if another group than the one with the id has the name.
+     */
+    pub fn is_name_taken(&self, display_name : &str, id : &str) -> bool {
+        self.get_all_groups().iter().any(|group| group.get_display_name() == display_name && group.get_id() != id)
+    }
+
+    /**
+     * Adds a group, an error is returned when its id or name is already taken.
+     */
+    pub fn add_group(&self, group : &IdentityGroup) -> Result<(), IdentityError> {
+        if self.is_name_taken(group.get_display_name(), group.get_id()) {
+            return Err(IdentityError::GroupNameIsAlreadyTaken)
+        }
+        match self.group_db_tree.compare_and_swap(group.get_id(), None as Option<&[u8]>, Some(group)) {
+            Ok(Ok(_)) => Ok(()),
+            _ => Err(IdentityError::IdIsAlreadyTaken)
+        }
+    }
+
+    /**
+     * Returns the group with the id, if there is none a None is returned.
+     */
+    pub fn get_group(&self, id : &str) -> Option<IdentityGroup> {
+        match self.group_db_tree.get(id) {
+            Ok(Some(value)) => Some(IdentityGroup::from(&value)),
+            _ => None
+        }
+    }
+
+    /**
+     * Returns all groups.
+     */
+    pub fn get_all_groups(&self) -> Vec<IdentityGroup> {
+        self.group_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .map(|(_, value)| IdentityGroup::from(&value))
+        .collect()
+    }
+
+    /**
+     * Returns the groups an user is a member of.
+     */
+    pub fn get_groups_of_user(&self, user_id : &str) -> Vec<IdentityGroup> {
+        self.get_all_groups().into_iter().filter(|group| group.has_member(user_id)).collect()
+    }
+
+    /**
+     * Replaces a group, an error is returned when it doesn't exist or its new name is taken by another group.
+     */
+    pub fn update_group(&self, group : &IdentityGroup) -> Result<(), IdentityError> {
+        if self.get_group(group.get_id()).is_none() {
+            return Err(IdentityError::GroupNotFound)
+        }
+        if self.is_name_taken(group.get_display_name(), group.get_id()) {
+            return Err(IdentityError::GroupNameIsAlreadyTaken)
+        }
+        match self.group_db_tree.insert(group.get_id(), group) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError("The group could not be stored".to_owned()))
+        }
+    }
+
+    /**
+     * Removes the group with the id and returns it.
+     */
+    pub fn remove_group(&self, id : &str) -> Option<IdentityGroup> {
+        match self.group_db_tree.remove(id) {
+            Ok(Some(value)) => Some(IdentityGroup::from(&value)),
+            _ => None
+        }
+    }
+
+    /**
+     * Removes an user from all groups he is a member of.
+     */
+    pub fn remove_member_from_all(&self, user_id : &str) -> Result<(), IdentityError> {
+        for mut group in self.get_groups_of_user(user_id) {
+            group.remove_member(user_id);
+            self.update_group(&group)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use crate::onboarding::invitation::Invitation;
+use crate::err::IdentityError;
+use super::user_config::UserConfig;
+use sled::Tree;
+
+/**
+ * Name of the sled tree in which the invitations are kept.
+ */
+pub static INVITATION_TREE : &str = "invitation";
+
+/**
+ * Name of the sled tree that maps the hashes of invitation tokens on invitation ids.
+ */
+pub static INVITATION_TOKEN_TREE : &str = "invitation_token";
+
+/**
+ * Invitation store represents the trees within the sled database where the invitations are kept, they can be looked up by id and by the hash of the token of their link.
+ */
+#[derive(Clone)]
+pub struct InvitationStore {
+    pub invitation_db_tree : Tree,
+    pub token_db_tree : Tree
+}
+
+impl InvitationStore {
+    /**
+     * Return the invitation trees on the database of the given config.
+     */
+    pub fn new_db(config : UserConfig) -> InvitationStore {
+        let open = |name : &str| match config.get_db().open_tree(name) {
+            Ok(tree) => tree,
+            Err(_) => panic!("Could not open the tree {}", name)
+        };
+        InvitationStore {
+            invitation_db_tree : open(INVITATION_TREE),
+            token_db_tree : open(INVITATION_TOKEN_TREE)
+        }
+    }
+
+    /**
+     * Stores an invitation, a stored invitation is replaced. The hash of its previous token can't be used anymore to find it.
+     */
+    pub fn save_invitation(&self, invitation : &Invitation) -> Result<(), IdentityError> {
+        if let Some(previous) = self.get_invitation(invitation.get_id()) {
+            let _ = self.token_db_tree.remove(previous.get_token_hash());
+        }
+        match self.invitation_db_tree.insert(invitation.get_id(), invitation)
+            .and_then(|_| self.token_db_tree.insert(invitation.get_token_hash(), invitation.get_id())) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError("Invitation could not be stored".to_owned()))
+        }
+    }
+
+    /**
+     * Returns the invitation with the id.
+     */
+    pub fn get_invitation(&self, id : &str) -> Option<Invitation> {
+        match self.invitation_db_tree.get(id) {
+            Ok(Some(value)) => Some(Invitation::from(&value)),
+            _ => None
+        }
+    }
+
+    /**
+     * Returns the invitation of the hash of the token of its link.
+     */
+    pub fn get_invitation_by_token_hash(&self, token_hash : &str) -> Option<Invitation> {
+        match self.token_db_tree.get(token_hash) {
+            Ok(Some(id)) => self.get_invitation(&String::from_utf8_lossy(&id)),
+            _ => None
+        }
+    }
+
+    /**
+     * Returns all invitations, the newest ones first.
+     */
+    pub fn get_all_invitations(&self) -> Vec<Invitation> {
+        let mut invitations : Vec<Invitation> = self.invitation_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .map(|(_, value)| Invitation::from(&value))
+        .collect();
+        invitations.sort_by_key(|invitation| -invitation.get_created_at());
+        invitations
+    }
+
+    /**
+     * Returns the pending invitation for an email.
+     */
+    pub fn get_pending_invitation_of_email(&self, email : &str) -> Option<Invitation> {
+        self.get_all_invitations().into_iter()
+            .find(|invitation| invitation.is_pending() && invitation.get_email().eq_ignore_ascii_case(email))
+    }
+}
+
+#[test]
+fn test_save_invitation() {
+    use std::collections::BTreeSet;
+
+    let store = InvitationStore::new_db(UserConfig::new_config("", "person", 100000));
+    let mut invitation = Invitation::new("1", "jane@corp.be", BTreeSet::new(), BTreeSet::new(), "ADMIN", "first", 60);
+    store.save_invitation(&invitation).unwrap();
+    assert_eq!(store.get_invitation_by_token_hash("first").unwrap().get_id(), "1");
+    assert!(store.get_pending_invitation_of_email("JANE@corp.be").is_some());
+
+    invitation.renew("second", 60);
+    store.save_invitation(&invitation).unwrap();
+    assert!(store.get_invitation_by_token_hash("first").is_none());
+    assert_eq!(store.get_invitation_by_token_hash("second").unwrap().get_id(), "1");
+
+    invitation.expire();
+    store.save_invitation(&invitation).unwrap();
+    assert!(store.get_pending_invitation_of_email("jane@corp.be").is_none());
+}
@@ -0,0 +1,48 @@
+use crate::err::IdentityError;
+use super::user_config::UserConfig;
+use sled::Tree;
+
+/**
+ * Name of the sled tree in which key material is kept.
+ */
+pub static KEY_TREE : &str = "signing_key";
+
+/**
+ * Key store represents the tree within the sled database where the server keeps its own keys, like the key used to sign id tokens when none is configured.
+ */
+#[derive(Clone)]
+pub struct KeyStore {
+    pub key_db_tree : Tree
+}
+
+impl KeyStore {
+    /**
+     * Return the key tree on the database of the given config.
+     */
+    pub fn new_db(config : UserConfig) -> KeyStore {
+        match config.get_db().open_tree(KEY_TREE) {
+            Ok(tree) => KeyStore{ key_db_tree : tree },
+            Err(_) => panic!("Could not open the tree {}", KEY_TREE)
+        }
+    }
+
+    /**
+     * Returns the bytes of the key with the given name, if there is no such key a None is returned.
+     */
+    pub fn get_key(&self, name : &str) -> Option<Vec<u8>> {
+        match self.key_db_tree.get(name) {
+            Ok(Some(key)) => Some(key.to_vec()),
+            _ => None
+        }
+    }
+
+    /**
+     * Stores the bytes of a key under the given name, an existing key with this name is overwritten.
+     */
+    pub fn insert_key(&self, name : &str, key : &[u8]) -> Result<(), IdentityError> {
+        match self.key_db_tree.insert(name, key) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError(format!("Key {} could not be stored", name)))
+        }
+    }
+}
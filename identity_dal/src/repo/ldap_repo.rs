@@ -0,0 +1,56 @@
+use crate::ldap::ldap_account::LdapAccount;
+use crate::err::IdentityError;
+use super::user_config::UserConfig;
+use sled::Tree;
+
+/**
+ * Name of the sled tree in which the LDAP accounts of users are kept.
+ */
+pub static LDAP_ACCOUNT_TREE : &str = "ldap_account";
+
+/**
+ * Ldap store represents the tree within the sled database where the LDAP accounts are kept, they are kept under the id of their user.
+ */
+#[derive(Clone)]
+pub struct LdapStore {
+    pub account_db_tree : Tree
+}
+
+impl LdapStore {
+    /**
+     * Return the LDAP account tree on the database of the given config.
+     */
+    pub fn new_db(config : UserConfig) -> LdapStore {
+        match config.get_db().open_tree(LDAP_ACCOUNT_TREE) {
+            Ok(tree) => LdapStore{ account_db_tree : tree },
+            Err(_) => panic!("Could not open the tree {}", LDAP_ACCOUNT_TREE)
+        }
+    }
+
+    /**
+     * Stores the LDAP account of an user, the previous one is replaced.
+     */
+    pub fn insert_account(&self, account : &LdapAccount) -> Result<(), IdentityError> {
+        match self.account_db_tree.insert(account.get_user_id(), account) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError("The LDAP account could not be stored".to_owned()))
+        }
+    }
+
+    /**
+     * Returns the LDAP account of an user, if he never logged in through LDAP a None is returned.
+     */
+    pub fn get_account(&self, user_id : &str) -> Option<LdapAccount> {
+        match self.account_db_tree.get(user_id) {
+            Ok(Some(value)) => Some(LdapAccount::from(&value)),
+            _ => None
+        }
+    }
+
+    /**
+     * Removes the LDAP account of an user, returns true if he had one.
+     */
+    pub fn remove_account(&self, user_id : &str) -> bool {
+        matches!(self.account_db_tree.remove(user_id), Ok(Some(_)))
+    }
+}
@@ -0,0 +1,88 @@
+use crate::passwordless::magic_link::MagicLink;
+use crate::err::IdentityError;
+use super::user_config::UserConfig;
+use chrono::Utc;
+use sled::Tree;
+
+/**
+ * Name of the sled tree in which the magic links are kept.
+ */
+pub static MAGIC_LINK_TREE : &str = "magic_link";
+
+/**
+ * Name of the sled tree in which the times magic links were requested for an email are kept.
+ */
+pub static MAGIC_LINK_REQUEST_TREE : &str = "magic_link_request";
+
+/**
+ * Magic link store represents the trees within the sled database where the magic links are kept under the hash of their token, and where the requests for them are counted to limit how many links are sent.
+ */
+#[derive(Clone)]
+pub struct MagicLinkStore {
+    pub link_db_tree : Tree,
+    pub request_db_tree : Tree
+}
+
+impl MagicLinkStore {
+    /**
+     * Return the magic link trees on the database of the given config.
+     */
+    pub fn new_db(config : UserConfig) -> MagicLinkStore {
+        let open = |name : &str| match config.get_db().open_tree(name) {
+            Ok(tree) => tree,
+            Err(_) => panic!("Could not open the tree {}", name)
+        };
+        MagicLinkStore {
+            link_db_tree : open(MAGIC_LINK_TREE),
+            request_db_tree : open(MAGIC_LINK_REQUEST_TREE)
+        }
+    }
+
+    /**
+     * Stores a magic link under the hash of its token.
+     */
+    pub fn add_link(&self, token_hash : &str, link : &MagicLink) -> Result<(), IdentityError> {
+        match self.link_db_tree.insert(token_hash, link) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError("Magic link could not be stored".to_owned()))
+        }
+    }
+
+    /**
+     * Removes the magic link from the database and returns it, so a link can only be used once. Expired links are also removed but never returned.
+     */
+    pub fn take_link(&self, token_hash : &str) -> Option<MagicLink> {
+        match self.link_db_tree.remove(token_hash) {
+            Ok(Some(value)) => Some(MagicLink::from(&value)).filter(|link| !link.is_expired()),
+            _ => None
+        }
+    }
+
+    /**
+     * Registers a request for a magic link for the key and returns false when there already were the maximum amount of requests within the window of seconds, such a request isn't registered.
+     */
+    pub fn register_request(&self, key : &str, max_requests : usize, window : i64) -> bool {
+        let now = Utc::now().timestamp();
+        let mut allowed = false;
+        let result = self.request_db_tree.fetch_and_update(key.to_lowercase(), |old| {
+            let mut requests : Vec<i64> = old.and_then(|bytes| serde_cbor::from_slice(bytes).ok()).unwrap_or_default();
+            requests.retain(|requested_at| now - requested_at < window);
+            allowed = requests.len() < max_requests;
+            if allowed {
+                requests.push(now);
+            }
+            serde_cbor::to_vec(&requests).ok()
+        });
+        result.is_ok() && allowed
+    }
+}
+
+#[test]
+fn test_register_request() {
+    let store = MagicLinkStore::new_db(UserConfig::new_config("", "person", 100000));
+    assert!(store.register_request("Jane@corp.be", 2, 60));
+    assert!(store.register_request("jane@corp.be", 2, 60));
+    assert!(!store.register_request("JANE@corp.be", 2, 60));
+    assert!(store.register_request("john@corp.be", 2, 60));
+    assert!(store.register_request("jane@corp.be", 2, 0));
+}
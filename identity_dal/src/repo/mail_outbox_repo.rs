@@ -0,0 +1,127 @@
+use crate::mail::outbox_mail::{ MailStatus, OutboxMail };
+use crate::err::IdentityError;
+use super::user_config::UserConfig;
+use sled::{ Db, Tree };
+
+/**
+ * Name of the sled tree that is the outbox of the mails.
+ */
+pub static MAIL_OUTBOX_TREE : &str = "mail_outbox";
+
+/**
+ * Mail outbox store represents the tree within the sled database where the mails wait to be sent. Mails are stored under their big endian id, so the outbox is ordered by when they were queued. Sent mails are removed since they can hold tokens, failed mails stay as dead letters.
+ */
+#[derive(Clone)]
+pub struct MailOutboxStore {
+    db : Db,
+    pub outbox_db_tree : Tree
+}
+
+impl MailOutboxStore {
+    /**
+     * Return the mail outbox tree on the database of the given config.
+     */
+    pub fn new_db(config : UserConfig) -> MailOutboxStore {
+        match config.get_db().open_tree(MAIL_OUTBOX_TREE) {
+            Ok(tree) => MailOutboxStore { db : config.get_db().clone(), outbox_db_tree : tree },
+            Err(_) => panic!("Could not open the tree {}", MAIL_OUTBOX_TREE)
+        }
+    }
+
+    /**
+     * Queues a mail in the outbox under a new monotonic id and returns it.
+     */
+    pub fn enqueue(&self, mut mail : OutboxMail) -> Result<OutboxMail, IdentityError> {
+        let id = self.db.generate_id().map_err(|_| IdentityError::CustomError("No id could be generated for the mail".to_owned()))?;
+        mail.set_id(id);
+        self.save_mail(&mail)?;
+        Ok(mail)
+    }
+
+    /**
+     * Stores a mail that has been queued, after an attempt or to retry it.
+     */
+    pub fn save_mail(&self, mail : &OutboxMail) -> Result<(), IdentityError> {
+        match self.outbox_db_tree.insert(mail.get_id().to_be_bytes(), mail) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError("Mail could not be stored in the outbox".to_owned()))
+        }
+    }
+
+    /**
+     * Returns the mail with the id.
+     */
+    pub fn get_mail(&self, id : u64) -> Option<OutboxMail> {
+        match self.outbox_db_tree.get(id.to_be_bytes()) {
+            Ok(Some(value)) => Some(OutboxMail::from(&value)),
+            _ => None
+        }
+    }
+
+    /**
+     * Removes the mail with the id.
+     */
+    pub fn remove_mail(&self, id : u64) -> Result<(), IdentityError> {
+        match self.outbox_db_tree.remove(id.to_be_bytes()) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError("Mail could not be removed from the outbox".to_owned()))
+        }
+    }
+
+    /**
+     * Returns the pending mails whose next attempt is due, the oldest first.
+     */
+    pub fn get_due_mails(&self, now : i64) -> Vec<OutboxMail> {
+        self.outbox_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .map(|(_, value)| OutboxMail::from(&value))
+        .filter(|mail| mail.is_due(now))
+        .collect()
+    }
+
+    /**
+     * Returns the mails with the status, or all mails without one, the newest first.
+     */
+    pub fn get_mails(&self, status : Option<&MailStatus>, limit : Option<usize>) -> Vec<OutboxMail> {
+        self.outbox_db_tree.iter()
+        .rev()
+        .filter_map(|entry| entry.ok())
+        .map(|(_, value)| OutboxMail::from(&value))
+        .filter(|mail| status.is_none_or(|status| mail.get_status() == status))
+        .take(limit.unwrap_or(usize::MAX))
+        .collect()
+    }
+
+    /**
+     * Removes the pending and failed mails to the recipient and returns how many were removed.
+     */
+    pub fn remove_mails_to(&self, recipient : &str) -> usize {
+        self.outbox_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|(_, value)| OutboxMail::from(value).get_content().recipient.eq_ignore_ascii_case(recipient))
+        .filter(|(key, _)| matches!(self.outbox_db_tree.remove(key), Ok(Some(_))))
+        .count()
+    }
+}
+
+#[test]
+fn test_mail_outbox() {
+    use crate::mail::outbox_mail::{ MailAttempt, MailContent };
+
+    let store = MailOutboxStore::new_db(UserConfig::new_config("", "person", 100000));
+    let content = |recipient : &str| MailContent { recipient : recipient.to_owned(), subject : "Welcome".to_owned(), ..MailContent::default() };
+    let first = store.enqueue(OutboxMail::new(content("jane@corp.be"))).unwrap();
+    let mut second = store.enqueue(OutboxMail::new(content("john@corp.be"))).unwrap();
+    assert!(first.get_id() < second.get_id());
+    second.record_attempt(MailAttempt::failure("connection refused"), 1, 60);
+    store.save_mail(&second).unwrap();
+
+    let now = chrono::Utc::now().timestamp();
+    assert_eq!(store.get_due_mails(now).iter().map(OutboxMail::get_id).collect::<Vec<u64>>(), vec![first.get_id()]);
+    assert_eq!(store.get_mails(Some(&MailStatus::Failed), None)[0].get_content().recipient, "john@corp.be");
+    assert_eq!(store.get_mails(None, Some(1))[0].get_id(), second.get_id());
+    store.remove_mail(first.get_id()).unwrap();
+    assert!(store.get_mail(first.get_id()).is_none());
+    assert_eq!(store.remove_mails_to("John@corp.be"), 1);
+    assert!(store.get_mail(second.get_id()).is_none());
+}
@@ -1,2 +1,19 @@
 pub mod user_repo;
-pub mod user_config;
\ No newline at end of file
+pub mod user_config;
+pub mod client_repo;
+pub mod code_repo;
+pub mod key_repo;
+pub mod token_repo;
+pub mod device_repo;
+pub mod federation_repo;
+pub mod saml_repo;
+pub mod ldap_repo;
+pub mod group_repo;
+pub mod scim_repo;
+pub mod magic_link_repo;
+pub mod invitation_repo;
+pub mod registration_repo;
+pub mod audit_repo;
+pub mod webhook_repo;
+pub mod change_feed_repo;
+pub mod mail_outbox_repo;
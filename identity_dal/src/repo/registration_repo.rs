@@ -0,0 +1,83 @@
+use crate::onboarding::pending_registration::PendingRegistration;
+use crate::traits::t_user::UserTrait;
+use crate::err::IdentityError;
+use super::user_config::UserConfig;
+use sled::Tree;
+
+/**
+ * Name of the sled tree in which the registrations that wait for approval are kept.
+ */
+pub static PENDING_REGISTRATION_TREE : &str = "pending_registration";
+
+/**
+ * Registration store represents the tree within the sled database where the registrations that wait for the approval of the admin are kept, they are kept under the id of their user.
+ */
+#[derive(Clone)]
+pub struct RegistrationStore {
+    pub registration_db_tree : Tree
+}
+
+impl RegistrationStore {
+    /**
+     * Return the registration tree on the database of the given config.
+     */
+    pub fn new_db(config : UserConfig) -> RegistrationStore {
+        match config.get_db().open_tree(PENDING_REGISTRATION_TREE) {
+            Ok(tree) => RegistrationStore{ registration_db_tree : tree },
+            Err(_) => panic!("Could not open the tree {}", PENDING_REGISTRATION_TREE)
+        }
+    }
+
+    /**
+     * Adds a registration, an error is returned when its email already waits for approval or its id is taken.
+     */
+    pub fn add_registration(&self, registration : &PendingRegistration) -> Result<(), IdentityError> {
+        if self.get_registration_by_email(registration.get_user().get_email()).is_some() {
+            return Err(IdentityError::EmailIsAwaitingApproval)
+        }
+        match self.registration_db_tree.compare_and_swap(registration.get_id(), None as Option<&[u8]>, Some(registration)) {
+            Ok(Ok(_)) => Ok(()),
+            _ => Err(IdentityError::IdIsAlreadyTaken)
+        }
+    }
+
+    /**
+     * Returns the registration with the id.
+     */
+    pub fn get_registration(&self, id : &str) -> Option<PendingRegistration> {
+        match self.registration_db_tree.get(id) {
+            Ok(Some(value)) => Some(PendingRegistration::from(&value)),
+            _ => None
+        }
+    }
+
+    /**
+     * Returns the registration of an email.
+     */
+    pub fn get_registration_by_email(&self, email : &str) -> Option<PendingRegistration> {
+        self.get_all_registrations().into_iter()
+            .find(|registration| registration.get_user().get_email().eq_ignore_ascii_case(email))
+    }
+
+    /**
+     * Returns all registrations, the oldest ones first.
+     */
+    pub fn get_all_registrations(&self) -> Vec<PendingRegistration> {
+        let mut registrations : Vec<PendingRegistration> = self.registration_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .map(|(_, value)| PendingRegistration::from(&value))
+        .collect();
+        registrations.sort_by_key(PendingRegistration::get_requested_at);
+        registrations
+    }
+
+    /**
+     * Removes the registration with the id and returns it, so it is decided on only once.
+     */
+    pub fn take_registration(&self, id : &str) -> Option<PendingRegistration> {
+        match self.registration_db_tree.remove(id) {
+            Ok(Some(value)) => Some(PendingRegistration::from(&value)),
+            _ => None
+        }
+    }
+}
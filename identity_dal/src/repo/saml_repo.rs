@@ -0,0 +1,66 @@
+use crate::saml::service_provider::ServiceProvider;
+use crate::err::IdentityError;
+use super::user_config::UserConfig;
+use sled::Tree;
+
+/**
+ * Name of the sled tree in which the registered SAML service providers are kept.
+ */
+pub static SERVICE_PROVIDER_TREE : &str = "saml_service_provider";
+
+/**
+ * Saml store represents the tree within the sled database where the SAML service providers are kept, they are kept under their entity id.
+ */
+#[derive(Clone)]
+pub struct SamlStore {
+    pub provider_db_tree : Tree
+}
+
+impl SamlStore {
+    /**
+     * Return the service provider tree on the database of the given config.
+     */
+    pub fn new_db(config : UserConfig) -> SamlStore {
+        match config.get_db().open_tree(SERVICE_PROVIDER_TREE) {
+            Ok(tree) => SamlStore{ provider_db_tree : tree },
+            Err(_) => panic!("Could not open the tree {}", SERVICE_PROVIDER_TREE)
+        }
+    }
+
+    /**
+     * Stores a service provider, a service provider with the same entity id is replaced so the metadata can be updated.
+     */
+    pub fn insert_service_provider(&self, provider : &ServiceProvider) -> Result<(), IdentityError> {
+        match self.provider_db_tree.insert(provider.get_entity_id(), provider) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError("The service provider could not be stored".to_owned()))
+        }
+    }
+
+    /**
+     * Returns the service provider with the entity id, if there is none a None is returned.
+     */
+    pub fn get_service_provider(&self, entity_id : &str) -> Option<ServiceProvider> {
+        match self.provider_db_tree.get(entity_id) {
+            Ok(Some(value)) => Some(ServiceProvider::from(&value)),
+            _ => None
+        }
+    }
+
+    /**
+     * Returns all registered service providers.
+     */
+    pub fn get_all_service_providers(&self) -> Vec<ServiceProvider> {
+        self.provider_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .map(|(_, value)| ServiceProvider::from(&value))
+        .collect()
+    }
+
+    /**
+     * Removes the service provider with the entity id, returns true if it existed.
+     */
+    pub fn remove_service_provider(&self, entity_id : &str) -> bool {
+        matches!(self.provider_db_tree.remove(entity_id), Ok(Some(_)))
+    }
+}
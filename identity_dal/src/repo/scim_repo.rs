@@ -0,0 +1,67 @@
+use crate::scim::scim_user::ScimUser;
+use crate::err::IdentityError;
+use super::user_config::UserConfig;
+use sled::Tree;
+
+/**
+ * Name of the sled tree in which the SCIM data of users is kept.
+ */
+pub static SCIM_USER_TREE : &str = "scim_user";
+
+/**
+ * Scim store represents the tree within the sled database where the SCIM data of users is kept, it is kept under the id of the user.
+ */
+#[derive(Clone)]
+pub struct ScimStore {
+    pub user_db_tree : Tree
+}
+
+impl ScimStore {
+    /**
+     * Return the SCIM tree on the database of the given config.
+     */
+    pub fn new_db(config : UserConfig) -> ScimStore {
+        match config.get_db().open_tree(SCIM_USER_TREE) {
+            Ok(tree) => ScimStore{ user_db_tree : tree },
+            Err(_) => panic!("Could not open the tree {}", SCIM_USER_TREE)
+        }
+    }
+
+    /**
+     * Stores the SCIM data of an user, the previous data is replaced.
+     */
+    pub fn insert_user(&self, user : &ScimUser) -> Result<(), IdentityError> {
+        match self.user_db_tree.insert(user.get_user_id(), user) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError("The SCIM data of the user could not be stored".to_owned()))
+        }
+    }
+
+    /**
+     * Returns the SCIM data of an user, None is returned when the user wasn't provisioned through SCIM.
+     */
+    pub fn get_user(&self, user_id : &str) -> Option<ScimUser> {
+        match self.user_db_tree.get(user_id) {
+            Ok(Some(value)) => Some(ScimUser::from(&value)),
+            _ => None
+        }
+    }
+
+    /**
+     * Returns the id of the user with the SCIM user name.
+     */
+    pub fn get_user_id_by_user_name(&self, user_name : &str) -> Option<String> {
+        self.user_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .map(|(_, value)| ScimUser::from(&value))
+        .find(|user| user.get_user_name().is_some_and(|name| name.eq_ignore_ascii_case(user_name)))
+        .map(|user| user.get_user_id().to_owned())
+    }
+
+    /**
+     * Removes the SCIM data of an user.
+     */
+    pub fn remove_user(&self, user_id : &str) -> bool {
+        matches!(self.user_db_tree.remove(user_id), Ok(Some(_)))
+    }
+}
@@ -0,0 +1,132 @@
+use crate::oauth::refresh_token::RefreshToken;
+use crate::err::IdentityError;
+use super::user_config::UserConfig;
+use chrono::Utc;
+use std::convert::TryInto;
+use sled::Tree;
+
+/**
+ * Name of the sled tree in which the refresh tokens are kept.
+ */
+pub static REFRESH_TOKEN_TREE : &str = "oauth_refresh_token";
+
+/**
+ * Name of the sled tree in which the revoked access tokens are kept until they expire.
+ */
+pub static REVOKED_TOKEN_TREE : &str = "oauth_revoked_token";
+
+/**
+ * Token store represents the trees within the sled database where the refresh tokens and the revoked access tokens are kept. Tokens are stored under a hash of them, so a leaked database doesn't contain usable tokens.
+ */
+#[derive(Clone)]
+pub struct TokenStore {
+    pub refresh_db_tree : Tree,
+    pub revoked_db_tree : Tree
+}
+
+impl TokenStore {
+    /**
+     * Return the token trees on the database of the given config.
+     */
+    pub fn new_db(config : UserConfig) -> TokenStore {
+        let open = |name : &str| match config.get_db().open_tree(name) {
+            Ok(tree) => tree,
+            Err(_) => panic!("Could not open the tree {}", name)
+        };
+        TokenStore {
+            refresh_db_tree : open(REFRESH_TOKEN_TREE),
+            revoked_db_tree : open(REVOKED_TOKEN_TREE)
+        }
+    }
+
+    /**
+     * Stores a refresh token under the hash of the token.
+     */
+    pub fn add_refresh_token(&self, token_hash : &str, token : &RefreshToken) -> Result<(), IdentityError> {
+        match self.refresh_db_tree.insert(token_hash, token) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError("Refresh token could not be stored".to_owned()))
+        }
+    }
+
+    /**
+     * Returns the refresh token with the given hash, expired tokens are never returned.
+     */
+    pub fn get_refresh_token(&self, token_hash : &str) -> Option<RefreshToken> {
+        match self.refresh_db_tree.get(token_hash) {
+            Ok(Some(value)) => Some(RefreshToken::from(&value)).filter(|token| !token.is_expired()),
+            _ => None
+        }
+    }
+
+    /**
+     * Removes the refresh token from the database and returns it, so a refresh token can only be used once. Expired tokens are also removed but never returned.
+     */
+    pub fn take_refresh_token(&self, token_hash : &str) -> Option<RefreshToken> {
+        match self.refresh_db_tree.remove(token_hash) {
+            Ok(Some(value)) => Some(RefreshToken::from(&value)).filter(|token| !token.is_expired()),
+            _ => None
+        }
+    }
+
+    /**
+     * Removes all refresh tokens of an user, returns how many were removed.
+     */
+    pub fn remove_refresh_tokens_of_user(&self, user_id : &str) -> usize {
+        self.refresh_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|(_, value)| RefreshToken::from(value).get_user_id() == user_id)
+        .filter(|(key, _)| matches!(self.refresh_db_tree.remove(key), Ok(Some(_))))
+        .count()
+    }
+
+    /**
+     * Marks an access token as revoked until it expires by itself.
+     */
+    pub fn revoke_access_token(&self, token_hash : &str, expires_at : i64) -> Result<(), IdentityError> {
+        match self.revoked_db_tree.insert(token_hash, &expires_at.to_be_bytes()) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError("Access token could not be revoked".to_owned()))
+        }
+    }
+
+    /**
+     * Returns true if the access token with the given hash has been revoked.
+     */
+    pub fn is_access_token_revoked(&self, token_hash : &str) -> bool {
+        matches!(self.revoked_db_tree.contains_key(token_hash), Ok(true))
+    }
+
+    /**
+     * Removes the expired refresh tokens and the revoked access tokens that have expired, returns how many were removed.
+     */
+    pub fn clean_expired_tokens(&self) -> usize {
+        let now = Utc::now().timestamp();
+        let refresh = self.refresh_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|(_, value)| RefreshToken::from(value).is_expired())
+        .filter(|(key, _)| matches!(self.refresh_db_tree.remove(key), Ok(Some(_))))
+        .count();
+        let revoked = self.revoked_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|(_, value)| value.as_ref().try_into().map(i64::from_be_bytes).unwrap_or(0) < now)
+        .filter(|(key, _)| matches!(self.revoked_db_tree.remove(key), Ok(Some(_))))
+        .count();
+        refresh + revoked
+    }
+}
+
+#[test]
+fn test_refresh_token_single_use() {
+    let store = TokenStore::new_db(UserConfig::new_config("","",100000));
+    let token = RefreshToken::new("client", "user", "openid", "authorization_code", 0, 60);
+    store.add_refresh_token("hash", &token).unwrap();
+    assert_eq!(store.get_refresh_token("hash"), Some(token.clone()));
+    assert_eq!(store.take_refresh_token("hash"), Some(token));
+    assert_eq!(store.take_refresh_token("hash"), None);
+
+    store.revoke_access_token("access", Utc::now().timestamp() - 1).unwrap();
+    assert!(store.is_access_token_revoked("access"));
+    assert_eq!(store.clean_expired_tokens(), 1);
+    assert!(!store.is_access_token_revoked("access"));
+}
@@ -2,6 +2,7 @@ use crate::traits::t_user_manager::UserStoreTrait;
 use crate::traits::t_user::UserTrait;
 use crate::traits::t_admin_manager::AdminStoreTrait;
 use crate::user::identity_user::IdentityUser;
+use crate::user::tombstone::Tombstone;
 use sled::Tree;
 use super::user_config::UserConfig;
 use crate::user::identity_user;
@@ -9,11 +10,17 @@ use crate::user::identity_user::RESERVED_ID;
 use crate::err::IdentityError;
 
 /**
- * User store represents a tree within a NO-SQL sled database, this will be the object through which user data will be solved.
+ * Suffix of the name of the sled tree in which the deleted users of a user tree are kept.
+ */
+pub static TOMBSTONE_TREE_SUFFIX : &str = "_tombstone";
+
+/**
+ * User store represents a tree within a NO-SQL sled database, this will be the object through which user data will be solved. Deleted users are moved to a tombstone tree, their id and email stay taken until they are purged.
  */
 #[derive(Clone)]
 pub struct UserStore {
-    pub user_db_tree : Tree
+    pub user_db_tree : Tree,
+    pub tombstone_db_tree : Tree
 }
 
 impl UserStore {
@@ -21,10 +28,66 @@ impl UserStore {
      * Return a new tree on a database. The tree is opened on a sled database through a given path and the tree name. If the path and tree are empty then a temporary database is created in memory.
      */
     pub fn new_db(config : UserConfig) -> UserStore {
-        match config.get_db().open_tree(&config.get_tree()) {
-            Ok(tree) => UserStore{ user_db_tree : tree },
-            Err(_) => panic!("Could not open the tree {}", &config.get_tree())
+        let open = |name : &str| match config.get_db().open_tree(name) {
+            Ok(tree) => tree,
+            Err(_) => panic!("Could not open the tree {}", name)
+        };
+        UserStore {
+            user_db_tree : open(&config.get_tree()),
+            tombstone_db_tree : open(&format!("{}{}", config.get_tree(), TOMBSTONE_TREE_SUFFIX))
+        }
+    }
+
+    /**
+     * Returns the deleted user with the id.
+     */
+    pub fn get_tombstone(&self, id : &str) -> Option<Tombstone> {
+        match self.tombstone_db_tree.get(id) {
+            Ok(Some(value)) => Some(Tombstone::from(&value)),
+            _ => None
+        }
+    }
+
+    /**
+     * Returns all deleted users that haven't been purged yet.
+     */
+    pub fn get_tombstones(&self) -> Vec<Tombstone> {
+        self.tombstone_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .map(|(_, value)| Tombstone::from(&value))
+        .collect()
+    }
+
+    /**
+     * Moves a deleted user back to the users.
+     */
+    pub fn restore_user(&self, id : &str) -> Result<IdentityUser, IdentityError> {
+        let tombstone = self.get_tombstone(id).ok_or(IdentityError::UserNotFound)?;
+        if self.user_db_tree.insert(id, tombstone.get_user()).is_err() {
+            return Err(IdentityError::UserCannotBeAdded)
         }
+        self.tombstone_db_tree.remove(id).map_err(|_| IdentityError::UserCannotBeUpdated)?;
+        Ok(tombstone.into_user())
+    }
+
+    /**
+     * Removes a deleted user for good and returns his tombstone, his id and email are free from then on.
+     */
+    pub fn purge_user(&self, id : &str) -> Option<Tombstone> {
+        match self.tombstone_db_tree.remove(id) {
+            Ok(Some(value)) => Some(Tombstone::from(&value)),
+            _ => None
+        }
+    }
+
+    /**
+     * Removes the deleted users whose grace period, in seconds, has passed and returns them.
+     */
+    pub fn purge_expired_tombstones(&self, grace_period : i64) -> Vec<Tombstone> {
+        self.get_tombstones().into_iter()
+        .filter(|tombstone| tombstone.is_expired(grace_period))
+        .filter_map(|tombstone| self.purge_user(tombstone.get_id()))
+        .collect()
     }
 }
 
@@ -124,22 +187,21 @@ impl UserStoreTrait<IdentityUser> for UserStore {
     }
 
     /**
-     * Returns a bool saying if an email is already taken in the database.
+     * Returns a bool saying if an email is already taken in the database, the email of a deleted user stays taken until he is purged.
      */
     fn is_email_taken(&self,email : &str) -> bool {
         self.user_db_tree
             .iter()
             .any(|ps| IdentityUser::from(&ps.unwrap().1).get_email() == email)
+        || self.get_tombstones().iter().any(|tombstone| tombstone.get_user().get_email() == email)
     }
 
     /**
-     * Returns a bool indicating if a id has been taken
+     * Returns a bool indicating if a id has been taken, also by a deleted user that hasn't been purged.
      */
     fn is_id_taken(&self, id : &str) -> bool {
-        match self.user_db_tree.contains_key(id) {
-            Ok(result) => result,
-            Err(_) => false
-        }
+        matches!(self.user_db_tree.contains_key(id), Ok(true))
+        || matches!(self.tombstone_db_tree.contains_key(id), Ok(true))
     }
     
     /**
@@ -172,6 +234,8 @@ impl UserStoreTrait<IdentityUser> for UserStore {
             old_user.set_hashed_password(user.get_hashed_password());
             old_user.set_security_stamp(user.get_security_stamp());
             old_user.set_flags(user.get_flags());
+            old_user.set_status(user.get_status().clone());
+            old_user.set_locale(user.get_locale());
             return Ok(
                 self.user_db_tree.insert(
                     &id,
@@ -183,7 +247,7 @@ impl UserStoreTrait<IdentityUser> for UserStore {
     }
     
     /**
-     * Deletes an user based on its id.
+     * Deletes an user based on its id, the user is moved to the tombstones so he can be restored until he is purged.
      * 
      * An error is thrown when the id is nothing or when its equal to the admin id.
      */
@@ -194,7 +258,15 @@ impl UserStoreTrait<IdentityUser> for UserStore {
         if id == RESERVED_ID {
             return Err(IdentityError::IdEqualsAdmin)
         }
-        Ok(self.user_db_tree.remove(id).unwrap().is_some())
+        match self.user_db_tree.remove(id) {
+            Ok(Some(value)) => {
+                let tombstone = Tombstone::new(IdentityUser::from(&value));
+                self.tombstone_db_tree.insert(id, &tombstone).map_err(|_| IdentityError::UserDeleteFailed)?;
+                Ok(true)
+            },
+            Ok(None) => Ok(false),
+            Err(_) => Err(IdentityError::UserDeleteFailed)
+        }
     }
     
     /**
@@ -260,9 +332,29 @@ fn test_update() {
 
     ps.set_email("michael@michael.be").unwrap();
     ps.set_password("michael@michael.be").unwrap();
+    ps.set_locale(Some(" nl-BE "));
     db.update_user(ps.get_id(), &ps.to_owned()).unwrap();
     ps = db.get_user_by_email("michael@michael.be").unwrap();
 
     assert_eq!(ps.get_email(),"michael@michael.be");
+    assert_eq!(ps.get_locale(), Some("nl-BE"));
     assert!(db.check_user_password("michael@michael.be", "michael@michael.be").unwrap());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_soft_delete() {
+    let db = UserStore::new_db(UserConfig::new_config("","",100000));
+
+    let ps = db.add_user(IdentityUser::new_user("michael@outlook.be","","hertsens").unwrap()).unwrap();
+    assert!(db.delete_user(ps.get_id()).unwrap());
+    assert!(db.get_user_by_uuid(ps.get_id()).is_none());
+    assert!(db.is_email_taken("michael@outlook.be"));
+    assert!(db.purge_expired_tombstones(60).is_empty());
+
+    assert_eq!(db.restore_user(ps.get_id()).unwrap().get_email(), "michael@outlook.be");
+    assert!(db.get_tombstones().is_empty());
+    db.delete_user(ps.get_id()).unwrap();
+    assert_eq!(db.purge_expired_tombstones(-1).len(), 1);
+    assert!(!db.is_email_taken("michael@outlook.be"));
+    assert!(db.restore_user(ps.get_id()).is_err());
+}
@@ -0,0 +1,187 @@
+use crate::webhook::webhook_delivery::WebhookDelivery;
+use crate::webhook::webhook_subscription::{ WebhookEventType, WebhookSubscription };
+use crate::err::IdentityError;
+use super::user_config::UserConfig;
+use sled::{ Db, Tree };
+
+/**
+ * Name of the sled tree in which the webhook subscriptions are kept.
+ */
+pub static WEBHOOK_SUBSCRIPTION_TREE : &str = "webhook_subscription";
+
+/**
+ * Name of the sled tree that is the outbox of the webhook deliveries.
+ */
+pub static WEBHOOK_OUTBOX_TREE : &str = "webhook_outbox";
+
+/**
+ * Webhook store represents the trees within the sled database where the webhook subscriptions and the outbox of their deliveries are kept. Deliveries are stored under their big endian id, so the outbox is ordered by when they were queued. Delivered and failed deliveries stay in the outbox as the delivery log.
+ */
+#[derive(Clone)]
+pub struct WebhookStore {
+    db : Db,
+    pub subscription_db_tree : Tree,
+    pub outbox_db_tree : Tree
+}
+
+impl WebhookStore {
+    /**
+     * Return the webhook trees on the database of the given config.
+     */
+    pub fn new_db(config : UserConfig) -> WebhookStore {
+        let open = |name : &str| match config.get_db().open_tree(name) {
+            Ok(tree) => tree,
+            Err(_) => panic!("Could not open the tree {}", name)
+        };
+        WebhookStore {
+            db : config.get_db().clone(),
+            subscription_db_tree : open(WEBHOOK_SUBSCRIPTION_TREE),
+            outbox_db_tree : open(WEBHOOK_OUTBOX_TREE)
+        }
+    }
+
+    /**
+     * Stores a subscription, a stored subscription is replaced.
+     */
+    pub fn save_subscription(&self, subscription : &WebhookSubscription) -> Result<(), IdentityError> {
+        match self.subscription_db_tree.insert(subscription.get_id(), subscription) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError("Webhook subscription could not be stored".to_owned()))
+        }
+    }
+
+    /**
+     * Returns the subscription with the id.
+     */
+    pub fn get_subscription(&self, id : &str) -> Option<WebhookSubscription> {
+        match self.subscription_db_tree.get(id) {
+            Ok(Some(value)) => Some(WebhookSubscription::from(&value)),
+            _ => None
+        }
+    }
+
+    /**
+     * Returns all subscriptions, the oldest first.
+     */
+    pub fn get_all_subscriptions(&self) -> Vec<WebhookSubscription> {
+        let mut subscriptions : Vec<WebhookSubscription> = self.subscription_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .map(|(_, value)| WebhookSubscription::from(&value))
+        .collect();
+        subscriptions.sort_by_key(WebhookSubscription::get_created_at);
+        subscriptions
+    }
+
+    /**
+     * Returns the active subscriptions to the event.
+     */
+    pub fn get_subscriptions_of_event(&self, event_type : WebhookEventType) -> Vec<WebhookSubscription> {
+        self.get_all_subscriptions().into_iter()
+            .filter(|subscription| subscription.is_subscribed_to(event_type))
+            .collect()
+    }
+
+    /**
+     * Removes a subscription together with its deliveries and returns it.
+     */
+    pub fn remove_subscription(&self, id : &str) -> Option<WebhookSubscription> {
+        let subscription = match self.subscription_db_tree.remove(id) {
+            Ok(Some(value)) => WebhookSubscription::from(&value),
+            _ => return None
+        };
+        for delivery in self.get_deliveries_of_subscription(id, None) {
+            let _ = self.outbox_db_tree.remove(delivery.get_id().to_be_bytes());
+        }
+        Some(subscription)
+    }
+
+    /**
+     * Queues a delivery in the outbox under a new monotonic id and returns it.
+     */
+    pub fn enqueue(&self, mut delivery : WebhookDelivery) -> Result<WebhookDelivery, IdentityError> {
+        let id = self.db.generate_id().map_err(|_| IdentityError::CustomError("No id could be generated for the webhook delivery".to_owned()))?;
+        delivery.set_id(id);
+        self.save_delivery(&delivery)?;
+        Ok(delivery)
+    }
+
+    /**
+     * Stores a delivery that has been queued, after an attempt or to redeliver it.
+     */
+    pub fn save_delivery(&self, delivery : &WebhookDelivery) -> Result<(), IdentityError> {
+        match self.outbox_db_tree.insert(delivery.get_id().to_be_bytes(), delivery) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(IdentityError::CustomError("Webhook delivery could not be stored".to_owned()))
+        }
+    }
+
+    /**
+     * Returns the delivery with the id.
+     */
+    pub fn get_delivery(&self, id : u64) -> Option<WebhookDelivery> {
+        match self.outbox_db_tree.get(id.to_be_bytes()) {
+            Ok(Some(value)) => Some(WebhookDelivery::from(&value)),
+            _ => None
+        }
+    }
+
+    /**
+     * Returns the pending deliveries whose next attempt is due, the oldest first.
+     */
+    pub fn get_due_deliveries(&self, now : i64) -> Vec<WebhookDelivery> {
+        self.outbox_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .map(|(_, value)| WebhookDelivery::from(&value))
+        .filter(|delivery| delivery.is_due(now))
+        .collect()
+    }
+
+    /**
+     * Returns the deliveries of a subscription, the newest first.
+     */
+    pub fn get_deliveries_of_subscription(&self, subscription_id : &str, limit : Option<usize>) -> Vec<WebhookDelivery> {
+        self.outbox_db_tree.iter()
+        .rev()
+        .filter_map(|entry| entry.ok())
+        .map(|(_, value)| WebhookDelivery::from(&value))
+        .filter(|delivery| delivery.get_subscription_id() == subscription_id)
+        .take(limit.unwrap_or(usize::MAX))
+        .collect()
+    }
+
+    /**
+     * Removes the deliveries of the events about the user, whatever their status, and returns how many were removed. Their payload holds the data of the user.
+     */
+    pub fn remove_deliveries_of_user(&self, user_id : &str) -> usize {
+        self.outbox_db_tree.iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|(_, value)| WebhookDelivery::from(value).get_user_id() == Some(user_id))
+        .filter(|(key, _)| matches!(self.outbox_db_tree.remove(key), Ok(Some(_))))
+        .count()
+    }
+}
+
+#[test]
+fn test_webhook_outbox() {
+    use crate::webhook::webhook_delivery::DeliveryAttempt;
+
+    let store = WebhookStore::new_db(UserConfig::new_config("", "person", 100000));
+    let events = [WebhookEventType::UserRegistered, WebhookEventType::UserDeleted].iter().copied().collect();
+    store.save_subscription(&WebhookSubscription::new("1", "http://localhost/hook", "secret", events, "ADMIN")).unwrap();
+    assert_eq!(store.get_subscriptions_of_event(WebhookEventType::UserDeleted).len(), 1);
+    assert!(store.get_subscriptions_of_event(WebhookEventType::EmailChanged).is_empty());
+
+    let first = store.enqueue(WebhookDelivery::new("1", WebhookEventType::UserRegistered, "{}")).unwrap();
+    let mut second = store.enqueue(WebhookDelivery::new("1", WebhookEventType::UserDeleted, "{}").for_user("jane")).unwrap();
+    assert!(first.get_id() < second.get_id());
+    second.record_attempt(DeliveryAttempt::response(500), 5, 60);
+    store.save_delivery(&second).unwrap();
+    let now = chrono::Utc::now().timestamp();
+    assert_eq!(store.get_due_deliveries(now).iter().map(WebhookDelivery::get_id).collect::<Vec<u64>>(), vec![first.get_id()]);
+    assert_eq!(store.get_deliveries_of_subscription("1", Some(1))[0].get_id(), second.get_id());
+    assert_eq!(store.remove_deliveries_of_user("jane"), 1);
+    assert!(store.get_delivery(second.get_id()).is_none());
+
+    assert!(store.remove_subscription("1").is_some());
+    assert!(store.get_delivery(first.get_id()).is_none());
+}
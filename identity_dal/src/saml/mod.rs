@@ -0,0 +1 @@
+pub mod service_provider;
\ No newline at end of file
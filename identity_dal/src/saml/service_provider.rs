@@ -0,0 +1,136 @@
+use serde::{Serialize, Deserialize};
+use std::convert::From;
+use chrono::Utc;
+
+/**
+ * Binding of SAML messages that are posted in a html form.
+ */
+pub static BINDING_HTTP_POST : &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST";
+
+/**
+ * Binding of SAML messages that are deflated and put in the query of a redirect.
+ */
+pub static BINDING_HTTP_REDIRECT : &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect";
+
+/**
+ * NameID format in which the email of the user is sent.
+ */
+pub static NAME_ID_EMAIL : &str = "urn:oasis:names:tc:SAML:1.1:nameid-format:emailAddress";
+
+/**
+ * NameID format in which the id of the user is sent, it stays the same when the user changes his email.
+ */
+pub static NAME_ID_PERSISTENT : &str = "urn:oasis:names:tc:SAML:2.0:nameid-format:persistent";
+
+/**
+ * Endpoint of a service provider to which the assertions are sent.
+ *
+ * Attributes:
+ * * binding: binding the endpoint accepts
+ * * location: url of the endpoint
+ * * is_default: whether the service provider marked the endpoint as the default one
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AssertionConsumerService {
+    binding : String,
+    location : String,
+    is_default : bool
+}
+
+impl AssertionConsumerService {
+    pub fn new(binding : &str, location : &str, is_default : bool) -> Self {
+        AssertionConsumerService {
+            binding : binding.to_owned(),
+            location : location.to_owned(),
+            is_default
+        }
+    }
+
+    pub fn get_binding(&self) -> &str { &self.binding }
+
+    pub fn get_location(&self) -> &str { &self.location }
+
+    pub fn is_default(&self) -> bool { self.is_default }
+}
+
+/**
+ * ServiceProvider is an application that lets its users log in through SAML 2.0 with this server as identity provider.
+ *
+ * Attributes:
+ * * entity_id: unique name of the service provider, it is the issuer of its authentication requests
+ * * assertion_consumer_services: endpoints to which the assertions can be sent
+ * * name_id_format: format of the NameID in the assertions
+ * * created_at: unix timestamp of when the service provider was registered
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ServiceProvider {
+    entity_id : String,
+    assertion_consumer_services : Vec<AssertionConsumerService>,
+    name_id_format : String,
+    created_at : i64
+}
+
+impl From<&sled::IVec> for ServiceProvider {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to a ServiceProvider struct.")
+    }
+}
+
+impl From<&ServiceProvider> for sled::IVec {
+    fn from(item : &ServiceProvider) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert ServiceProvider struct to bytes"))
+    }
+}
+
+impl ServiceProvider {
+    /**
+     * Makes a service provider, when the NameID format isn't the persistent one the email of the user is used.
+     */
+    pub fn new(entity_id : &str, assertion_consumer_services : Vec<AssertionConsumerService>, name_id_format : Option<&str>) -> Self {
+        ServiceProvider {
+            entity_id : entity_id.to_owned(),
+            assertion_consumer_services,
+            name_id_format : match name_id_format {
+                Some(format) if format == NAME_ID_PERSISTENT => NAME_ID_PERSISTENT.to_owned(),
+                _ => NAME_ID_EMAIL.to_owned()
+            },
+            created_at : Utc::now().timestamp()
+        }
+    }
+
+    pub fn get_entity_id(&self) -> &str { &self.entity_id }
+
+    pub fn get_assertion_consumer_services(&self) -> &[AssertionConsumerService] { &self.assertion_consumer_services }
+
+    pub fn get_name_id_format(&self) -> &str { &self.name_id_format }
+
+    pub fn get_created_at(&self) -> i64 { self.created_at }
+
+    /**
+     * Returns the HTTP-POST endpoint the assertion has to be sent to. When a location is requested it has to be one of the registered endpoints, otherwise the default endpoint or else the first one is used.
+     */
+    pub fn get_assertion_consumer_service(&self, location : Option<&str>) -> Option<&AssertionConsumerService> {
+        let mut services = self.assertion_consumer_services.iter().filter(|service| service.binding == BINDING_HTTP_POST);
+        match location {
+            Some(location) => services.find(|service| service.location == location),
+            None => {
+                let services : Vec<&AssertionConsumerService> = services.collect();
+                services.iter().find(|service| service.is_default).or_else(|| services.first()).copied()
+            }
+        }
+    }
+}
+
+#[test]
+fn test_assertion_consumer_service() {
+    let provider = ServiceProvider::new("https://sp.example.com", vec![
+        AssertionConsumerService::new(BINDING_HTTP_REDIRECT, "https://sp.example.com/redirect", true),
+        AssertionConsumerService::new(BINDING_HTTP_POST, "https://sp.example.com/first", false),
+        AssertionConsumerService::new(BINDING_HTTP_POST, "https://sp.example.com/second", true)
+    ], None);
+    assert_eq!(provider.get_name_id_format(), NAME_ID_EMAIL);
+    assert_eq!(provider.get_assertion_consumer_service(None).unwrap().get_location(), "https://sp.example.com/second");
+    assert_eq!(provider.get_assertion_consumer_service(Some("https://sp.example.com/first")).unwrap().get_location(), "https://sp.example.com/first");
+    assert!(provider.get_assertion_consumer_service(Some("https://sp.example.com/redirect")).is_none());
+    assert!(provider.get_assertion_consumer_service(Some("https://evil.example.com")).is_none());
+}
@@ -0,0 +1 @@
+pub mod scim_user;
\ No newline at end of file
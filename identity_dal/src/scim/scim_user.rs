@@ -0,0 +1,66 @@
+use serde::{Serialize, Deserialize};
+use std::convert::From;
+use chrono::Utc;
+
+/**
+ * ScimUser keeps what the SCIM API knows of an user besides the user itself.
+ *
+ * Attributes:
+ * * user_id: id of the user
+ * * user_name: unique name the provisioning system gave the user, without one the email is his name
+ * * external_id: id the user has in the system that provisions him
+ * * created_at: unix timestamp of when the user was provisioned
+ * * updated_at: unix timestamp of the last change through the SCIM API
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScimUser {
+    user_id : String,
+    user_name : Option<String>,
+    external_id : Option<String>,
+    created_at : i64,
+    updated_at : i64
+}
+
+impl From<&sled::IVec> for ScimUser {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to a ScimUser struct.")
+    }
+}
+
+impl From<&ScimUser> for sled::IVec {
+    fn from(item : &ScimUser) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert ScimUser struct to bytes"))
+    }
+}
+
+impl ScimUser {
+    pub fn new(user_id : &str, user_name : Option<&str>, external_id : Option<&str>) -> Self {
+        let now = Utc::now().timestamp();
+        ScimUser {
+            user_id : user_id.to_owned(),
+            user_name : user_name.map(str::to_owned),
+            external_id : external_id.map(str::to_owned),
+            created_at : now,
+            updated_at : now
+        }
+    }
+
+    pub fn get_user_id(&self) -> &str { &self.user_id }
+
+    pub fn get_user_name(&self) -> Option<&str> { self.user_name.as_deref() }
+
+    pub fn get_external_id(&self) -> Option<&str> { self.external_id.as_deref() }
+
+    pub fn get_created_at(&self) -> i64 { self.created_at }
+
+    pub fn get_updated_at(&self) -> i64 { self.updated_at }
+
+    /**
+     * Sets the user name and external id and remembers the user has been changed.
+     */
+    pub fn update(&mut self, user_name : Option<&str>, external_id : Option<&str>) {
+        self.user_name = user_name.map(str::to_owned);
+        self.external_id = external_id.map(str::to_owned);
+        self.updated_at = Utc::now().timestamp();
+    }
+}
@@ -5,6 +5,7 @@ use argon2::Config;
 use crate::err::IdentityError;
 use crate::util::get_hash;
 use std::collections::BTreeSet;
+use super::user_status::UserStatus;
 
 //Reserved id that is used only for the admin.
 pub static RESERVED_ID : &str = "ADMIN";
@@ -20,6 +21,8 @@ pub static RESERVED_ID : &str = "ADMIN";
  * * first_name
  * * last_name
  * * flags: these are the attributes that a user can have can be both claims and roles.
+ * * status: lifecycle status of the user, users that were stored before it existed are active
+ * * locale: locale the mails to the user are written in, the default locale when he has none
  */
 #[derive(Serialize, Deserialize, Debug,Clone,PartialEq,PartialOrd,Eq,Hash)]
 pub struct IdentityUser {
@@ -28,7 +31,11 @@ pub struct IdentityUser {
     user_name : String,
     hashed_password : String,
     security_stamp : String,
-    flags : BTreeSet<String>
+    flags : BTreeSet<String>,
+    #[serde(default)]
+    status : UserStatus,
+    #[serde(default)]
+    locale : Option<String>
 }
 
 impl From<&sled::IVec> for IdentityUser {
@@ -50,6 +57,30 @@ impl IdentityUser {
     pub fn is_pwd_empty(&self) -> bool {
         self.hashed_password.is_empty() && self.security_stamp.is_empty()
     }
+
+    pub fn get_status(&self) -> &UserStatus { &self.status }
+
+    pub fn set_status(&mut self, status : UserStatus) {
+        self.status = status;
+    }
+
+    pub fn is_active(&self) -> bool { self.status.is_active() }
+
+    pub fn get_locale(&self) -> Option<&str> { self.locale.as_deref() }
+
+    /**
+     * Sets the locale of the user, an empty locale is the same as none.
+     */
+    pub fn set_locale(&mut self, locale : Option<&str>) {
+        self.locale = locale.map(str::trim).filter(|locale| !locale.is_empty()).map(str::to_owned);
+    }
+
+    /**
+     * Returns an error when the user isn't active, so he can't log in or use his tokens.
+     */
+    pub fn control_status(&self) -> Result<(), IdentityError> {
+        self.status.control()
+    }
 }
 
 impl UserTrait for IdentityUser {
@@ -100,7 +131,9 @@ impl UserTrait for IdentityUser {
             hashed_password : hashed_pwd,
             security_stamp : hash,
             user_name : "".to_owned(),
-            flags : BTreeSet::default()
+            flags : BTreeSet::default(),
+            status : UserStatus::default(),
+            locale : None
         })
     }
 
@@ -135,7 +168,9 @@ impl UserTrait for IdentityUser {
             hashed_password : hashed_pwd,
             security_stamp : hash,
             user_name : user_name.to_owned(),
-            flags : BTreeSet::default()
+            flags : BTreeSet::default(),
+            status : UserStatus::default(),
+            locale : None
         })
     }
     
@@ -171,7 +206,9 @@ impl UserTrait for IdentityUser {
             hashed_password : hashed_pwd,
             security_stamp : hash,
             user_name : user_name.to_owned(),
-            flags : BTreeSet::default()
+            flags : BTreeSet::default(),
+            status : UserStatus::default(),
+            locale : None
         })
     }
 
@@ -179,10 +216,10 @@ impl UserTrait for IdentityUser {
      * Checks if given password is equal to the person's password
      **/
     fn check_pwd(&self, pwd : &str) -> bool {
-        if pwd.is_empty() {
+        if pwd.is_empty() || self.is_pwd_empty() {
             return false
         }
-        argon2::verify_encoded(&self.hashed_password, pwd.as_bytes()).unwrap()
+        argon2::verify_encoded(&self.hashed_password, pwd.as_bytes()).unwrap_or(false)
     }
 
     /**
@@ -1 +1,4 @@
-pub mod identity_user;
\ No newline at end of file
+pub mod identity_user;
+pub mod user_status;
+pub mod tombstone;
+pub mod user_change;
@@ -0,0 +1,54 @@
+use serde::{Serialize, Deserialize};
+use std::convert::From;
+use chrono::Utc;
+use crate::user::identity_user::IdentityUser;
+use crate::traits::t_user::UserTrait;
+
+/**
+ * Tombstone is a deleted user that is kept until the grace period ends, until then the user can be restored and his email stays taken.
+ *
+ * Attributes:
+ * * user: user as he was when he was deleted
+ * * deleted_at: unix timestamp of when the user was deleted
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Tombstone {
+    user : IdentityUser,
+    deleted_at : i64
+}
+
+impl From<&sled::IVec> for Tombstone {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to a Tombstone struct.")
+    }
+}
+
+impl From<&Tombstone> for sled::IVec {
+    fn from(item : &Tombstone) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert Tombstone struct to bytes"))
+    }
+}
+
+impl Tombstone {
+    pub fn new(user : IdentityUser) -> Self {
+        Tombstone {
+            user,
+            deleted_at : Utc::now().timestamp()
+        }
+    }
+
+    pub fn get_id(&self) -> &str { self.user.get_id() }
+
+    pub fn get_user(&self) -> &IdentityUser { &self.user }
+
+    pub fn get_deleted_at(&self) -> i64 { self.deleted_at }
+
+    /**
+     * Returns true when the grace period, in seconds, has passed since the user was deleted.
+     */
+    pub fn is_expired(&self, grace_period : i64) -> bool {
+        Utc::now().timestamp() > self.deleted_at + grace_period
+    }
+
+    pub fn into_user(self) -> IdentityUser { self.user }
+}
@@ -0,0 +1,73 @@
+use serde::{Serialize, Deserialize};
+use std::convert::From;
+use chrono::Utc;
+
+/**
+ * Kind of change to an user record. A restored user is created again.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UserChangeKind {
+    Created,
+    Updated,
+    Deleted
+}
+
+impl UserChangeKind {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            UserChangeKind::Created => "created",
+            UserChangeKind::Updated => "updated",
+            UserChangeKind::Deleted => "deleted"
+        }
+    }
+}
+
+/**
+ * User change is an entry of the change feed of the user records.
+ *
+ * Attributes:
+ * * sequence: monotonic number of the change, given by the store. A consumer resumes the feed after the last sequence it has seen
+ * * kind: whether the user was created, updated or deleted
+ * * user_id: id of the user that changed
+ * * changed_at: unix timestamp of when the change was recorded
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UserChange {
+    sequence : u64,
+    kind : UserChangeKind,
+    user_id : String,
+    changed_at : i64
+}
+
+impl From<&sled::IVec> for UserChange {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to an UserChange struct.")
+    }
+}
+
+impl From<&UserChange> for sled::IVec {
+    fn from(item : &UserChange) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert UserChange struct to bytes"))
+    }
+}
+
+impl UserChange {
+    pub fn new(kind : UserChangeKind, user_id : &str) -> Self {
+        UserChange {
+            sequence : 0,
+            kind,
+            user_id : user_id.to_owned(),
+            changed_at : Utc::now().timestamp()
+        }
+    }
+
+    pub fn get_sequence(&self) -> u64 { self.sequence }
+
+    pub(crate) fn set_sequence(&mut self, sequence : u64) { self.sequence = sequence; }
+
+    pub fn get_kind(&self) -> UserChangeKind { self.kind }
+
+    pub fn get_user_id(&self) -> &str { &self.user_id }
+
+    pub fn get_changed_at(&self) -> i64 { self.changed_at }
+}
@@ -0,0 +1,105 @@
+use serde::{Serialize, Deserialize};
+use chrono::{Duration, Utc};
+use crate::err::IdentityError;
+
+/**
+ * Lifecycle status of an user, only an active user can log in and use his tokens.
+ *
+ * * Active: the user can use his account
+ * * Suspended: the admin or the provisioning system stopped the account, only the admin can reactivate it
+ * * Deactivated: the user stopped his own account, he can undo this himself until the grace period ends
+ * * Pending: the account waits for something before it can be used, like a verification
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, PartialOrd, Eq, Hash, Default)]
+pub enum UserStatus {
+    #[default]
+    Active,
+    Suspended { reason : String, suspended_at : i64 },
+    Deactivated { reason : Option<String>, deactivated_at : i64, restorable_until : i64 },
+    Pending { reason : String, since : i64 }
+}
+
+impl UserStatus {
+    pub fn suspended(reason : &str) -> Self {
+        UserStatus::Suspended { reason : reason.to_owned(), suspended_at : Utc::now().timestamp() }
+    }
+
+    /**
+     * Returns the status of an user that deactivated his account, he can undo it for the given amount of seconds.
+     */
+    pub fn deactivated(reason : Option<&str>, grace_period : i64) -> Self {
+        let now = Utc::now();
+        UserStatus::Deactivated {
+            reason : reason.map(str::to_owned),
+            deactivated_at : now.timestamp(),
+            restorable_until : (now + Duration::seconds(grace_period)).timestamp()
+        }
+    }
+
+    pub fn pending(reason : &str) -> Self {
+        UserStatus::Pending { reason : reason.to_owned(), since : Utc::now().timestamp() }
+    }
+
+    /**
+     * Returns the name of the status: active, suspended, deactivated or pending.
+     */
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            UserStatus::Active => "active",
+            UserStatus::Suspended { .. } => "suspended",
+            UserStatus::Deactivated { .. } => "deactivated",
+            UserStatus::Pending { .. } => "pending"
+        }
+    }
+
+    pub fn get_reason(&self) -> Option<&str> {
+        match self {
+            UserStatus::Active => None,
+            UserStatus::Suspended { reason, .. } | UserStatus::Pending { reason, .. } => Some(reason),
+            UserStatus::Deactivated { reason, .. } => reason.as_deref()
+        }
+    }
+
+    /**
+     * Returns the unix timestamp of when the status started, None for an active user.
+     */
+    pub fn get_since(&self) -> Option<i64> {
+        match self {
+            UserStatus::Active => None,
+            UserStatus::Suspended { suspended_at, .. } => Some(*suspended_at),
+            UserStatus::Deactivated { deactivated_at, .. } => Some(*deactivated_at),
+            UserStatus::Pending { since, .. } => Some(*since)
+        }
+    }
+
+    pub fn is_active(&self) -> bool { *self == UserStatus::Active }
+
+    /**
+     * Returns true when the user deactivated his account himself and the grace period hasn't ended.
+     */
+    pub fn is_restorable(&self) -> bool {
+        matches!(self, UserStatus::Deactivated { restorable_until, .. } if Utc::now().timestamp() <= *restorable_until)
+    }
+
+    /**
+     * Returns an error that tells why the account can't be used when the user isn't active.
+     */
+    pub fn control(&self) -> Result<(), IdentityError> {
+        match self {
+            UserStatus::Active => Ok(()),
+            UserStatus::Suspended { .. } => Err(IdentityError::UserIsSuspended),
+            UserStatus::Deactivated { .. } => Err(IdentityError::UserIsDeactivated),
+            UserStatus::Pending { .. } => Err(IdentityError::UserIsPending)
+        }
+    }
+}
+
+#[test]
+fn test_user_status() {
+    assert!(UserStatus::default().control().is_ok());
+    assert!(matches!(UserStatus::suspended("fraud").control(), Err(IdentityError::UserIsSuspended)));
+    assert_eq!(UserStatus::suspended("fraud").get_reason(), Some("fraud"));
+    assert!(UserStatus::deactivated(None, 60).is_restorable());
+    assert!(!UserStatus::deactivated(None, -1).is_restorable());
+    assert!(!UserStatus::suspended("fraud").is_restorable());
+}
@@ -15,4 +15,13 @@ static HEXA_ALPHABET : [char;16] = ['1', '2', '3', '4', '5', '6', '7', '8', '9',
  */
 pub fn get_hash(amount : usize) -> String {
     (0..amount).map(|_| HEXA_ALPHABET[thread_rng().gen_range(0, HEXA_ALPHABET.len())] as char ).collect()
-}
\ No newline at end of file
+}
+
+static USER_CODE_ALPHABET : [char;20] = ['B', 'C', 'D', 'F', 'G', 'H', 'J', 'K', 'L', 'M', 'N', 'P', 'Q', 'R', 'S', 'T', 'V', 'W', 'X', 'Z'];
+
+/**
+ * Returns a code of the given length made off consonants, so it can't spell words and is easy to type over.
+ */
+pub fn get_user_code(amount : usize) -> String {
+    (0..amount).map(|_| USER_CODE_ALPHABET[thread_rng().gen_range(0, USER_CODE_ALPHABET.len())]).collect()
+}
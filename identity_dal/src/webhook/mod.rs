@@ -0,0 +1,2 @@
+pub mod webhook_subscription;
+pub mod webhook_delivery;
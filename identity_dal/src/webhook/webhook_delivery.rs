@@ -0,0 +1,190 @@
+use serde::{Serialize, Deserialize};
+use std::convert::From;
+use chrono::Utc;
+use super::webhook_subscription::WebhookEventType;
+
+/**
+ * State of a delivery in the outbox. A pending delivery is tried again until it is delivered or it has used all its attempts, then it has failed.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed
+}
+
+/**
+ * One attempt to post a delivery, the status code is None when no response was received.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryAttempt {
+    attempted_at : i64,
+    status_code : Option<u16>,
+    error : Option<String>
+}
+
+impl DeliveryAttempt {
+    /**
+     * Returns an attempt that got a response with the status code.
+     */
+    pub fn response(status_code : u16) -> Self {
+        DeliveryAttempt {
+            attempted_at : Utc::now().timestamp(),
+            status_code : Some(status_code),
+            error : if (200..300).contains(&status_code) { None } else { Some(format!("the response has status {}", status_code)) }
+        }
+    }
+
+    /**
+     * Returns an attempt that failed without a response.
+     */
+    pub fn failure(error : &str) -> Self {
+        DeliveryAttempt { attempted_at : Utc::now().timestamp(), status_code : None, error : Some(error.to_owned()) }
+    }
+
+    pub fn get_attempted_at(&self) -> i64 { self.attempted_at }
+
+    pub fn get_status_code(&self) -> Option<u16> { self.status_code }
+
+    pub fn get_error(&self) -> Option<&str> { self.error.as_deref() }
+
+    pub fn is_success(&self) -> bool { self.error.is_none() }
+}
+
+/**
+ * Webhook delivery is an event waiting in the outbox to be posted to one subscription, it keeps a log of every attempt.
+ *
+ * Attributes:
+ * * id: monotonic id, given by the store when the delivery is queued
+ * * subscription_id: id of the subscription the event is posted to
+ * * event_type: type of the event
+ * * payload: the JSON body that is posted, the same body is posted on every attempt
+ * * user_id: user the event is about, his deliveries are removed when he is purged
+ * * status: pending until it is delivered or has failed
+ * * attempts: log of the attempts, the oldest first
+ * * next_attempt_at: unix timestamp from which a pending delivery is tried again
+ * * created_at: unix timestamp of when the delivery was queued
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookDelivery {
+    id : u64,
+    subscription_id : String,
+    event_type : WebhookEventType,
+    payload : String,
+    #[serde(default)]
+    user_id : Option<String>,
+    status : DeliveryStatus,
+    attempts : Vec<DeliveryAttempt>,
+    next_attempt_at : i64,
+    created_at : i64
+}
+
+impl From<&sled::IVec> for WebhookDelivery {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to a WebhookDelivery struct.")
+    }
+}
+
+impl From<&WebhookDelivery> for sled::IVec {
+    fn from(item : &WebhookDelivery) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert WebhookDelivery struct to bytes"))
+    }
+}
+
+impl WebhookDelivery {
+    /**
+     * Returns a new pending delivery that is due right away.
+     */
+    pub fn new(subscription_id : &str, event_type : WebhookEventType, payload : &str) -> Self {
+        let now = Utc::now().timestamp();
+        WebhookDelivery {
+            id : 0,
+            subscription_id : subscription_id.to_owned(),
+            event_type,
+            payload : payload.to_owned(),
+            user_id : None,
+            status : DeliveryStatus::Pending,
+            attempts : Vec::new(),
+            next_attempt_at : now,
+            created_at : now
+        }
+    }
+
+    /**
+     * Marks the delivery as one of an event about the user.
+     */
+    pub fn for_user(mut self, user_id : &str) -> Self {
+        self.user_id = Some(user_id.to_owned());
+        self
+    }
+
+    pub fn get_id(&self) -> u64 { self.id }
+
+    pub(crate) fn set_id(&mut self, id : u64) { self.id = id; }
+
+    pub fn get_user_id(&self) -> Option<&str> { self.user_id.as_deref() }
+
+    pub fn get_subscription_id(&self) -> &str { &self.subscription_id }
+
+    pub fn get_event_type(&self) -> WebhookEventType { self.event_type }
+
+    pub fn get_payload(&self) -> &str { &self.payload }
+
+    pub fn get_status(&self) -> &DeliveryStatus { &self.status }
+
+    pub fn get_attempts(&self) -> &[DeliveryAttempt] { &self.attempts }
+
+    pub fn get_next_attempt_at(&self) -> i64 { self.next_attempt_at }
+
+    pub fn get_created_at(&self) -> i64 { self.created_at }
+
+    /**
+     * Returns true if the delivery is pending and its next attempt is due.
+     */
+    pub fn is_due(&self, now : i64) -> bool {
+        self.status == DeliveryStatus::Pending && self.next_attempt_at <= now
+    }
+
+    /**
+     * Logs an attempt. A failed attempt is tried again after a delay that doubles with every attempt, starting from the backoff, until the maximum of attempts is reached.
+     */
+    pub fn record_attempt(&mut self, attempt : DeliveryAttempt, max_attempts : usize, backoff : i64) {
+        let now = attempt.get_attempted_at();
+        self.status = if attempt.is_success() {
+            DeliveryStatus::Delivered
+        } else if self.attempts.len() + 1 >= max_attempts {
+            DeliveryStatus::Failed
+        } else {
+            DeliveryStatus::Pending
+        };
+        let exponent = self.attempts.len().min(30) as u32;
+        self.next_attempt_at = now.saturating_add(backoff.saturating_mul(2i64.saturating_pow(exponent)));
+        self.attempts.push(attempt);
+    }
+
+    /**
+     * Makes the delivery pending and due right away, whatever its status. The log of the earlier attempts is kept.
+     */
+    pub fn redeliver(&mut self) {
+        self.status = DeliveryStatus::Pending;
+        self.next_attempt_at = Utc::now().timestamp();
+    }
+}
+
+#[test]
+fn test_delivery_backoff() {
+    let mut delivery = WebhookDelivery::new("1", WebhookEventType::UserRegistered, "{}");
+    delivery.record_attempt(DeliveryAttempt::response(500), 3, 10);
+    assert_eq!(delivery.get_status(), &DeliveryStatus::Pending);
+    assert_eq!(delivery.get_next_attempt_at() - delivery.get_attempts()[0].get_attempted_at(), 10);
+    delivery.record_attempt(DeliveryAttempt::failure("connection refused"), 3, 10);
+    assert_eq!(delivery.get_next_attempt_at() - delivery.get_attempts()[1].get_attempted_at(), 20);
+    delivery.record_attempt(DeliveryAttempt::response(404), 3, 10);
+    assert_eq!(delivery.get_status(), &DeliveryStatus::Failed);
+    assert!(!delivery.is_due(i64::MAX));
+
+    delivery.redeliver();
+    assert!(delivery.is_due(delivery.get_next_attempt_at()));
+    delivery.record_attempt(DeliveryAttempt::response(204), 3, 10);
+    assert_eq!((delivery.get_status(), delivery.get_attempts().len()), (&DeliveryStatus::Delivered, 4));
+}
@@ -0,0 +1,121 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeSet;
+use std::convert::From;
+use chrono::Utc;
+
+/**
+ * Events in the life of an user that other services can subscribe to.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum WebhookEventType {
+    UserRegistered,
+    EmailVerified,
+    EmailChanged,
+    UserDeleted,
+    RolesChanged
+}
+
+impl WebhookEventType {
+    pub const ALL : [WebhookEventType; 5] = [
+        WebhookEventType::UserRegistered,
+        WebhookEventType::EmailVerified,
+        WebhookEventType::EmailChanged,
+        WebhookEventType::UserDeleted,
+        WebhookEventType::RolesChanged
+    ];
+
+    /**
+     * Returns the event type of a name like user.registered, the case is ignored.
+     */
+    pub fn parse(name : &str) -> Option<WebhookEventType> {
+        WebhookEventType::ALL.iter().copied().find(|event_type| event_type.get_name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /**
+     * Returns the name under which the event is sent.
+     */
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            WebhookEventType::UserRegistered => "user.registered",
+            WebhookEventType::EmailVerified => "user.email_verified",
+            WebhookEventType::EmailChanged => "user.email_changed",
+            WebhookEventType::UserDeleted => "user.deleted",
+            WebhookEventType::RolesChanged => "user.roles_changed"
+        }
+    }
+}
+
+/**
+ * Webhook subscription is an url that gets the events it subscribed to as JSON posts, signed with the secret of the subscription.
+ *
+ * Attributes:
+ * * id: id of the subscription
+ * * url: url the events are posted to
+ * * secret: key of the HMAC signature of every post
+ * * events: events that are sent to the url
+ * * active: a disabled subscription gets no new events
+ * * created_by: id of the admin or admin client that made the subscription
+ * * created_at: unix timestamp of when the subscription was made
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookSubscription {
+    id : String,
+    url : String,
+    secret : String,
+    events : BTreeSet<WebhookEventType>,
+    active : bool,
+    created_by : String,
+    created_at : i64
+}
+
+impl From<&sled::IVec> for WebhookSubscription {
+    fn from(item : &sled::IVec) -> Self {
+        serde_cbor::from_slice(item).expect("Could not convert the bytes to a WebhookSubscription struct.")
+    }
+}
+
+impl From<&WebhookSubscription> for sled::IVec {
+    fn from(item : &WebhookSubscription) -> Self {
+        sled::IVec::from(serde_cbor::to_vec(&item).expect("Could not convert WebhookSubscription struct to bytes"))
+    }
+}
+
+impl WebhookSubscription {
+    /**
+     * Returns a new active subscription.
+     */
+    pub fn new(id : &str, url : &str, secret : &str, events : BTreeSet<WebhookEventType>, created_by : &str) -> Self {
+        WebhookSubscription {
+            id : id.to_owned(),
+            url : url.to_owned(),
+            secret : secret.to_owned(),
+            events,
+            active : true,
+            created_by : created_by.to_owned(),
+            created_at : Utc::now().timestamp()
+        }
+    }
+
+    pub fn get_id(&self) -> &str { &self.id }
+
+    pub fn get_url(&self) -> &str { &self.url }
+
+    pub fn get_secret(&self) -> &str { &self.secret }
+
+    pub fn get_events(&self) -> &BTreeSet<WebhookEventType> { &self.events }
+
+    pub fn is_active(&self) -> bool { self.active }
+
+    pub fn set_active(&mut self, active : bool) { self.active = active; }
+
+    pub fn get_created_by(&self) -> &str { &self.created_by }
+
+    pub fn get_created_at(&self) -> i64 { self.created_at }
+
+    /**
+     * Returns true if the subscription is active and subscribed to the event.
+     */
+    pub fn is_subscribed_to(&self, event_type : WebhookEventType) -> bool {
+        self.active && self.events.contains(&event_type)
+    }
+}
@@ -30,6 +30,8 @@ lazy_static! {
  * * exp : datetime which indicates the date that it will be valid
  * * iat : datetime the claim was issued
  * * is_admin : Claim that is used to identify if the user is an administrator
+ * * client_id : OAuth client the claim was issued to, empty for first party logins
+ * * scope : space delimited scopes granted to the OAuth client
  */
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Claim {
@@ -38,7 +40,11 @@ pub struct Claim {
     #[serde(with = "util::jwt_numeric_date")]
     pub exp: DateTime<Utc>,
     #[serde(with = "util::jwt_numeric_date")]
-    pub iat: DateTime<Utc>
+    pub iat: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>
 }
 
 impl Claim {
@@ -56,9 +62,42 @@ impl Claim {
             iss: ISSUER.clone(),
             exp: today + chrono::Duration::seconds(*EXPIRATION),
             iat: today,
+            client_id: None,
+            scope: None
         })
     }
 
+    /**
+     * Makes a claim for a token that is handed out to an OAuth client on behalf of the subject. It has the same lifetime as a normal read write claim, but also contains the client and the granted scopes.
+     */
+    pub fn new_oauth_claim(subject: &str, client_id: &str, scope: &str) -> Result<Claim, IdentityError> {
+        let mut claim = Claim::new_read_write_claim(subject)?;
+        claim.client_id = Some(client_id.to_owned());
+        claim.scope = Some(scope.to_owned());
+        Ok(claim)
+    }
+
+    /**
+     * Makes a claim for a token that an OAuth client gets for itself through the client credentials grant. The client is both the subject and the client of the claim.
+     */
+    pub fn new_client_claim(client_id: &str, scope: &str) -> Result<Claim, IdentityError> {
+        Claim::new_oauth_claim(client_id, client_id, scope)
+    }
+
+    /**
+     * Returns true if the claim was issued to an OAuth client for itself instead of on behalf of an user.
+     */
+    pub fn is_client_claim(&self) -> bool {
+        self.client_id.as_deref() == Some(self.sub.as_str())
+    }
+
+    /**
+     * Returns the amount of seconds the claim is valid from the moment it was issued.
+     */
+    pub fn expires_in(&self) -> i64 {
+        (self.exp - self.iat).num_seconds()
+    }
+
     pub fn new_change_password_claim(subject: &str) -> Result<Claim, IdentityError> {
         if subject.is_empty() {
             warn!("The subject of the jwt claim is empty");
@@ -69,7 +108,9 @@ impl Claim {
             sub: subject.to_string(),
             iss: ISSUER.clone(),
             exp: today + chrono::Duration::seconds(*EXPIRATION_CHANGE_PWD),
-            iat: today
+            iat: today,
+            client_id: None,
+            scope: None
         })
     }
 
@@ -132,12 +173,26 @@ impl Claim {
     }
 
     /**
-     * Token function that decodes a token and makes a claim out of it. From the claim it takes the subject which is the user id and it seeks based on this the user associated with that id. If the user isn't found an error is then returned.
+     * Controls that the claim is one of a first party login. Claims that were issued to an OAuth client are refused, the client may only use them for the scopes the user consented to and they can be revoked.
+     */
+    pub fn control_session(&self) -> Result<(), IdentityError> {
+        if let Some(client_id) = &self.client_id {
+            warn!("A token of OAuth client {} has been used as a session token", client_id);
+            return Err(IdentityError::InsufficientScope)
+        }
+        Ok(())
+    }
+
+    /**
+     * Token function that decodes a token and makes a claim out of it. From the claim it takes the subject which is the user id and it seeks based on this the user associated with that id. If the user isn't found or the token was issued to an OAuth client an error is then returned.
      */
     pub fn token_to_user(token: &str, db: &Store) -> Result<IdentityUser, IdentityError> {
         match Claim::decode_token(token) {
-            Ok(token) => match db.get_user_by_uuid(&token.claims.sub) {
-                Some(user) => Ok(user),
+            Ok(token) => match token.claims.control_session().map(|_| db.get_user_by_uuid(&token.claims.sub))? {
+                Some(user) => {
+                    user.control_status()?;
+                    Ok(user)
+                },
                 None => {
                     warn!("The subject of the token is not mapped to an user.");
                     Err(IdentityError::UserNotFound)
@@ -149,4 +204,16 @@ impl Claim {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/**
+ * Sets the variables the claims of the tests are signed with, when they aren't configured.
+ */
+#[cfg(test)]
+pub(crate) fn set_test_config() {
+    for (key, value) in &[("PERSON_ISSUER", "identity"), ("PERSON_SECRET", "secret"), ("PERSON_EXPIRATION", "3600")] {
+        if get_value_from_key(key).is_none() {
+            std::env::set_var(key, value);
+        }
+    }
+}
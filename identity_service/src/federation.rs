@@ -0,0 +1,274 @@
+use std::sync::Arc;
+use std::time::Duration;
+use jsonwebtoken::{decode, decode_header, Algorithm, Validation};
+use openssl::bn::BigNum;
+use openssl::rsa::Rsa;
+use serde::Deserialize;
+use crate::id_token;
+use crate::util::{ append_query, get_value_from_key };
+use crate::IdentityError;
+
+lazy_static! {
+    static ref PROVIDERS : Vec<UpstreamProvider> = load_providers();
+    static ref AGENT : ureq::Agent = ureq::AgentBuilder::new()
+        .tls_connector(Arc::new(native_tls::TlsConnector::new().expect("Could not make a TLS connector")))
+        .timeout(Duration::from_secs(10))
+        .build();
+}
+
+/**
+ * External OpenID Connect provider users can log in with.
+ *
+ * Attributes:
+ * * name: name of the provider in the urls, e.g. corp in /federation/corp/login
+ * * display_name: name shown to the user
+ * * issuer: issuer identifier of the provider, its discovery document is found under it
+ * * client_id: id of this server as a client at the provider
+ * * client_secret: secret of this server as a client at the provider
+ * * scopes: space delimited scopes asked to the provider
+ */
+#[derive(Clone, Debug)]
+pub struct UpstreamProvider {
+    name : String,
+    display_name : String,
+    issuer : String,
+    client_id : String,
+    client_secret : String,
+    scopes : String
+}
+
+/**
+ * The parts of the discovery document of a provider that are needed to log in.
+ */
+#[derive(Deserialize, Clone, Debug)]
+pub struct ProviderMetadata {
+    issuer : String,
+    authorization_endpoint : String,
+    token_endpoint : String,
+    jwks_uri : String
+}
+
+/**
+ * Claims of the id token of a provider that are used to find or provision the user.
+ */
+#[derive(Deserialize, Debug)]
+pub struct UpstreamClaims {
+    pub sub : String,
+    aud : serde_json::Value,
+    #[serde(default)]
+    nonce : Option<String>,
+    #[serde(default)]
+    pub email : Option<String>,
+    #[serde(default)]
+    email_verified : Option<serde_json::Value>,
+    #[serde(default)]
+    pub name : Option<String>,
+    #[serde(default)]
+    pub preferred_username : Option<String>,
+    #[serde(default)]
+    pub locale : Option<String>
+}
+
+impl UpstreamClaims {
+    /**
+     * Returns true if the provider has verified the email, some providers send the boolean as a string.
+     */
+    pub fn is_email_verified(&self) -> bool {
+        match &self.email_verified {
+            Some(serde_json::Value::Bool(verified)) => *verified,
+            Some(serde_json::Value::String(verified)) => verified == "true",
+            _ => false
+        }
+    }
+
+    fn has_audience(&self, client_id : &str) -> bool {
+        match &self.aud {
+            serde_json::Value::String(aud) => aud == client_id,
+            serde_json::Value::Array(auds) => auds.iter().any(|aud| aud.as_str() == Some(client_id)),
+            _ => false
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct UpstreamTokenResponse {
+    id_token : Option<String>
+}
+
+#[derive(Deserialize)]
+struct UpstreamJsonWebKey {
+    #[serde(default)]
+    kid : Option<String>,
+    kty : String,
+    #[serde(default)]
+    n : Option<String>,
+    #[serde(default)]
+    e : Option<String>
+}
+
+#[derive(Deserialize)]
+struct UpstreamJsonWebKeySet {
+    keys : Vec<UpstreamJsonWebKey>
+}
+
+/**
+ * Reads the providers out of the config. PERSON_FEDERATION_PROVIDERS is a comma separated list of names, for every name the settings are read out of PERSON_FEDERATION_<NAME>_ISSUER, _CLIENT_ID, _CLIENT_SECRET and the optional _SCOPES and _DISPLAY_NAME.
+ */
+fn load_providers() -> Vec<UpstreamProvider> {
+    get_value_from_key("PERSON_FEDERATION_PROVIDERS")
+    .unwrap_or_default()
+    .split(',')
+    .map(str::trim)
+    .filter(|name| !name.is_empty())
+    .map(|name| {
+        let key = |setting : &str| get_value_from_key(&format!("PERSON_FEDERATION_{}_{}", name.to_uppercase(), setting));
+        let required = |setting : &str| key(setting)
+            .unwrap_or_else(|| panic!("PERSON_FEDERATION_{}_{} variable not found in the .env config file or as environment variable", name.to_uppercase(), setting));
+        UpstreamProvider::new(
+            name,
+            &key("DISPLAY_NAME").unwrap_or_else(|| name.to_owned()),
+            &required("ISSUER"),
+            &required("CLIENT_ID"),
+            &required("CLIENT_SECRET"),
+            &key("SCOPES").unwrap_or_else(|| "openid email profile".to_owned())
+        )
+    })
+    .collect()
+}
+
+/**
+ * Returns all configured providers.
+ */
+pub fn get_providers() -> &'static [UpstreamProvider] {
+    &PROVIDERS
+}
+
+/**
+ * Returns the configured provider with the given name.
+ */
+pub fn get_provider(name : &str) -> Result<&'static UpstreamProvider, IdentityError> {
+    PROVIDERS.iter().find(|provider| provider.name == name).ok_or(IdentityError::ProviderNotFound)
+}
+
+fn federation_error<E : std::fmt::Display>(e : E) -> IdentityError {
+    IdentityError::FederationFailed(format!("{}", e))
+}
+
+impl UpstreamProvider {
+    pub fn new(name : &str, display_name : &str, issuer : &str, client_id : &str, client_secret : &str, scopes : &str) -> Self {
+        UpstreamProvider {
+            name : name.to_owned(),
+            display_name : display_name.to_owned(),
+            issuer : issuer.to_owned(),
+            client_id : client_id.to_owned(),
+            client_secret : client_secret.to_owned(),
+            scopes : scopes.to_owned()
+        }
+    }
+
+    pub fn get_name(&self) -> &str { &self.name }
+
+    pub fn get_display_name(&self) -> &str { &self.display_name }
+
+    /**
+     * Uri to which the provider sends the user back, it has to be registered at the provider.
+     */
+    pub fn redirect_uri(&self) -> String {
+        format!("{}/federation/{}/callback", id_token::issuer().trim_end_matches('/'), self.name)
+    }
+
+    /**
+     * Fetches the discovery document of the provider, the issuer in it has to be the configured one.
+     */
+    pub fn discover(&self) -> Result<ProviderMetadata, IdentityError> {
+        let uri = format!("{}/.well-known/openid-configuration", self.issuer.trim_end_matches('/'));
+        let metadata : ProviderMetadata = get_json(&uri)?;
+        if metadata.issuer != self.issuer {
+            return Err(IdentityError::FederationFailed("the discovery document has another issuer".to_owned()))
+        }
+        Ok(metadata)
+    }
+
+    /**
+     * Returns the uri of the provider the user is sent to for logging in.
+     */
+    pub fn authorization_uri(&self, metadata : &ProviderMetadata, state : &str, nonce : &str, code_verifier : &str) -> String {
+        let digest = ring::digest::digest(&ring::digest::SHA256, code_verifier.as_bytes());
+        let challenge = base64::encode_config(digest.as_ref(), base64::URL_SAFE_NO_PAD);
+        append_query(&metadata.authorization_endpoint, &[
+            ("response_type", "code"),
+            ("client_id", &self.client_id),
+            ("redirect_uri", &self.redirect_uri()),
+            ("scope", &self.scopes),
+            ("state", state),
+            ("nonce", nonce),
+            ("code_challenge", &challenge),
+            ("code_challenge_method", "S256")
+        ])
+    }
+
+    /**
+     * Exchanges the authorization code at the token endpoint of the provider and returns the id token.
+     */
+    pub fn exchange_code(&self, metadata : &ProviderMetadata, code : &str, code_verifier : &str) -> Result<String, IdentityError> {
+        let response = AGENT.post(&metadata.token_endpoint)
+            .send_form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &self.redirect_uri()),
+                ("code_verifier", code_verifier),
+                ("client_id", &self.client_id),
+                ("client_secret", &self.client_secret)
+            ])
+            .map_err(federation_error)?;
+        let token : UpstreamTokenResponse = serde_json::from_str(&response.into_string().map_err(federation_error)?)
+            .map_err(federation_error)?;
+        token.id_token.ok_or_else(|| IdentityError::FederationFailed("the provider didn't return an id token".to_owned()))
+    }
+
+    /**
+     * Validates the id token of the provider: the RS256 signature has to be made by one of the keys of the provider, the issuer, audience and nonce have to be right and the token may not have expired.
+     */
+    pub fn validate_id_token(&self, metadata : &ProviderMetadata, id_token : &str, nonce : &str) -> Result<UpstreamClaims, IdentityError> {
+        let header = decode_header(id_token).map_err(federation_error)?;
+        if header.alg != Algorithm::RS256 {
+            return Err(IdentityError::FederationFailed("the id token isn't signed with RS256".to_owned()))
+        }
+        let keys : UpstreamJsonWebKeySet = get_json(&metadata.jwks_uri)?;
+        let key = keys.keys.iter()
+            .filter(|key| key.kty == "RSA")
+            .find(|key| header.kid.is_none() || key.kid == header.kid)
+            .ok_or_else(|| IdentityError::FederationFailed("the key of the id token is unknown".to_owned()))?;
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.iss = Some(self.issuer.clone());
+        validation.leeway = 60;
+        let claims = decode::<UpstreamClaims>(id_token, &public_key_der(key)?, &validation)
+            .map_err(federation_error)?
+            .claims;
+        if !claims.has_audience(&self.client_id) {
+            return Err(IdentityError::FederationFailed("the id token isn't meant for this server".to_owned()))
+        }
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(IdentityError::FederationFailed("the nonce of the id token isn't right".to_owned()))
+        }
+        Ok(claims)
+    }
+}
+
+/**
+ * Makes the PKCS#1 DER encoded public key out of a JSON Web Key.
+ */
+fn public_key_der(key : &UpstreamJsonWebKey) -> Result<Vec<u8>, IdentityError> {
+    let component = |value : &Option<String>| -> Result<BigNum, IdentityError> {
+        let bytes = base64::decode_config(value.as_deref().unwrap_or_default(), base64::URL_SAFE_NO_PAD).map_err(federation_error)?;
+        BigNum::from_slice(&bytes).map_err(federation_error)
+    };
+    Rsa::from_public_components(component(&key.n)?, component(&key.e)?)
+        .and_then(|rsa| rsa.public_key_to_der_pkcs1())
+        .map_err(federation_error)
+}
+
+fn get_json<T : serde::de::DeserializeOwned>(uri : &str) -> Result<T, IdentityError> {
+    let response = AGENT.get(uri).call().map_err(federation_error)?;
+    serde_json::from_str(&response.into_string().map_err(federation_error)?).map_err(federation_error)
+}
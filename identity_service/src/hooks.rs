@@ -0,0 +1,135 @@
+use crate::viewmodels::auth::person_info::PersonInfoViewModel;
+use crate::IdentityError;
+
+/**
+ * Hooks that are called around the actions in the life of an user. A pre hook can veto the action by returning an error, the action is then not done and the error is returned to the requester. A post hook is called after the action succeeded, its error can't undo the action and is logged.
+ *
+ * Every hook does nothing by default, so an implementation only implements the hooks it needs.
+ *
+ * The hooks are called whoever does the action: the user himself, the admin, a SCIM client, an identity provider, the LDAP directory, a magic link or an invitation. The exceptions are:
+ * * the approval of a pending registration, its pre registration hooks were called when it was registered and no post registration hooks are called when the admin approves it
+ * * the sync of the user name and flags of an LDAP user at his login, and flag and group changes in general, there are no hooks for them
+ * * status changes (suspension, deactivation, reactivation by the admin) and the purge of a deleted user, they have no hooks either
+ * * the exchange of a refresh token or device code for a new token, which isn't a login
+ */
+pub trait LifecycleHooks : Send + Sync {
+    /**
+     * Name of the hooks, used in the logs.
+     */
+    fn name(&self) -> &str;
+
+    fn pre_registration(&self, _email : &str) -> Result<(), IdentityError> { Ok(()) }
+
+    fn post_registration(&self, _user : &PersonInfoViewModel) -> Result<(), IdentityError> { Ok(()) }
+
+    fn pre_login(&self, _email : &str) -> Result<(), IdentityError> { Ok(()) }
+
+    fn post_login(&self, _user : &PersonInfoViewModel) -> Result<(), IdentityError> { Ok(()) }
+
+    fn pre_password_change(&self, _user : &PersonInfoViewModel) -> Result<(), IdentityError> { Ok(()) }
+
+    fn post_password_change(&self, _user : &PersonInfoViewModel) -> Result<(), IdentityError> { Ok(()) }
+
+    /**
+     * Called with the user as he is and as he will be after the update.
+     */
+    fn pre_update(&self, _user : &PersonInfoViewModel, _updated : &PersonInfoViewModel) -> Result<(), IdentityError> { Ok(()) }
+
+    fn post_update(&self, _user : &PersonInfoViewModel) -> Result<(), IdentityError> { Ok(()) }
+
+    fn pre_delete(&self, _user : &PersonInfoViewModel) -> Result<(), IdentityError> { Ok(()) }
+
+    fn post_delete(&self, _user : &PersonInfoViewModel) -> Result<(), IdentityError> { Ok(()) }
+
+    /**
+     * Called when the user asked to reset his forgotten password, with the token that allows the reset.
+     */
+    fn post_password_reset_request(&self, _user : &PersonInfoViewModel, _token : &str) -> Result<(), IdentityError> { Ok(()) }
+}
+
+/**
+ * The lifecycle hooks that are registered at startup, they are called in the order they were registered.
+ */
+#[derive(Default)]
+pub struct HookRegistry {
+    hooks : Vec<Box<dyn LifecycleHooks>>
+}
+
+impl HookRegistry {
+    pub fn new() -> Self {
+        HookRegistry::default()
+    }
+
+    /**
+     * Registers hooks after the ones that are already registered.
+     */
+    pub fn register<H : LifecycleHooks + 'static>(mut self, hooks : H) -> Self {
+        info!("Lifecycle hooks {} have been registered", hooks.name());
+        self.hooks.push(Box::new(hooks));
+        self
+    }
+
+    /**
+     * Calls a pre hook of every registered hooks. The first one that vetoes stops the others and its error is returned.
+     */
+    pub fn before<F>(&self, action : &str, hook : F) -> Result<(), IdentityError>
+    where F : Fn(&dyn LifecycleHooks) -> Result<(), IdentityError> {
+        for hooks in &self.hooks {
+            if let Err(e) = hook(hooks.as_ref()) {
+                warn!("Lifecycle hooks {} vetoed the {}: {}", hooks.name(), action, e);
+                return Err(e)
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Calls a post hook of every registered hooks, a failing hook doesn't stop the others.
+     */
+    pub fn after<F>(&self, action : &str, hook : F)
+    where F : Fn(&dyn LifecycleHooks) -> Result<(), IdentityError> {
+        for hooks in &self.hooks {
+            if let Err(e) = hook(hooks.as_ref()) {
+                error!("Lifecycle hooks {} failed after the {}: {}", hooks.name(), action, e);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_hook_registry() {
+    use std::sync::Mutex;
+    use identity_dal::traits::t_user::UserTrait;
+    use identity_dal::user::identity_user::IdentityUser;
+
+    struct Recorder { name : &'static str, calls : std::sync::Arc<Mutex<Vec<String>>> }
+
+    impl LifecycleHooks for Recorder {
+        fn name(&self) -> &str { self.name }
+
+        fn pre_registration(&self, email : &str) -> Result<(), IdentityError> {
+            self.calls.lock().unwrap().push(format!("{} pre {}", self.name, email));
+            if email.ends_with("@spam.com") {
+                return Err(IdentityError::EmailDomainIsNotAllowed)
+            }
+            Ok(())
+        }
+
+        fn post_delete(&self, user : &PersonInfoViewModel) -> Result<(), IdentityError> {
+            self.calls.lock().unwrap().push(format!("{} post {}", self.name, user.get_email()));
+            Err(IdentityError::CouldNotSendEmail)
+        }
+    }
+
+    let calls = std::sync::Arc::new(Mutex::new(Vec::new()));
+    let hooks = HookRegistry::new()
+        .register(Recorder { name : "first", calls : calls.clone() })
+        .register(Recorder { name : "second", calls : calls.clone() });
+    assert!(hooks.before("registration", |hook| hook.pre_registration("jane@corp.be")).is_ok());
+    assert!(matches!(hooks.before("registration", |hook| hook.pre_registration("bot@spam.com")), Err(IdentityError::EmailDomainIsNotAllowed)));
+    let user = IdentityUser::new_user("jane@corp.be", "jane", "Passw0rd!").unwrap();
+    hooks.after("deletion", |hook| hook.post_delete(&PersonInfoViewModel::from_identity_user(&user)));
+    assert_eq!(*calls.lock().unwrap(), vec![
+        "first pre jane@corp.be", "second pre jane@corp.be", "first pre bot@spam.com", "first post jane@corp.be", "second post jane@corp.be"
+    ]);
+}
@@ -0,0 +1,72 @@
+use chrono::prelude::*;
+use serde::Serialize;
+use crate::claim::Claim;
+use crate::util::{ self, get_value_from_key };
+use crate::viewmodels::oauth::user_info::UserInfoViewModel;
+
+lazy_static! {
+    static ref OIDC_ISSUER : String = get_value_from_key("PERSON_OIDC_ISSUER")
+    .unwrap_or_else(|| "https://localhost:8000".to_owned());
+}
+
+/**
+ * Returns the issuer identifier of the OpenID Connect provider, this is the url on which the discovery document can be found.
+ */
+pub fn issuer() -> &'static str {
+    &OIDC_ISSUER
+}
+
+/**
+ * Claim of an OpenID Connect id token, this tells the client who the user is and when he authenticated.
+ *
+ * Attributes:
+ * * iss : issuer identifier of the provider
+ * * aud : client the id token is meant for
+ * * exp : datetime after which the id token isn't valid
+ * * iat : datetime the id token was issued
+ * * auth_time : unix timestamp of when the user authenticated
+ * * nonce : value of the authentication request, used by the client to prevent replays
+ * * at_hash : hash of the access token that was issued together with the id token
+ * * user : sub and the claims allowed by the granted scopes
+ */
+#[derive(Serialize)]
+pub struct IdTokenClaim {
+    pub iss: String,
+    pub aud: String,
+    #[serde(with = "util::jwt_numeric_date")]
+    pub exp: DateTime<Utc>,
+    #[serde(with = "util::jwt_numeric_date")]
+    pub iat: DateTime<Utc>,
+    pub auth_time: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    pub at_hash: String,
+    #[serde(flatten)]
+    pub user: UserInfoViewModel
+}
+
+impl IdTokenClaim {
+    /**
+     * Makes the id token claim that accompanies an access token, it has the same lifetime as the access token.
+     */
+    pub fn new(access_claim : &Claim, access_token : &str, user : UserInfoViewModel, auth_time : i64, nonce : Option<&str>) -> Self {
+        IdTokenClaim {
+            iss : OIDC_ISSUER.clone(),
+            aud : access_claim.client_id.clone().unwrap_or_default(),
+            exp : access_claim.exp,
+            iat : access_claim.iat,
+            auth_time,
+            nonce : nonce.map(String::from),
+            at_hash : access_token_hash(access_token),
+            user
+        }
+    }
+}
+
+/**
+ * at_hash of an access token: the base64url encoding of the left-most half of its SHA-256 hash.
+ */
+pub fn access_token_hash(access_token : &str) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, access_token.as_bytes());
+    base64::encode_config(&digest.as_ref()[..16], base64::URL_SAFE_NO_PAD)
+}
@@ -0,0 +1,208 @@
+use std::collections::BTreeSet;
+use std::time::Duration;
+use ldap3::{ ldap_escape, LdapConn, LdapConnSettings, LdapError, Scope, SearchEntry };
+use crate::util::get_value_from_key;
+use crate::IdentityError;
+
+lazy_static! {
+    static ref DIRECTORY : Option<LdapDirectory> = load_directory();
+}
+
+/**
+ * Result code LDAP servers send back when the password of a bind isn't right.
+ */
+const INVALID_CREDENTIALS : u32 = 49;
+
+/**
+ * LDAP directory the users of some email domains log in with.
+ *
+ * Attributes:
+ * * url: url of the directory, e.g. ldap://ldap.corp.be:389 or ldaps://ldap.corp.be
+ * * domains: email domains of which the users are in the directory
+ * * base_dn: entry under which the users are searched
+ * * bind_dn: entry this server binds as to search the users, without one it searches anonymously
+ * * bind_password: password of the bind_dn
+ * * user_filter: filter that finds an user, {email} is replaced by the escaped email
+ * * name_attribute: attribute whose value becomes the user name
+ * * group_attribute: attribute that holds the groups of an user, they become flags of the user
+ * * starttls: whether the connection is upgraded with StartTLS
+ * * timeout: timeout of the connection and the operations
+ */
+#[derive(Clone, Debug)]
+pub struct LdapDirectory {
+    url : String,
+    domains : Vec<String>,
+    base_dn : String,
+    bind_dn : Option<String>,
+    bind_password : Option<String>,
+    user_filter : String,
+    name_attribute : String,
+    group_attribute : String,
+    starttls : bool,
+    timeout : Duration
+}
+
+/**
+ * Entry of an user in the directory, after his password has been verified.
+ *
+ * Attributes:
+ * * dn: distinguished name of the entry
+ * * user_name: value of the name attribute
+ * * groups: names of the groups the user is a member of
+ */
+#[derive(Debug)]
+pub struct DirectoryEntry {
+    pub dn : String,
+    pub user_name : Option<String>,
+    pub groups : BTreeSet<String>
+}
+
+fn load_directory() -> Option<LdapDirectory> {
+    let url = get_value_from_key("PERSON_LDAP_URL")?;
+    let key = |setting : &str| get_value_from_key(&format!("PERSON_LDAP_{}", setting));
+    let domains : Vec<String> = key("DOMAINS").unwrap_or_default().split(',').map(str::to_owned).collect();
+    let mut directory = LdapDirectory::new(
+        &url,
+        &domains,
+        &key("BASE_DN").expect("PERSON_LDAP_BASE_DN variable not found in the .env config file or as environment variable")
+    );
+    if let (Some(bind_dn), Some(bind_password)) = (key("BIND_DN"), key("BIND_PASSWORD")) {
+        directory = directory.with_bind(&bind_dn, &bind_password);
+    }
+    if let Some(filter) = key("USER_FILTER") {
+        directory.user_filter = filter;
+    }
+    if let Some(attribute) = key("NAME_ATTRIBUTE") {
+        directory.name_attribute = attribute;
+    }
+    if let Some(attribute) = key("GROUP_ATTRIBUTE") {
+        directory.group_attribute = attribute;
+    }
+    directory.starttls = key("STARTTLS").map(|starttls| starttls == "true").unwrap_or(false);
+    if let Some(timeout) = key("TIMEOUT") {
+        directory.timeout = Duration::from_secs(timeout.parse::<u64>().expect("Could not parse PERSON_LDAP_TIMEOUT to u64"));
+    }
+    info!("Users of the domains {} log in with the LDAP directory {}", directory.domains.join(", "), &url);
+    Some(directory)
+}
+
+/**
+ * Returns the configured LDAP directory, None is returned when PERSON_LDAP_URL isn't set.
+ */
+pub fn get_directory() -> Option<&'static LdapDirectory> {
+    DIRECTORY.as_ref()
+}
+
+fn directory_error(e : LdapError) -> IdentityError {
+    warn!("The LDAP directory couldn't be used. Reason: {}", e);
+    IdentityError::DirectoryIsUnavailable(format!("{}", e))
+}
+
+impl LdapDirectory {
+    /**
+     * Makes a directory for the email domains that searches anonymously with the filter (mail={email}). The cn becomes the user name and the memberOf groups become flags.
+     */
+    pub fn new(url : &str, domains : &[String], base_dn : &str) -> Self {
+        LdapDirectory {
+            url : url.to_owned(),
+            domains : domains.iter()
+                .map(|domain| domain.trim().trim_start_matches('@').to_lowercase())
+                .filter(|domain| !domain.is_empty())
+                .collect(),
+            base_dn : base_dn.to_owned(),
+            bind_dn : None,
+            bind_password : None,
+            user_filter : "(mail={email})".to_owned(),
+            name_attribute : "cn".to_owned(),
+            group_attribute : "memberOf".to_owned(),
+            starttls : false,
+            timeout : Duration::from_secs(10)
+        }
+    }
+
+    /**
+     * Lets the directory bind with the given entry before it searches users.
+     */
+    pub fn with_bind(mut self, bind_dn : &str, bind_password : &str) -> Self {
+        self.bind_dn = Some(bind_dn.to_owned());
+        self.bind_password = Some(bind_password.to_owned());
+        self
+    }
+
+    /**
+     * Returns true if the users of the domain of the email are in this directory.
+     */
+    pub fn handles_email(&self, email : &str) -> bool {
+        match email.rsplit_once('@') {
+            Some((_, domain)) => self.domains.iter().any(|handled| handled.eq_ignore_ascii_case(domain)),
+            None => false
+        }
+    }
+
+    fn connect(&self) -> Result<LdapConn, IdentityError> {
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(self.timeout)
+            .set_starttls(self.starttls);
+        let mut conn = LdapConn::with_settings(settings, &self.url).map_err(directory_error)?;
+        conn.with_timeout(self.timeout);
+        Ok(conn)
+    }
+
+    /**
+     * Searches the user with the email and verifies his password by binding as him. The entry of the user is returned.
+     *
+     * An error is returned when:
+     * * the password is empty, an empty password would be an unauthenticated bind
+     * * no or more than one user is found
+     * * the password isn't right
+     * * the directory can't be reached or refuses the search
+     */
+    pub fn authenticate(&self, email : &str, password : &str) -> Result<DirectoryEntry, IdentityError> {
+        if password.is_empty() {
+            return Err(IdentityError::PasswordIsNotCorrect)
+        }
+        let mut conn = self.connect()?;
+        if let (Some(bind_dn), Some(bind_password)) = (&self.bind_dn, &self.bind_password) {
+            conn.simple_bind(bind_dn, bind_password).and_then(|result| result.success()).map_err(directory_error)?;
+        }
+        let filter = self.user_filter.replace("{email}", &ldap_escape(email));
+        let attributes = [self.name_attribute.as_str(), self.group_attribute.as_str()];
+        let (entries, _) = conn.search(&self.base_dn, Scope::Subtree, &filter, &attributes[..])
+            .and_then(|result| result.success())
+            .map_err(directory_error)?;
+        let entry = match entries.len() {
+            1 => SearchEntry::construct(entries.into_iter().next().unwrap()),
+            0 => return Err(IdentityError::UserIsNotPresent),
+            _ => {
+                warn!("More than one LDAP entry has been found for an email");
+                return Err(IdentityError::UserIsNotPresent)
+            }
+        };
+        match conn.simple_bind(&entry.dn, password).and_then(|result| result.success()) {
+            Ok(_) => {},
+            Err(LdapError::LdapResult { result }) if result.rc == INVALID_CREDENTIALS => return Err(IdentityError::PasswordIsNotCorrect),
+            Err(e) => return Err(directory_error(e))
+        }
+        let _ = conn.unbind();
+        let value_of = |attribute : &str| entry.attrs.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(attribute))
+            .map(|(_, values)| values.clone())
+            .unwrap_or_default();
+        Ok(DirectoryEntry {
+            user_name : value_of(&self.name_attribute).into_iter().next(),
+            groups : value_of(&self.group_attribute).iter().map(|group| group_name(group)).collect(),
+            dn : entry.dn
+        })
+    }
+}
+
+/**
+ * Returns the name of a group. Groups are often given by their distinguished name, then the value of the first part is the name, e.g. sales for cn=sales,ou=groups,dc=corp,dc=be.
+ */
+pub fn group_name(group : &str) -> String {
+    let first = group.split(',').next().unwrap_or_default();
+    match first.split_once('=') {
+        Some((_, name)) => name.trim().to_owned(),
+        None => group.trim().to_owned()
+    }
+}
@@ -1,10 +1,19 @@
 pub mod claim;
+pub mod id_token;
+pub mod signing_key;
+pub mod federation;
+pub mod saml;
+pub mod ldap;
+pub mod scim;
 pub mod service;
 pub mod store;
 pub mod viewmodels;
 pub mod mail_struct;
+pub mod mail_template;
+pub mod mail_transport;
 pub mod util;
 pub mod map_token_pwd;
+pub mod hooks;
 
 #[macro_use] extern crate lazy_static;
 #[macro_use] extern crate log;
@@ -1,12 +1,64 @@
 use crate::IdentityError;
 
-use lettre_email::EmailBuilder;
+use identity_dal::mail::outbox_mail::{ MailContent, MailImage };
+use lettre_email::{ EmailBuilder, MimeMultipartType, PartBuilder };
 
+/**
+ * Headers that are set by the report itself and can't be given as custom header.
+ */
+static RESERVED_HEADERS : [&str; 8] = ["to", "from", "subject", "date", "reply-to", "content-type", "mime-version", "message-id"];
+
+/**
+ * Image that is sent within a html mail, the html refers to it with cid:{content_id}.
+ */
+#[derive(Clone, Debug)]
+pub struct InlineImage {
+    content_id : String,
+    content_type : String,
+    data : Vec<u8>
+}
+
+impl InlineImage {
+    pub fn new(content_id : &str, content_type : &str, data : Vec<u8>) -> InlineImage {
+        InlineImage { content_id : content_id.to_owned(), content_type : content_type.to_owned(), data }
+    }
+
+    // reference getter for the content id property
+    pub fn get_content_id(&self) -> &str { &self.content_id }
+
+    fn to_image(&self) -> MailImage {
+        MailImage { content_id : self.content_id.clone(), content_type : self.content_type.clone(), data : self.data.clone() }
+    }
+
+    fn from_image(image : &MailImage) -> InlineImage {
+        InlineImage::new(&image.content_id, &image.content_type, image.data.clone())
+    }
+
+    fn part(&self) -> lettre_email::MimeMessage {
+        let encoded = base64::encode(&self.data).into_bytes();
+        let lines : Vec<&str> = encoded.chunks(76).map(|line| std::str::from_utf8(line).unwrap_or_default()).collect();
+        PartBuilder::new()
+        .body(lines.join("\r\n"))
+        .header(("Content-Type", self.content_type.as_str()))
+        .header(("Content-Transfer-Encoding", "base64"))
+        .header(("Content-ID", format!("<{}>", self.content_id)))
+        .header(("Content-Disposition", format!("inline; filename=\"{}\"", self.content_id)))
+        .build()
+    }
+}
+
+/**
+ * Mail to a recipient. Without html it is a plain text mail, with html it is a multipart/alternative mail with the message as text part, and a multipart/related mail when it has inline images.
+ */
 pub struct Report {
     recipient : String,
     alias : String,
     subject : String,
-    message : String
+    message : String,
+    html : Option<String>,
+    images : Vec<InlineImage>,
+    reply_to : Option<String>,
+    headers : Vec<(String, String)>
 }
 
 impl Report {
@@ -22,11 +74,63 @@ impl Report {
                 recipient : recipient_email.to_owned(),
                 alias : alias_name.to_owned(),
                 subject : subject_string.to_owned(),
-                message : msg.to_owned()
+                message : msg.to_owned(),
+                html : None,
+                images : Vec::new(),
+                reply_to : None,
+                headers : Vec::new()
             }
         )
     }
 
+    /**
+     * Adds a html part, the message is sent with it as the plain text alternative.
+     */
+    pub fn with_html(mut self, html : &str) -> Self {
+        self.html = Some(html.to_owned());
+        self
+    }
+
+    /**
+     * Adds an image the html part can show, e.g. a logo.
+     */
+    pub fn with_inline_image(mut self, image : InlineImage) -> Self {
+        self.images.push(image);
+        self
+    }
+
+    pub fn with_reply_to(mut self, email : &str) -> Result<Self, IdentityError> {
+        if !identity_dal::util::control_email(email) {
+            return Err(IdentityError::EmailNotCorrectFormat)
+        }
+        self.reply_to = Some(email.to_owned());
+        Ok(self)
+    }
+
+    /**
+     * Adds a custom header. The headers the report sets itself can't be given, nor can a header be split over lines.
+     */
+    pub fn with_header(mut self, name : &str, value : &str) -> Result<Self, IdentityError> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(IdentityError::CustomError(format!("{} is not a valid header name", name)))
+        }
+        if RESERVED_HEADERS.contains(&name.to_lowercase().as_str()) {
+            return Err(IdentityError::CustomError(format!("The header {} is set by the report itself", name)))
+        }
+        if value.contains(['\r', '\n']) {
+            return Err(IdentityError::CustomError(format!("The value of the header {} can't contain a line break", name)))
+        }
+        self.headers.push((name.to_owned(), value.to_owned()));
+        Ok(self)
+    }
+
+    /**
+     * Adds the List-Unsubscribe header with the url where the recipient can unsubscribe.
+     */
+    pub fn with_list_unsubscribe(self, url : &str) -> Result<Self, IdentityError> {
+        self.with_header("List-Unsubscribe", &format!("<{}>", url))
+    }
+
     // reference getter for the recipient property
     pub fn get_recipient(&self) -> &str { &self.recipient }
 
@@ -39,10 +143,105 @@ impl Report {
     // reference getter for the message property
     pub fn get_message(&self) -> &str { &self.message }
 
+    // reference getter for the html property
+    pub fn get_html(&self) -> Option<&str> { self.html.as_deref() }
+
+    /**
+     * Returns the content of the report as it is kept in the mail outbox.
+     */
+    pub fn to_content(&self) -> MailContent {
+        MailContent {
+            recipient : self.recipient.clone(),
+            alias : self.alias.clone(),
+            subject : self.subject.clone(),
+            text : self.message.clone(),
+            html : self.html.clone(),
+            images : self.images.iter().map(InlineImage::to_image).collect(),
+            reply_to : self.reply_to.clone(),
+            headers : self.headers.clone()
+        }
+    }
+
+    /**
+     * Returns the report of a mail of the outbox, its content was controlled when it was queued.
+     */
+    pub fn from_content(content : &MailContent) -> Report {
+        Report {
+            recipient : content.recipient.clone(),
+            alias : content.alias.clone(),
+            subject : content.subject.clone(),
+            message : content.text.clone(),
+            html : content.html.clone(),
+            images : content.images.iter().map(InlineImage::from_image).collect(),
+            reply_to : content.reply_to.clone(),
+            headers : content.headers.clone()
+        }
+    }
+
     pub fn email(&self) -> EmailBuilder {
-        EmailBuilder::new()
+        let mut builder = EmailBuilder::new()
         .to((self.get_recipient(), self.get_alias()))
-        .subject(self.get_subject())
-        .body(self.get_message())
+        .subject(self.get_subject());
+        if let Some(reply_to) = &self.reply_to {
+            builder = builder.reply_to(reply_to.as_str());
+        }
+        for (name, value) in &self.headers {
+            builder = builder.header((name.as_str(), value.as_str()));
+        }
+        let html = match &self.html {
+            Some(html) => html,
+            None => return builder.body(self.get_message())
+        };
+        if self.images.is_empty() {
+            return builder.message_type(MimeMultipartType::Alternative)
+            .text(self.get_message())
+            .html(html.as_str())
+        }
+        let alternative = PartBuilder::new()
+        .message_type(MimeMultipartType::Alternative)
+        .child(PartBuilder::new().body(self.get_message()).header(("Content-Type", lettre_email::mime::TEXT_PLAIN_UTF_8.to_string())).build())
+        .child(PartBuilder::new().body(html.as_str()).header(("Content-Type", lettre_email::mime::TEXT_HTML_UTF_8.to_string())).build())
+        .build();
+        // the email crate has no multipart/related type, so that part is written here with a boundary of its own
+        let boundary = format!("{}_related", alternative.boundary);
+        let mut body = format!("--{}\r\n{}\r\n", boundary, alternative.as_string());
+        for image in &self.images {
+            body.push_str(&format!("--{}\r\n{}\r\n", boundary, image.part().as_string()));
+        }
+        body.push_str(&format!("--{}--", boundary));
+        builder.header(("Content-Type", format!("multipart/related; boundary=\"{}\"", boundary))).body(body)
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_multipart_report() {
+    use lettre::SendableEmail;
+
+    let raw = |report : Report| {
+        let email : SendableEmail = report.email().from(("noreply@corp.be", "Corp Identity")).build().unwrap().into();
+        email.message_to_string().unwrap()
+    };
+    let plain = raw(Report::new("jane@corp.be", "jane", "Welcome", "Welcome jane").unwrap());
+    assert!(plain.ends_with("\r\n\r\nWelcome jane\r\n"));
+    assert!(!plain.contains("multipart"));
+
+    let report = Report::new("jane@corp.be", "jane", "Welcome", "Welcome jane").unwrap()
+    .with_html("<p>Welcome jane</p><img src=\"cid:logo\">")
+    .with_reply_to("support@corp.be").unwrap()
+    .with_list_unsubscribe("https://corp.be/unsubscribe").unwrap();
+    let alternative = raw(report);
+    assert!(alternative.contains("multipart/alternative"));
+    assert!(alternative.contains("text/plain; charset=utf-8") && alternative.contains("text/html; charset=utf-8"));
+    assert!(alternative.contains("Reply-To: <support@corp.be>"));
+    assert!(alternative.contains("List-Unsubscribe: <https://corp.be/unsubscribe>"));
+    assert!(alternative.contains("From: \"Corp Identity\" <noreply@corp.be>"));
+
+    let related = raw(Report::new("jane@corp.be", "jane", "Welcome", "Welcome jane").unwrap()
+    .with_html("<img src=\"cid:logo\">")
+    .with_inline_image(InlineImage::new("logo", "image/png", vec![0; 100])));
+    assert!(related.contains("multipart/related") && related.contains("multipart/alternative"));
+    assert!(related.contains("Content-ID: <logo>"));
+
+    assert!(Report::new("jane@corp.be", "jane", "Welcome", "").unwrap().with_header("Subject", "Other").is_err());
+    assert!(Report::new("jane@corp.be", "jane", "Welcome", "").unwrap().with_header("X-Tag", "a\r\nBcc: john@corp.be").is_err());
+}
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::path::Path;
+use crate::util::get_value_from_key;
+use crate::IdentityError;
+
+lazy_static! {
+    static ref TEMPLATES : TemplateStore = load_templates();
+}
+
+/**
+ * Values of the variables of a template by their name, {{name}} in a template is replaced by the value.
+ */
+pub type TemplateVariables<'a> = [(&'a str, &'a str)];
+
+/**
+ * Template of which a mail is made.
+ *
+ * Attributes:
+ * * subject: subject line, read from {name}.subject
+ * * text: plain text part, read from {name}.txt
+ * * html: html part, read from {name}.html when the file exists
+ */
+#[derive(Clone, Debug)]
+pub struct MailTemplate {
+    subject : String,
+    text : String,
+    html : Option<String>
+}
+
+/**
+ * Mail of which the variables of the template have been replaced.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderedMail {
+    pub subject : String,
+    pub text : String,
+    pub html : Option<String>
+}
+
+/**
+ * Templates by their locale and name. They are loaded from a directory with a subdirectory per locale, e.g. templates/mail/en/welcome.subject. A template that has no variant for a locale falls back to the language of the locale and then to the default locale.
+ */
+#[derive(Debug, Default)]
+pub struct TemplateStore {
+    templates : HashMap<String, HashMap<String, MailTemplate>>,
+    default_locale : String
+}
+
+fn load_templates() -> TemplateStore {
+    let dir = get_value_from_key("PERSON_MAIL_TEMPLATE_DIR").unwrap_or_else(|| "templates/mail".to_owned());
+    let locale = get_value_from_key("PERSON_MAIL_LOCALE").unwrap_or_else(|| "en".to_owned());
+    TemplateStore::load(Path::new(&dir), &locale)
+    .unwrap_or_else(|e| panic!("The mail templates of {} could not be loaded: {}", dir, e))
+}
+
+/**
+ * Returns the templates of the directory of PERSON_MAIL_TEMPLATE_DIR, by default templates/mail. PERSON_MAIL_LOCALE is the default locale, by default en.
+ */
+pub fn get_templates() -> &'static TemplateStore {
+    &TEMPLATES
+}
+
+/**
+ * Returns the locale of an Accept-Language header the requester prefers most, e.g. nl-BE of "nl-BE,nl;q=0.9,en;q=0.8". The wildcard and locales with a weight of 0 are left out.
+ */
+pub fn preferred_locale(accept_language : &str) -> Option<String> {
+    accept_language.split(',')
+    .filter_map(|range| {
+        let mut parts = range.split(';');
+        let locale = parts.next()?.trim();
+        let weight = parts.find_map(|part| part.trim().strip_prefix("q="))
+        .map_or(Some(1.0), |weight| weight.trim().parse::<f32>().ok())?;
+        Some((locale, weight)).filter(|(locale, weight)| !locale.is_empty() && *locale != "*" && *weight > 0.0)
+    })
+    .fold(None, |best : Option<(&str, f32)>, (locale, weight)| match best {
+        Some((_, best_weight)) if best_weight >= weight => best,
+        _ => Some((locale, weight))
+    })
+    .map(|(locale, _)| locale.to_owned())
+}
+
+fn read_part(path : &Path) -> Result<String, IdentityError> {
+    std::fs::read_to_string(path)
+    .map_err(|e| IdentityError::MailTemplateIsInvalid(format!("{} can't be read: {}", path.display(), e)))
+}
+
+/**
+ * Returns the names of the variables in the content, in the order they are used.
+ */
+fn variables_of<'a>(template : &str, content : &'a str) -> Result<Vec<&'a str>, IdentityError> {
+    let mut variables = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..].find("}}")
+        .ok_or_else(|| IdentityError::MailTemplateIsInvalid(format!("{} has a variable that isn't closed", template)))?;
+        variables.push(rest[start + 2..start + end].trim());
+        rest = &rest[start + end + 2..];
+    }
+    Ok(variables)
+}
+
+fn escape_html(value : &str) -> String {
+    value.chars().fold(String::with_capacity(value.len()), |mut escaped, c| {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c)
+        }
+        escaped
+    })
+}
+
+/**
+ * Replaces the variables in the content by their values, the values are escaped in html parts. A variable without a value is an error, so a mail is never sent with a placeholder in it.
+ */
+fn substitute(template : &str, content : &str, variables : &TemplateVariables, html : bool) -> Result<String, IdentityError> {
+    let mut substituted = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..].find("}}")
+        .ok_or_else(|| IdentityError::MailTemplateIsInvalid(format!("{} has a variable that isn't closed", template)))?;
+        let name = rest[start + 2..start + end].trim();
+        let value = variables.iter().find(|(variable, _)| *variable == name).map(|(_, value)| *value)
+        .ok_or_else(|| IdentityError::MailTemplateIsInvalid(format!("{} uses the variable {} that has no value", template, name)))?;
+        substituted.push_str(&rest[..start]);
+        if html {
+            substituted.push_str(&escape_html(value));
+        } else {
+            substituted.push_str(value);
+        }
+        rest = &rest[start + end + 2..];
+    }
+    substituted.push_str(rest);
+    Ok(substituted)
+}
+
+impl MailTemplate {
+    /**
+     * Reads the template with the name out of the directory of a locale. Only the html part is optional.
+     */
+    pub fn load(dir : &Path, name : &str) -> Result<MailTemplate, IdentityError> {
+        let html = dir.join(format!("{}.html", name));
+        Ok(MailTemplate {
+            subject : read_part(&dir.join(format!("{}.subject", name)))?.trim().to_owned(),
+            text : read_part(&dir.join(format!("{}.txt", name)))?,
+            html : if html.exists() { Some(read_part(&html)?) } else { None }
+        })
+    }
+
+    /**
+     * Returns the names of the variables used in the subject and the parts of the template.
+     */
+    pub fn get_variables(&self, name : &str) -> Result<Vec<&str>, IdentityError> {
+        let mut variables = variables_of(name, &self.subject)?;
+        variables.extend(variables_of(name, &self.text)?);
+        if let Some(html) = &self.html {
+            variables.extend(variables_of(name, html)?);
+        }
+        Ok(variables)
+    }
+
+    /**
+     * Makes the mail of the template with the values of the variables. Line breaks in the values are left out of the subject.
+     */
+    pub fn render(&self, name : &str, variables : &TemplateVariables) -> Result<RenderedMail, IdentityError> {
+        Ok(RenderedMail {
+            subject : substitute(name, &self.subject, variables, false)?.replace(['\r', '\n'], " "),
+            text : substitute(name, &self.text, variables, false)?,
+            html : match &self.html {
+                Some(html) => Some(substitute(name, html, variables, true)?),
+                None => None
+            }
+        })
+    }
+}
+
+impl TemplateStore {
+    /**
+     * Loads the templates of every locale directory in the directory, a template is found through its .subject file.
+     */
+    pub fn load(dir : &Path, default_locale : &str) -> Result<TemplateStore, IdentityError> {
+        let read_dir = |dir : &Path| std::fs::read_dir(dir)
+        .map_err(|e| IdentityError::MailTemplateIsInvalid(format!("{} can't be read: {}", dir.display(), e)));
+        let mut store = TemplateStore { templates : HashMap::new(), default_locale : default_locale.to_owned() };
+        for locale_dir in read_dir(dir)?.filter_map(|entry| entry.ok()).map(|entry| entry.path()).filter(|path| path.is_dir()) {
+            let locale = locale_dir.file_name().and_then(|name| name.to_str()).unwrap_or_default().to_owned();
+            for file in read_dir(&locale_dir)?.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+                if file.extension().and_then(|extension| extension.to_str()) != Some("subject") {
+                    continue
+                }
+                if let Some(name) = file.file_stem().and_then(|name| name.to_str()) {
+                    let template = MailTemplate::load(&locale_dir, name)?;
+                    store.templates.entry(locale.clone()).or_default().insert(name.to_owned(), template);
+                }
+            }
+        }
+        Ok(store)
+    }
+
+    /**
+     * Returns the locales that are looked at for a template, the most specific first.
+     */
+    fn fallbacks<'a>(&'a self, locale : Option<&'a str>) -> Vec<&'a str> {
+        let mut locales = Vec::new();
+        if let Some(locale) = locale {
+            locales.push(locale);
+            if let Some(language) = locale.split(['-', '_']).next() {
+                locales.push(language);
+            }
+        }
+        locales.push(&self.default_locale);
+        locales
+    }
+
+    /**
+     * Returns the template with the name of the locale, of its language or of the default locale.
+     */
+    pub fn get(&self, name : &str, locale : Option<&str>) -> Result<&MailTemplate, IdentityError> {
+        self.fallbacks(locale).into_iter()
+        .find_map(|locale| self.templates.get(locale).and_then(|templates| templates.get(name)))
+        .ok_or_else(|| IdentityError::MailTemplateNotFound(name.to_owned()))
+    }
+
+    /**
+     * Makes the mail of the template with the name in the locale, or the default locale when there is none.
+     */
+    pub fn render(&self, name : &str, locale : Option<&str>, variables : &TemplateVariables) -> Result<RenderedMail, IdentityError> {
+        self.get(name, locale)?.render(name, variables)
+    }
+
+    /**
+     * Controls that every required template exists in the default locale, and that all variants of it only use the variables it will get. This is done at startup so a missing or broken template isn't noticed when the first mail is sent.
+     */
+    pub fn validate(&self, required : &[(&str, &[&str])]) -> Result<(), IdentityError> {
+        for (name, allowed) in required {
+            if !self.templates.get(&self.default_locale).is_some_and(|templates| templates.contains_key(*name)) {
+                return Err(IdentityError::MailTemplateNotFound(format!("{} of the locale {}", name, self.default_locale)))
+            }
+            for (locale, templates) in &self.templates {
+                if let Some(template) = templates.get(*name) {
+                    if let Some(unknown) = template.get_variables(name)?.into_iter().find(|variable| !allowed.contains(variable)) {
+                        return Err(IdentityError::MailTemplateIsInvalid(format!("{} of the locale {} uses the unknown variable {}", name, locale, unknown)))
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_mail_templates() {
+    let dir = std::env::temp_dir().join(format!("mail_template_test_{}", std::process::id()));
+    let write = |locale : &str, file : &str, content : &str| {
+        std::fs::create_dir_all(dir.join(locale)).unwrap();
+        std::fs::write(dir.join(locale).join(file), content).unwrap();
+    };
+    write("en", "welcome.subject", "Welcome {{ user_name }}\n");
+    write("en", "welcome.txt", "Dear {{user_name}}, welcome.");
+    write("en", "welcome.html", "<p>Dear {{ user_name }}, welcome.</p>");
+    write("nl", "welcome.subject", "Welkom {{ user_name }}");
+    write("nl", "welcome.txt", "Beste {{ user_name }}, welkom.");
+    write("en", "password_reset.subject", "Reset your password");
+    write("en", "password_reset.txt", "Token: {{ token }}");
+
+    let store = TemplateStore::load(&dir, "en").unwrap();
+    let jane = [("user_name", "Jane <admin>")];
+    let mail = store.render("welcome", None, &jane).unwrap();
+    assert_eq!(mail.subject, "Welcome Jane <admin>");
+    assert_eq!(mail.text, "Dear Jane <admin>, welcome.");
+    assert_eq!(mail.html.as_deref(), Some("<p>Dear Jane &lt;admin&gt;, welcome.</p>"));
+    assert_eq!(store.render("welcome", Some("nl-BE"), &jane).unwrap().text, "Beste Jane <admin>, welkom.");
+    assert_eq!(store.render("welcome", Some("fr"), &jane).unwrap().subject, "Welcome Jane <admin>");
+    assert_eq!(store.render("password_reset", Some("nl"), &[("token", "abc")]).unwrap().text, "Token: abc");
+    assert!(matches!(store.render("password_reset", None, &jane), Err(IdentityError::MailTemplateIsInvalid(_))));
+    assert!(matches!(store.render("invitation", None, &jane), Err(IdentityError::MailTemplateNotFound(_))));
+
+    assert!(store.validate(&[("welcome", &["user_name"]), ("password_reset", &["user_name", "token"])]).is_ok());
+    assert!(matches!(store.validate(&[("invitation", &["link"])]), Err(IdentityError::MailTemplateNotFound(_))));
+    assert!(matches!(store.validate(&[("welcome", &["email"])]), Err(IdentityError::MailTemplateIsInvalid(_))));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_preferred_locale() {
+    assert_eq!(preferred_locale("nl-BE,nl;q=0.9,en;q=0.8").as_deref(), Some("nl-BE"));
+    assert_eq!(preferred_locale("en;q=0.5, fr ; q=0.7, de;q=0.6").as_deref(), Some("fr"));
+    assert_eq!(preferred_locale("*, nl;q=0.3").as_deref(), Some("nl"));
+    assert_eq!(preferred_locale("nl;q=0, en;q=bad").as_deref(), None);
+    assert_eq!(preferred_locale("").as_deref(), None);
+}
@@ -0,0 +1,620 @@
+use lettre::{ ClientSecurity, ClientTlsParameters, SendableEmail, SmtpClient, SmtpTransport, Transport };
+use lettre::smtp::{ ConnectionReuseParameters, SMTP_PORT, SUBMISSION_PORT, SUBMISSIONS_PORT };
+use lettre::smtp::authentication::{ Credentials, Mechanism };
+use native_tls::{ Certificate, Protocol, TlsConnector };
+use openssl::x509::X509;
+use std::path::PathBuf;
+use std::sync::{ Arc, Mutex };
+use std::time::Duration;
+use crate::util::get_value_from_key;
+use crate::IdentityError;
+
+lazy_static! {
+    /**
+     * Mails captured by the memory transport of PERSON_MAIL_TRANSPORT=memory.
+     */
+    static ref CAPTURED : MemoryTransport = MemoryTransport::default();
+}
+
+/**
+ * Way the mails of the outbox leave the server, the outbox task owns one. The error is returned as text so it can be logged with the attempt.
+ */
+pub trait MailTransport : Send {
+    /**
+     * Name of the transport, as it is logged.
+     */
+    fn name(&self) -> &str;
+
+    fn send(&mut self, mail : SendableEmail) -> Result<(), String>;
+
+    /**
+     * Called once the due mails have been sent, a transport that keeps its connection open closes it here.
+     */
+    fn close(&mut self) {}
+}
+
+/**
+ * Mail as a transport has received it: the envelope and the message as it would be sent.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct CapturedMail {
+    pub from : Option<String>,
+    pub to : Vec<String>,
+    pub message_id : String,
+    pub message : String
+}
+
+impl CapturedMail {
+    pub fn capture(mail : SendableEmail) -> Result<CapturedMail, String> {
+        let from = mail.envelope().from().map(|from| from.to_string());
+        let to = mail.envelope().to().iter().map(|to| to.to_string()).collect();
+        let message_id = mail.message_id().to_owned();
+        let message = mail.message_to_string().map_err(|e| e.to_string())?;
+        Ok(CapturedMail { from, to, message_id, message })
+    }
+}
+
+/**
+ * How the connection with the smtp server is secured: not at all, upgraded with STARTTLS or encrypted from the start (implicit TLS).
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmtpTls {
+    None,
+    StartTls,
+    Implicit
+}
+
+impl SmtpTls {
+    pub fn parse(name : &str) -> Option<SmtpTls> {
+        match name {
+            "none" => Some(SmtpTls::None),
+            "starttls" => Some(SmtpTls::StartTls),
+            "implicit" => Some(SmtpTls::Implicit),
+            _ => None
+        }
+    }
+
+    /**
+     * Returns the usual port of the mode: 25 without TLS, the submission port 587 for STARTTLS and 465 for implicit TLS.
+     */
+    pub fn default_port(self) -> u16 {
+        match self {
+            SmtpTls::None => SMTP_PORT,
+            SmtpTls::StartTls => SUBMISSION_PORT,
+            SmtpTls::Implicit => SUBMISSIONS_PORT
+        }
+    }
+}
+
+/**
+ * Settings of the connection with the smtp server, read from the PERSON_SMTP_ variables.
+ *
+ * Attributes:
+ * * host: PERSON_SMTP_HOST, or PERSON_SMTP_DOMAIN when it isn't set
+ * * port: PERSON_SMTP_PORT, the default port of the TLS mode when it isn't set
+ * * tls: PERSON_SMTP_TLS none, starttls or implicit, implicit by default
+ * * ca_bundle: PERSON_SMTP_CA_BUNDLE, PEM file with the certificates that are trusted instead of the ones of the system
+ * * credentials: PERSON_SMTP_USERNAME and PERSON_SMTP_PASSWORD, without an username the transport doesn't log in
+ * * mechanism: PERSON_SMTP_AUTH plain, login or xoauth2, by default plain or login is used once the connection is encrypted
+ * * timeout: PERSON_SMTP_TIMEOUT in seconds, 60 by default
+ * * reuse: PERSON_SMTP_CONNECTION_REUSE, the number of mails sent over one connection, 0 to connect for every mail, unlimited when it isn't set
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct SmtpSettings {
+    pub host : String,
+    pub port : u16,
+    pub tls : SmtpTls,
+    pub ca_bundle : Option<PathBuf>,
+    pub credentials : Option<(String, String)>,
+    pub mechanism : Option<Mechanism>,
+    pub timeout : Duration,
+    pub reuse : Option<u16>
+}
+
+impl SmtpSettings {
+    /**
+     * Reads the settings with the function that gives the value of a variable, returns an error when one is missing or invalid.
+     */
+    pub fn from_key(key : impl Fn(&str) -> Option<String>) -> Result<SmtpSettings, IdentityError> {
+        let setting = |name : &str| key(&format!("PERSON_SMTP_{}", name));
+        let missing = |name : &str| IdentityError::CustomError(format!("PERSON_SMTP_{} variable not found in the .env config file or as environment variable", name));
+        let invalid = |name : &str, value : &str, expected : &str| IdentityError::CustomError(format!("PERSON_SMTP_{} {} is not {}", name, value, expected));
+
+        let host = setting("HOST").or_else(|| setting("DOMAIN")).ok_or_else(|| missing("HOST"))?;
+        let tls = match setting("TLS") {
+            Some(name) => SmtpTls::parse(&name).ok_or_else(|| invalid("TLS", &name, "none, starttls or implicit"))?,
+            None => SmtpTls::Implicit
+        };
+        let port = match setting("PORT") {
+            Some(port) => port.parse().map_err(|_| invalid("PORT", &port, "a port"))?,
+            None => tls.default_port()
+        };
+        let credentials = match setting("USERNAME") {
+            Some(username) => Some((username, setting("PASSWORD").ok_or_else(|| missing("PASSWORD"))?)),
+            None => None
+        };
+        let mechanism = match setting("AUTH").as_deref() {
+            None => None,
+            Some("plain") => Some(Mechanism::Plain),
+            Some("login") => Some(Mechanism::Login),
+            Some("xoauth2") => Some(Mechanism::Xoauth2),
+            Some(other) => return Err(invalid("AUTH", other, "plain, login or xoauth2"))
+        };
+        let timeout = match setting("TIMEOUT") {
+            Some(timeout) => Duration::from_secs(timeout.parse().map_err(|_| invalid("TIMEOUT", &timeout, "a number of seconds"))?),
+            None => Duration::from_secs(60)
+        };
+        let reuse = match setting("CONNECTION_REUSE") {
+            Some(reuse) => Some(reuse.parse().map_err(|_| invalid("CONNECTION_REUSE", &reuse, "a number of mails"))?),
+            None => None
+        };
+        Ok(SmtpSettings { host, port, tls, ca_bundle : setting("CA_BUNDLE").map(PathBuf::from), credentials, mechanism, timeout, reuse })
+    }
+
+    pub fn from_config() -> SmtpSettings {
+        SmtpSettings::from_key(get_value_from_key).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /**
+     * Returns the TLS parameters of the host, at least TLS 1.2 is required. With a CA bundle only its certificates are trusted.
+     */
+    fn tls_parameters(&self) -> Result<ClientTlsParameters, IdentityError> {
+        let to_error = |e : &dyn std::fmt::Display| IdentityError::CustomError(format!("Invalid TLS settings for the smtp server: {}", e));
+        let mut builder = TlsConnector::builder();
+        builder.min_protocol_version(Some(Protocol::Tlsv12));
+        if let Some(bundle) = &self.ca_bundle {
+            let pem = std::fs::read(bundle).map_err(|e| IdentityError::CustomError(format!("Could not read the CA bundle {}: {}", bundle.display(), e)))?;
+            let certificates = X509::stack_from_pem(&pem).map_err(|e| to_error(&e))?;
+            if certificates.is_empty() {
+                return Err(IdentityError::CustomError(format!("The CA bundle {} holds no certificate", bundle.display())));
+            }
+            builder.disable_built_in_roots(true);
+            for certificate in certificates {
+                let der = certificate.to_der().map_err(|e| to_error(&e))?;
+                builder.add_root_certificate(Certificate::from_der(&der).map_err(|e| to_error(&e))?);
+            }
+        }
+        let connector = builder.build().map_err(|e| to_error(&e))?;
+        Ok(ClientTlsParameters::new(self.host.clone(), connector))
+    }
+
+    /**
+     * Returns the smtp client of the settings, the address of the host is resolved here.
+     */
+    pub fn client(&self) -> Result<SmtpClient, IdentityError> {
+        let security = match self.tls {
+            SmtpTls::None => ClientSecurity::None,
+            SmtpTls::StartTls => ClientSecurity::Required(self.tls_parameters()?),
+            SmtpTls::Implicit => ClientSecurity::Wrapper(self.tls_parameters()?)
+        };
+        let reuse = match self.reuse {
+            None => ConnectionReuseParameters::ReuseUnlimited,
+            Some(0) => ConnectionReuseParameters::NoReuse,
+            Some(limit) => ConnectionReuseParameters::ReuseLimited(limit)
+        };
+        let mut client = SmtpClient::new((self.host.as_str(), self.port), security)
+            .map_err(|e| IdentityError::CustomError(format!("Could not resolve the smtp server {}:{}: {}", self.host, self.port, e)))?
+            .timeout(Some(self.timeout))
+            .connection_reuse(reuse);
+        if let Some((username, password)) = &self.credentials {
+            client = client.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        if let Some(mechanism) = self.mechanism {
+            client = client.authentication_mechanism(mechanism);
+        }
+        Ok(client)
+    }
+}
+
+/**
+ * Sends the mails to an smtp server. The connection is kept open while the due mails are sent, as the settings allow, and closed afterwards.
+ */
+pub struct SmtpMailTransport(SmtpTransport);
+
+impl SmtpMailTransport {
+    pub fn new(transport : SmtpTransport) -> SmtpMailTransport {
+        SmtpMailTransport(transport)
+    }
+
+    pub fn from_settings(settings : &SmtpSettings) -> Result<SmtpMailTransport, IdentityError> {
+        Ok(SmtpMailTransport(settings.client()?.transport()))
+    }
+
+    pub fn from_config() -> SmtpMailTransport {
+        let settings = SmtpSettings::from_config();
+        info!("Mails are sent to {}:{} with TLS mode {:?}", settings.host, settings.port, settings.tls);
+        SmtpMailTransport::from_settings(&settings).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl MailTransport for SmtpMailTransport {
+    fn name(&self) -> &str {
+        "smtp"
+    }
+
+    fn send(&mut self, mail : SendableEmail) -> Result<(), String> {
+        self.0.send(mail).map(|_| ()).map_err(|e| e.to_string())
+    }
+
+    fn close(&mut self) {
+        self.0.close();
+    }
+}
+
+/**
+ * Drops every mail as a .eml file in a directory, named after the time it was sent and its message id.
+ */
+pub struct FileTransport {
+    dir : PathBuf
+}
+
+impl FileTransport {
+    pub fn new(dir : &str) -> FileTransport {
+        FileTransport { dir : PathBuf::from(dir) }
+    }
+}
+
+impl MailTransport for FileTransport {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    fn send(&mut self, mail : SendableEmail) -> Result<(), String> {
+        let mail = CapturedMail::capture(mail)?;
+        let file = self.dir.join(format!("{}_{}.eml", chrono::Utc::now().timestamp_millis(), mail.message_id.replace(['/', '\\'], "_")));
+        std::fs::create_dir_all(&self.dir)
+            .and_then(|_| std::fs::write(&file, mail.message))
+            .map_err(|e| format!("could not write {}: {}", file.display(), e))
+    }
+}
+
+/**
+ * Logs every mail instead of sending it.
+ */
+pub struct StdoutTransport;
+
+impl MailTransport for StdoutTransport {
+    fn name(&self) -> &str {
+        "stdout"
+    }
+
+    fn send(&mut self, mail : SendableEmail) -> Result<(), String> {
+        let mail = CapturedMail::capture(mail)?;
+        info!("----- mail {} from {} to {} -----\n{}", mail.message_id, mail.from.unwrap_or_default(), mail.to.join(", "), mail.message);
+        Ok(())
+    }
+}
+
+/**
+ * Keeps every mail in memory, so tests can assert on the mails that have been sent. Clones share the captured mails.
+ */
+#[derive(Clone, Default)]
+pub struct MemoryTransport {
+    mails : Arc<Mutex<Vec<CapturedMail>>>
+}
+
+impl MemoryTransport {
+    /**
+     * Returns the mails that have been sent, the oldest first.
+     */
+    pub fn get_mails(&self) -> Vec<CapturedMail> {
+        self.mails.lock().map(|mails| mails.clone()).unwrap_or_default()
+    }
+
+    /**
+     * Returns the mails that have been sent and forgets them.
+     */
+    pub fn take_mails(&self) -> Vec<CapturedMail> {
+        self.mails.lock().map(|mut mails| std::mem::take(&mut *mails)).unwrap_or_default()
+    }
+}
+
+impl MailTransport for MemoryTransport {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn send(&mut self, mail : SendableEmail) -> Result<(), String> {
+        let mail = CapturedMail::capture(mail)?;
+        self.mails.lock().map_err(|_| "the captured mails are locked".to_owned())?.push(mail);
+        Ok(())
+    }
+}
+
+/**
+ * Returns the memory transport that PERSON_MAIL_TRANSPORT=memory sends with, so the mails can be read in the same process.
+ */
+pub fn get_captured_mails() -> &'static MemoryTransport {
+    &CAPTURED
+}
+
+/**
+ * Returns true when the mails are sent with the smtp transport, which is the default.
+ */
+pub fn uses_smtp() -> bool {
+    get_value_from_key("PERSON_MAIL_TRANSPORT").as_deref().unwrap_or("smtp") == "smtp"
+}
+
+/**
+ * Returns the transport of PERSON_MAIL_TRANSPORT: smtp, the default, file into the directory of PERSON_MAIL_DIR, stdout or memory. Only the smtp transport needs the smtp settings.
+ */
+pub fn from_config() -> Box<dyn MailTransport> {
+    let transport : Box<dyn MailTransport> = match get_value_from_key("PERSON_MAIL_TRANSPORT").as_deref().unwrap_or("smtp") {
+        "smtp" => Box::new(SmtpMailTransport::from_config()),
+        "file" => Box::new(FileTransport::new(&get_value_from_key("PERSON_MAIL_DIR").unwrap_or_else(|| "mails".to_owned()))),
+        "stdout" => Box::new(StdoutTransport),
+        "memory" => Box::new(CAPTURED.clone()),
+        other => panic!("PERSON_MAIL_TRANSPORT {} is not smtp, file, stdout or memory", other)
+    };
+    info!("Mails are sent with the {} transport", transport.name());
+    transport
+}
+
+#[test]
+fn test_mail_transports() {
+    use lettre::{ EmailAddress, Envelope };
+
+    let mail = |id : &str| SendableEmail::new(
+        Envelope::new(Some(EmailAddress::new("noreply@corp.be".to_owned()).unwrap()), vec![EmailAddress::new("jane@corp.be".to_owned()).unwrap()]).unwrap(),
+        id.to_owned(),
+        b"Subject: Welcome\r\n\r\nWelcome jane\r\n".to_vec()
+    );
+    let mut memory = MemoryTransport::default();
+    let reader = memory.clone();
+    memory.send(mail("1")).unwrap();
+    assert_eq!(reader.get_mails(), vec![CapturedMail {
+        from : Some("noreply@corp.be".to_owned()),
+        to : vec!["jane@corp.be".to_owned()],
+        message_id : "1".to_owned(),
+        message : "Subject: Welcome\r\n\r\nWelcome jane\r\n".to_owned()
+    }]);
+    assert_eq!(reader.take_mails().len(), 1);
+    assert!(memory.get_mails().is_empty());
+
+    let dir = std::env::temp_dir().join(format!("mail_transport_test_{}", std::process::id()));
+    let mut file = FileTransport::new(dir.to_str().unwrap());
+    file.send(mail("2")).unwrap();
+    let dropped : Vec<PathBuf> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+    assert_eq!(dropped.len(), 1);
+    assert!(dropped[0].to_str().unwrap().ends_with("_2.eml"));
+    assert_eq!(std::fs::read_to_string(&dropped[0]).unwrap(), "Subject: Welcome\r\n\r\nWelcome jane\r\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/**
+ * SMTP server that runs in the tests. It speaks EHLO, STARTTLS, AUTH PLAIN and LOGIN, MAIL, RCPT, DATA and QUIT, with the TLS mode it is started with.
+ */
+#[cfg(test)]
+mod stub_smtp {
+    use std::io::{ Read, Write };
+    use std::net::{ TcpListener, TcpStream };
+    use std::sync::{ Arc, Mutex };
+    use native_tls::{ Identity, TlsAcceptor, TlsStream };
+    use openssl::asn1::Asn1Time;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use openssl::x509::{ X509, X509NameBuilder };
+    use openssl::x509::extension::SubjectAlternativeName;
+    use super::SmtpTls;
+
+    /**
+     * Connection the server served: whether it was encrypted, the mechanism, username and password the client logged in with and the mails it sent.
+     */
+    #[derive(Clone, Debug, Default)]
+    pub struct Session {
+        pub encrypted : bool,
+        pub login : Option<(String, String, String)>,
+        pub mails : Vec<String>
+    }
+
+    pub type Sessions = Arc<Mutex<Vec<Session>>>;
+
+    enum Stream {
+        Plain(TcpStream),
+        Tls(Box<TlsStream<TcpStream>>)
+    }
+
+    impl Read for Stream {
+        fn read(&mut self, buf : &mut [u8]) -> std::io::Result<usize> {
+            match self {
+                Stream::Plain(stream) => stream.read(buf),
+                Stream::Tls(stream) => stream.read(buf)
+            }
+        }
+    }
+
+    impl Write for Stream {
+        fn write(&mut self, buf : &[u8]) -> std::io::Result<usize> {
+            match self {
+                Stream::Plain(stream) => stream.write(buf),
+                Stream::Tls(stream) => stream.write(buf)
+            }
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            match self {
+                Stream::Plain(stream) => stream.flush(),
+                Stream::Tls(stream) => stream.flush()
+            }
+        }
+    }
+
+    /**
+     * Makes a self signed certificate for 127.0.0.1, returns it PEM encoded with the identity the server presents.
+     */
+    pub fn certificate() -> (Vec<u8>, Identity) {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "127.0.0.1").unwrap();
+        let name = name.build();
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+        let alternative_name = SubjectAlternativeName::new().ip("127.0.0.1").build(&builder.x509v3_context(None, None)).unwrap();
+        builder.append_extension(alternative_name).unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+        let pem = builder.build().to_pem().unwrap();
+        let identity = Identity::from_pkcs8(&pem, &key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+        (pem, identity)
+    }
+
+    /**
+     * Starts the server on a free port, every connection is served on its own thread. Returns the port and the sessions, a session is kept once the client quits.
+     */
+    pub fn start(tls : SmtpTls, identity : Identity) -> (u16, Sessions) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let acceptor = Arc::new(TlsAcceptor::new(identity).unwrap());
+        let sessions : Sessions = Arc::new(Mutex::new(Vec::new()));
+        let server_sessions = sessions.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (acceptor, sessions) = (acceptor.clone(), server_sessions.clone());
+                std::thread::spawn(move || serve(stream, tls, &acceptor, &sessions));
+            }
+        });
+        (port, sessions)
+    }
+
+    fn read_line(stream : &mut Stream) -> Option<String> {
+        let mut line = Vec::new();
+        let mut byte = [0u8];
+        while !line.ends_with(b"\r\n") {
+            if stream.read(&mut byte).ok()? == 0 {
+                return None;
+            }
+            line.push(byte[0]);
+        }
+        line.truncate(line.len() - 2);
+        String::from_utf8(line).ok()
+    }
+
+    fn decode(encoded : &str) -> String {
+        String::from_utf8(base64::decode(encoded).unwrap()).unwrap()
+    }
+
+    fn serve(stream : TcpStream, tls : SmtpTls, acceptor : &TlsAcceptor, sessions : &Sessions) {
+        let mut session = Session { encrypted : tls == SmtpTls::Implicit, ..Session::default() };
+        let mut stream = match tls {
+            SmtpTls::Implicit => match acceptor.accept(stream) {
+                Ok(stream) => Stream::Tls(Box::new(stream)),
+                Err(_) => return
+            },
+            _ => Stream::Plain(stream)
+        };
+        let reply = |stream : &mut Stream, text : &str| stream.write_all(format!("{}\r\n", text).as_bytes()).unwrap();
+        reply(&mut stream, "220 stub ESMTP");
+        while let Some(line) = read_line(&mut stream) {
+            let command = line.to_uppercase();
+            if command.starts_with("EHLO") {
+                let starttls = if tls == SmtpTls::StartTls && !session.encrypted { "250-STARTTLS\r\n" } else { "" };
+                reply(&mut stream, &format!("250-stub\r\n{}250-AUTH PLAIN LOGIN\r\n250 8BITMIME", starttls));
+            } else if command == "STARTTLS" {
+                reply(&mut stream, "220 ready to start TLS");
+                stream = match stream {
+                    Stream::Plain(stream) => Stream::Tls(Box::new(acceptor.accept(stream).unwrap())),
+                    encrypted => encrypted
+                };
+                session.encrypted = true;
+            } else if command.starts_with("AUTH PLAIN ") {
+                let response = decode(&line[11..]);
+                let parts : Vec<&str> = response.split('\0').collect();
+                session.login = Some(("PLAIN".to_owned(), parts[1].to_owned(), parts[2].to_owned()));
+                reply(&mut stream, "235 authenticated");
+            } else if command == "AUTH LOGIN" {
+                reply(&mut stream, &format!("334 {}", base64::encode("Username:")));
+                let username = decode(&read_line(&mut stream).unwrap());
+                reply(&mut stream, &format!("334 {}", base64::encode("Password:")));
+                let password = decode(&read_line(&mut stream).unwrap());
+                session.login = Some(("LOGIN".to_owned(), username, password));
+                reply(&mut stream, "235 authenticated");
+            } else if command == "DATA" {
+                reply(&mut stream, "354 end the mail with a dot");
+                let mut mail = Vec::new();
+                while let Some(line) = read_line(&mut stream).filter(|line| line != ".") {
+                    mail.push(line);
+                }
+                session.mails.push(mail.join("\r\n"));
+                reply(&mut stream, "250 queued");
+            } else if command == "QUIT" {
+                sessions.lock().unwrap().push(session);
+                reply(&mut stream, "221 bye");
+                return;
+            } else {
+                reply(&mut stream, "250 ok");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_smtp_settings() {
+    use lettre::{ EmailAddress, Envelope };
+
+    let settings = |values : &[(&str, &str)]| {
+        let values : Vec<(String, String)> = values.iter().map(|(key, value)| (format!("PERSON_SMTP_{}", key), value.to_string())).collect();
+        SmtpSettings::from_key(move |key| values.iter().find(|(name, _)| name == key).map(|(_, value)| value.clone()))
+    };
+    let default = settings(&[("DOMAIN", "smtp.corp.be"), ("USERNAME", "noreply@corp.be"), ("PASSWORD", "secret")]).unwrap();
+    assert_eq!((default.host.as_str(), default.port, default.tls, default.mechanism, default.timeout, default.reuse),
+        ("smtp.corp.be", 465, SmtpTls::Implicit, None, Duration::from_secs(60), None));
+    assert_eq!(settings(&[("HOST", "relay.corp.be"), ("DOMAIN", "smtp.corp.be"), ("TLS", "starttls")]).unwrap().port, 587);
+    assert!(settings(&[("HOST", "relay.corp.be"), ("USERNAME", "noreply@corp.be")]).is_err());
+    assert!(settings(&[("HOST", "relay.corp.be"), ("TLS", "ssl")]).is_err());
+    assert!(settings(&[("HOST", "relay.corp.be"), ("AUTH", "cram-md5")]).is_err());
+    assert!(settings(&[("TLS", "none")]).is_err());
+
+    let (pem, identity) = stub_smtp::certificate();
+    let bundle = std::env::temp_dir().join(format!("smtp_ca_bundle_test_{}.pem", std::process::id()));
+    std::fs::write(&bundle, &pem).unwrap();
+    let mail = |id : &str| SendableEmail::new(
+        Envelope::new(Some(EmailAddress::new("noreply@corp.be".to_owned()).unwrap()), vec![EmailAddress::new("jane@corp.be".to_owned()).unwrap()]).unwrap(),
+        id.to_owned(),
+        format!("Subject: Welcome\r\nMessage-ID: <{}@corp.be>\r\n\r\nWelcome jane\r\n", id).into_bytes()
+    );
+    let relay = |tls : SmtpTls, values : &[(&str, &str)]| {
+        let (port, sessions) = stub_smtp::start(tls, identity.clone());
+        let (port, bundle) = (port.to_string(), bundle.to_str().unwrap().to_owned());
+        let mut values = values.to_vec();
+        values.extend_from_slice(&[("HOST", "127.0.0.1"), ("PORT", &port), ("CA_BUNDLE", &bundle), ("TIMEOUT", "5")]);
+        (SmtpMailTransport::from_settings(&settings(&values).unwrap()).unwrap(), sessions)
+    };
+
+    let (mut plain, sessions) = relay(SmtpTls::None, &[("TLS", "none"), ("USERNAME", "relay"), ("PASSWORD", "secret"), ("AUTH", "plain")]);
+    plain.send(mail("1")).unwrap();
+    plain.send(mail("2")).unwrap();
+    plain.close();
+    let served = sessions.lock().unwrap().clone();
+    assert_eq!(served.len(), 1);
+    assert!(!served[0].encrypted);
+    assert_eq!(served[0].login, Some(("PLAIN".to_owned(), "relay".to_owned(), "secret".to_owned())));
+    assert_eq!(served[0].mails.len(), 2);
+    assert!(served[0].mails[0].contains("Message-ID: <1@corp.be>\r\n\r\nWelcome jane"));
+
+    let (mut starttls, sessions) = relay(SmtpTls::StartTls, &[("TLS", "starttls"), ("USERNAME", "relay"), ("PASSWORD", "secret"), ("AUTH", "login"), ("CONNECTION_REUSE", "1")]);
+    starttls.send(mail("3")).unwrap();
+    starttls.send(mail("4")).unwrap();
+    starttls.close();
+    let served = sessions.lock().unwrap().clone();
+    assert_eq!(served.len(), 2);
+    assert!(served.iter().all(|session| session.encrypted && session.mails.len() == 1));
+    assert_eq!(served[0].login, Some(("LOGIN".to_owned(), "relay".to_owned(), "secret".to_owned())));
+
+    let (mut implicit, sessions) = relay(SmtpTls::Implicit, &[("USERNAME", "relay"), ("PASSWORD", "secret")]);
+    implicit.send(mail("5")).unwrap();
+    implicit.close();
+    let served = sessions.lock().unwrap().clone();
+    assert!(served[0].encrypted);
+    assert_eq!(served[0].login.as_ref().map(|(mechanism, _, _)| mechanism.as_str()), Some("PLAIN"));
+
+    let (port, sessions) = stub_smtp::start(SmtpTls::Implicit, identity.clone());
+    let mut untrusted = SmtpMailTransport::from_settings(&settings(&[("HOST", "127.0.0.1"), ("PORT", &port.to_string()), ("TIMEOUT", "5")]).unwrap()).unwrap();
+    assert!(untrusted.send(mail("6")).is_err());
+    assert!(sessions.lock().unwrap().is_empty());
+    std::fs::remove_file(&bundle).unwrap();
+}
@@ -0,0 +1,419 @@
+use std::io::Read;
+use chrono::{ DateTime, Duration, Utc };
+use flate2::read::DeflateDecoder;
+use roxmltree::{ Document, Node };
+use identity_dal::saml::service_provider::{ AssertionConsumerService, ServiceProvider, NAME_ID_EMAIL, NAME_ID_PERSISTENT };
+pub use identity_dal::saml::service_provider::{ BINDING_HTTP_POST, BINDING_HTTP_REDIRECT };
+use identity_dal::util::get_hash;
+use crate::id_token;
+use crate::signing_key::SigningKey;
+use crate::store::StoreManager;
+use crate::util::get_value_from_key;
+use crate::IdentityError;
+
+lazy_static! {
+    static ref ENTITY_ID : String = get_value_from_key("PERSON_SAML_ENTITY_ID")
+    .unwrap_or_else(|| format!("{}/saml/metadata", id_token::issuer()));
+    static ref ASSERTION_EXPIRATION : i64 = get_value_from_key("PERSON_SAML_ASSERTION_EXPIRATION")
+    .unwrap_or_else(|| "300".to_owned())
+    .parse::<i64>().expect("Could not parse this string to i64");
+}
+
+pub static NS_METADATA : &str = "urn:oasis:names:tc:SAML:2.0:metadata";
+pub static NS_PROTOCOL : &str = "urn:oasis:names:tc:SAML:2.0:protocol";
+pub static NS_ASSERTION : &str = "urn:oasis:names:tc:SAML:2.0:assertion";
+pub static NS_XMLDSIG : &str = "http://www.w3.org/2000/09/xmldsig#";
+
+/**
+ * Name under which the certificate of the signing key is kept in the key store.
+ */
+static CERTIFICATE_NAME : &str = "saml_certificate";
+
+/**
+ * Largest SAML message that is accepted, a deflated request isn't inflated further than this.
+ */
+const MAX_MESSAGE_SIZE : u64 = 256 * 1024;
+
+/**
+ * Returns the entity id of the identity provider, by default this is the url of its metadata.
+ */
+pub fn entity_id() -> &'static str {
+    &ENTITY_ID
+}
+
+/**
+ * Returns the url on which service providers send their authentication requests.
+ */
+pub fn sso_url() -> String {
+    format!("{}/saml/sso", id_token::issuer())
+}
+
+/**
+ * Self signed certificate of the signing key, service providers use it to verify the signed assertions.
+ */
+pub struct IdpCertificate {
+    der : Vec<u8>
+}
+
+impl IdpCertificate {
+    /**
+     * Loads the certificate kept in the sled database. When there is none yet or it belongs to another key, because PERSON_OIDC_PRIVATE_KEY changed, a new one is made and stored.
+     */
+    pub fn load(manager : &StoreManager, key : &SigningKey) -> IdpCertificate {
+        let store = manager.give_key_store();
+        if let Some(der) = store.get_key(CERTIFICATE_NAME).filter(|der| key.is_certificate_of_key(der)) {
+            return IdpCertificate { der }
+        }
+        let certificate = IdpCertificate::generate(key).expect("Could not make a certificate of the signing key");
+        store.insert_key(CERTIFICATE_NAME, &certificate.der).expect("Could not store the certificate of the signing key");
+        info!("A new SAML certificate has been made for the signing key {}", key.get_kid());
+        certificate
+    }
+
+    /**
+     * Makes a new self signed certificate of the key.
+     */
+    pub fn generate(key : &SigningKey) -> Result<IdpCertificate, IdentityError> {
+        Ok(IdpCertificate { der : key.self_signed_certificate(entity_id())? })
+    }
+
+    pub fn get_der(&self) -> &[u8] { &self.der }
+
+    /**
+     * Returns the certificate base64 encoded, as it is put in XML documents.
+     */
+    pub fn get_base64(&self) -> String {
+        base64::encode(&self.der)
+    }
+}
+
+/**
+ * Authentication request of a service provider.
+ *
+ * Attributes:
+ * * id: id of the request, the response refers to it
+ * * issuer: entity id of the service provider
+ * * assertion_consumer_service_url: endpoint the service provider wants the response to be sent to
+ * * xml: the request itself
+ */
+pub struct AuthnRequest {
+    id : String,
+    issuer : String,
+    assertion_consumer_service_url : Option<String>,
+    xml : String
+}
+
+impl AuthnRequest {
+    /**
+     * Decodes a request that was sent with the HTTP-Redirect binding, such a request is deflated and base64 encoded.
+     */
+    pub fn from_redirect_binding(saml_request : &str) -> Result<AuthnRequest, IdentityError> {
+        let deflated = decode_base64(saml_request)?;
+        let mut xml = String::new();
+        DeflateDecoder::new(deflated.as_slice()).take(MAX_MESSAGE_SIZE).read_to_string(&mut xml)
+            .map_err(|_| IdentityError::SamlRequestIsInvalid("the request could not be inflated".to_owned()))?;
+        AuthnRequest::parse(&xml)
+    }
+
+    /**
+     * Decodes a request that was sent with the HTTP-POST binding, such a request is only base64 encoded.
+     */
+    pub fn from_post_binding(saml_request : &str) -> Result<AuthnRequest, IdentityError> {
+        let bytes = decode_base64(saml_request)?;
+        let xml = String::from_utf8(bytes).map_err(|_| IdentityError::SamlRequestIsInvalid("the request is not UTF-8".to_owned()))?;
+        AuthnRequest::parse(&xml)
+    }
+
+    /**
+     * Decodes a request of the given binding.
+     */
+    pub fn from_binding(binding : &str, saml_request : &str) -> Result<AuthnRequest, IdentityError> {
+        if binding == BINDING_HTTP_REDIRECT {
+            AuthnRequest::from_redirect_binding(saml_request)
+        } else if binding == BINDING_HTTP_POST {
+            AuthnRequest::from_post_binding(saml_request)
+        } else {
+            Err(IdentityError::SamlRequestIsInvalid(format!("binding {} is not supported", binding)))
+        }
+    }
+
+    /**
+     * Parses the XML of an authentication request.
+     */
+    pub fn parse(xml : &str) -> Result<AuthnRequest, IdentityError> {
+        let invalid = |reason : &str| IdentityError::SamlRequestIsInvalid(reason.to_owned());
+        if xml.len() as u64 > MAX_MESSAGE_SIZE {
+            return Err(invalid("the request is too large"))
+        }
+        let document = Document::parse(xml).map_err(|_| invalid("the request is not valid XML"))?;
+        let root = document.root_element();
+        if !has_name(&root, NS_PROTOCOL, "AuthnRequest") {
+            return Err(invalid("the message is not an AuthnRequest"))
+        }
+        if root.attribute("Version") != Some("2.0") {
+            return Err(invalid("only SAML 2.0 is supported"))
+        }
+        if let Some(destination) = root.attribute("Destination") {
+            if destination != sso_url() {
+                return Err(invalid("the destination is not this identity provider"))
+            }
+        }
+        if let Some(binding) = root.attribute("ProtocolBinding") {
+            if binding != BINDING_HTTP_POST {
+                return Err(invalid("the response can only be sent with the HTTP-POST binding"))
+            }
+        }
+        let id = root.attribute("ID").filter(|id| !id.is_empty()).ok_or_else(|| invalid("the request has no ID"))?;
+        let issuer = root.children()
+            .find(|node| has_name(node, NS_ASSERTION, "Issuer"))
+            .and_then(|node| node.text())
+            .map(str::trim)
+            .filter(|issuer| !issuer.is_empty())
+            .ok_or_else(|| invalid("the request has no issuer"))?;
+        Ok(AuthnRequest {
+            id : id.to_owned(),
+            issuer : issuer.to_owned(),
+            assertion_consumer_service_url : root.attribute("AssertionConsumerServiceURL").map(str::to_owned),
+            xml : xml.to_owned()
+        })
+    }
+
+    pub fn get_id(&self) -> &str { &self.id }
+
+    pub fn get_issuer(&self) -> &str { &self.issuer }
+
+    pub fn get_assertion_consumer_service_url(&self) -> Option<&str> { self.assertion_consumer_service_url.as_deref() }
+
+    /**
+     * Returns the request encoded for the HTTP-POST binding.
+     */
+    pub fn to_post_binding(&self) -> String {
+        base64::encode(&self.xml)
+    }
+}
+
+/**
+ * Attribute of the user that is put in an assertion.
+ */
+pub struct SamlAttribute {
+    pub name : String,
+    pub values : Vec<String>
+}
+
+impl SamlAttribute {
+    pub fn new(name : &str, values : Vec<String>) -> SamlAttribute {
+        SamlAttribute { name : name.to_owned(), values }
+    }
+}
+
+/**
+ * Makes a service provider out of its metadata. The metadata has to describe exactly one entity with at least one assertion consumer service of the HTTP-POST binding.
+ */
+pub fn parse_service_provider_metadata(xml : &str) -> Result<ServiceProvider, IdentityError> {
+    let invalid = |reason : &str| IdentityError::ServiceProviderMetadataIsInvalid(reason.to_owned());
+    let document = Document::parse(xml).map_err(|_| invalid("the metadata is not valid XML"))?;
+    let entities : Vec<Node> = document.descendants().filter(|node| has_name(node, NS_METADATA, "EntityDescriptor")).collect();
+    let entity = match entities.as_slice() {
+        [entity] => entity,
+        _ => return Err(invalid("the metadata has to describe exactly one entity"))
+    };
+    let entity_id = entity.attribute("entityID").filter(|id| !id.is_empty()).ok_or_else(|| invalid("the entity has no entityID"))?;
+    let descriptor = entity.children()
+        .find(|node| has_name(node, NS_METADATA, "SPSSODescriptor"))
+        .ok_or_else(|| invalid("the entity is not a service provider"))?;
+    let mut services = Vec::new();
+    for node in descriptor.children().filter(|node| has_name(node, NS_METADATA, "AssertionConsumerService")) {
+        let binding = node.attribute("Binding").ok_or_else(|| invalid("an assertion consumer service has no binding"))?;
+        let location = node.attribute("Location")
+            .filter(|location| location.starts_with("https://") || location.starts_with("http://"))
+            .ok_or_else(|| invalid("an assertion consumer service has no valid location"))?;
+        let is_default = matches!(node.attribute("isDefault"), Some("true") | Some("1"));
+        services.push(AssertionConsumerService::new(binding, location, is_default));
+    }
+    let name_id_format = descriptor.children()
+        .filter(|node| has_name(node, NS_METADATA, "NameIDFormat"))
+        .filter_map(|node| node.text())
+        .map(str::trim)
+        .find(|format| *format == NAME_ID_PERSISTENT || *format == NAME_ID_EMAIL);
+    let provider = ServiceProvider::new(entity_id, services, name_id_format);
+    if provider.get_assertion_consumer_service(None).is_none() {
+        return Err(invalid("the service provider has no assertion consumer service of the HTTP-POST binding"))
+    }
+    Ok(provider)
+}
+
+/**
+ * Makes the response to an authentication request, the assertion in it is signed with an enveloped XML signature.
+ *
+ * The XML is written in the form exclusive canonicalization gives it, so the digest and signature can be calculated over the written text.
+ */
+pub fn build_response(
+    request : &AuthnRequest,
+    provider : &ServiceProvider,
+    destination : &str,
+    name_id : &str,
+    attributes : &[SamlAttribute],
+    key : &SigningKey,
+    certificate : &IdpCertificate
+) -> Result<String, IdentityError> {
+    let now = Utc::now();
+    let issue_instant = format_instant(now);
+    let not_before = format_instant(now - Duration::seconds(60));
+    let not_on_or_after = format_instant(now + Duration::seconds(*ASSERTION_EXPIRATION));
+    let response_id = format!("_{}", get_hash(40));
+    let assertion_id = format!("_{}", get_hash(40));
+    let issuer = format!("<saml:Issuer>{}</saml:Issuer>", escape_text(entity_id()));
+    let attribute_statement : String = attributes.iter().map(|attribute| format!(
+        r#"<saml:Attribute Name="{}" NameFormat="urn:oasis:names:tc:SAML:2.0:attrname-format:basic">{}</saml:Attribute>"#,
+        escape_attribute(&attribute.name),
+        attribute.values.iter().map(|value| format!("<saml:AttributeValue>{}</saml:AttributeValue>", escape_text(value))).collect::<String>()
+    )).collect();
+    let assertion_body = format!(
+        concat!(
+            r#"<saml:Subject><saml:NameID Format="{format}">{name_id}</saml:NameID>"#,
+            r#"<saml:SubjectConfirmation Method="urn:oasis:names:tc:SAML:2.0:cm:bearer">"#,
+            r#"<saml:SubjectConfirmationData InResponseTo="{in_response_to}" NotOnOrAfter="{not_on_or_after}" Recipient="{destination}"></saml:SubjectConfirmationData>"#,
+            r#"</saml:SubjectConfirmation></saml:Subject>"#,
+            r#"<saml:Conditions NotBefore="{not_before}" NotOnOrAfter="{not_on_or_after}">"#,
+            r#"<saml:AudienceRestriction><saml:Audience>{audience}</saml:Audience></saml:AudienceRestriction></saml:Conditions>"#,
+            r#"<saml:AuthnStatement AuthnInstant="{issue_instant}" SessionIndex="{assertion_id}"><saml:AuthnContext>"#,
+            r#"<saml:AuthnContextClassRef>urn:oasis:names:tc:SAML:2.0:ac:classes:PasswordProtectedTransport</saml:AuthnContextClassRef>"#,
+            r#"</saml:AuthnContext></saml:AuthnStatement>"#,
+            r#"<saml:AttributeStatement>{attributes}</saml:AttributeStatement>"#
+        ),
+        format = escape_attribute(provider.get_name_id_format()),
+        name_id = escape_text(name_id),
+        in_response_to = escape_attribute(request.get_id()),
+        not_on_or_after = not_on_or_after,
+        destination = escape_attribute(destination),
+        not_before = not_before,
+        audience = escape_text(provider.get_entity_id()),
+        issue_instant = issue_instant,
+        assertion_id = assertion_id,
+        attributes = attribute_statement
+    );
+    let assertion_start = format!(
+        r#"<saml:Assertion xmlns:saml="{}" ID="{}" IssueInstant="{}" Version="2.0">"#,
+        NS_ASSERTION, assertion_id, issue_instant
+    );
+    let digest = ring::digest::digest(&ring::digest::SHA256, format!("{}{}{}</saml:Assertion>", assertion_start, issuer, assertion_body).as_bytes());
+    let signed_info = format!(
+        concat!(
+            r#"<ds:SignedInfo><ds:CanonicalizationMethod Algorithm="http://www.w3.org/2001/10/xml-exc-c14n#"></ds:CanonicalizationMethod>"#,
+            r#"<ds:SignatureMethod Algorithm="http://www.w3.org/2001/04/xmldsig-more#rsa-sha256"></ds:SignatureMethod>"#,
+            r##"<ds:Reference URI="#{}"><ds:Transforms>"##,
+            r#"<ds:Transform Algorithm="http://www.w3.org/2000/09/xmldsig#enveloped-signature"></ds:Transform>"#,
+            r#"<ds:Transform Algorithm="http://www.w3.org/2001/10/xml-exc-c14n#"></ds:Transform></ds:Transforms>"#,
+            r#"<ds:DigestMethod Algorithm="http://www.w3.org/2001/04/xmlenc#sha256"></ds:DigestMethod>"#,
+            r#"<ds:DigestValue>{}</ds:DigestValue></ds:Reference></ds:SignedInfo>"#
+        ),
+        assertion_id,
+        base64::encode(digest.as_ref())
+    );
+    let signature_value = key.sign_rsa_sha256(canonical_signed_info(&signed_info).as_bytes())?;
+    let signature = format!(
+        r#"<ds:Signature xmlns:ds="{}">{}<ds:SignatureValue>{}</ds:SignatureValue><ds:KeyInfo><ds:X509Data><ds:X509Certificate>{}</ds:X509Certificate></ds:X509Data></ds:KeyInfo></ds:Signature>"#,
+        NS_XMLDSIG, signed_info, base64::encode(&signature_value), certificate.get_base64()
+    );
+    Ok(format!(
+        concat!(
+            r#"<samlp:Response xmlns:samlp="{protocol}" xmlns:saml="{assertion}" ID="{response_id}" Version="2.0" IssueInstant="{issue_instant}" Destination="{destination}" InResponseTo="{in_response_to}">"#,
+            r#"{issuer}<samlp:Status><samlp:StatusCode Value="urn:oasis:names:tc:SAML:2.0:status:Success"></samlp:StatusCode></samlp:Status>"#,
+            r#"{assertion_start}{issuer}{signature}{assertion_body}</saml:Assertion></samlp:Response>"#
+        ),
+        protocol = NS_PROTOCOL,
+        assertion = NS_ASSERTION,
+        response_id = response_id,
+        issue_instant = issue_instant,
+        destination = escape_attribute(destination),
+        in_response_to = escape_attribute(request.get_id()),
+        issuer = issuer,
+        assertion_start = assertion_start,
+        signature = signature,
+        assertion_body = assertion_body
+    ))
+}
+
+/**
+ * Returns the metadata of the identity provider, service providers are configured with it.
+ */
+pub fn idp_metadata(certificate : &IdpCertificate) -> String {
+    format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#, "\n",
+            r#"<md:EntityDescriptor xmlns:md="{metadata}" xmlns:ds="{xmldsig}" entityID="{entity_id}">"#, "\n",
+            r#"  <md:IDPSSODescriptor WantAuthnRequestsSigned="false" protocolSupportEnumeration="{protocol}">"#, "\n",
+            r#"    <md:KeyDescriptor use="signing"><ds:KeyInfo><ds:X509Data><ds:X509Certificate>{certificate}</ds:X509Certificate></ds:X509Data></ds:KeyInfo></md:KeyDescriptor>"#, "\n",
+            r#"    <md:NameIDFormat>{email}</md:NameIDFormat>"#, "\n",
+            r#"    <md:NameIDFormat>{persistent}</md:NameIDFormat>"#, "\n",
+            r#"    <md:SingleSignOnService Binding="{redirect}" Location="{sso}"/>"#, "\n",
+            r#"    <md:SingleSignOnService Binding="{post}" Location="{sso}"/>"#, "\n",
+            r#"  </md:IDPSSODescriptor>"#, "\n",
+            r#"</md:EntityDescriptor>"#, "\n"
+        ),
+        metadata = NS_METADATA,
+        xmldsig = NS_XMLDSIG,
+        entity_id = escape_attribute(entity_id()),
+        protocol = NS_PROTOCOL,
+        certificate = certificate.get_base64(),
+        email = NAME_ID_EMAIL,
+        persistent = NAME_ID_PERSISTENT,
+        redirect = BINDING_HTTP_REDIRECT,
+        post = BINDING_HTTP_POST,
+        sso = escape_attribute(&sso_url())
+    )
+}
+
+/**
+ * Returns the SignedInfo element in its canonical form, in which the namespace of its prefix is declared on it.
+ */
+pub fn canonical_signed_info(signed_info : &str) -> String {
+    signed_info.replacen("<ds:SignedInfo>", &format!(r#"<ds:SignedInfo xmlns:ds="{}">"#, NS_XMLDSIG), 1)
+}
+
+fn has_name(node : &Node, namespace : &str, name : &str) -> bool {
+    node.is_element() && node.tag_name().name() == name && node.tag_name().namespace() == Some(namespace)
+}
+
+fn decode_base64(value : &str) -> Result<Vec<u8>, IdentityError> {
+    let value : String = value.chars().filter(|c| !c.is_whitespace()).collect();
+    base64::decode(&value).map_err(|_| IdentityError::SamlRequestIsInvalid("the request is not base64 encoded".to_owned()))
+}
+
+fn format_instant(instant : DateTime<Utc>) -> String {
+    instant.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/**
+ * Escapes text content the way canonical XML does.
+ */
+fn escape_text(value : &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('\r', "&#xD;")
+}
+
+/**
+ * Escapes an attribute value the way canonical XML does.
+ */
+fn escape_attribute(value : &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('"', "&quot;")
+        .replace('\t', "&#x9;").replace('\n', "&#xA;").replace('\r', "&#xD;")
+}
+
+#[test]
+fn test_parse_service_provider_metadata() {
+    let metadata = r#"<?xml version="1.0"?>
+<md:EntityDescriptor xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata" entityID="https://sp.example.com/metadata">
+  <md:SPSSODescriptor protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+    <md:NameIDFormat>urn:oasis:names:tc:SAML:2.0:nameid-format:persistent</md:NameIDFormat>
+    <md:AssertionConsumerService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST" Location="https://sp.example.com/acs" index="0" isDefault="true"/>
+  </md:SPSSODescriptor>
+</md:EntityDescriptor>"#;
+    let provider = parse_service_provider_metadata(metadata).unwrap();
+    assert_eq!(provider.get_entity_id(), "https://sp.example.com/metadata");
+    assert_eq!(provider.get_name_id_format(), NAME_ID_PERSISTENT);
+    assert_eq!(provider.get_assertion_consumer_service(None).unwrap().get_location(), "https://sp.example.com/acs");
+
+    let without_post = metadata.replace(BINDING_HTTP_POST, BINDING_HTTP_REDIRECT);
+    assert!(parse_service_provider_metadata(&without_post).is_err());
+    assert!(parse_service_provider_metadata("<md:EntityDescriptor").is_err());
+}
@@ -0,0 +1,376 @@
+use serde_json::Value;
+use crate::IdentityError;
+
+pub static SCHEMA_USER : &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+pub static SCHEMA_GROUP : &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+pub static SCHEMA_LIST_RESPONSE : &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+pub static SCHEMA_PATCH_OP : &str = "urn:ietf:params:scim:api:messages:2.0:PatchOp";
+pub static SCHEMA_ERROR : &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+pub static SCHEMA_SERVICE_PROVIDER_CONFIG : &str = "urn:ietf:params:scim:schemas:core:2.0:ServiceProviderConfig";
+pub static SCHEMA_RESOURCE_TYPE : &str = "urn:ietf:params:scim:schemas:core:2.0:ResourceType";
+pub static SCHEMA_SCHEMA : &str = "urn:ietf:params:scim:schemas:core:2.0:Schema";
+
+/**
+ * Filter of a SCIM list request, e.g. userName eq "jane@corp.be" and active pr.
+ *
+ * Attribute names are compared case insensitive and so are string values, as the core attributes aren't case exact.
+ */
+#[derive(Debug, PartialEq)]
+pub enum Filter {
+    Compare(String, String, Value),
+    Present(String),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    Open,
+    Close,
+    Word(String),
+    Text(String)
+}
+
+fn invalid_filter(reason : &str) -> IdentityError {
+    IdentityError::InvalidFilter(reason.to_owned())
+}
+
+fn tokenize(filter : &str) -> Result<Vec<Token>, IdentityError> {
+    let mut tokens = Vec::new();
+    let mut chars = filter.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {},
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => text.push(chars.next().ok_or_else(|| invalid_filter("unfinished escape"))?),
+                        Some(c) => text.push(c),
+                        None => return Err(invalid_filter("unfinished string"))
+                    }
+                }
+                tokens.push(Token::Text(text));
+            },
+            '[' | ']' => return Err(invalid_filter("complex attribute filters are not supported")),
+            c => {
+                let mut word = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == '(' || next == ')' || next == '"' {
+                        break
+                    }
+                    if next == '[' || next == ']' {
+                        return Err(invalid_filter("complex attribute filters are not supported"))
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens : Vec<Token>,
+    position : usize
+}
+
+impl Parser {
+    fn peek_keyword(&self, keyword : &str) -> bool {
+        matches!(self.tokens.get(self.position), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, IdentityError> {
+        let mut filter = self.parse_and()?;
+        while self.peek_keyword("or") {
+            self.position += 1;
+            filter = Filter::Or(Box::new(filter), Box::new(self.parse_and()?));
+        }
+        Ok(filter)
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, IdentityError> {
+        let mut filter = self.parse_term()?;
+        while self.peek_keyword("and") {
+            self.position += 1;
+            filter = Filter::And(Box::new(filter), Box::new(self.parse_term()?));
+        }
+        Ok(filter)
+    }
+
+    fn parse_term(&mut self) -> Result<Filter, IdentityError> {
+        if self.peek_keyword("not") {
+            self.position += 1;
+            return Ok(Filter::Not(Box::new(self.parse_term()?)))
+        }
+        match self.next() {
+            Some(Token::Open) => {
+                let filter = self.parse_or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(filter),
+                    _ => Err(invalid_filter("a parenthesis is not closed"))
+                }
+            },
+            Some(Token::Word(attribute)) => {
+                let operator = match self.next() {
+                    Some(Token::Word(operator)) => operator.to_lowercase(),
+                    _ => return Err(invalid_filter("an operator is missing"))
+                };
+                if operator == "pr" {
+                    return Ok(Filter::Present(attribute))
+                }
+                if !["eq", "ne", "co", "sw", "ew", "gt", "ge", "lt", "le"].contains(&operator.as_str()) {
+                    return Err(invalid_filter(&format!("operator {} is not known", operator)))
+                }
+                let value = match self.next() {
+                    Some(Token::Text(text)) => Value::String(text),
+                    Some(Token::Word(word)) => serde_json::from_str(&word).map_err(|_| invalid_filter(&format!("value {} is not valid", word)))?,
+                    _ => return Err(invalid_filter("a value is missing"))
+                };
+                Ok(Filter::Compare(attribute, operator, value))
+            },
+            _ => Err(invalid_filter("an attribute is missing"))
+        }
+    }
+}
+
+impl Filter {
+    /**
+     * Parses a filter, an InvalidFilter error is returned when it isn't valid or uses complex attribute filters.
+     */
+    pub fn parse(filter : &str) -> Result<Filter, IdentityError> {
+        let mut parser = Parser { tokens : tokenize(filter)?, position : 0 };
+        let parsed = parser.parse_or()?;
+        if parser.position != parser.tokens.len() {
+            return Err(invalid_filter("the filter has trailing parts"))
+        }
+        Ok(parsed)
+    }
+
+    /**
+     * Returns true if the resource matches the filter.
+     */
+    pub fn matches(&self, resource : &Value) -> bool {
+        match self {
+            Filter::And(left, right) => left.matches(resource) && right.matches(resource),
+            Filter::Or(left, right) => left.matches(resource) || right.matches(resource),
+            Filter::Not(filter) => !filter.matches(resource),
+            Filter::Present(attribute) => resolve(resource, attribute).iter().any(|value| !value.is_null() && value.as_str() != Some("")),
+            Filter::Compare(attribute, operator, expected) => {
+                let values = resolve(resource, attribute);
+                if operator == "ne" {
+                    return !values.iter().any(|value| compare(value, "eq", expected))
+                }
+                values.iter().any(|value| compare(value, operator, expected))
+            }
+        }
+    }
+}
+
+/**
+ * Returns the values of an attribute path like name.givenName, the values of multi valued attributes are all returned. For complex values without a sub attribute the value sub attribute is used.
+ */
+fn resolve<'a>(resource : &'a Value, path : &str) -> Vec<&'a Value> {
+    let mut values = vec![resource];
+    for part in path.split('.') {
+        values = values.into_iter()
+            .flat_map(|value| match value {
+                Value::Array(items) => items.iter().collect(),
+                value => vec![value]
+            })
+            .filter_map(|value| get_case_insensitive(value, part))
+            .collect();
+    }
+    values.into_iter()
+        .flat_map(|value| match value {
+            Value::Array(items) => items.iter().collect(),
+            value => vec![value]
+        })
+        .map(|value| match value {
+            Value::Object(_) => get_case_insensitive(value, "value").unwrap_or(value),
+            value => value
+        })
+        .collect()
+}
+
+fn get_case_insensitive<'a>(value : &'a Value, key : &str) -> Option<&'a Value> {
+    value.as_object()?.iter().find(|(name, _)| name.eq_ignore_ascii_case(key)).map(|(_, value)| value)
+}
+
+fn compare(value : &Value, operator : &str, expected : &Value) -> bool {
+    match (value, expected) {
+        (Value::String(value), Value::String(expected)) => {
+            let (value, expected) = (value.to_lowercase(), expected.to_lowercase());
+            match operator {
+                "eq" => value == expected,
+                "co" => value.contains(&expected),
+                "sw" => value.starts_with(&expected),
+                "ew" => value.ends_with(&expected),
+                "gt" => value > expected,
+                "ge" => value >= expected,
+                "lt" => value < expected,
+                "le" => value <= expected,
+                _ => false
+            }
+        },
+        (Value::Number(value), Value::Number(expected)) => {
+            let (value, expected) = (value.as_f64().unwrap_or_default(), expected.as_f64().unwrap_or_default());
+            match operator {
+                "eq" => (value - expected).abs() < f64::EPSILON,
+                "gt" => value > expected,
+                "ge" => value >= expected,
+                "lt" => value < expected,
+                "le" => value <= expected,
+                _ => false
+            }
+        },
+        (value, expected) => operator == "eq" && value == expected
+    }
+}
+
+/**
+ * Applies a PATCH operation to the JSON representation of a resource.
+ *
+ * Paths are attribute paths like displayName or name.givenName, or a multi valued attribute with a filter like members[value eq "2"], optionally followed by a sub attribute. Without a path the value has to be an object whose attributes are added or replaced.
+ */
+pub fn apply_patch_operation(resource : &mut Value, op : &str, path : Option<&str>, value : Option<&Value>) -> Result<(), IdentityError> {
+    let invalid = |reason : &str| IdentityError::InvalidRequest(reason.to_owned());
+    let op = op.to_lowercase();
+    if !["add", "replace", "remove"].contains(&op.as_str()) {
+        return Err(invalid("op has to be add, replace or remove"))
+    }
+    let path = match path.map(str::trim).filter(|path| !path.is_empty()) {
+        Some(path) => path,
+        None => {
+            if op == "remove" {
+                return Err(IdentityError::InvalidRequest("a remove operation needs a path".to_owned()))
+            }
+            let attributes = value.and_then(Value::as_object).ok_or_else(|| invalid("the value of an operation without path has to be an object"))?;
+            for (name, value) in attributes {
+                apply_patch_operation(resource, &op, Some(name), Some(value))?;
+            }
+            return Ok(())
+        }
+    };
+    let (attribute, filter, sub_attribute) = match path.find('[') {
+        Some(start) => {
+            let end = path.rfind(']').filter(|end| *end > start).ok_or_else(|| invalid("the path has an unclosed filter"))?;
+            let sub = path[end + 1..].trim_start_matches('.');
+            (&path[..start], Some(Filter::parse(&path[start + 1..end])?), if sub.is_empty() { None } else { Some(sub) })
+        },
+        None => (path, None, None)
+    };
+    let (parents, last) = match attribute.rsplit_once('.') {
+        Some((parents, last)) if filter.is_none() => (Some(parents), last),
+        _ => (None, attribute)
+    };
+    let mut target = resource;
+    if let Some(parents) = parents {
+        for part in parents.split('.') {
+            target = object_entry(target, part, op != "remove").ok_or_else(|| invalid("the path can't be followed"))?;
+        }
+    }
+    let object = match target.as_object_mut() {
+        Some(object) => object,
+        None => return Err(invalid("the path can't be followed"))
+    };
+    let key = object.keys().find(|name| name.eq_ignore_ascii_case(last)).cloned().unwrap_or_else(|| last.to_owned());
+    match filter {
+        None => match op.as_str() {
+            "remove" => { object.remove(&key); },
+            "add" => match (object.get_mut(&key), value) {
+                (Some(Value::Array(items)), Some(Value::Array(new_items))) => {
+                    for item in new_items {
+                        if !items.contains(item) {
+                            items.push(item.clone());
+                        }
+                    }
+                },
+                (_, Some(value)) => { object.insert(key, value.clone()); },
+                (_, None) => return Err(invalid("an add operation needs a value"))
+            },
+            _ => { object.insert(key, value.cloned().ok_or_else(|| invalid("a replace operation needs a value"))?); }
+        },
+        Some(filter) => {
+            let items = match object.get_mut(&key) {
+                Some(Value::Array(items)) => items,
+                _ if op == "remove" => return Ok(()),
+                _ => return Err(invalid("the path doesn't point to a multi valued attribute"))
+            };
+            match (op.as_str(), sub_attribute) {
+                ("remove", None) => items.retain(|item| !filter.matches(item)),
+                ("remove", Some(sub)) => items.iter_mut().filter(|item| filter.matches(item)).for_each(|item| {
+                    if let Some(item) = item.as_object_mut() {
+                        item.retain(|name, _| !name.eq_ignore_ascii_case(sub));
+                    }
+                }),
+                (_, sub) => {
+                    let value = value.ok_or_else(|| invalid("the operation needs a value"))?;
+                    for item in items.iter_mut().filter(|item| filter.matches(item)) {
+                        match (sub, item.as_object_mut()) {
+                            (Some(sub), Some(item)) => { item.insert(sub.to_owned(), value.clone()); },
+                            _ => *item = value.clone()
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn object_entry<'a>(value : &'a mut Value, key : &str, create : bool) -> Option<&'a mut Value> {
+    let object = value.as_object_mut()?;
+    let existing = object.keys().find(|name| name.eq_ignore_ascii_case(key)).cloned();
+    match existing {
+        Some(existing) => object.get_mut(&existing),
+        None if create => Some(object.entry(key.to_owned()).or_insert_with(|| Value::Object(Default::default()))),
+        None => None
+    }
+}
+
+#[test]
+fn test_filter() {
+    let user = serde_json::json!({
+        "userName" : "Jane@corp.be",
+        "name" : { "givenName" : "Jane" },
+        "emails" : [{ "value" : "jane@corp.be", "primary" : true }],
+        "active" : true
+    });
+    assert!(Filter::parse(r#"username eq "jane@corp.be""#).unwrap().matches(&user));
+    assert!(Filter::parse(r#"emails eq "jane@corp.be" and name.givenName sw "ja""#).unwrap().matches(&user));
+    assert!(Filter::parse(r#"userName eq "john@corp.be" or (active eq true and not (externalId pr))"#).unwrap().matches(&user));
+    assert!(!Filter::parse(r#"userName ne "jane@corp.be""#).unwrap().matches(&user));
+    assert!(Filter::parse(r#"emails[type eq "work"]"#).is_err());
+    assert!(Filter::parse(r#"userName eq"#).is_err());
+}
+
+#[test]
+fn test_apply_patch_operation() {
+    let mut group = serde_json::json!({
+        "displayName" : "sales",
+        "members" : [{ "value" : "1" }, { "value" : "2" }]
+    });
+    apply_patch_operation(&mut group, "add", Some("members"), Some(&serde_json::json!([{ "value" : "3" }]))).unwrap();
+    apply_patch_operation(&mut group, "remove", Some(r#"members[value eq "1"]"#), None).unwrap();
+    apply_patch_operation(&mut group, "replace", None, Some(&serde_json::json!({ "displayName" : "marketing" }))).unwrap();
+    apply_patch_operation(&mut group, "Add", Some("name.formatted"), Some(&serde_json::json!("Marketing"))).unwrap();
+    assert_eq!(group, serde_json::json!({
+        "displayName" : "marketing",
+        "members" : [{ "value" : "2" }, { "value" : "3" }],
+        "name" : { "formatted" : "Marketing" }
+    }));
+    assert!(apply_patch_operation(&mut group, "remove", None, None).is_err());
+}
@@ -1,4 +1,5 @@
 use crate::claim::Claim;
+use crate::hooks::HookRegistry;
 use crate::store::Store;
 use identity_dal::traits::t_user::UserTrait;
 use identity_dal::user::identity_user::IdentityUser;
@@ -8,9 +9,58 @@ use crate::viewmodels::admin::delete_user::DeleteUserViewModel;
 use crate::viewmodels::admin::update_user::AdminUpdateUserViewModel;
 use crate::viewmodels::admin::update_user_pwd::AdminChangePasswordUserViewModel;
 use crate::viewmodels::admin::all_users::AllNonAdminUsersViewModel;
+use crate::viewmodels::auth::person_info::PersonInfoViewModel;
+use crate::viewmodels::auth::user_id::UserIdViewModel;
+use crate::viewmodels::federation::linked_identity::LinkedIdentitiesViewModel;
+use identity_dal::repo::federation_repo::FederationStore;
 use identity_dal::traits::t_admin_manager::AdminStoreTrait;
+use identity_dal::repo::client_repo::ClientStore;
+use identity_dal::repo::token_repo::TokenStore;
+use crate::util::hash_token;
+use crate::service::oidc_service;
 use crate::IdentityError;
 
+/**
+ * Scope an OAuth client needs in its access token to be able to use the admin functions.
+ */
+pub static ADMIN_SCOPE : &str = "admin";
+
+/**
+ * Controls that a token may be used for the admin functions and returns its claim. This is the case for a token of the admin itself, or for an OAuth token with the admin scope of a client that isn't disabled and that hasn't been revoked. Such a client token is either one the client got for itself through the client credentials grant or one it got on behalf of the admin.
+ *
+ * An error is returned when:
+ * * the token can't be decoded
+ * * the token is that of an user that isn't the admin
+ * * the OAuth token hasn't got the admin scope or has been revoked
+ * * the client of the OAuth token doesn't exist anymore or is disabled
+ */
+pub fn control_admin_token(token : &str, db : &Store, clients : &ClientStore, tokens : &TokenStore) -> Result<Claim, IdentityError> {
+    let claim = Claim::decode_token(token)?.claims;
+    if let Some(client_id) = &claim.client_id {
+        if tokens.is_access_token_revoked(&hash_token(token)) {
+            warn!("A revoked token of client {} has been used", client_id);
+            return Err(IdentityError::TokenIsInvalid)
+        }
+        if !oidc_service::has_scope(claim.scope.as_deref().unwrap_or_default(), ADMIN_SCOPE) {
+            warn!("Client {} used a token without the admin scope", client_id);
+            return Err(IdentityError::InsufficientScope)
+        }
+        match clients.get_client(client_id) {
+            Some(client) if !client.is_disabled() => {},
+            Some(_) => return Err(IdentityError::ClientIsDisabled),
+            None => return Err(IdentityError::ClientNotFound)
+        }
+        if claim.is_client_claim() {
+            return Ok(claim)
+        }
+    }
+    if db.is_id_admin(&claim.sub) {
+        return Ok(claim)
+    }
+    warn!("Token user id isn't that one of the admin");
+    Err(IdentityError::IdNotEqualToAdmin)
+}
+
 /**
  * Function that the admin is used to create an user with its personal email, password and id.
  * 
@@ -18,12 +68,16 @@ use crate::IdentityError;
  * * the password and its confirmation aren't the same
  * * if the user's email already is taken
  * * When the id from the token is not the right one, that of an admin
+ * * a pre registration hook vetoes the user
  */
 pub fn create_user(
     token : &str,
     model: AdminCreateUserViewModel,
     id: &str,
-    db: Store
+    db: Store,
+    clients: ClientStore,
+    tokens: TokenStore,
+    hooks : &HookRegistry
 ) -> Result<IdentityUser, IdentityError> {
     if model.get_confirmed_password() != model.get_password() {
         warn!("A password and its confirmation has to be the same");
@@ -33,73 +87,87 @@ pub fn create_user(
         warn!("The email is already taken in the sled database");
         return Err(IdentityError::EmailIsAlreadyTaken)
     }
-    let claim_token = Claim::decode_token(token)?;
-    if db.is_id_admin(&claim_token.claims.sub) {
-        let person = match IdentityUser::new_user_with_personal_id(id,model.get_email(),"",model.get_password()) {
-            Ok(user) => user,
-            Err(e) => {
-                error!("An user could not be made");
-                return Err(IdentityError::CustomError(format!("{}",e)))
-            }
-        };
-        return match db.add_user(person) {
-            Ok(user) => Ok(user),
-            Err(_) => {
-                error!("Could not add a user to the sled database");
-                Err(IdentityError::UserCannotBeAdded)
-            }
+    control_admin_token(token, &db, &clients, &tokens)?;
+    hooks.before("registration", |hook| hook.pre_registration(model.get_email()))?;
+    let mut person = match IdentityUser::new_user_with_personal_id(id,model.get_email(),"",model.get_password()) {
+        Ok(user) => user,
+        Err(e) => {
+            error!("An user could not be made");
+            return Err(IdentityError::CustomError(format!("{}",e)))
+        }
+    };
+    person.set_locale(model.get_locale());
+    match db.add_user(person) {
+        Ok(user) => {
+            let info = PersonInfoViewModel::from_identity_user(&user);
+            hooks.after("registration", |hook| hook.post_registration(&info));
+            Ok(user)
+        },
+        Err(_) => {
+            error!("Could not add a user to the sled database");
+            Err(IdentityError::UserCannotBeAdded)
         }
     }
-    warn!("The user id wasn't that of an admin. id: {}",&claim_token.claims.sub);
-    Err(IdentityError::IdEqualsAdmin)
 }
 
 /**
- * Controls the id of an token so that it is equal to that of an admin. The user id that comes in the viewmodel is used to delete the user.
+ * Controls the id of an token so that it is equal to that of an admin. The user id that comes in the viewmodel is used to delete the user, the pre delete hooks can veto it.
  */
 pub fn delete_user(
     token : &str,
     model : DeleteUserViewModel,
-    db : Store
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore,
+    hooks : &HookRegistry
 ) -> Result<bool,IdentityError> {
-    let claim_token = Claim::decode_token(token)?;
-    if db.is_id_admin(&claim_token.claims.sub) {
-        return Ok(db.delete_user(model.get_user_id()).expect("The deletion of the user didn't succeed."))
-    } 
-    warn!("Token user id isn't that one of the admin");
-    Err(IdentityError::IdNotEqualToAdmin)
+    control_admin_token(token, &db, &clients, &tokens)?;
+    let user = db.get_user_by_uuid(model.get_user_id()).ok_or(IdentityError::UserNotFound)?;
+    let info = PersonInfoViewModel::from_identity_user(&user);
+    hooks.before("deletion", |hook| hook.pre_delete(&info))?;
+    let deleted = db.delete_user(user.get_id()).expect("The deletion of the user didn't succeed.");
+    hooks.after("deletion", |hook| hook.post_delete(&info));
+    Ok(deleted)
 }
 
 /**
- * Controls the id of an token so that it is equal to that of an admin. It will then seek the id of the user if it exists and update this user on the attributes that aren't empty in the viewmodel(new_email, new_first_name,new_last_name).
+ * Controls the id of an token so that it is equal to that of an admin. It will then seek the id of the user if it exists and update this user on the attributes that aren't empty in the viewmodel(new_email, new_first_name,new_last_name,new_locale).
  * 
  * An error is thrown when:
  * * id isn't that of one of the admin
  * * token is empty
  * * password and confirmation pasword aren't the same
  * * user id isn't mapped to an user
+ * * a pre update hook vetoes the update
 */
 pub fn update_user(
     token : &str,
     model : AdminUpdateUserViewModel,
-    db : Store
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore,
+    hooks : &HookRegistry
 ) -> Result<bool,IdentityError> {
-    let claim_token = Claim::decode_token(token)?;
-    if db.is_id_admin(&claim_token.claims.sub) {
-        let mut user = db.get_user_by_uuid(model.get_user_id())
-            .expect("Could not map the user id to an actual user in the sled database.");
-        if let Some(new_email) = &model.new_email {
-            if !db.is_email_taken(&new_email) {
-                user.set_email(new_email).expect("Could not change the email of the user.");
-            }
+    control_admin_token(token, &db, &clients, &tokens)?;
+    let user = db.get_user_by_uuid(model.get_user_id())
+        .expect("Could not map the user id to an actual user in the sled database.");
+    let mut updated = user.clone();
+    if let Some(new_email) = &model.new_email {
+        if !db.is_email_taken(&new_email) {
+            updated.set_email(new_email).expect("Could not change the email of the user.");
         }
-        if let Some(new_user_name) = &model.new_user_name {
-            user.set_user_name(new_user_name);
-        }
-        return Ok(db.update_user(model.get_user_id(), &user).expect("Could not update a user."))
-    } 
-    warn!("Token user id isn't that one of the admin");
-    Err(IdentityError::IdNotEqualToAdmin)
+    }
+    if let Some(new_user_name) = &model.new_user_name {
+        updated.set_user_name(new_user_name);
+    }
+    if let Some(new_locale) = &model.new_locale {
+        updated.set_locale(Some(new_locale));
+    }
+    let (info, updated_info) = (PersonInfoViewModel::from_identity_user(&user), PersonInfoViewModel::from_identity_user(&updated));
+    hooks.before("update", |hook| hook.pre_update(&info, &updated_info))?;
+    let result = db.update_user(model.get_user_id(), &updated).expect("Could not update a user.");
+    hooks.after("update", |hook| hook.post_update(&updated_info));
+    Ok(result)
 }
 
 /**
@@ -110,29 +178,33 @@ pub fn update_user(
  * * token is empty
  * * password and confirmation pasword aren't the same
  * * user id isn't mapped to an user
+ * * a pre password change hook vetoes the change
  */
 pub fn update_user_pwd(
     token : &str,
     model : AdminChangePasswordUserViewModel,
-    db : Store
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore,
+    hooks : &HookRegistry
 ) -> Result<bool,IdentityError> {
-    let claim_token = Claim::decode_token(token)?;
-    if db.is_id_admin(&claim_token.claims.sub) {
-        if model.get_password().is_empty() {
-            return Err(IdentityError::PasswordIsEmpty)
-        }
-        if model.get_password() != model.get_confirm_password() {
-            return Err(IdentityError::PasswordAndPasswordConfirmedNotEqual)
-        }
-        let mut user = db.get_user_by_uuid(model.get_id_user())
-            .expect("Could not map the user id to an actual user in the sled database.");
-        return match user.set_password(&model.get_password()) {
-            Ok(_) => db.update_user(user.get_id(), &user),
-            Err(e) => Err(IdentityError::CustomError(format!("{}",e))),
-        }
+    control_admin_token(token, &db, &clients, &tokens)?;
+    if model.get_password().is_empty() {
+        return Err(IdentityError::PasswordIsEmpty)
     }
-    warn!("Token user id isn't that one of the admin");
-    Err(IdentityError::IdNotEqualToAdmin)
+    if model.get_password() != model.get_confirm_password() {
+        return Err(IdentityError::PasswordAndPasswordConfirmedNotEqual)
+    }
+    let mut user = db.get_user_by_uuid(model.get_id_user())
+        .expect("Could not map the user id to an actual user in the sled database.");
+    let info = PersonInfoViewModel::from_identity_user(&user);
+    hooks.before("password change", |hook| hook.pre_password_change(&info))?;
+    let result = match user.set_password(&model.get_password()) {
+        Ok(_) => db.update_user(user.get_id(), &user)?,
+        Err(e) => return Err(IdentityError::CustomError(format!("{}",e))),
+    };
+    hooks.after("password change", |hook| hook.post_password_change(&info));
+    Ok(result)
 }
 
 /**
@@ -140,12 +212,28 @@ pub fn update_user_pwd(
  */
 pub fn get_all_users(
     token : &str,
-    db : Store
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore
 ) -> Result<AllNonAdminUsersViewModel,IdentityError> {
-    let claim_token = Claim::decode_token(token)?;
-    if db.is_id_admin(&claim_token.claims.sub) {
-        return Ok(AllNonAdminUsersViewModel::from_users_vector(db.get_non_admin_users()))
+    control_admin_token(token, &db, &clients, &tokens)?;
+    Ok(AllNonAdminUsersViewModel::from_users_vector(db.get_non_admin_users()))
+}
+
+/**
+ * Returns the external identities that are linked to an user, can only be called through a admin user.
+ */
+pub fn get_linked_identities_of_user(
+    token : &str,
+    model : UserIdViewModel,
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore,
+    federation : FederationStore
+) -> Result<LinkedIdentitiesViewModel,IdentityError> {
+    control_admin_token(token, &db, &clients, &tokens)?;
+    if db.get_user_by_uuid(model.get_id()).is_none() {
+        return Err(IdentityError::UserNotFound)
     }
-    warn!("Token user id isn't that one of the admin");
-    Err(IdentityError::IdNotEqualToAdmin)
-}
\ No newline at end of file
+    Ok(LinkedIdentitiesViewModel::from_identities_vector(federation.get_linked_identities_of_user(model.get_id())))
+}
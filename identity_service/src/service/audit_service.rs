@@ -0,0 +1,210 @@
+use crate::claim::Claim;
+use crate::signing_key::SigningKey;
+use crate::store::Store;
+use crate::service::admin_service;
+use crate::util::get_value_from_key;
+use crate::viewmodels::admin::audit::{ AllAuditEventsViewModel, AuditChainViewModel, AuditCheckpointViewModel, AuditQueryViewModel };
+use identity_dal::audit::audit_event::AuditOutcome;
+use identity_dal::repo::audit_repo::AuditQuery;
+use identity_dal::repo::client_repo::ClientStore;
+use identity_dal::repo::token_repo::TokenStore;
+use serde::Serialize;
+use std::path::Path;
+use std::thread::{ self, JoinHandle };
+use std::time::Duration;
+use crate::IdentityError;
+
+pub type AuditStore = identity_dal::repo::audit_repo::AuditStore;
+pub type AuditEvent = identity_dal::audit::audit_event::AuditEvent;
+pub type AuditEventType = identity_dal::audit::audit_event::AuditEventType;
+
+/**
+ * Amount of events a query returns when it doesn't ask for a limit, and the most it may ask for.
+ */
+static DEFAULT_LIMIT : usize = 100;
+static MAX_LIMIT : usize = 1000;
+
+lazy_static! {
+    static ref CHECKPOINT_DIR : Option<String> = get_value_from_key("PERSON_AUDIT_CHECKPOINT_DIR");
+    static ref CHECKPOINT_INTERVAL : u64 = get_value_from_key("PERSON_AUDIT_CHECKPOINT_INTERVAL")
+    .unwrap_or_else(|| "86400".to_owned())
+    .parse::<u64>()
+    .expect("PERSON_AUDIT_CHECKPOINT_INTERVAL has to be a number of seconds");
+}
+
+/**
+ * Claims of a checkpoint: the last event of the audit log at the time it was made. As long as the signed checkpoint is kept outside of the database, the events up to it can't be rewritten without it being noticed.
+ */
+#[derive(Serialize)]
+struct AuditCheckpoint {
+    last_id : u64,
+    last_hash : String,
+    entries : usize,
+    iat : i64
+}
+
+/**
+ * Returns the base64url encoded SHA256 hash of the content of an audit event, with which the events are chained.
+ */
+pub fn chain_hash(content : &[u8]) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, content);
+    base64::encode_config(digest.as_ref(), base64::URL_SAFE_NO_PAD)
+}
+
+/**
+ * Records an event in the audit log. A failure to record is only logged, it never makes the action itself fail.
+ */
+pub fn record(audit : &AuditStore, event : AuditEvent) {
+    if let Err(e) = audit.append(event, chain_hash) {
+        error!("An audit event could not be recorded: {}", e);
+    }
+}
+
+/**
+ * Returns the outcome of the result of an action.
+ */
+pub fn outcome_of<T>(result : &Result<T, IdentityError>) -> AuditOutcome {
+    match result {
+        Ok(_) => AuditOutcome::Success,
+        Err(e) => AuditOutcome::Failure(e.to_string())
+    }
+}
+
+/**
+ * Returns who did a request with the token: the client for a token a client got for itself, otherwise the user. None is returned for a token that can't be decoded.
+ */
+pub fn actor_of_token(token : &str) -> Option<String> {
+    let claim = Claim::decode_token(token).ok()?.claims;
+    if claim.is_client_claim() {
+        return claim.client_id
+    }
+    Some(claim.sub)
+}
+
+/**
+ * Turns the viewmodel into a query, an unknown event type is refused.
+ */
+fn to_query(model : AuditQueryViewModel) -> Result<AuditQuery, IdentityError> {
+    let event_type = match model.event_type.as_deref().filter(|name| !name.trim().is_empty()) {
+        Some(name) => Some(AuditEventType::parse(name).ok_or_else(|| IdentityError::InvalidRequest(format!("{} is not an audit event type", name)))?),
+        None => None
+    };
+    Ok(AuditQuery {
+        user : model.user.filter(|user| !user.is_empty()),
+        actor : model.actor.filter(|actor| !actor.is_empty()),
+        event_type,
+        from : model.from,
+        to : model.to,
+        limit : Some(model.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT))
+    })
+}
+
+/**
+ * Admin function that returns the audit events that match the query, the newest first.
+ */
+pub fn query_events(
+    token : &str,
+    model : AuditQueryViewModel,
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore,
+    audit : AuditStore
+) -> Result<AllAuditEventsViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    Ok(AllAuditEventsViewModel::from_events_vector(audit.query(&to_query(model)?)))
+}
+
+/**
+ * Admin function that walks the hash chain of the audit log and reports the first broken link.
+ */
+pub fn verify_audit_log(token : &str, db : Store, clients : ClientStore, tokens : TokenStore, audit : AuditStore) -> Result<AuditChainViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    let verification = audit.verify_chain(chain_hash);
+    if let Some(id) = verification.broken_at {
+        error!("The audit log has been changed at event {}", id);
+    }
+    Ok(AuditChainViewModel::from_verification(verification))
+}
+
+/**
+ * Verifies the audit log and writes a checkpoint of its last event, signed with the signing key, to a file in the directory. A broken or empty log gets no checkpoint.
+ */
+fn checkpoint(audit : &AuditStore, key : &SigningKey, dir : &Path) -> Result<AuditCheckpointViewModel, IdentityError> {
+    let verification = audit.verify_chain(chain_hash);
+    if let Some(id) = verification.broken_at {
+        return Err(IdentityError::AuditLogIsBroken(id))
+    }
+    let (last_id, last_hash) = match (verification.last_id, verification.last_hash) {
+        (Some(last_id), Some(last_hash)) => (last_id, last_hash),
+        _ => return Err(IdentityError::InvalidRequest("the audit log is empty".to_owned()))
+    };
+    let signed = key.sign(&AuditCheckpoint { last_id, last_hash : last_hash.clone(), entries : verification.checked, iat : chrono::Utc::now().timestamp() })?;
+    let file = dir.join(format!("audit_checkpoint_{}.jwt", last_id));
+    std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&file, signed))
+        .map_err(|e| IdentityError::CustomError(format!("The audit checkpoint could not be written: {}", e)))?;
+    info!("An audit checkpoint up to event {} has been written to {}", last_id, file.display());
+    Ok(AuditCheckpointViewModel { file : file.display().to_string(), last_id, last_hash, entries : verification.checked })
+}
+
+/**
+ * Admin function that exports a signed checkpoint of the audit log to PERSON_AUDIT_CHECKPOINT_DIR right away.
+ */
+pub fn export_checkpoint(
+    token : &str,
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore,
+    audit : AuditStore,
+    key : &SigningKey
+) -> Result<AuditCheckpointViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    let dir = CHECKPOINT_DIR.as_ref().ok_or_else(|| IdentityError::InvalidRequest("PERSON_AUDIT_CHECKPOINT_DIR is not set".to_owned()))?;
+    checkpoint(&audit, key, Path::new(dir))
+}
+
+/**
+ * Starts the background task that exports a signed checkpoint at the configured interval. No task is started when PERSON_AUDIT_CHECKPOINT_DIR isn't set.
+ */
+pub fn start_checkpoint_task(audit : AuditStore, key : SigningKey) -> Option<JoinHandle<()>> {
+    let dir = CHECKPOINT_DIR.clone()?;
+    let interval = Duration::from_secs(*CHECKPOINT_INTERVAL);
+    Some(thread::spawn(move || loop {
+        thread::sleep(interval);
+        match checkpoint(&audit, &key, Path::new(&dir)) {
+            Ok(_) | Err(IdentityError::InvalidRequest(_)) => {},
+            Err(e) => error!("No audit checkpoint could be made: {}", e)
+        }
+    }))
+}
+
+#[test]
+fn test_audit_query_viewmodel() {
+    let query = to_query(AuditQueryViewModel { event_type : Some("Password_Reset".to_owned()), limit : Some(5000), ..AuditQueryViewModel::default() }).unwrap();
+    assert_eq!(query.event_type, Some(AuditEventType::PasswordReset));
+    assert_eq!(query.limit, Some(MAX_LIMIT));
+    assert_eq!(to_query(AuditQueryViewModel::default()).unwrap().limit, Some(DEFAULT_LIMIT));
+    assert!(matches!(to_query(AuditQueryViewModel { event_type : Some("logout".to_owned()), ..AuditQueryViewModel::default() }), Err(IdentityError::InvalidRequest(_))));
+    assert!(matches!(outcome_of::<()>(&Err(IdentityError::PasswordIsNotCorrect)), AuditOutcome::Failure(_)));
+}
+
+#[test]
+fn test_audit_checkpoint() {
+    use identity_dal::repo::user_config::UserConfig;
+
+    let audit = AuditStore::new_db(UserConfig::new_config("", "person", 100000));
+    let key = SigningKey::generate().unwrap();
+    let dir = std::env::temp_dir().join(format!("audit_checkpoint_test_{}", std::process::id()));
+    assert!(matches!(checkpoint(&audit, &key, &dir), Err(IdentityError::InvalidRequest(_))));
+
+    record(&audit, AuditEvent::new(AuditEventType::Login, "login", AuditOutcome::Success).with_target(Some("jane@corp.be")));
+    record(&audit, AuditEvent::new(AuditEventType::Deletion, "delete_user", AuditOutcome::Success).with_actor(Some("jane")));
+    let exported = checkpoint(&audit, &key, &dir).unwrap();
+    assert_eq!((exported.entries, Some(exported.last_id)), (2, audit.get_last_event().map(|event| event.get_id())));
+    assert_eq!(std::fs::read_to_string(&exported.file).unwrap().split('.').count(), 3);
+
+    let last = audit.get_last_event().unwrap();
+    audit.audit_db_tree.insert(last.get_id().to_be_bytes(), &last.with_actor(Some("admin"))).unwrap();
+    assert!(matches!(checkpoint(&audit, &key, &dir), Err(IdentityError::AuditLogIsBroken(id)) if id == exported.last_id));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
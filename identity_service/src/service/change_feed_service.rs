@@ -0,0 +1,121 @@
+use crate::store::Store;
+use crate::service::admin_service;
+use crate::util::get_value_from_key;
+use crate::viewmodels::admin::user_change::{ ChangeFeedQueryViewModel, UserChangeViewModel, UserChangesViewModel };
+use crate::viewmodels::auth::person_info::PersonInfoViewModel;
+use identity_dal::repo::client_repo::ClientStore;
+use identity_dal::repo::token_repo::TokenStore;
+use identity_dal::traits::t_user_manager::UserStoreTrait;
+use identity_dal::user::user_change::{ UserChange, UserChangeKind };
+use std::thread::{ self, JoinHandle };
+use std::time::Duration;
+use crate::IdentityError;
+
+pub type ChangeFeedStore = identity_dal::repo::change_feed_repo::ChangeFeedStore;
+
+/**
+ * Amount of changes a page has when the consumer doesn't ask for a limit, and the most it may ask for.
+ */
+static DEFAULT_LIMIT : usize = 100;
+static MAX_LIMIT : usize = 1000;
+
+lazy_static! {
+    static ref MAX_WAIT : u64 = get_value_from_key("PERSON_CHANGE_FEED_MAX_WAIT")
+    .unwrap_or_else(|| "30".to_owned())
+    .parse::<u64>()
+    .expect("PERSON_CHANGE_FEED_MAX_WAIT has to be a number of seconds");
+}
+
+/**
+ * Starts the task that records every change of the user tree in the change feed. Users that were created or deleted while the server was down are recorded first.
+ */
+pub fn start_change_feed(db : Store, feed : ChangeFeedStore) -> JoinHandle<()> {
+    let subscriber = db.user_db_tree.watch_prefix(vec![]);
+    match feed.backfill(&db) {
+        Ok(0) => {},
+        Ok(recorded) => info!("{} missed user changes have been added to the change feed", recorded),
+        Err(e) => error!("The change feed could not be brought up to date: {}", e)
+    }
+    thread::spawn(move || {
+        for event in subscriber {
+            if let Err(e) = feed.record_event(&event) {
+                error!("A change of user {} could not be added to the change feed: {}", String::from_utf8_lossy(event.key()), e);
+            }
+        }
+    })
+}
+
+/**
+ * Returns the changes after the cursor. When there are none yet, it waits for the next change until the wait time has passed.
+ */
+fn changes_after(cursor : u64, limit : usize, wait : Duration, feed : &ChangeFeedStore) -> Vec<UserChange> {
+    let mut subscriber = feed.watch_changes();
+    let changes = feed.get_changes_after(cursor, limit);
+    if !changes.is_empty() || wait.as_secs() == 0 {
+        return changes
+    }
+    let _ = subscriber.next_timeout(wait);
+    feed.get_changes_after(cursor, limit)
+}
+
+/**
+ * Turns changes into a page of the feed, the cursor of the next page is the sequence of the last change.
+ */
+fn to_page(changes : Vec<UserChange>, cursor : u64, db : &Store) -> UserChangesViewModel {
+    UserChangesViewModel {
+        cursor : changes.last().map_or(cursor, UserChange::get_sequence),
+        changes : changes.iter().map(|change| {
+            let user = match change.get_kind() {
+                UserChangeKind::Deleted => None,
+                _ => db.get_user_by_uuid(change.get_user_id()).map(|user| PersonInfoViewModel::from_identity_user(&user))
+            };
+            UserChangeViewModel::from_change(change, user)
+        }).collect()
+    }
+}
+
+/**
+ * Admin function that returns the changes of the user records after the cursor, the oldest first. When there are none it long-polls: it waits up to the asked amount of seconds, at most PERSON_CHANGE_FEED_MAX_WAIT, for the next change. A consumer keeps the returned cursor to resume after a restart.
+ */
+pub fn get_changes(
+    token : &str,
+    model : ChangeFeedQueryViewModel,
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore,
+    feed : ChangeFeedStore
+) -> Result<UserChangesViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    let cursor = model.cursor.unwrap_or(0);
+    let limit = model.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let wait = Duration::from_secs(model.wait.unwrap_or(0).min(*MAX_WAIT));
+    Ok(to_page(changes_after(cursor, limit, wait, &feed), cursor, &db))
+}
+
+#[test]
+fn test_long_poll_change_feed() {
+    use identity_dal::repo::user_config::UserConfig;
+    use identity_dal::traits::t_user::UserTrait;
+    use identity_dal::user::identity_user::IdentityUser;
+
+    let config = UserConfig::new_config("", "person", 100000);
+    let (db, feed) = (Store::new_db(config.clone()), ChangeFeedStore::new_db(config));
+    let jane = db.add_user(IdentityUser::new_user("jane@corp.be", "jane", "Passw0rd!").unwrap()).unwrap();
+    start_change_feed(db.clone(), feed.clone());
+    let page = to_page(changes_after(0, 10, Duration::from_secs(0), &feed), 0, &db);
+    assert_eq!((page.changes.len(), page.cursor), (1, feed.get_last_sequence()));
+    assert!(changes_after(page.cursor, 10, Duration::from_secs(0), &feed).is_empty());
+
+    let writer = db.clone();
+    let john = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        writer.add_user(IdentityUser::new_user("john@corp.be", "john", "Passw0rd!").unwrap()).unwrap()
+    });
+    let changes = changes_after(page.cursor, 10, Duration::from_secs(5), &feed);
+    let john = john.join().unwrap();
+    assert_eq!(changes.iter().map(|change| (change.get_kind(), change.get_user_id())).collect::<Vec<(UserChangeKind, &str)>>(), vec![(UserChangeKind::Created, john.get_id())]);
+
+    db.delete_user(jane.get_id()).unwrap();
+    let page = to_page(changes_after(changes[0].get_sequence(), 10, Duration::from_secs(5), &feed), changes[0].get_sequence(), &db);
+    assert!(page.changes[0].user.is_none());
+}
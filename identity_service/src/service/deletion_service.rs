@@ -0,0 +1,178 @@
+use crate::store::Store;
+use crate::service::admin_service;
+use crate::util::get_value_from_key;
+use crate::viewmodels::admin::deleted_user::AllDeletedUsersViewModel;
+use crate::viewmodels::auth::person_info::PersonInfoViewModel;
+use crate::viewmodels::auth::user_id::UserIdViewModel;
+use identity_dal::repo::client_repo::ClientStore;
+use identity_dal::repo::federation_repo::FederationStore;
+use identity_dal::repo::group_repo::GroupStore;
+use identity_dal::repo::ldap_repo::LdapStore;
+use identity_dal::repo::mail_outbox_repo::MailOutboxStore;
+use identity_dal::repo::scim_repo::ScimStore;
+use identity_dal::repo::token_repo::TokenStore;
+use identity_dal::repo::webhook_repo::WebhookStore;
+use identity_dal::user::identity_user::IdentityUser;
+use identity_dal::traits::t_user::UserTrait;
+use identity_dal::user::tombstone::Tombstone;
+use std::thread::{ self, JoinHandle };
+use std::time::Duration;
+use crate::IdentityError;
+
+lazy_static! {
+    static ref DELETION_GRACE_PERIOD : i64 = get_value_from_key("PERSON_DELETION_GRACE_PERIOD")
+    .unwrap_or_else(|| "2592000".to_owned())
+    .parse::<i64>()
+    .expect("PERSON_DELETION_GRACE_PERIOD has to be a number of seconds");
+    static ref PURGE_INTERVAL : u64 = get_value_from_key("PERSON_PURGE_INTERVAL")
+    .unwrap_or_else(|| "3600".to_owned())
+    .parse::<u64>()
+    .expect("PERSON_PURGE_INTERVAL has to be a number of seconds");
+}
+
+/**
+ * Stores that hold data of an user, everything of a purged user is removed from them.
+ */
+pub struct PurgeStores {
+    pub db : Store,
+    pub groups : GroupStore,
+    pub scim : ScimStore,
+    pub federation : FederationStore,
+    pub ldap : LdapStore,
+    pub tokens : TokenStore,
+    pub outbox : MailOutboxStore,
+    pub webhooks : WebhookStore
+}
+
+/**
+ * Removes what other stores hold of an user that has been purged, the mails to him and the webhook deliveries about him included.
+ */
+fn remove_user_data(tombstone : &Tombstone, stores : &PurgeStores) -> Result<(), IdentityError> {
+    let user_id = tombstone.get_id();
+    stores.groups.remove_member_from_all(user_id)?;
+    stores.scim.remove_user(user_id);
+    stores.federation.remove_linked_identities_of_user(user_id);
+    stores.ldap.remove_account(user_id);
+    stores.tokens.remove_refresh_tokens_of_user(user_id);
+    stores.outbox.remove_mails_to(tombstone.get_user().get_email());
+    stores.webhooks.remove_deliveries_of_user(user_id);
+    Ok(())
+}
+
+/**
+ * Purges the deleted users whose grace period has passed, returns how many were purged.
+ */
+fn purge_expired(stores : &PurgeStores, grace_period : i64) -> usize {
+    let purged = stores.db.purge_expired_tombstones(grace_period);
+    for tombstone in &purged {
+        if let Err(e) = remove_user_data(tombstone, stores) {
+            error!("The data of purged user {} could not be removed: {}", tombstone.get_id(), e);
+        }
+    }
+    purged.len()
+}
+
+/**
+ * Moves a deleted user back to the users as long as his grace period hasn't passed.
+ */
+fn restore(id : &str, grace_period : i64, db : &Store) -> Result<IdentityUser, IdentityError> {
+    let tombstone = db.get_tombstone(id).ok_or(IdentityError::UserNotFound)?;
+    if tombstone.is_expired(grace_period) {
+        return Err(IdentityError::UserIsNotRestorable)
+    }
+    let user = db.restore_user(tombstone.get_id())?;
+    info!("Deleted user {} has been restored", id);
+    Ok(user)
+}
+
+/**
+ * Purges the deleted users whose grace period has passed and removes their data, returns how many were purged.
+ */
+pub fn purge_expired_users(stores : &PurgeStores) -> usize {
+    purge_expired(stores, *DELETION_GRACE_PERIOD)
+}
+
+/**
+ * Starts the background task that purges the expired deleted users at the configured interval.
+ */
+pub fn start_purge_task(stores : PurgeStores) -> JoinHandle<()> {
+    let interval = Duration::from_secs(*PURGE_INTERVAL);
+    thread::spawn(move || loop {
+        let purged = purge_expired_users(&stores);
+        if purged > 0 {
+            info!("{} deleted users have been purged", purged);
+        }
+        thread::sleep(interval);
+    })
+}
+
+/**
+ * Admin function that returns the deleted users that haven't been purged yet.
+ */
+pub fn get_deleted_users(token : &str, db : Store, clients : ClientStore, tokens : TokenStore) -> Result<AllDeletedUsersViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    Ok(AllDeletedUsersViewModel::from_tombstones_vector(db.get_tombstones(), *DELETION_GRACE_PERIOD))
+}
+
+/**
+ * Admin function that restores a deleted user within the grace period.
+ */
+pub fn restore_deleted_user(
+    token : &str,
+    model : UserIdViewModel,
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore
+) -> Result<PersonInfoViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    restore(model.get_id(), *DELETION_GRACE_PERIOD, &db).map(|user| PersonInfoViewModel::from_identity_user(&user))
+}
+
+/**
+ * Admin function that purges a deleted user right away instead of waiting for the grace period.
+ */
+pub fn purge_deleted_user(token : &str, model : UserIdViewModel, stores : PurgeStores, clients : ClientStore) -> Result<(), IdentityError> {
+    admin_service::control_admin_token(token, &stores.db, &clients, &stores.tokens)?;
+    let tombstone = stores.db.purge_user(model.get_id()).ok_or(IdentityError::UserNotFound)?;
+    info!("Deleted user {} has been purged by the admin", tombstone.get_id());
+    remove_user_data(&tombstone, &stores)
+}
+
+#[test]
+fn test_purge_deleted_users() {
+    use identity_dal::repo::user_config::UserConfig;
+    use identity_dal::federation::linked_identity::LinkedIdentity;
+    use identity_dal::mail::outbox_mail::{ MailContent, OutboxMail };
+    use identity_dal::webhook::webhook_delivery::WebhookDelivery;
+    use identity_dal::webhook::webhook_subscription::WebhookEventType;
+    use identity_dal::traits::t_user_manager::UserStoreTrait;
+
+    let config = UserConfig::new_config("", "person", 100000);
+    let stores = PurgeStores {
+        db : Store::new_db(config.clone()),
+        groups : GroupStore::new_db(config.clone()),
+        scim : ScimStore::new_db(config.clone()),
+        federation : FederationStore::new_db(config.clone()),
+        ldap : LdapStore::new_db(config.clone()),
+        tokens : TokenStore::new_db(config.clone()),
+        outbox : MailOutboxStore::new_db(config.clone()),
+        webhooks : WebhookStore::new_db(config)
+    };
+    let jane = stores.db.add_user(IdentityUser::new_user("jane@corp.be", "jane", "Passw0rd!").unwrap()).unwrap();
+    stores.federation.add_linked_identity(&LinkedIdentity::new("google", "sub-jane", jane.get_id(), "jane@corp.be")).unwrap();
+    let mail = stores.outbox.enqueue(OutboxMail::new(MailContent { recipient : "jane@corp.be".to_owned(), ..MailContent::default() })).unwrap();
+    let delivery = stores.webhooks.enqueue(WebhookDelivery::new("1", WebhookEventType::UserDeleted, "{}").for_user(jane.get_id())).unwrap();
+    stores.db.delete_user(jane.get_id()).unwrap();
+
+    assert!(matches!(restore(jane.get_id(), -1, &stores.db), Err(IdentityError::UserIsNotRestorable)));
+    assert_eq!(purge_expired(&stores, 60), 0);
+    assert!(restore(jane.get_id(), 60, &stores.db).is_ok());
+    assert!(matches!(restore(jane.get_id(), 60, &stores.db), Err(IdentityError::UserNotFound)));
+
+    stores.db.delete_user(jane.get_id()).unwrap();
+    assert_eq!(purge_expired(&stores, -1), 1);
+    assert!(stores.federation.get_linked_identities_of_user(jane.get_id()).is_empty());
+    assert!(stores.outbox.get_mail(mail.get_id()).is_none());
+    assert!(stores.webhooks.get_delivery(delivery.get_id()).is_none());
+    assert!(!stores.db.is_email_taken("jane@corp.be"));
+}
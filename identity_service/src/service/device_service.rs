@@ -0,0 +1,185 @@
+use crate::claim::Claim;
+use crate::id_token;
+use crate::store::Store;
+use crate::service::oauth_service::{ authenticate_client, control_allowed_scopes, control_user_is_active, issue_refresh_token, normalize_scope };
+use crate::util::get_value_from_key;
+use crate::viewmodels::oauth::device_authorization::{ DeviceAuthorizationRequestViewModel, DeviceAuthorizationResponseViewModel, DeviceDecisionViewModel };
+use crate::viewmodels::oauth::token_request::TokenRequestViewModel;
+use crate::viewmodels::oauth::token_response::TokenResponseViewModel;
+use identity_dal::oauth::oauth_client::{ OAuthClient, GRANT_DEVICE_CODE };
+use identity_dal::oauth::device_authorization::{ DeviceAuthorization, DeviceStatus };
+use identity_dal::repo::client_repo::ClientStore;
+use identity_dal::repo::device_repo::DeviceStore;
+use identity_dal::repo::token_repo::TokenStore;
+use identity_dal::traits::t_user_manager::UserStoreTrait;
+use identity_dal::traits::t_user::UserTrait;
+use crate::IdentityError;
+
+lazy_static! {
+    static ref DEVICE_EXPIRATION : i64 = get_value_from_key("PERSON_OAUTH_DEVICE_EXPIRATION")
+    .unwrap_or_else(|| "600".to_owned())
+    .parse::<i64>().expect("Could not parse this string to i64");
+    static ref DEVICE_INTERVAL : i64 = get_value_from_key("PERSON_OAUTH_DEVICE_INTERVAL")
+    .unwrap_or_else(|| "5".to_owned())
+    .parse::<i64>().expect("Could not parse this string to i64");
+}
+
+/**
+ * Returns the uri of the page on which the user enters the user code.
+ */
+pub fn verification_uri() -> String {
+    format!("{}/oauth/device", id_token::issuer().trim_end_matches('/'))
+}
+
+/**
+ * Starts a device authorization for a client that can't open a browser itself. The returned device code is used to poll the token endpoint, the user code is entered by the user on the verification page.
+ *
+ * An error is returned when:
+ * * the client can't be authenticated
+ * * the client may not use the device code grant
+ * * one of the asked scopes isn't allowed for the client
+ */
+pub fn authorize_device(
+    model : DeviceAuthorizationRequestViewModel,
+    clients : ClientStore,
+    devices : DeviceStore
+) -> Result<DeviceAuthorizationResponseViewModel, IdentityError> {
+    let client = authenticate_client(model.client_id.as_deref(), model.client_secret.as_deref(), &clients)?;
+    if !client.is_grant_type_allowed(GRANT_DEVICE_CODE) {
+        return Err(IdentityError::UnauthorizedClient)
+    }
+    control_allowed_scopes(&client, model.get_scope())?;
+    let device = DeviceAuthorization::new(client.get_client_id(), &normalize_scope(model.get_scope()), *DEVICE_EXPIRATION, *DEVICE_INTERVAL);
+    devices.add_device(&device)?;
+    info!("A device authorization has been started for client {}", client.get_client_id());
+    Ok(DeviceAuthorizationResponseViewModel::from_device(&device, &verification_uri()))
+}
+
+/**
+ * Looks up the pending device authorization of a user code together with its client, so the user can be shown what he approves.
+ */
+pub fn get_device_for_user_code(
+    user_code : &str,
+    devices : &DeviceStore,
+    clients : &ClientStore
+) -> Result<(DeviceAuthorization, OAuthClient), IdentityError> {
+    let device = devices.get_device_by_user_code(user_code)
+        .filter(|device| *device.get_status() == DeviceStatus::Pending)
+        .ok_or(IdentityError::DeviceCodeNotFound)?;
+    let client = clients.get_client(device.get_client_id())
+        .filter(|client| !client.is_disabled())
+        .ok_or(IdentityError::ClientIsDisabled)?;
+    Ok((device, client))
+}
+
+/**
+ * Saves the decision of the user about the device authorization of a user code. A decision can only be made once.
+ */
+pub fn decide_device_authorization(
+    user_code : &str,
+    user_id : &str,
+    approve : bool,
+    devices : DeviceStore,
+    clients : ClientStore
+) -> Result<(), IdentityError> {
+    let (mut device, client) = get_device_for_user_code(user_code, &devices, &clients)?;
+    if approve {
+        device.approve(user_id);
+        info!("The user has approved a device of client {}", client.get_client_id());
+    } else {
+        device.deny();
+        info!("The user has denied a device of client {}", client.get_client_id());
+    }
+    devices.update_device(&device)
+}
+
+/**
+ * Decision about a device authorization made by a logged in user with his own token. Tokens that were issued to OAuth clients can't be used, a client may not approve devices on behalf of the user.
+ */
+pub fn decide_device_authorization_with_token(
+    token : &str,
+    model : DeviceDecisionViewModel,
+    db : Store,
+    devices : DeviceStore,
+    clients : ClientStore
+) -> Result<(), IdentityError> {
+    let claim = Claim::decode_token(token)?.claims;
+    if claim.client_id.is_some() {
+        warn!("An OAuth token has been used to approve a device");
+        return Err(IdentityError::InsufficientScope)
+    }
+    let user = db.get_user_by_uuid(&claim.sub).ok_or(IdentityError::UserNotFound)?;
+    user.control_status()?;
+    decide_device_authorization(model.get_user_code(), user.get_id(), model.is_approved(), devices, clients)
+}
+
+/**
+ * Handles the polling of a device on the token endpoint. As long as the user hasn't decided authorization_pending is returned, polling faster than the interval returns slow_down. Once approved the device gets an access token and a refresh token, the device code can't be used anymore after that.
+ *
+ * An error is returned when:
+ * * the client can't be authenticated or may not use the device code grant
+ * * the device code doesn't exist or was issued to another client
+ * * the device code has expired or the user has denied the request
+ */
+pub fn exchange_device_code(
+    model : TokenRequestViewModel,
+    clients : ClientStore,
+    devices : DeviceStore,
+    tokens : TokenStore,
+    db : Store
+) -> Result<TokenResponseViewModel, IdentityError> {
+    let client = authenticate_client(model.client_id.as_deref(), model.client_secret.as_deref(), &clients)?;
+    if !client.is_grant_type_allowed(GRANT_DEVICE_CODE) {
+        return Err(IdentityError::UnauthorizedClient)
+    }
+    let device_code = model.device_code.as_deref().unwrap_or_default();
+    let mut device = devices.get_device(device_code).ok_or(IdentityError::InvalidGrant)?;
+    if device.get_client_id() != client.get_client_id() {
+        warn!("Client {} tried to use a device code of another client", client.get_client_id());
+        return Err(IdentityError::InvalidGrant)
+    }
+    if device.is_expired() {
+        devices.take_device(device_code);
+        return Err(IdentityError::ExpiredToken)
+    }
+    match device.get_status().clone() {
+        DeviceStatus::Pending => {
+            let in_time = device.poll();
+            devices.update_device(&device)?;
+            Err(if in_time { IdentityError::AuthorizationPending } else { IdentityError::SlowDown })
+        },
+        DeviceStatus::Denied => {
+            devices.take_device(device_code);
+            Err(IdentityError::AccessDenied)
+        },
+        DeviceStatus::Approved { user_id, auth_time } => {
+            devices.take_device(device_code).ok_or(IdentityError::InvalidGrant)?;
+            control_user_is_active(&user_id, &db)?;
+            let claim = Claim::new_oauth_claim(&user_id, client.get_client_id(), device.get_scope())?;
+            let refresh_token = issue_refresh_token(client.get_client_id(), &user_id, device.get_scope(), GRANT_DEVICE_CODE, auth_time, &tokens)?;
+            info!("An access token has been issued to a device of client {}", client.get_client_id());
+            Ok(TokenResponseViewModel::from_claim(&claim)?.with_refresh_token(refresh_token))
+        }
+    }
+}
+
+#[test]
+fn test_device_authorization_of_allowed_scopes() {
+    use identity_dal::oauth::oauth_client::ClientType;
+    use identity_dal::repo::user_config::UserConfig;
+
+    let config = UserConfig::new_config("", "person", 100000);
+    let (clients, devices) = (ClientStore::new_db(config.clone()), DeviceStore::new_db(config));
+    let (mut client, _) = OAuthClient::new_client("tv", Vec::new(), ClientType::Public).unwrap();
+    client.set_grant_types(vec![GRANT_DEVICE_CODE.to_owned()].into_iter().collect()).unwrap();
+    client.set_allowed_scopes(["openid", "offline_access"].iter().map(|scope| scope.to_string()).collect());
+    let client = clients.add_client(client).unwrap();
+    let request = |scope : &str| DeviceAuthorizationRequestViewModel {
+        client_id : Some(client.get_client_id().to_owned()),
+        scope : Some(scope.to_owned()),
+        ..DeviceAuthorizationRequestViewModel::default()
+    };
+
+    assert!(matches!(authorize_device(request("openid admin"), clients.clone(), devices.clone()), Err(IdentityError::InvalidScope)));
+    assert!(authorize_device(request("openid offline_access"), clients.clone(), devices.clone()).is_ok());
+}
@@ -0,0 +1,432 @@
+use crate::claim::Claim;
+use crate::hooks::HookRegistry;
+use crate::store::Store;
+use crate::federation::{ self, UpstreamProvider, UpstreamClaims };
+use crate::service::registration_service::{ self, RegistrationMode };
+use crate::service::webhook_service::{ self, WebhookEventType, WebhookStore };
+use crate::util::{ get_value_from_key, hash_token };
+use crate::viewmodels::auth::person_info::PersonInfoViewModel;
+use crate::viewmodels::federation::linked_identity::LinkedIdentitiesViewModel;
+use crate::viewmodels::federation::provider::ProviderViewModel;
+use crate::viewmodels::federation::unlink_identity::UnlinkIdentityViewModel;
+use identity_dal::federation::linked_identity::LinkedIdentity;
+use identity_dal::federation::pending_login::PendingLogin;
+use identity_dal::onboarding::pending_registration::PendingRegistration;
+use identity_dal::repo::federation_repo::FederationStore;
+use identity_dal::repo::registration_repo::RegistrationStore;
+use identity_dal::traits::t_user::UserTrait;
+use identity_dal::traits::t_user_manager::UserStoreTrait;
+use identity_dal::user::identity_user::IdentityUser;
+use identity_dal::util::get_hash;
+use crate::IdentityError;
+
+lazy_static! {
+    static ref LOGIN_EXPIRATION : i64 = get_value_from_key("PERSON_FEDERATION_LOGIN_EXPIRATION")
+    .unwrap_or_else(|| "600".to_owned())
+    .parse::<i64>().expect("Could not parse this string to i64");
+}
+
+/**
+ * Name of the cookie that binds a login at an identity provider to the browser that started it.
+ */
+pub static LOGIN_COOKIE : &str = "federation_login";
+
+/**
+ * Stores a login at an identity provider works with, the registrations are needed when a new user waits for approval and the webhooks when one is provisioned.
+ */
+pub struct FederationStores {
+    pub federation : FederationStore,
+    pub db : Store,
+    pub registrations : RegistrationStore,
+    pub webhooks : WebhookStore
+}
+
+/**
+ * Returns the identity providers users can log in with.
+ */
+pub fn get_providers() -> Vec<ProviderViewModel> {
+    federation::get_providers().iter().map(ProviderViewModel::from_provider).collect()
+}
+
+/**
+ * Starts a login at an identity provider and returns the uri the user has to be sent to, with the value of the login cookie. The state, nonce and PKCE verifier are kept until the provider sends the user back.
+ */
+pub fn start_login(provider : &UpstreamProvider, federation : &FederationStore) -> Result<(String, String), IdentityError> {
+    let redirect = redirect_to_provider(provider, PendingLogin::new(provider.get_name(), *LOGIN_EXPIRATION), federation)?;
+    info!("A login at identity provider {} has been started", provider.get_name());
+    Ok(redirect)
+}
+
+/**
+ * Starts linking an identity of a provider to the user of the token and returns the uri the user has to be sent to, with the value of the login cookie. When the provider sends the user back, the identity is linked to this user instead of being looked up.
+ */
+pub fn start_link(provider : &UpstreamProvider, token : &str, db : &Store, federation : &FederationStore) -> Result<(String, String), IdentityError> {
+    let user = Claim::token_to_user(token, db)?;
+    let login = PendingLogin::new(provider.get_name(), *LOGIN_EXPIRATION).for_user(user.get_id());
+    let redirect = redirect_to_provider(provider, login, federation)?;
+    info!("User {} has started linking an identity of provider {}", user.get_id(), provider.get_name());
+    Ok(redirect)
+}
+
+/**
+ * Keeps the pending login and returns the uri of the provider with the hash of the state. The hash is set as the login cookie, so the callback can only be finished by the browser that started the login.
+ */
+fn redirect_to_provider(provider : &UpstreamProvider, login : PendingLogin, federation : &FederationStore) -> Result<(String, String), IdentityError> {
+    let metadata = provider.discover()?;
+    federation.add_pending_login(&login)?;
+    let uri = provider.authorization_uri(&metadata, login.get_state(), login.get_nonce(), login.get_code_verifier());
+    Ok((uri, hash_token(login.get_state())))
+}
+
+/**
+ * Finishes a login at an identity provider and returns the claim of the local user. The pre login hooks are called once the user is known and can veto the login.
+ */
+pub fn finish_login(
+    provider : &UpstreamProvider,
+    code : &str,
+    state : &str,
+    cookie : Option<&str>,
+    stores : FederationStores,
+    id : &str,
+    hooks : &HookRegistry
+) -> Result<Claim, IdentityError> {
+    let user = resolve_login(provider, code, state, cookie, &stores, id, hooks)?;
+    hooks.before("login", |hook| hook.pre_login(user.get_email()))?;
+    let claim = Claim::new_read_write_claim(user.get_id())?;
+    let info = PersonInfoViewModel::from_identity_user(&user);
+    hooks.after("login", |hook| hook.post_login(&info));
+    Ok(claim)
+}
+
+/**
+ * Handles the callback of an identity provider: the code is exchanged for an id token, which is validated, and the local user of the identity is returned. When the login was started to link an identity, the identity is linked to the user that started it.
+ *
+ * An error is returned when:
+ * * the state is unknown, has expired or belongs to another provider
+ * * the login cookie of the browser isn't the one of the state, the login was started in another browser
+ * * the code can't be exchanged or the id token isn't valid
+ * * the identity has to be linked or provisioned but the provider didn't verify the email
+ * * a new user has to be provisioned but the registration mode doesn't allow it, it waits for approval or a pre registration hook vetoes it
+ * * the user of the identity has been deleted and isn't purged yet
+ * * the identity is already linked to another user
+ * * the user isn't active
+ */
+pub fn resolve_login(
+    provider : &UpstreamProvider,
+    code : &str,
+    state : &str,
+    cookie : Option<&str>,
+    stores : &FederationStores,
+    id : &str,
+    hooks : &HookRegistry
+) -> Result<IdentityUser, IdentityError> {
+    let login = stores.federation.take_pending_login(state)
+        .filter(|login| login.get_provider() == provider.get_name())
+        .ok_or_else(|| IdentityError::FederationFailed("the state is unknown or has expired".to_owned()))?;
+    if cookie != Some(hash_token(login.get_state()).as_str()) {
+        warn!("A login at identity provider {} has been finished in another browser than the one that started it", provider.get_name());
+        return Err(IdentityError::FederationFailed("the login was started in another browser".to_owned()))
+    }
+    let metadata = provider.discover()?;
+    let id_token = provider.exchange_code(&metadata, code, login.get_code_verifier())?;
+    let claims = provider.validate_id_token(&metadata, &id_token, login.get_nonce())?;
+    let user = match login.get_link_user_id() {
+        Some(user_id) => link_identity(provider, &claims, user_id, &stores.federation, &stores.db),
+        None => find_or_provision_user(provider, &claims, stores, registration_service::registration_mode(), id, hooks)
+    }?;
+    user.control_status()?;
+    Ok(user)
+}
+
+/**
+ * Links the identity to a logged in user. The user proved he owns the identity by logging in at the provider, so the email doesn't have to be verified.
+ */
+fn link_identity(
+    provider : &UpstreamProvider,
+    claims : &UpstreamClaims,
+    user_id : &str,
+    federation : &FederationStore,
+    db : &Store
+) -> Result<IdentityUser, IdentityError> {
+    let user = db.get_user_by_uuid(user_id).ok_or(IdentityError::UserNotFound)?;
+    if let Some(identity) = federation.get_linked_identity(provider.get_name(), &claims.sub) {
+        if identity.get_user_id() == user.get_id() {
+            return Ok(user)
+        }
+        warn!("User {} tried to link an identity that belongs to another user", user.get_id());
+        return Err(IdentityError::IdentityIsAlreadyLinked)
+    }
+    let identity = LinkedIdentity::new(provider.get_name(), &claims.sub, user.get_id(), claims.email.as_deref().unwrap_or_default());
+    federation.add_linked_identity(&identity)?;
+    info!("User {} has linked an identity of provider {}", user.get_id(), provider.get_name());
+    Ok(user)
+}
+
+/**
+ * Returns the user the identity is linked to. An identity that isn't linked yet is linked to the user with the same email, when there is none a new user without password is provisioned as the registration mode allows. Both only happen when the provider has verified the email. In the approval mode the new user is kept as a pending registration, the identity is linked to it so the user can log in once it has been approved. The pre registration hooks can veto a new user.
+ */
+fn find_or_provision_user(
+    provider : &UpstreamProvider,
+    claims : &UpstreamClaims,
+    stores : &FederationStores,
+    mode : &RegistrationMode,
+    id : &str,
+    hooks : &HookRegistry
+) -> Result<IdentityUser, IdentityError> {
+    let (federation, db) = (&stores.federation, &stores.db);
+    if let Some(identity) = federation.get_linked_identity(provider.get_name(), &claims.sub) {
+        if let Some(user) = db.get_user_by_uuid(identity.get_user_id()) {
+            info!("User {} has logged in with identity provider {}", user.get_id(), provider.get_name());
+            return Ok(user)
+        }
+        if db.get_tombstone(identity.get_user_id()).is_some() {
+            warn!("The user of an identity of provider {} has been deleted, the identity stays linked until he is purged", provider.get_name());
+            return Err(IdentityError::UserIsDeleted)
+        }
+        if stores.registrations.get_registration(identity.get_user_id()).is_some() {
+            info!("The registration {} of identity provider {} still waits for approval", identity.get_user_id(), provider.get_name());
+            return Err(IdentityError::EmailIsAwaitingApproval)
+        }
+        warn!("The user of a linked identity doesn't exist anymore, the identity is unlinked");
+        federation.remove_linked_identity(provider.get_name(), &claims.sub);
+    }
+    let email = match claims.email.as_deref() {
+        Some(email) if claims.is_email_verified() => email,
+        _ => return Err(IdentityError::UpstreamEmailNotVerified)
+    };
+    let user = match db.get_user_by_email(email) {
+        Some(user) => user,
+        None => {
+            registration_service::control_registration_mode(mode, email)?;
+            hooks.before("registration", |hook| hook.pre_registration(email))?;
+            let user_name = claims.preferred_username.as_deref().or(claims.name.as_deref()).unwrap_or_default();
+            let mut user = IdentityUser::new_user_with_personal_id(id, email, user_name, &get_hash(32))?;
+            user.set_hashed_password("");
+            user.set_security_stamp("");
+            user.set_locale(claims.locale.as_deref());
+            if *mode == RegistrationMode::Approval {
+                let registration = PendingRegistration::new(user);
+                stores.registrations.add_registration(&registration)?;
+                federation.add_linked_identity(&LinkedIdentity::new(provider.get_name(), &claims.sub, registration.get_id(), email))?;
+                info!("Registration {} of identity provider {} waits for approval", registration.get_id(), provider.get_name());
+                return Err(IdentityError::EmailIsAwaitingApproval)
+            }
+            let user = db.add_user(user)?;
+            info!("User {} has been provisioned for identity provider {}", user.get_id(), provider.get_name());
+            let info = PersonInfoViewModel::from_identity_user(&user);
+            hooks.after("registration", |hook| hook.post_registration(&info));
+            webhook_service::publish(&stores.webhooks, WebhookEventType::UserRegistered, user.get_id(), db);
+            user
+        }
+    };
+    federation.add_linked_identity(&LinkedIdentity::new(provider.get_name(), &claims.sub, user.get_id(), email))?;
+    info!("An identity of provider {} has been linked to user {}", provider.get_name(), user.get_id());
+    Ok(user)
+}
+
+/**
+ * Returns the external identities linked to the user of the token.
+ */
+pub fn get_linked_identities(token : &str, db : &Store, federation : &FederationStore) -> Result<LinkedIdentitiesViewModel, IdentityError> {
+    let user = Claim::token_to_user(token, db)?;
+    Ok(LinkedIdentitiesViewModel::from_identities_vector(federation.get_linked_identities_of_user(user.get_id())))
+}
+
+/**
+ * Unlinks an external identity of the user of the token.
+ *
+ * An error is returned when:
+ * * the identity isn't linked to the user
+ * * the identity is the last way for the user to log in, he has no password and no other linked identity
+ */
+pub fn unlink_identity(token : &str, model : UnlinkIdentityViewModel, db : &Store, federation : &FederationStore) -> Result<(), IdentityError> {
+    let user = Claim::token_to_user(token, db)?;
+    remove_identity_of_user(&user, model.get_provider(), model.get_subject(), federation)
+}
+
+fn remove_identity_of_user(user : &IdentityUser, provider : &str, subject : &str, federation : &FederationStore) -> Result<(), IdentityError> {
+    federation.get_linked_identity(provider, subject)
+        .filter(|identity| identity.get_user_id() == user.get_id())
+        .ok_or(IdentityError::LinkedIdentityNotFound)?;
+    if user.is_pwd_empty() && federation.get_linked_identities_of_user(user.get_id()).len() <= 1 {
+        warn!("User {} tried to unlink his last way to log in", user.get_id());
+        return Err(IdentityError::LastLoginMethod)
+    }
+    federation.remove_linked_identity(provider, subject);
+    info!("User {} has unlinked an identity of provider {}", user.get_id(), provider);
+    Ok(())
+}
+
+#[cfg(test)]
+mod mock_provider {
+    use crate::signing_key::SigningKey;
+    use std::io::{ BufRead, BufReader, Read, Write };
+    use std::net::TcpListener;
+    use std::sync::{ Arc, Mutex };
+
+    /**
+     * Minimal OpenID Connect provider on a local port, it serves the discovery document, the keys and a token endpoint that returns an id token for every code.
+     */
+    pub struct MockProvider {
+        pub issuer : String,
+        pub nonce : Arc<Mutex<String>>
+    }
+
+    pub fn start(client_id : &'static str, subject : &'static str, email : &'static str) -> MockProvider {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let nonce = Arc::new(Mutex::new(String::new()));
+        let (server_issuer, server_nonce) = (issuer.clone(), nonce.clone());
+        std::thread::spawn(move || {
+            let key = SigningKey::generate().unwrap();
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() { break }
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+                reader.by_ref().take(length).read_to_end(&mut Vec::new()).unwrap();
+                let path = request_line.split_whitespace().nth(1).unwrap_or_default().to_owned();
+                let body = match path.as_str() {
+                    "/.well-known/openid-configuration" => serde_json::json!({
+                        "issuer" : server_issuer,
+                        "authorization_endpoint" : format!("{}/authorize", server_issuer),
+                        "token_endpoint" : format!("{}/token", server_issuer),
+                        "jwks_uri" : format!("{}/jwks", server_issuer)
+                    }).to_string(),
+                    "/jwks" => serde_json::to_string(&key.jwks()).unwrap(),
+                    "/token" => {
+                        let now = chrono::Utc::now().timestamp();
+                        let id_token = key.sign(&serde_json::json!({
+                            "iss" : server_issuer,
+                            "aud" : client_id,
+                            "sub" : subject,
+                            "exp" : now + 60,
+                            "iat" : now,
+                            "nonce" : *server_nonce.lock().unwrap(),
+                            "email" : email,
+                            "email_verified" : true
+                        })).unwrap();
+                        serde_json::json!({ "access_token" : "upstream", "token_type" : "Bearer", "id_token" : id_token }).to_string()
+                    },
+                    _ => String::new()
+                };
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body).unwrap();
+            }
+        });
+        MockProvider { issuer, nonce }
+    }
+}
+
+#[test]
+fn test_login_with_mock_provider() {
+    use identity_dal::repo::user_config::UserConfig;
+    use identity_dal::repo::user_repo::UserStore;
+
+    let mock = mock_provider::start("identity", "upstream-1", "jane@corp.be");
+    let provider = UpstreamProvider::new("corp", "Corp", &mock.issuer, "identity", "secret", "openid email");
+    let stores = FederationStores {
+        federation : FederationStore::new_db(UserConfig::new_config("", "", 100000)),
+        db : UserStore::new_db(UserConfig::new_config("", "person", 100000)),
+        registrations : RegistrationStore::new_db(UserConfig::new_config("", "", 100000)),
+        webhooks : WebhookStore::new_db(UserConfig::new_config("", "", 100000))
+    };
+
+    let login = |id : &str| {
+        let (uri, cookie) = start_login(&provider, &stores.federation).unwrap();
+        let param = |name : &str| uri.split(&['?', '&'][..])
+            .find_map(|pair| pair.strip_prefix(&format!("{}=", name)))
+            .unwrap()
+            .to_owned();
+        *mock.nonce.lock().unwrap() = param("nonce");
+        resolve_login(&provider, "code", &param("state"), Some(&cookie), &stores, id, &HookRegistry::new())
+    };
+    let user = login("1").unwrap();
+    assert_eq!(user.get_email(), "jane@corp.be");
+    assert!(user.is_pwd_empty());
+    assert_eq!(login("2").unwrap().get_id(), user.get_id());
+    assert_eq!(stores.federation.get_linked_identities_of_user(user.get_id()).len(), 1);
+
+    *mock.nonce.lock().unwrap() = "replayed".to_owned();
+    let state_of = |uri : &str| uri.split(&['?', '&'][..]).find_map(|pair| pair.strip_prefix("state=")).unwrap().to_owned();
+    let (uri, cookie) = start_login(&provider, &stores.federation).unwrap();
+    assert!(resolve_login(&provider, "code", &state_of(&uri), Some(&cookie), &stores, "3", &HookRegistry::new()).is_err());
+    assert!(resolve_login(&provider, "code", &state_of(&uri), Some(&cookie), &stores, "3", &HookRegistry::new()).is_err());
+
+    let (uri, _) = start_login(&provider, &stores.federation).unwrap();
+    let (_, other_browser) = start_login(&provider, &stores.federation).unwrap();
+    assert!(matches!(resolve_login(&provider, "code", &state_of(&uri), Some(&other_browser), &stores, "3", &HookRegistry::new()), Err(IdentityError::FederationFailed(_))));
+    let (uri, _) = start_login(&provider, &stores.federation).unwrap();
+    assert!(matches!(resolve_login(&provider, "code", &state_of(&uri), None, &stores, "3", &HookRegistry::new()), Err(IdentityError::FederationFailed(_))));
+}
+
+#[test]
+fn test_unlink_last_login_method() {
+    use identity_dal::repo::user_config::UserConfig;
+
+    let federation = FederationStore::new_db(UserConfig::new_config("", "", 100000));
+    let mut user = IdentityUser::new_user_with_personal_id("1", "jane@corp.be", "", "password").unwrap();
+    user.set_hashed_password("");
+    user.set_security_stamp("");
+    federation.add_linked_identity(&LinkedIdentity::new("corp", "a", "1", "jane@corp.be")).unwrap();
+    federation.add_linked_identity(&LinkedIdentity::new("other", "b", "1", "jane@corp.be")).unwrap();
+
+    assert!(remove_identity_of_user(&user, "corp", "unknown", &federation).is_err());
+    assert!(remove_identity_of_user(&user, "corp", "a", &federation).is_ok());
+    assert!(matches!(remove_identity_of_user(&user, "other", "b", &federation), Err(IdentityError::LastLoginMethod)));
+    user.set_password("password").unwrap();
+    assert!(remove_identity_of_user(&user, "other", "b", &federation).is_ok());
+}
+
+#[test]
+fn test_provision_by_registration_mode() {
+    use identity_dal::repo::user_config::UserConfig;
+    use identity_dal::webhook::webhook_subscription::WebhookSubscription;
+
+    let config = UserConfig::new_config("", "person", 100000);
+    let stores = FederationStores {
+        federation : FederationStore::new_db(config.clone()),
+        db : Store::new_db(config.clone()),
+        registrations : RegistrationStore::new_db(config.clone()),
+        webhooks : WebhookStore::new_db(config)
+    };
+    stores.webhooks.save_subscription(&WebhookSubscription::new("1", "http://localhost", "secret", [WebhookEventType::UserRegistered].iter().copied().collect(), "admin")).unwrap();
+    let provider = UpstreamProvider::new("corp", "Corp", "https://corp.be", "identity", "secret", "openid email");
+    let claims = |sub : &str, email : &str| -> UpstreamClaims {
+        serde_json::from_value(serde_json::json!({ "sub" : sub, "aud" : "identity", "email" : email, "email_verified" : true, "locale" : "nl-BE" })).unwrap()
+    };
+
+    let jane = claims("a", "jane@corp.be");
+    assert!(matches!(find_or_provision_user(&provider, &jane, &stores, &RegistrationMode::Disabled, "1", &HookRegistry::new()), Err(IdentityError::RegistrationIsDisabled)));
+    assert!(matches!(find_or_provision_user(&provider, &jane, &stores, &RegistrationMode::InviteOnly, "1", &HookRegistry::new()), Err(IdentityError::RegistrationIsInviteOnly)));
+    assert!(stores.db.get_user_by_email("jane@corp.be").is_none());
+    assert!(stores.federation.get_linked_identity("corp", "a").is_none());
+
+    assert!(matches!(find_or_provision_user(&provider, &jane, &stores, &RegistrationMode::Approval, "1", &HookRegistry::new()), Err(IdentityError::EmailIsAwaitingApproval)));
+    assert!(matches!(find_or_provision_user(&provider, &jane, &stores, &RegistrationMode::Approval, "2", &HookRegistry::new()), Err(IdentityError::EmailIsAwaitingApproval)));
+    assert!(stores.db.get_user_by_email("jane@corp.be").is_none());
+    stores.db.add_user(stores.registrations.take_registration("1").unwrap().into_user()).unwrap();
+    assert_eq!(find_or_provision_user(&provider, &jane, &stores, &RegistrationMode::Approval, "3", &HookRegistry::new()).unwrap().get_id(), "1");
+
+    let existing = find_or_provision_user(&provider, &claims("b", "jane@corp.be"), &stores, &RegistrationMode::Disabled, "4", &HookRegistry::new()).unwrap();
+    assert_eq!(existing.get_id(), "1");
+
+    stores.db.delete_user("1").unwrap();
+    assert!(matches!(find_or_provision_user(&provider, &jane, &stores, &RegistrationMode::Open, "5", &HookRegistry::new()), Err(IdentityError::UserIsDeleted)));
+    assert!(stores.db.get_user_by_uuid("5").is_none());
+    stores.db.restore_user("1").unwrap();
+    assert_eq!(find_or_provision_user(&provider, &jane, &stores, &RegistrationMode::Open, "5", &HookRegistry::new()).unwrap().get_id(), "1");
+
+    assert!(stores.webhooks.get_deliveries_of_subscription("1", None).is_empty());
+    assert_eq!(find_or_provision_user(&provider, &claims("c", "john@corp.be"), &stores, &RegistrationMode::Open, "6", &HookRegistry::new()).unwrap().get_id(), "6");
+    assert_eq!(stores.webhooks.get_deliveries_of_subscription("1", None)[0].get_user_id(), Some("6"));
+    assert_eq!(stores.db.get_user_by_uuid("6").unwrap().get_locale(), Some("nl-BE"));
+}
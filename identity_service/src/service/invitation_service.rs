@@ -0,0 +1,333 @@
+use std::collections::BTreeSet;
+use crate::claim::Claim;
+use crate::hooks::HookRegistry;
+use crate::id_token;
+use crate::store::Store;
+use crate::service::admin_service;
+use crate::service::mail_service::MailOutbox;
+use crate::service::person_service::control_password_length;
+use crate::util::{ append_query, get_value_from_key, hash_token };
+use crate::viewmodels::auth::person_info::PersonInfoViewModel;
+use crate::viewmodels::invitation::accept::AcceptInvitationViewModel;
+use crate::viewmodels::invitation::invitation_info::{ AllInvitationsViewModel, InvitationViewModel };
+use crate::viewmodels::invitation::invite::{ InvitationIdViewModel, InviteViewModel };
+use identity_dal::onboarding::invitation::{ Invitation, InvitationStatus };
+use identity_dal::repo::client_repo::ClientStore;
+use identity_dal::repo::group_repo::GroupStore;
+use identity_dal::repo::invitation_repo::InvitationStore;
+use identity_dal::repo::token_repo::TokenStore;
+use identity_dal::traits::t_user::UserTrait;
+use identity_dal::traits::t_user_manager::UserStoreTrait;
+use identity_dal::user::identity_user::IdentityUser;
+use identity_dal::util::{ control_email, get_hash };
+use crate::IdentityError;
+
+lazy_static! {
+    /**
+     * Page the invitation links point to, it lets the invitee choose his password and sends it with the token to the accept endpoint.
+     */
+    static ref INVITATION_URL : String = get_value_from_key("PERSON_INVITATION_URL")
+    .unwrap_or_else(|| format!("{}/invitation", id_token::issuer().trim_end_matches('/')));
+    static ref INVITATION_EXPIRATION : i64 = get_value_from_key("PERSON_INVITATION_EXPIRATION")
+    .unwrap_or_else(|| "604800".to_owned())
+    .parse::<i64>().expect("Could not parse this string to i64");
+    /**
+     * Flag an user needs to be allowed to invite others.
+     */
+    static ref INVITER_FLAG : String = get_value_from_key("PERSON_INVITER_FLAG")
+    .unwrap_or_else(|| "inviter".to_owned());
+}
+
+/**
+ * Function that sends the invitation link to the invited email in the locale of the invitation.
+ */
+pub type InvitationDelegate = fn(email : &str, locale : Option<&str>, link : &str, outbox : &MailOutbox) -> Result<(), IdentityError>;
+
+/**
+ * Stores the invitations work with.
+ */
+pub struct InvitationStores {
+    pub db : Store,
+    pub groups : GroupStore,
+    pub invitations : InvitationStore
+}
+
+/**
+ * The admin, or an admin client, may manage all invitations and assign everything. An user with the inviter flag may only manage his own invitations and only assign his own flags and groups.
+ */
+enum Inviter {
+    Admin(String),
+    User(IdentityUser)
+}
+
+impl Inviter {
+    fn get_id(&self) -> &str {
+        match self {
+            Inviter::Admin(id) => id,
+            Inviter::User(user) => user.get_id()
+        }
+    }
+
+    fn may_manage(&self, invitation : &Invitation) -> bool {
+        match self {
+            Inviter::Admin(_) => true,
+            Inviter::User(user) => invitation.get_invited_by() == user.get_id()
+        }
+    }
+}
+
+/**
+ * Returns who uses the token to manage invitations, an error is returned when it's neither an admin token nor the token of an user with the inviter flag.
+ */
+fn control_inviter_token(token : &str, db : &Store, clients : &ClientStore, tokens : &TokenStore) -> Result<Inviter, IdentityError> {
+    match admin_service::control_admin_token(token, db, clients, tokens) {
+        Ok(claim) => Ok(Inviter::Admin(claim.sub)),
+        Err(IdentityError::IdNotEqualToAdmin) => {
+            let user = Claim::token_to_user(token, db)?;
+            if !user.get_flags().contains(INVITER_FLAG.as_str()) {
+                warn!("User {} isn't allowed to invite others", user.get_id());
+                return Err(IdentityError::InsufficientScope)
+            }
+            Ok(Inviter::User(user))
+        },
+        Err(e) => Err(e)
+    }
+}
+
+/**
+ * Sends the link of an invitation with a new token, earlier links of the invitation stop working. A failed mail is only logged, the invitation can be resent.
+ */
+fn send_invitation(invitation : &mut Invitation, stores : &InvitationStores, lifetime : i64, outbox : &MailOutbox, send_function : InvitationDelegate) -> Result<(), IdentityError> {
+    let token = get_hash(40);
+    invitation.renew(&hash_token(&token), lifetime);
+    stores.invitations.save_invitation(invitation)?;
+    match send_function(invitation.get_email(), invitation.get_locale(), &append_query(&INVITATION_URL, &[("token", &token)]), outbox) {
+        Ok(_) => info!("Invitation {} has been sent", invitation.get_id()),
+        Err(e) => warn!("Invitation {} could not be sent: {}", invitation.get_id(), e)
+    }
+    Ok(())
+}
+
+/**
+ * Makes an invitation after it controls that the email can be invited and the inviter may assign the flags and groups.
+ */
+fn make_invitation(inviter : &Inviter, model : &InviteViewModel, stores : &InvitationStores, lifetime : i64) -> Result<Invitation, IdentityError> {
+    let email = model.get_email().trim();
+    if email.is_empty() {
+        return Err(IdentityError::EmailIsEmpty)
+    }
+    if !control_email(email) {
+        return Err(IdentityError::EmailNotCorrectFormat)
+    }
+    if stores.db.is_email_taken(email) {
+        return Err(IdentityError::EmailIsAlreadyTaken)
+    }
+    if stores.invitations.get_pending_invitation_of_email(email).is_some() {
+        return Err(IdentityError::EmailIsAlreadyInvited)
+    }
+    let flags : BTreeSet<String> = model.get_flags().iter().map(|flag| flag.trim().to_owned()).filter(|flag| !flag.is_empty()).collect();
+    let group_ids : BTreeSet<String> = model.get_group_ids().iter().cloned().collect();
+    for group_id in &group_ids {
+        let group = stores.groups.get_group(group_id).ok_or(IdentityError::GroupNotFound)?;
+        if let Inviter::User(user) = inviter {
+            if !group.has_member(user.get_id()) {
+                warn!("User {} can't invite into group {} he isn't a member of", user.get_id(), group_id);
+                return Err(IdentityError::InsufficientScope)
+            }
+        }
+    }
+    if let Inviter::User(user) = inviter {
+        if !flags.is_subset(&user.get_flags()) {
+            warn!("User {} can't give flags he hasn't got himself", user.get_id());
+            return Err(IdentityError::InsufficientScope)
+        }
+    }
+    Ok(Invitation::new(&get_hash(21), email, flags, group_ids, inviter.get_id(), "", lifetime).with_locale(model.get_locale()))
+}
+
+/**
+ * Returns the invitation with the id if the inviter may manage it.
+ */
+fn find_invitation(inviter : &Inviter, id : &str, invitations : &InvitationStore) -> Result<Invitation, IdentityError> {
+    invitations.get_invitation(id)
+        .filter(|invitation| inviter.may_manage(invitation))
+        .ok_or(IdentityError::InvitationNotFound)
+}
+
+/**
+ * Makes the account of an accepted invitation with the chosen name and password, it gets the flags, groups and locale of the invitation.
+ */
+fn accept(model : &AcceptInvitationViewModel, stores : &InvitationStores, hooks : &HookRegistry) -> Result<IdentityUser, IdentityError> {
+    let mut invitation = stores.invitations.get_invitation_by_token_hash(&hash_token(model.get_token()))
+        .ok_or(IdentityError::InvitationNotFound)?;
+    if !invitation.is_pending() {
+        return Err(IdentityError::InvitationIsNotPending)
+    }
+    if model.get_confirmed_password() != model.get_password() {
+        warn!("A password and its confirmation has to be the same");
+        return Err(IdentityError::PasswordAndPasswordConfirmedNotEqual)
+    }
+    if stores.db.is_email_taken(invitation.get_email()) {
+        return Err(IdentityError::EmailIsAlreadyTaken)
+    }
+    hooks.before("registration", |hook| hook.pre_registration(invitation.get_email()))?;
+    hooks.before("login", |hook| hook.pre_login(invitation.get_email()))?;
+    let mut user = IdentityUser::new_user(invitation.get_email(), model.get_user_name().trim(), model.get_password())?;
+    user.set_locale(invitation.get_locale());
+    let groups : Vec<_> = invitation.get_group_ids().iter().filter_map(|id| stores.groups.get_group(id)).collect();
+    for flag in invitation.get_flags().iter().map(String::as_str).chain(groups.iter().map(|group| group.get_display_name())) {
+        user.add_flag(flag);
+    }
+    let user = stores.db.add_user(user)?;
+    for mut group in groups {
+        group.add_member(user.get_id());
+        stores.groups.update_group(&group)?;
+    }
+    invitation.accept(user.get_id());
+    stores.invitations.save_invitation(&invitation)?;
+    info!("Invitation {} has been accepted by user {}", invitation.get_id(), user.get_id());
+    let info = PersonInfoViewModel::from_identity_user(&user);
+    hooks.after("registration", |hook| hook.post_registration(&info));
+    Ok(user)
+}
+
+/**
+ * Invites an email and sends it a link to accept the invitation. The admin can invite with any flags and groups, an user with the inviter flag only with his own flags and groups.
+ *
+ * An error is returned when:
+ * * the token is neither that of the admin nor that of an user with the inviter flag
+ * * the email isn't valid, is already taken or already has a pending invitation
+ * * a group doesn't exist, or the inviter may not assign a flag or group
+ */
+pub fn invite(
+    token : &str,
+    model : InviteViewModel,
+    stores : &InvitationStores,
+    clients : &ClientStore,
+    tokens : &TokenStore,
+    outbox : &MailOutbox,
+    send_function : InvitationDelegate
+) -> Result<InvitationViewModel, IdentityError> {
+    let inviter = control_inviter_token(token, &stores.db, clients, tokens)?;
+    let mut invitation = make_invitation(&inviter, &model, stores, *INVITATION_EXPIRATION)?;
+    send_invitation(&mut invitation, stores, *INVITATION_EXPIRATION, outbox, send_function)?;
+    Ok(InvitationViewModel::from_invitation(&invitation))
+}
+
+/**
+ * Returns the invitations, an user with the inviter flag only gets his own invitations.
+ */
+pub fn get_invitations(token : &str, stores : &InvitationStores, clients : &ClientStore, tokens : &TokenStore) -> Result<AllInvitationsViewModel, IdentityError> {
+    let inviter = control_inviter_token(token, &stores.db, clients, tokens)?;
+    Ok(AllInvitationsViewModel::from_invitations_vector(
+        stores.invitations.get_all_invitations().into_iter().filter(|invitation| inviter.may_manage(invitation)).collect()
+    ))
+}
+
+/**
+ * Sends a new link for an invitation that hasn't been accepted or revoked, also when it has expired. The link gets a new expiration and earlier links stop working.
+ */
+pub fn resend_invitation(
+    token : &str,
+    model : InvitationIdViewModel,
+    stores : &InvitationStores,
+    clients : &ClientStore,
+    tokens : &TokenStore,
+    outbox : &MailOutbox,
+    send_function : InvitationDelegate
+) -> Result<InvitationViewModel, IdentityError> {
+    let inviter = control_inviter_token(token, &stores.db, clients, tokens)?;
+    let mut invitation = find_invitation(&inviter, model.get_id(), &stores.invitations)?;
+    if *invitation.get_status() != InvitationStatus::Pending {
+        return Err(IdentityError::InvitationIsNotPending)
+    }
+    send_invitation(&mut invitation, stores, *INVITATION_EXPIRATION, outbox, send_function)?;
+    Ok(InvitationViewModel::from_invitation(&invitation))
+}
+
+/**
+ * Lets a pending invitation expire right away, it can still be resent afterwards.
+ */
+pub fn expire_invitation(token : &str, model : InvitationIdViewModel, stores : &InvitationStores, clients : &ClientStore, tokens : &TokenStore) -> Result<InvitationViewModel, IdentityError> {
+    let inviter = control_inviter_token(token, &stores.db, clients, tokens)?;
+    let mut invitation = find_invitation(&inviter, model.get_id(), &stores.invitations)?;
+    if !invitation.is_pending() {
+        return Err(IdentityError::InvitationIsNotPending)
+    }
+    invitation.expire();
+    stores.invitations.save_invitation(&invitation)?;
+    info!("Invitation {} has been expired", invitation.get_id());
+    Ok(InvitationViewModel::from_invitation(&invitation))
+}
+
+/**
+ * Revokes an invitation that hasn't been accepted, it can't be resent anymore.
+ */
+pub fn revoke_invitation(token : &str, model : InvitationIdViewModel, stores : &InvitationStores, clients : &ClientStore, tokens : &TokenStore) -> Result<InvitationViewModel, IdentityError> {
+    let inviter = control_inviter_token(token, &stores.db, clients, tokens)?;
+    let mut invitation = find_invitation(&inviter, model.get_id(), &stores.invitations)?;
+    if *invitation.get_status() != InvitationStatus::Pending {
+        return Err(IdentityError::InvitationIsNotPending)
+    }
+    invitation.revoke();
+    stores.invitations.save_invitation(&invitation)?;
+    info!("Invitation {} has been revoked", invitation.get_id());
+    Ok(InvitationViewModel::from_invitation(&invitation))
+}
+
+/**
+ * Accepts an invitation with the token of its link, the account is made with the chosen password and the invitee is logged in.
+ *
+ * An error is returned when:
+ * * the token doesn't belong to a pending invitation
+ * * the password is too short or isn't the same as its confirmation
+ * * the email has been taken in the meantime
+ * * a pre registration or pre login hook vetoes it
+ */
+pub fn accept_invitation(model : AcceptInvitationViewModel, stores : &InvitationStores, hooks : &HookRegistry) -> Result<Claim, IdentityError> {
+    control_password_length(model.get_password())?;
+    let user = accept(&model, stores, hooks)?;
+    let claim = Claim::new_read_write_claim(user.get_id())?;
+    let info = PersonInfoViewModel::from_identity_user(&user);
+    hooks.after("login", |hook| hook.post_login(&info));
+    Ok(claim)
+}
+
+#[test]
+fn test_invitation() {
+    use identity_dal::group::identity_group::IdentityGroup;
+    use identity_dal::repo::user_config::UserConfig;
+
+    let config = UserConfig::new_config("", "person", 100000);
+    let stores = InvitationStores { db : Store::new_db(config.clone()), groups : GroupStore::new_db(config.clone()), invitations : InvitationStore::new_db(config) };
+    let mut inviter = IdentityUser::new_user("lead@corp.be", "lead", "Passw0rd!").unwrap();
+    inviter.add_flag("inviter");
+    inviter.add_flag("sales");
+    let inviter = Inviter::User(stores.db.add_user(inviter).unwrap());
+    let mut group = IdentityGroup::new("group", "team");
+    group.add_member(inviter.get_id());
+    stores.groups.add_group(&group).unwrap();
+
+    assert!(matches!(make_invitation(&inviter, &InviteViewModel::new("jane@corp.be", &["admin"], &[]), &stores, 60), Err(IdentityError::InsufficientScope)));
+    assert!(matches!(make_invitation(&inviter, &InviteViewModel::new("lead@corp.be", &[], &[]), &stores, 60), Err(IdentityError::EmailIsAlreadyTaken)));
+    let mut invitation = make_invitation(&inviter, &InviteViewModel::new("jane@corp.be", &["sales"], &["group"]).with_locale("nl"), &stores, 60).unwrap();
+    invitation.renew(&hash_token("first"), 60);
+    stores.invitations.save_invitation(&invitation).unwrap();
+    assert!(matches!(make_invitation(&Inviter::Admin("ADMIN".to_owned()), &InviteViewModel::new("jane@corp.be", &[], &[]), &stores, 60), Err(IdentityError::EmailIsAlreadyInvited)));
+    assert!(find_invitation(&Inviter::Admin("ADMIN".to_owned()), invitation.get_id(), &stores.invitations).is_ok());
+
+    invitation.renew(&hash_token("second"), 60);
+    stores.invitations.save_invitation(&invitation).unwrap();
+    assert!(matches!(accept(&AcceptInvitationViewModel::new("first", "Jane", "Passw0rd!", "Passw0rd!"), &stores, &HookRegistry::new()), Err(IdentityError::InvitationNotFound)));
+    assert!(matches!(accept(&AcceptInvitationViewModel::new("second", "Jane", "Passw0rd!", "other"), &stores, &HookRegistry::new()), Err(IdentityError::PasswordAndPasswordConfirmedNotEqual)));
+    let user = accept(&AcceptInvitationViewModel::new("second", "Jane", "Passw0rd!", "Passw0rd!"), &stores, &HookRegistry::new()).unwrap();
+    assert!(user.check_pwd("Passw0rd!"));
+    assert_eq!(user.get_flags(), ["sales", "team"].iter().map(|flag| flag.to_string()).collect());
+    assert!(stores.groups.get_group("group").unwrap().has_member(user.get_id()));
+    assert_eq!(user.get_locale(), Some("nl"));
+    assert_eq!(InvitationViewModel::from_invitation(&stores.invitations.get_invitation(invitation.get_id()).unwrap()).get_status(), "accepted");
+    assert!(matches!(accept(&AcceptInvitationViewModel::new("second", "Jane", "Passw0rd!", "Passw0rd!"), &stores, &HookRegistry::new()), Err(IdentityError::InvitationIsNotPending)));
+
+    let other = Inviter::User(user);
+    assert!(matches!(find_invitation(&other, invitation.get_id(), &stores.invitations), Err(IdentityError::InvitationNotFound)));
+}
@@ -0,0 +1,231 @@
+use crate::hooks::HookRegistry;
+use crate::ldap::{ DirectoryEntry, LdapDirectory };
+use crate::store::Store;
+use crate::service::webhook_service::{ self, WebhookEventType, WebhookStore };
+use crate::viewmodels::auth::login::LoginViewModel;
+use crate::viewmodels::auth::person_info::PersonInfoViewModel;
+use identity_dal::ldap::ldap_account::LdapAccount;
+use identity_dal::repo::ldap_repo::LdapStore;
+use identity_dal::traits::t_user::UserTrait;
+use identity_dal::traits::t_user_manager::UserStoreTrait;
+use identity_dal::user::identity_user::IdentityUser;
+use identity_dal::util::get_hash;
+use crate::IdentityError;
+
+/**
+ * Verifies the credentials of an user of the directory and returns the local user, whose user name and flags are synced with his entry. An user that logs in for the first time is provisioned without a local password.
+ */
+pub fn login(directory : &LdapDirectory, model : &LoginViewModel, db : &Store, ldap : &LdapStore, webhooks : &WebhookStore, hooks : &HookRegistry) -> Result<IdentityUser, IdentityError> {
+    let entry = directory.authenticate(model.get_email(), model.get_password())?;
+    sync_user(model.get_email(), &entry, db, ldap, webhooks, hooks)
+}
+
+/**
+ * Syncs the entry of the directory into the local user of the email. The groups of the entry become flags, groups the user isn't a member of anymore are removed while flags that didn't come from the directory are kept. The provisioning of an user and a change of his flags are published to the webhooks, the pre registration hooks can veto the provisioning.
+ */
+pub fn sync_user(email : &str, entry : &DirectoryEntry, db : &Store, ldap : &LdapStore, webhooks : &WebhookStore, hooks : &HookRegistry) -> Result<IdentityUser, IdentityError> {
+    let existing = db.get_user_by_email(email);
+    let provisioned = existing.is_none();
+    let mut user = match existing {
+        Some(user) => user,
+        None => {
+            let mut user = IdentityUser::new_user(email, entry.user_name.as_deref().unwrap_or_default(), &get_hash(32))?;
+            user.set_hashed_password("");
+            user.set_security_stamp("");
+            hooks.before("registration", |hook| hook.pre_registration(email))?;
+            let user = db.add_user(user)?;
+            info!("User {} has been provisioned from the LDAP directory", user.get_id());
+            user
+        }
+    };
+    if let Some(user_name) = &entry.user_name {
+        user.set_user_name(user_name);
+    }
+    let previous_groups = ldap.get_account(user.get_id()).map(|account| account.get_groups().clone()).unwrap_or_default();
+    let previous_flags = user.get_flags();
+    let mut flags = previous_flags.clone();
+    flags.retain(|flag| !previous_groups.contains(flag));
+    flags.extend(entry.groups.iter().cloned());
+    user.set_flags(flags);
+    db.update_user(user.get_id(), &user)?;
+    ldap.insert_account(&LdapAccount::new(user.get_id(), &entry.dn, entry.groups.clone()))?;
+    info!("User {} has been synced with the LDAP directory", user.get_id());
+    if provisioned {
+        let info = PersonInfoViewModel::from_identity_user(&user);
+        hooks.after("registration", |hook| hook.post_registration(&info));
+        webhook_service::publish(webhooks, WebhookEventType::UserRegistered, user.get_id(), db);
+    } else if user.get_flags() != previous_flags {
+        webhook_service::publish(webhooks, WebhookEventType::RolesChanged, user.get_id(), db);
+    }
+    Ok(user)
+}
+
+/**
+ * LDAP server that runs in the tests. It knows the bind, search and unbind operations, searches only support an equality filter.
+ */
+#[cfg(test)]
+mod stub_directory {
+    use std::io::{ Read, Write };
+    use std::net::{ TcpListener, TcpStream };
+
+    pub struct StubEntry {
+        pub dn : &'static str,
+        pub password : &'static str,
+        pub attributes : Vec<(&'static str, Vec<&'static str>)>
+    }
+
+    /**
+     * Starts the server on a free port and returns its url.
+     */
+    pub fn start(entries : Vec<StubEntry>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ldap://{}", listener.local_addr().unwrap());
+        let entries : &'static [StubEntry] = Box::leak(entries.into_boxed_slice());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                std::thread::spawn(move || serve(stream, entries));
+            }
+        });
+        url
+    }
+
+    fn serve(mut stream : TcpStream, entries : &[StubEntry]) {
+        while let Some((0x30, message)) = read_tlv(&mut stream) {
+            let mut parts = Tlvs(&message);
+            let (_, message_id) = parts.next().unwrap();
+            let (operation, request) = parts.next().unwrap();
+            let reply = |stream : &mut TcpStream, op : Vec<u8>| {
+                stream.write_all(&tlv(0x30, &[tlv(0x02, message_id), op].concat())).unwrap();
+            };
+            match operation {
+                0x60 => {
+                    let mut fields = Tlvs(request);
+                    fields.next();
+                    let dn = fields.next().unwrap().1;
+                    let password = fields.next().unwrap().1;
+                    let known = entries.iter().any(|entry| entry.dn.as_bytes() == dn && entry.password.as_bytes() == password && !password.is_empty());
+                    reply(&mut stream, tlv(0x61, &result(if known { 0 } else { 49 })));
+                },
+                0x63 => {
+                    let filter = Tlvs(request).nth(6).unwrap();
+                    let mut assertion = Tlvs(filter.1);
+                    let attribute = String::from_utf8_lossy(assertion.next().unwrap().1).to_string();
+                    let value = String::from_utf8_lossy(assertion.next().unwrap().1).to_string();
+                    for entry in entries.iter().filter(|entry| entry.attributes.iter()
+                        .any(|(name, values)| name.eq_ignore_ascii_case(&attribute) && values.iter().any(|v| v.eq_ignore_ascii_case(&value))))
+                    {
+                        let attributes : Vec<u8> = entry.attributes.iter().map(|(name, values)| tlv(0x30, &[
+                            tlv(0x04, name.as_bytes()),
+                            tlv(0x31, &values.iter().map(|value| tlv(0x04, value.as_bytes())).collect::<Vec<_>>().concat())
+                        ].concat())).collect::<Vec<_>>().concat();
+                        reply(&mut stream, tlv(0x64, &[tlv(0x04, entry.dn.as_bytes()), tlv(0x30, &attributes)].concat()));
+                    }
+                    reply(&mut stream, tlv(0x65, &result(0)));
+                },
+                _ => return
+            }
+        }
+    }
+
+    fn result(code : u8) -> Vec<u8> {
+        [tlv(0x0a, &[code]), tlv(0x04, b""), tlv(0x04, b"")].concat()
+    }
+
+    fn tlv(tag : u8, value : &[u8]) -> Vec<u8> {
+        let length = match value.len() {
+            len if len < 0x80 => vec![len as u8],
+            len if len < 0x100 => vec![0x81, len as u8],
+            len => vec![0x82, (len >> 8) as u8, len as u8]
+        };
+        [&[tag][..], &length, value].concat()
+    }
+
+    fn read_tlv(stream : &mut TcpStream) -> Option<(u8, Vec<u8>)> {
+        let mut header = [0u8; 2];
+        stream.read_exact(&mut header).ok()?;
+        let length = if header[1] < 0x80 {
+            header[1] as usize
+        } else {
+            let mut bytes = vec![0u8; (header[1] & 0x7f) as usize];
+            stream.read_exact(&mut bytes).ok()?;
+            bytes.iter().fold(0, |length, byte| (length << 8) | *byte as usize)
+        };
+        let mut value = vec![0u8; length];
+        stream.read_exact(&mut value).ok()?;
+        Some((header[0], value))
+    }
+
+    /**
+     * Iterator over the elements in the value of a constructed element.
+     */
+    struct Tlvs<'a>(&'a [u8]);
+
+    impl<'a> Iterator for Tlvs<'a> {
+        type Item = (u8, &'a [u8]);
+
+        fn next(&mut self) -> Option<Self::Item> {
+            if self.0.len() < 2 {
+                return None
+            }
+            let (offset, length) = if self.0[1] < 0x80 {
+                (2, self.0[1] as usize)
+            } else {
+                let count = (self.0[1] & 0x7f) as usize;
+                (2 + count, self.0[2..2 + count].iter().fold(0, |length, byte| (length << 8) | *byte as usize))
+            };
+            let item = (self.0[0], &self.0[offset..offset + length]);
+            self.0 = &self.0[offset + length..];
+            Some(item)
+        }
+    }
+}
+
+#[test]
+fn test_login_with_stub_directory() {
+    use identity_dal::repo::user_config::UserConfig;
+    use identity_dal::webhook::webhook_subscription::WebhookSubscription;
+    use stub_directory::StubEntry;
+
+    let url = stub_directory::start(vec![
+        StubEntry { dn : "cn=reader,dc=corp,dc=be", password : "reader", attributes : vec![] },
+        StubEntry { dn : "uid=jane,ou=people,dc=corp,dc=be", password : "secret", attributes : vec![
+            ("mail", vec!["jane@corp.be"]),
+            ("cn", vec!["Jane Doe"]),
+            ("memberOf", vec!["cn=sales,ou=groups,dc=corp,dc=be", "cn=staff,ou=groups,dc=corp,dc=be"])
+        ]}
+    ]);
+    let directory = LdapDirectory::new(&url, &["corp.be".to_owned()], "dc=corp,dc=be").with_bind("cn=reader,dc=corp,dc=be", "reader");
+    let config = UserConfig::new_config("", "person", 100000);
+    let db = Store::new_db(config.clone());
+    let ldap = LdapStore::new_db(config.clone());
+    let webhooks = WebhookStore::new_db(config);
+    webhooks.save_subscription(&WebhookSubscription::new("1", "http://localhost", "secret", WebhookEventType::ALL.iter().copied().collect(), "admin")).unwrap();
+    let published = || webhooks.get_deliveries_of_subscription("1", None).iter().map(|delivery| delivery.get_event_type()).collect::<Vec<_>>();
+
+    assert!(directory.handles_email("Jane@CORP.be"));
+    assert!(!directory.handles_email("jane@other.be"));
+    assert!(matches!(login(&directory, &LoginViewModel::new("jane@corp.be", "wrong"), &db, &ldap, &webhooks, &HookRegistry::new()), Err(IdentityError::PasswordIsNotCorrect)));
+    assert!(matches!(login(&directory, &LoginViewModel::new("john@corp.be", "secret"), &db, &ldap, &webhooks, &HookRegistry::new()), Err(IdentityError::UserIsNotPresent)));
+    assert!(db.get_user_by_email("jane@corp.be").is_none());
+
+    let user = login(&directory, &LoginViewModel::new("jane@corp.be", "secret"), &db, &ldap, &webhooks, &HookRegistry::new()).unwrap();
+    assert_eq!(user.get_user_name(), "Jane Doe");
+    assert_eq!(user.get_flag_list(), vec!["sales", "staff"]);
+    assert!(!user.check_pwd("secret"));
+    assert_eq!(published(), vec![WebhookEventType::UserRegistered]);
+    login(&directory, &LoginViewModel::new("jane@corp.be", "secret"), &db, &ldap, &webhooks, &HookRegistry::new()).unwrap();
+    assert_eq!(published().len(), 1);
+
+    let mut local = user.clone();
+    local.add_flag("local");
+    local.remove_flag("staff");
+    db.update_user(local.get_id(), &local).unwrap();
+    let synced_entry = DirectoryEntry { dn : "uid=jane,ou=people,dc=corp,dc=be".to_owned(), user_name : None, groups : vec!["sales".to_owned()].into_iter().collect() };
+    let user = sync_user("jane@corp.be", &synced_entry, &db, &ldap, &webhooks, &HookRegistry::new()).unwrap();
+    assert_eq!(user.get_flag_list(), vec!["local", "sales"]);
+    assert_eq!(user.get_user_name(), "Jane Doe");
+    assert_eq!(published().len(), 1);
+    let synced_entry = DirectoryEntry { groups : vec!["support".to_owned()].into_iter().collect(), ..synced_entry };
+    assert_eq!(sync_user("jane@corp.be", &synced_entry, &db, &ldap, &webhooks, &HookRegistry::new()).unwrap().get_flag_list(), vec!["local", "support"]);
+    assert_eq!(published(), vec![WebhookEventType::RolesChanged, WebhookEventType::UserRegistered]);
+}
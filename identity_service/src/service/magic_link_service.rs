@@ -0,0 +1,155 @@
+use crate::claim::Claim;
+use crate::hooks::HookRegistry;
+use crate::ldap;
+use crate::store::Store;
+use crate::service::mail_service::MailOutbox;
+use crate::util::{ append_query, get_value_from_key, hash_token };
+use crate::viewmodels::auth::email::EmailViewModel;
+use crate::viewmodels::auth::person_info::PersonInfoViewModel;
+use crate::viewmodels::auth::token::TokenHolderViewModel;
+use identity_dal::passwordless::magic_link::MagicLink;
+use identity_dal::repo::magic_link_repo::MagicLinkStore;
+use identity_dal::traits::t_user::UserTrait;
+use identity_dal::traits::t_user_manager::UserStoreTrait;
+use identity_dal::user::identity_user::{ IdentityUser, RESERVED_ID };
+use identity_dal::util::get_hash;
+use crate::IdentityError;
+
+lazy_static! {
+    /**
+     * Page the magic links point to, it sends the token of the link to the login endpoint. Logging in with a magic link is only possible when it is set.
+     */
+    static ref MAGIC_LINK_URL : Option<String> = get_value_from_key("PERSON_MAGIC_LINK_URL");
+    static ref MAGIC_LINK_EXPIRATION : i64 = get_value_from_key("PERSON_MAGIC_LINK_EXPIRATION")
+    .unwrap_or_else(|| "600".to_owned())
+    .parse::<i64>().expect("Could not parse this string to i64");
+    static ref MAGIC_LINK_MAX_REQUESTS : usize = get_value_from_key("PERSON_MAGIC_LINK_MAX_REQUESTS")
+    .unwrap_or_else(|| "3".to_owned())
+    .parse::<usize>().expect("Could not convert the string to a usize type.");
+    static ref MAGIC_LINK_WINDOW : i64 = get_value_from_key("PERSON_MAGIC_LINK_WINDOW")
+    .unwrap_or_else(|| "900".to_owned())
+    .parse::<i64>().expect("Could not parse this string to i64");
+}
+
+/**
+ * Function that mails the magic link to the user in his locale.
+ */
+pub type MagicLinkDelegate = fn(email : &str, user_name : &str, locale : Option<&str>, link : &str, outbox : &MailOutbox) -> Result<(), IdentityError>;
+
+/**
+ * Returns true when users can log in with a magic link.
+ */
+pub fn is_enabled() -> bool {
+    MAGIC_LINK_URL.is_some()
+}
+
+/**
+ * Sends a magic link to the user with the email, with which he can log in once without his password. Just like a password reset the same answer is given whether the email is known or not, and when too many links were asked for the email no link is sent.
+ *
+ * An error is returned when logging in with a magic link isn't enabled.
+ */
+pub fn demand_magic_link(
+    model : EmailViewModel,
+    store : Store,
+    links : MagicLinkStore,
+    outbox : &MailOutbox,
+    send_link_function : MagicLinkDelegate
+) -> Result<(), IdentityError> {
+    let url = MAGIC_LINK_URL.as_deref().ok_or(IdentityError::MagicLinkIsDisabled)?;
+    if ldap::get_directory().is_some_and(|directory| directory.handles_email(model.get_email())) {
+        warn!("A magic link has been asked for an user of the LDAP directory");
+        return Ok(())
+    }
+    if let Some((user, link)) = make_magic_link(model.get_email(), url, &store, &links, *MAGIC_LINK_MAX_REQUESTS, *MAGIC_LINK_WINDOW, *MAGIC_LINK_EXPIRATION)? {
+        match send_link_function(user.get_email(), user.get_user_name(), user.get_locale(), &link, outbox) {
+            Ok(_) => info!("A magic link has been sent to user {}", user.get_id()),
+            Err(_) => warn!("Could not send the magic link to user {}", user.get_id())
+        }
+    }
+    Ok(())
+}
+
+/**
+ * Logs the user in with the token of a magic link, the link can't be used again.
+ *
+ * An error is returned when logging in with a magic link isn't enabled, when the link doesn't exist, has expired or the password of the user has changed since it was sent, and when a pre login hook vetoes the login. The link is used up by a vetoed login too.
+ */
+pub fn login_with_magic_link(model : TokenHolderViewModel, store : Store, links : MagicLinkStore, hooks : &HookRegistry) -> Result<Claim, IdentityError> {
+    if !is_enabled() {
+        return Err(IdentityError::MagicLinkIsDisabled)
+    }
+    let user = take_magic_link(model.get_token(), &store, &links)?;
+    hooks.before("login", |hook| hook.pre_login(user.get_email()))?;
+    info!("User {} has logged in with a magic link", user.get_id());
+    let claim = Claim::new_read_write_claim(user.get_id())?;
+    let info = PersonInfoViewModel::from_identity_user(&user);
+    hooks.after("login", |hook| hook.post_login(&info));
+    Ok(claim)
+}
+
+/**
+ * Makes a magic link for the user with the email and returns it together with the user. None is returned when the email isn't known or too many links were asked for it within the window.
+ */
+fn make_magic_link(
+    email : &str,
+    url : &str,
+    store : &Store,
+    links : &MagicLinkStore,
+    max_requests : usize,
+    window : i64,
+    lifetime : i64
+) -> Result<Option<(IdentityUser, String)>, IdentityError> {
+    if !links.register_request(email, max_requests, window) {
+        warn!("Too many magic links have been asked for {}", email);
+        return Ok(None)
+    }
+    let user = match store.get_user_by_email(email).filter(|user| user.get_id() != RESERVED_ID && user.is_active()) {
+        Some(user) => user,
+        None => {
+            warn!("A magic link has been asked for the unknown or inactive email {}", email);
+            return Ok(None)
+        }
+    };
+    let token = get_hash(40);
+    links.add_link(&hash_token(&token), &MagicLink::new(user.get_id(), user.get_security_stamp(), lifetime))?;
+    let link = append_query(url, &[("token", &token)]);
+    Ok(Some((user, link)))
+}
+
+/**
+ * Uses up the magic link of the token and returns its user.
+ */
+fn take_magic_link(token : &str, store : &Store, links : &MagicLinkStore) -> Result<IdentityUser, IdentityError> {
+    let link = links.take_link(&hash_token(token)).ok_or(IdentityError::MagicLinkIsInvalid)?;
+    store.get_user_by_uuid(link.get_user_id())
+        .filter(|user| user.get_security_stamp() == link.get_security_stamp() && user.is_active())
+        .ok_or(IdentityError::MagicLinkIsInvalid)
+}
+
+#[test]
+fn test_magic_link() {
+    use identity_dal::repo::user_config::UserConfig;
+
+    let config = UserConfig::new_config("", "person", 100000);
+    let store = Store::new_db(config.clone());
+    let links = MagicLinkStore::new_db(config);
+    let user = store.add_user(IdentityUser::new_user("jane@corp.be", "jane", "Passw0rd!").unwrap()).unwrap();
+
+    let (_, link) = make_magic_link("jane@corp.be", "https://app.corp.be/magic?lang=en", &store, &links, 2, 60, 60).unwrap().unwrap();
+    let token = link.split("token=").nth(1).unwrap();
+    assert!(link.starts_with("https://app.corp.be/magic?lang=en&token="));
+    assert_eq!(take_magic_link(token, &store, &links).unwrap().get_id(), user.get_id());
+    assert!(matches!(take_magic_link(token, &store, &links), Err(IdentityError::MagicLinkIsInvalid)));
+
+    assert!(make_magic_link("nobody@corp.be", "https://app.corp.be/magic", &store, &links, 2, 60, 60).unwrap().is_none());
+    let (_, link) = make_magic_link("jane@corp.be", "https://app.corp.be/magic", &store, &links, 2, 60, 60).unwrap().unwrap();
+    assert!(make_magic_link("jane@corp.be", "https://app.corp.be/magic", &store, &links, 2, 60, 60).unwrap().is_none());
+
+    let mut changed = store.get_user_by_uuid(user.get_id()).unwrap();
+    changed.set_password("An0therPassw0rd!").unwrap();
+    store.update_user(user.get_id(), &changed).unwrap();
+    assert!(matches!(take_magic_link(link.split("token=").nth(1).unwrap(), &store, &links), Err(IdentityError::MagicLinkIsInvalid)));
+
+    let (_, link) = make_magic_link("jane@corp.be", "https://app.corp.be/magic", &store, &links, 5, 60, -1).unwrap().unwrap();
+    assert!(matches!(take_magic_link(link.split("token=").nth(1).unwrap(), &store, &links), Err(IdentityError::MagicLinkIsInvalid)));
+}
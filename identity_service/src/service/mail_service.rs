@@ -1,53 +1,246 @@
-use lettre::{ SmtpTransport, Transport };
-use lettre::smtp::authentication::Credentials;
-use crate::mail_struct::Report;
+use crate::mail_struct::{ InlineImage, Report };
+use crate::mail_transport;
 use crate::IdentityError;
-use std::sync::Mutex;
+use crate::store::Store;
+use crate::service::admin_service;
+use crate::viewmodels::admin::mail::{ AllOutboxMailsViewModel, MailIdViewModel, OutboxMailViewModel };
+use identity_dal::mail::outbox_mail::{ MailAttempt, MailStatus, OutboxMail };
+use identity_dal::repo::client_repo::ClientStore;
+use identity_dal::repo::token_repo::TokenStore;
+use std::thread::{ self, JoinHandle };
+use std::time::Duration;
 use crate::util::get_value_from_key;
 
+pub type MailOutbox = identity_dal::repo::mail_outbox_repo::MailOutboxStore;
+pub type MailTransport = dyn mail_transport::MailTransport;
+
+/**
+ * Amount of mails the admin gets to see of the outbox.
+ */
+static OUTBOX_LOG_LIMIT : usize = 100;
+
+/**
+ * Email the mails are sent from when the transport isn't smtp and no sender is configured.
+ */
+static LOCAL_SENDER : &str = "identity@localhost";
+
 lazy_static! {
     /**
-     * Variable that is used to get the email that will be used as the smtp client.
+     * Email the mails are sent from, PERSON_SMTP_FROM or else the smtp username, a relay without login needs the first. Only the smtp transport requires one, the other transports send from identity@localhost without it.
+     */
+    static ref EMAIL : String = get_value_from_key("PERSON_SMTP_FROM").or_else(|| get_value_from_key("PERSON_SMTP_USERNAME"))
+    .or_else(|| Some(LOCAL_SENDER.to_owned()).filter(|_| !mail_transport::uses_smtp()))
+    .expect("PERSON_SMTP_FROM variable not found in the .env config file or as environment variable");
+
+    /**
+     * Display name the mails are sent from, without it only the email is shown.
+     */
+    static ref FROM_NAME : Option<String> = get_value_from_key("PERSON_SMTP_FROM_NAME");
+
+    /**
+     * Logo html mails can show with cid:logo, read from the image file of PERSON_MAIL_LOGO.
      */
-    static ref EMAIL : String = get_value_from_key("PERSON_SMTP_USERNAME")
-    .expect("PERSON_SMTP_USERNAME variable not found in the .env config file or as environment variable");
+    static ref LOGO : Option<InlineImage> = load_logo();
+    static ref MAX_ATTEMPTS : usize = get_value_from_key("PERSON_MAIL_MAX_ATTEMPTS")
+    .unwrap_or_else(|| "6".to_owned())
+    .parse::<usize>()
+    .expect("PERSON_MAIL_MAX_ATTEMPTS has to be a number");
+    static ref BACKOFF : i64 = get_value_from_key("PERSON_MAIL_BACKOFF")
+    .unwrap_or_else(|| "60".to_owned())
+    .parse::<i64>()
+    .expect("PERSON_MAIL_BACKOFF has to be a number of seconds");
+    static ref OUTBOX_INTERVAL : u64 = get_value_from_key("PERSON_MAIL_INTERVAL")
+    .unwrap_or_else(|| "10".to_owned())
+    .parse::<u64>()
+    .expect("PERSON_MAIL_INTERVAL has to be a number of seconds");
 }
 
-pub type MailTransport = Mutex<SmtpTransport>;
+fn load_logo() -> Option<InlineImage> {
+    let path = get_value_from_key("PERSON_MAIL_LOGO")?;
+    let content_type = match path.rsplit('.').next().map(str::to_lowercase).as_deref() {
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        _ => panic!("The logo of PERSON_MAIL_LOGO has to be a png, jpeg, gif or svg image")
+    };
+    let data = std::fs::read(&path).unwrap_or_else(|e| panic!("Could not read the logo of PERSON_MAIL_LOGO: {}", e));
+    Some(InlineImage::new("logo", content_type, data))
+}
 
 /**
- * Returns the smtp transport that can later be used then
+ * Returns the logo when one is configured with PERSON_MAIL_LOGO.
  */
-pub fn get_transport() -> MailTransport {
-    Mutex::new(lettre::SmtpClient::new_simple(
-        &get_value_from_key("PERSON_SMTP_DOMAIN")
-        .expect("PERSON_SMTP_DOMAIN variable not found in the .env config file or as environment variable")
-    )
-    .expect("stmp domain is not good")
-    .credentials(
-        Credentials::new(EMAIL.to_string(),
-        get_value_from_key("PERSON_SMTP_PASSWORD")
-        .expect("PERSON_SMTP_PASSWORD variable not found in the .env config file or as environment variable"))
-    )
-    .transport())
+pub fn get_logo() -> Option<&'static InlineImage> {
+    LOGO.as_ref()
 }
 
 /**
- * Function that takes in a report Structure that it then uses to send a email.
+ * Returns the transport the mails are sent with, see mail_transport::from_config.
  */
-pub fn send_email(transport : &MailTransport, report : Report) -> Result<(),IdentityError> {
-    let mail = match report.email().from(EMAIL.clone()).build() {
-        Ok(mail) => mail,
-        Err(_) => return Err(IdentityError::CustomError("Faulthy email structure.".to_string()))
+pub fn get_transport() -> Box<MailTransport> {
+    mail_transport::from_config()
+}
+
+/**
+ * How the outbox is sent: the sender of the mails, and how many times a mail is tried with which backoff in seconds before it has failed.
+ */
+struct OutboxSettings {
+    from : String,
+    from_name : Option<String>,
+    max_attempts : usize,
+    backoff : i64
+}
+
+impl OutboxSettings {
+    fn from_config() -> OutboxSettings {
+        OutboxSettings { from : EMAIL.clone(), from_name : FROM_NAME.clone(), max_attempts : *MAX_ATTEMPTS, backoff : *BACKOFF }
+    }
+}
+
+/**
+ * Builds the mail of the report from the sender and sends it through the transport, the error of the transport is returned as text so it can be logged with the attempt.
+ */
+fn send_report(transport : &mut MailTransport, report : &Report, from : &str, from_name : Option<&str>) -> Result<(), String> {
+    let builder = match from_name {
+        Some(name) => report.email().from((from, name)),
+        None => report.email().from(from)
     };
-    match transport.lock().expect("Could not lock the email transport").send(mail.into()) {
-        Ok(_) => {
-            info!("Email has been sent through the SMTP transport bus.");
-            Ok(())
-        },
-        Err(_) => {
-            warn!("{}",IdentityError::CouldNotSendEmail);
-            Err(IdentityError::CouldNotSendEmail)
+    let mail = builder.build().map_err(|e| format!("Faulthy email structure: {}", e))?;
+    transport.send(mail.into())
+}
+
+/**
+ * Does one attempt to send the mail and stores the result. A sent mail is removed from the outbox, a mail that used all its attempts stays in it as failed.
+ */
+fn deliver(mut mail : OutboxMail, outbox : &MailOutbox, transport : &mut MailTransport, settings : &OutboxSettings) -> Result<OutboxMail, IdentityError> {
+    let attempt = match send_report(transport, &Report::from_content(mail.get_content()), &settings.from, settings.from_name.as_deref()) {
+        Ok(_) => MailAttempt::success(),
+        Err(e) => {
+            warn!("Mail {} could not be sent: {}", mail.get_id(), e);
+            MailAttempt::failure(&e)
         }
+    };
+    mail.record_attempt(attempt, settings.max_attempts, settings.backoff);
+    match mail.get_status() {
+        MailStatus::Sent => outbox.remove_mail(mail.get_id())?,
+        MailStatus::Failed => {
+            error!("Mail {} to {} has failed after {} attempts", mail.get_id(), mail.get_content().recipient, mail.get_attempts().len());
+            outbox.save_mail(&mail)?
+        },
+        MailStatus::Pending => outbox.save_mail(&mail)?
+    }
+    Ok(mail)
+}
+
+/**
+ * Does an attempt of every due mail and closes the transport afterwards, returns how many were sent.
+ */
+fn deliver_due(outbox : &MailOutbox, transport : &mut MailTransport, settings : &OutboxSettings) -> usize {
+    let due = outbox.get_due_mails(chrono::Utc::now().timestamp());
+    if due.is_empty() {
+        return 0;
     }
-}
\ No newline at end of file
+    let sent = due.into_iter()
+        .filter_map(|mail| deliver(mail, outbox, transport, settings).ok())
+        .filter(|mail| mail.get_status() == &MailStatus::Sent)
+        .count();
+    transport.close();
+    sent
+}
+
+/**
+ * Function that takes in a report Structure and queues it in the outbox, the background task sends it. The request doesn't wait on the smtp server and the mail isn't lost when it is down.
+ */
+pub fn send_email(outbox : &MailOutbox, report : Report) -> Result<(),IdentityError> {
+    let mail = outbox.enqueue(OutboxMail::new(report.to_content()))?;
+    info!("Email {} has been queued in the outbox.", mail.get_id());
+    Ok(())
+}
+
+/**
+ * Starts the background task that sends the due mails of the outbox through the transport at the configured interval.
+ */
+pub fn start_outbox_task(outbox : MailOutbox, mut transport : Box<MailTransport>) -> JoinHandle<()> {
+    let interval = Duration::from_secs(*OUTBOX_INTERVAL);
+    let settings = OutboxSettings::from_config();
+    thread::spawn(move || loop {
+        let sent = deliver_due(&outbox, transport.as_mut(), &settings);
+        if sent > 0 {
+            info!("{} emails have been sent through the {} transport.", sent, transport.name());
+        }
+        thread::sleep(interval);
+    })
+}
+
+/**
+ * Admin function that returns the mails of the outbox with the status (pending or failed), or all of them, the newest first.
+ */
+pub fn get_outbox_mails(
+    token : &str,
+    status : Option<&str>,
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore,
+    outbox : MailOutbox
+) -> Result<AllOutboxMailsViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    let status = match status {
+        Some(name) => Some(MailStatus::parse(name).ok_or_else(|| IdentityError::InvalidRequest(format!("{} is not a mail status", name)))?),
+        None => None
+    };
+    Ok(AllOutboxMailsViewModel::from_mails_vector(outbox.get_mails(status.as_ref(), Some(OUTBOX_LOG_LIMIT))))
+}
+
+/**
+ * Admin function that makes a mail of the outbox pending again, the background task sends it right away with a new round of attempts.
+ */
+pub fn retry_mail(
+    token : &str,
+    model : MailIdViewModel,
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore,
+    outbox : MailOutbox
+) -> Result<OutboxMailViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    let mut mail = outbox.get_mail(model.get_id()).ok_or_else(|| IdentityError::InvalidRequest("the mail doesn't exist".to_owned()))?;
+    mail.retry();
+    outbox.save_mail(&mail)?;
+    info!("Mail {} will be sent again", mail.get_id());
+    Ok(OutboxMailViewModel::from_mail(&mail))
+}
+
+#[test]
+fn test_mail_outbox_delivery() {
+    use identity_dal::repo::user_config::UserConfig;
+    use crate::mail_transport::{ MemoryTransport, SmtpMailTransport };
+
+    let outbox = MailOutbox::new_db(UserConfig::new_config("", "person", 100000));
+    let closed_port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let mut smtp = SmtpMailTransport::new(lettre::SmtpClient::new(closed_port, lettre::ClientSecurity::None).unwrap().transport());
+    let settings = OutboxSettings { from : "noreply@corp.be".to_owned(), from_name : Some("Corp".to_owned()), max_attempts : 2, backoff : 0 };
+    send_email(&outbox, Report::new("jane@corp.be", "jane", "Welcome", "Welcome jane").unwrap().with_html("<p>Welcome jane</p>")).unwrap();
+
+    let queued = outbox.get_mails(None, None).remove(0);
+    assert_eq!(Report::from_content(queued.get_content()).get_html(), Some("<p>Welcome jane</p>"));
+    assert_eq!(deliver_due(&outbox, &mut smtp, &settings), 0);
+    assert_eq!(outbox.get_mail(queued.get_id()).unwrap().get_status(), &MailStatus::Pending);
+    assert_eq!(deliver_due(&outbox, &mut smtp, &settings), 0);
+    let mut failed = outbox.get_mail(queued.get_id()).unwrap();
+    assert_eq!((failed.get_status(), failed.get_attempts().len()), (&MailStatus::Failed, 2));
+    assert!(failed.get_attempts().iter().all(|attempt| attempt.get_error().is_some()));
+    assert_eq!(deliver_due(&outbox, &mut smtp, &settings), 0);
+    assert_eq!(outbox.get_mail(queued.get_id()).unwrap().get_attempts().len(), 2);
+
+    let mut memory = MemoryTransport::default();
+    failed.retry();
+    outbox.save_mail(&failed).unwrap();
+    assert_eq!(deliver_due(&outbox, &mut memory, &settings), 1);
+    assert!(outbox.get_mail(queued.get_id()).is_none());
+    let sent = memory.take_mails();
+    assert_eq!((sent.len(), sent[0].from.as_deref(), sent[0].to.clone()), (1, Some("noreply@corp.be"), vec!["jane@corp.be".to_owned()]));
+    assert!(sent[0].message.contains("Subject: Welcome\r\n"));
+    assert!(sent[0].message.contains("From: \"Corp\" <noreply@corp.be>"));
+    assert!(sent[0].message.contains("<p>Welcome jane</p>"));
+}
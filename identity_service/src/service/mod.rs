@@ -1,3 +1,18 @@
 pub mod admin_service;
 pub mod person_service;
-pub mod mail_service;
\ No newline at end of file
+pub mod mail_service;
+pub mod oauth_service;
+pub mod oidc_service;
+pub mod device_service;
+pub mod federation_service;
+pub mod saml_service;
+pub mod ldap_service;
+pub mod scim_service;
+pub mod magic_link_service;
+pub mod invitation_service;
+pub mod registration_service;
+pub mod status_service;
+pub mod deletion_service;
+pub mod audit_service;
+pub mod webhook_service;
+pub mod change_feed_service;
@@ -0,0 +1,598 @@
+use crate::claim::Claim;
+use crate::store::Store;
+use crate::signing_key::SigningKey;
+use crate::service::{ admin_service, device_service, oidc_service };
+use crate::util::{ append_query, get_value_from_key, hash_token };
+use crate::viewmodels::oauth::register_client::{ RegisterClientViewModel, RegisteredClientViewModel };
+use crate::viewmodels::oauth::authorization_request::AuthorizationRequestViewModel;
+use crate::viewmodels::oauth::token_request::TokenRequestViewModel;
+use crate::viewmodels::oauth::token_response::TokenResponseViewModel;
+use crate::viewmodels::oauth::token_reference::TokenReferenceViewModel;
+use crate::viewmodels::oauth::introspection::IntrospectionViewModel;
+use crate::viewmodels::admin::all_clients::AllClientsViewModel;
+use crate::viewmodels::admin::client_id::ClientIdViewModel;
+use crate::viewmodels::admin::disable_client::DisableClientViewModel;
+use identity_dal::oauth::oauth_client::{ OAuthClient, ClientType, GRANT_AUTHORIZATION_CODE, GRANT_CLIENT_CREDENTIALS };
+use identity_dal::oauth::authorization_code::AuthorizationCode;
+use identity_dal::oauth::refresh_token::RefreshToken;
+use identity_dal::repo::client_repo::ClientStore;
+use identity_dal::repo::code_repo::CodeStore;
+use identity_dal::repo::token_repo::TokenStore;
+use identity_dal::repo::device_repo::DeviceStore;
+use identity_dal::traits::t_user_manager::UserStoreTrait;
+use std::collections::BTreeSet;
+use crate::IdentityError;
+
+lazy_static! {
+    static ref CODE_EXPIRATION : i64 = get_value_from_key("PERSON_OAUTH_CODE_EXPIRATION")
+    .unwrap_or_else(|| "60".to_owned())
+    .parse::<i64>().expect("Could not parse this string to i64");
+    static ref REFRESH_EXPIRATION : i64 = get_value_from_key("PERSON_OAUTH_REFRESH_EXPIRATION")
+    .unwrap_or_else(|| "2592000".to_owned())
+    .parse::<i64>().expect("Could not parse this string to i64");
+}
+
+/**
+ * Only PKCE method that is accepted, plain challenges aren't allowed.
+ */
+pub static CODE_CHALLENGE_METHOD : &str = "S256";
+
+/**
+ * Function that the admin uses to register an OAuth client. The secret of a confidential client is returned in the viewmodel, this is the only time it can be seen.
+ *
+ * An error is returned when:
+ * * the token can't be used for the admin functions
+ * * a grant type isn't supported or a public client asks for the client credentials grant
+ * * the client can use the authorization code grant but has no redirect uri
+ */
+pub fn register_client(
+    token : &str,
+    model : RegisterClientViewModel,
+    clients : ClientStore,
+    db : Store,
+    tokens : TokenStore
+) -> Result<RegisteredClientViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    let client_type = if model.is_confidential() { ClientType::Confidential } else { ClientType::Public };
+    let (mut client, secret) = OAuthClient::new_client(model.get_client_name(), model.get_redirect_uris().to_vec(), client_type)?;
+    if let Some(grant_types) = &model.grant_types {
+        client.set_grant_types(grant_types.iter().cloned().collect())?;
+    }
+    if client.is_grant_type_allowed(GRANT_AUTHORIZATION_CODE) && client.get_redirect_uris().is_empty() {
+        return Err(IdentityError::RedirectUriIsInvalid)
+    }
+    client.set_allowed_scopes(model.scopes.iter().flat_map(|scope| scope.split_whitespace()).map(str::to_owned).collect());
+    let client = clients.add_client(client)?;
+    info!("Admin has registered the client {}", client.get_client_id());
+    Ok(RegisteredClientViewModel::from_client(&client, secret))
+}
+
+/**
+ * Returns all registered clients, their secrets can't be seen.
+ */
+pub fn get_all_clients(token : &str, clients : ClientStore, db : Store, tokens : TokenStore) -> Result<AllClientsViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    Ok(AllClientsViewModel::from_clients_vector(clients.get_all_clients()))
+}
+
+/**
+ * Gives a confidential client a new secret, the old secret can't be used anymore. The new secret is returned in the viewmodel.
+ */
+pub fn rotate_client_secret(
+    token : &str,
+    model : ClientIdViewModel,
+    clients : ClientStore,
+    db : Store,
+    tokens : TokenStore
+) -> Result<RegisteredClientViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    let mut client = clients.get_client(model.get_client_id()).ok_or(IdentityError::ClientNotFound)?;
+    if !client.is_confidential() {
+        return Err(IdentityError::InvalidRequest("A public client has no secret".to_owned()))
+    }
+    let secret = client.new_secret()?;
+    clients.update_client(&client)?;
+    info!("Admin has rotated the secret of client {}", client.get_client_id());
+    Ok(RegisteredClientViewModel::from_client(&client, Some(secret)))
+}
+
+/**
+ * Disables a client or enables it again. A disabled client can't get tokens and the tokens it already has can't be used for the admin functions anymore.
+ */
+pub fn set_client_disabled(
+    token : &str,
+    model : DisableClientViewModel,
+    clients : ClientStore,
+    db : Store,
+    tokens : TokenStore
+) -> Result<(), IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    let mut client = clients.get_client(model.get_client_id()).ok_or(IdentityError::ClientNotFound)?;
+    client.set_disabled(model.is_disabled());
+    clients.update_client(&client)?;
+    info!("Admin has set client {} disabled: {}", client.get_client_id(), model.is_disabled());
+    Ok(())
+}
+
+/**
+ * Looks up the client of an authorization request and controls the redirect uri. Errors returned here may not be redirected to the client, because it isn't sure the redirect uri belongs to it.
+ */
+pub fn get_client_for_authorization(
+    model : &AuthorizationRequestViewModel,
+    clients : &ClientStore
+) -> Result<OAuthClient, IdentityError> {
+    let client = clients.get_client(model.get_client_id()).ok_or(IdentityError::ClientNotFound)?;
+    if client.is_disabled() {
+        return Err(IdentityError::ClientIsDisabled)
+    }
+    if !client.is_grant_type_allowed(GRANT_AUTHORIZATION_CODE) {
+        return Err(IdentityError::UnauthorizedClient)
+    }
+    if !client.is_redirect_uri_registered(model.get_redirect_uri()) {
+        warn!("Redirect uri {} isn't registered for client {}", model.get_redirect_uri(), client.get_client_id());
+        return Err(IdentityError::RedirectUriIsInvalid)
+    }
+    Ok(client)
+}
+
+/**
+ * Controls the parameters of an authorization request after the client and redirect uri have been checked. Errors returned here are sent back to the redirect uri of the client.
+ *
+ * An error is returned when:
+ * * the response type isn't code
+ * * one of the asked scopes isn't allowed for the client
+ * * the PKCE code challenge is missing or doesn't use the S256 method
+ */
+pub fn validate_authorization_request(model : &AuthorizationRequestViewModel, client : &OAuthClient) -> Result<(), IdentityError> {
+    if model.response_type.as_deref() != Some("code") {
+        return Err(IdentityError::UnsupportedResponseType)
+    }
+    control_allowed_scopes(client, model.get_scope())?;
+    if model.code_challenge_method.as_deref() != Some(CODE_CHALLENGE_METHOD) {
+        return Err(IdentityError::InvalidRequest("code_challenge_method has to be S256".to_owned()))
+    }
+    match model.code_challenge.as_deref() {
+        Some(challenge) if challenge.len() == 43 && is_pkce_charset(challenge) => Ok(()),
+        _ => Err(IdentityError::InvalidRequest("code_challenge is missing or malformed".to_owned()))
+    }
+}
+
+/**
+ * Issues an authorization code for the user that has approved the request and returns the uri the user agent has to be redirected to.
+ */
+pub fn issue_authorization_code(
+    model : &AuthorizationRequestViewModel,
+    client : &OAuthClient,
+    user_id : &str,
+    codes : CodeStore
+) -> Result<String, IdentityError> {
+    validate_authorization_request(model, client)?;
+    let code = AuthorizationCode::new(
+        model.get_client_id(),
+        user_id,
+        model.get_redirect_uri(),
+        &normalize_scope(model.get_scope()),
+        model.code_challenge.as_deref().unwrap_or_default(),
+        *CODE_EXPIRATION
+    ).with_nonce(model.get_nonce());
+    codes.add_code(&code)?;
+    info!("An authorization code has been issued to client {}", model.get_client_id());
+    let mut params = vec![("code", code.get_code())];
+    if let Some(state) = model.get_state() {
+        params.push(("state", state));
+    }
+    Ok(append_query(model.get_redirect_uri(), &params))
+}
+
+/**
+ * Returns the uri to which the user agent is redirected when the authorization request has failed or was denied.
+ */
+pub fn error_redirect_uri(model : &AuthorizationRequestViewModel, error : &IdentityError) -> String {
+    let description = format!("{}", error);
+    let mut params = vec![("error", oauth_error_code(error)), ("error_description", &description)];
+    if let Some(state) = model.get_state() {
+        params.push(("state", state));
+    }
+    append_query(model.get_redirect_uri(), &params)
+}
+
+/**
+ * Handles a request to the token endpoint, the grant type decides how the token is obtained.
+ */
+pub fn exchange_token(
+    model : TokenRequestViewModel,
+    clients : ClientStore,
+    codes : CodeStore,
+    tokens : TokenStore,
+    devices : DeviceStore,
+    db : Store,
+    key : &SigningKey
+) -> Result<TokenResponseViewModel, IdentityError> {
+    match model.get_grant_type() {
+        "authorization_code" => exchange_authorization_code(model, clients, codes, tokens, db, key),
+        "refresh_token" => exchange_refresh_token(model, clients, tokens, db),
+        "client_credentials" => exchange_client_credentials(model, clients),
+        "urn:ietf:params:oauth:grant-type:device_code" => device_service::exchange_device_code(model, clients, devices, tokens, db),
+        "" => Err(IdentityError::InvalidRequest("grant_type is missing".to_owned())),
+        _ => Err(IdentityError::UnsupportedGrantType)
+    }
+}
+
+/**
+ * Exchanges an authorization code for an access token and a refresh token. The code is taken out of the store before anything is checked so it can never be used twice. When the openid scope was granted an id token is added to the response.
+ *
+ * An error is returned when:
+ * * the client can't be authenticated
+ * * the code doesn't exist, has expired or was issued to another client
+ * * the redirect uri isn't the same as in the authorization request
+ * * the code verifier doesn't match the code challenge
+ * * the user of the code isn't active anymore
+ */
+fn exchange_authorization_code(
+    model : TokenRequestViewModel,
+    clients : ClientStore,
+    codes : CodeStore,
+    tokens : TokenStore,
+    db : Store,
+    key : &SigningKey
+) -> Result<TokenResponseViewModel, IdentityError> {
+    let client = authenticate_client(model.client_id.as_deref(), model.client_secret.as_deref(), &clients)?;
+    if !client.is_grant_type_allowed(GRANT_AUTHORIZATION_CODE) {
+        return Err(IdentityError::UnauthorizedClient)
+    }
+    let code = codes.take_code(model.code.as_deref().unwrap_or_default()).ok_or(IdentityError::InvalidGrant)?;
+    if code.get_client_id() != client.get_client_id() {
+        warn!("Client {} tried to use a code of another client", client.get_client_id());
+        return Err(IdentityError::InvalidGrant)
+    }
+    if model.redirect_uri.as_deref() != Some(code.get_redirect_uri()) {
+        return Err(IdentityError::InvalidGrant)
+    }
+    if !verify_code_challenge(model.code_verifier.as_deref().unwrap_or_default(), code.get_code_challenge()) {
+        warn!("PKCE code verifier doesn't match the challenge of the code");
+        return Err(IdentityError::InvalidGrant)
+    }
+    control_user_is_active(code.get_user_id(), &db)?;
+    let claim = Claim::new_oauth_claim(code.get_user_id(), client.get_client_id(), code.get_scope())?;
+    info!("An access token has been issued to client {}", client.get_client_id());
+    let refresh_token = issue_refresh_token(client.get_client_id(), code.get_user_id(), code.get_scope(), GRANT_AUTHORIZATION_CODE, code.get_auth_time(), &tokens)?;
+    let response = TokenResponseViewModel::from_claim(&claim)?.with_refresh_token(refresh_token);
+    if !oidc_service::has_scope(code.get_scope(), "openid") {
+        return Ok(response)
+    }
+    let id_token = oidc_service::issue_id_token(&claim, response.get_access_token(), &code, &db, key)?;
+    Ok(response.with_id_token(id_token))
+}
+
+/**
+ * Exchanges a refresh token for a new access token. The refresh token is rotated: the used one is taken out of the store and a new one is returned. A narrower scope than the one originally granted can be asked.
+ *
+ * An error is returned when:
+ * * the client can't be authenticated
+ * * the refresh token doesn't exist, has expired or was issued to another client
+ * * the client may not use the grant the refresh token was issued with anymore, the authorization code or the device code grant
+ * * a scope is asked that wasn't originally granted
+ * * the user of the refresh token isn't active anymore
+ */
+fn exchange_refresh_token(model : TokenRequestViewModel, clients : ClientStore, tokens : TokenStore, db : Store) -> Result<TokenResponseViewModel, IdentityError> {
+    let client = authenticate_client(model.client_id.as_deref(), model.client_secret.as_deref(), &clients)?;
+    let refresh_token = tokens.take_refresh_token(&hash_token(model.refresh_token.as_deref().unwrap_or_default()))
+        .ok_or(IdentityError::InvalidGrant)?;
+    if refresh_token.get_client_id() != client.get_client_id() {
+        warn!("Client {} tried to use a refresh token of another client", client.get_client_id());
+        return Err(IdentityError::InvalidGrant)
+    }
+    if !client.is_grant_type_allowed(refresh_token.get_grant_type()) {
+        warn!("Client {} may not use the {} grant of its refresh token anymore", client.get_client_id(), refresh_token.get_grant_type());
+        return Err(IdentityError::UnauthorizedClient)
+    }
+    let scope = match model.scope.as_deref().map(normalize_scope) {
+        Some(scope) if !scope.is_empty() => {
+            if !scope.split_whitespace().all(|wanted| oidc_service::has_scope(refresh_token.get_scope(), wanted)) {
+                return Err(IdentityError::InvalidScope)
+            }
+            scope
+        },
+        _ => refresh_token.get_scope().to_owned()
+    };
+    control_user_is_active(refresh_token.get_user_id(), &db)?;
+    let claim = Claim::new_oauth_claim(refresh_token.get_user_id(), client.get_client_id(), &scope)?;
+    let new_refresh_token = issue_refresh_token(client.get_client_id(), refresh_token.get_user_id(), refresh_token.get_scope(), refresh_token.get_grant_type(), refresh_token.get_auth_time(), &tokens)?;
+    info!("An access token has been refreshed for client {}", client.get_client_id());
+    Ok(TokenResponseViewModel::from_claim(&claim)?.with_refresh_token(new_refresh_token))
+}
+
+/**
+ * Controls that the user a token is issued for still exists and is active.
+ */
+pub(crate) fn control_user_is_active(user_id : &str, db : &Store) -> Result<(), IdentityError> {
+    db.get_user_by_uuid(user_id).ok_or(IdentityError::InvalidGrant)?.control_status()
+}
+
+/**
+ * Stores a new refresh token under the hash of a newly generated token and returns the token itself.
+ */
+pub(crate) fn issue_refresh_token(
+    client_id : &str,
+    user_id : &str,
+    scope : &str,
+    grant_type : &str,
+    auth_time : i64,
+    tokens : &TokenStore
+) -> Result<String, IdentityError> {
+    let token = identity_dal::util::get_hash(64);
+    tokens.add_refresh_token(&hash_token(&token), &RefreshToken::new(client_id, user_id, scope, grant_type, auth_time, *REFRESH_EXPIRATION))?;
+    Ok(token)
+}
+
+/**
+ * Issues an access token to a confidential client for itself, the client is the subject of the token. When no scope is asked all scopes the client is allowed are granted.
+ *
+ * An error is returned when:
+ * * the client can't be authenticated or isn't confidential
+ * * the client may not use the client credentials grant
+ * * one of the asked scopes isn't allowed for the client
+ */
+fn exchange_client_credentials(model : TokenRequestViewModel, clients : ClientStore) -> Result<TokenResponseViewModel, IdentityError> {
+    let client = authenticate_client(model.client_id.as_deref(), model.client_secret.as_deref(), &clients)?;
+    if !client.is_confidential() || !client.is_grant_type_allowed(GRANT_CLIENT_CREDENTIALS) {
+        return Err(IdentityError::UnauthorizedClient)
+    }
+    control_allowed_scopes(&client, model.scope.as_deref().unwrap_or_default())?;
+    let asked : BTreeSet<String> = model.scope.as_deref().unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_owned)
+        .collect();
+    let scope = if asked.is_empty() {
+        client.get_allowed_scopes().iter().cloned().collect::<Vec<String>>().join(" ")
+    } else {
+        asked.into_iter().collect::<Vec<String>>().join(" ")
+    };
+    let claim = Claim::new_client_claim(client.get_client_id(), &scope)?;
+    info!("An access token has been issued to client {} for itself", client.get_client_id());
+    TokenResponseViewModel::from_claim(&claim)
+}
+
+/**
+ * Authenticates the client of a request to the token, introspection or revocation endpoint. Confidential clients need their secret, public clients only identify themselves with their id. Disabled clients are refused.
+ */
+pub fn authenticate_client(client_id : Option<&str>, client_secret : Option<&str>, clients : &ClientStore) -> Result<OAuthClient, IdentityError> {
+    let client = clients.get_client(client_id.unwrap_or_default())
+        .ok_or(IdentityError::InvalidClient)?;
+    if client.is_confidential() && !client.check_secret(client_secret.unwrap_or_default()) {
+        warn!("Client {} couldn't be authenticated", client.get_client_id());
+        return Err(IdentityError::InvalidClient)
+    }
+    if client.is_disabled() {
+        warn!("Disabled client {} tried to get a token", client.get_client_id());
+        return Err(IdentityError::InvalidClient)
+    }
+    Ok(client)
+}
+
+/**
+ * Tells a resource server if a token is active and what it grants. Access tokens and refresh tokens can be introspected, the token type hint decides which is looked up first. Only confidential clients may introspect tokens.
+ *
+ * A token isn't active when it can't be decoded, has expired, has been revoked, when its client has been disabled or when its user isn't active anymore.
+ */
+pub fn introspect_token(
+    model : TokenReferenceViewModel,
+    clients : ClientStore,
+    tokens : TokenStore,
+    db : Store
+) -> Result<IntrospectionViewModel, IdentityError> {
+    let client = authenticate_client(model.client_id.as_deref(), model.client_secret.as_deref(), &clients)?;
+    if !client.is_confidential() {
+        return Err(IdentityError::UnauthorizedClient)
+    }
+    if model.get_token().is_empty() {
+        return Err(IdentityError::InvalidRequest("token is missing".to_owned()))
+    }
+    let hash = hash_token(model.get_token());
+    let is_client_active = |client_id : &str| clients.get_client(client_id).is_some_and(|client| !client.is_disabled());
+    let access = || Claim::decode_token(model.get_token()).ok()
+        .map(|token| token.claims)
+        .filter(|_| !tokens.is_access_token_revoked(&hash))
+        .filter(|claim| claim.client_id.as_deref().is_none_or(is_client_active))
+        .filter(|claim| claim.is_client_claim() || control_user_is_active(&claim.sub, &db).is_ok())
+        .map(|claim| IntrospectionViewModel::from_claim(&claim));
+    let refresh = || tokens.get_refresh_token(&hash)
+        .filter(|token| is_client_active(token.get_client_id()))
+        .filter(|token| control_user_is_active(token.get_user_id(), &db).is_ok())
+        .map(|token| IntrospectionViewModel::from_refresh_token(&token));
+    let introspection = if model.is_refresh_token_hint() {
+        refresh().or_else(access)
+    } else {
+        access().or_else(refresh)
+    };
+    info!("Client {} has introspected a token", client.get_client_id());
+    Ok(introspection.unwrap_or_else(IntrospectionViewModel::inactive))
+}
+
+/**
+ * Revokes a refresh token or an access token of the authenticated client. Following RFC 7009 nothing is returned for tokens that are invalid, unknown or of another client, so the response doesn't tell anything about the token.
+ */
+pub fn revoke_token(
+    model : TokenReferenceViewModel,
+    clients : ClientStore,
+    tokens : TokenStore
+) -> Result<(), IdentityError> {
+    let client = authenticate_client(model.client_id.as_deref(), model.client_secret.as_deref(), &clients)?;
+    if model.get_token().is_empty() {
+        return Err(IdentityError::InvalidRequest("token is missing".to_owned()))
+    }
+    let hash = hash_token(model.get_token());
+    if let Some(refresh_token) = tokens.get_refresh_token(&hash) {
+        if refresh_token.get_client_id() == client.get_client_id() {
+            tokens.take_refresh_token(&hash);
+            info!("Client {} has revoked a refresh token", client.get_client_id());
+        } else {
+            warn!("Client {} tried to revoke a refresh token of another client", client.get_client_id());
+        }
+        return Ok(())
+    }
+    if let Ok(token) = Claim::decode_token(model.get_token()) {
+        if token.claims.client_id.as_deref() == Some(client.get_client_id()) {
+            tokens.revoke_access_token(&hash, token.claims.exp.timestamp())?;
+            info!("Client {} has revoked an access token", client.get_client_id());
+        } else {
+            warn!("Client {} tried to revoke an access token that wasn't issued to it", client.get_client_id());
+        }
+    }
+    Ok(())
+}
+
+/**
+ * Controls a PKCE code verifier against its S256 challenge: BASE64URL(SHA256(verifier)) has to equal the challenge.
+ */
+pub fn verify_code_challenge(code_verifier : &str, code_challenge : &str) -> bool {
+    if code_verifier.len() < 43 || code_verifier.len() > 128 || !is_pkce_charset(code_verifier) {
+        return false
+    }
+    let digest = ring::digest::digest(&ring::digest::SHA256, code_verifier.as_bytes());
+    base64::encode_config(digest.as_ref(), base64::URL_SAFE_NO_PAD) == code_challenge
+}
+
+/**
+ * Returns the error code of RFC 6749 that belongs to an error.
+ */
+pub fn oauth_error_code(error : &IdentityError) -> &'static str {
+    match error {
+        IdentityError::InvalidRequest(_) | IdentityError::RedirectUriIsInvalid => "invalid_request",
+        IdentityError::InvalidClient | IdentityError::ClientNotFound | IdentityError::ClientIsDisabled => "invalid_client",
+        IdentityError::InvalidGrant | IdentityError::UserIsSuspended | IdentityError::UserIsDeactivated
+        | IdentityError::UserIsPending => "invalid_grant",
+        IdentityError::InvalidScope => "invalid_scope",
+        IdentityError::UnauthorizedClient => "unauthorized_client",
+        IdentityError::UnsupportedGrantType => "unsupported_grant_type",
+        IdentityError::UnsupportedResponseType => "unsupported_response_type",
+        IdentityError::AccessDenied => "access_denied",
+        IdentityError::InsufficientScope => "insufficient_scope",
+        IdentityError::AuthorizationPending => "authorization_pending",
+        IdentityError::SlowDown => "slow_down",
+        IdentityError::ExpiredToken => "expired_token",
+        IdentityError::DeviceCodeNotFound => "invalid_request",
+        IdentityError::TokenIsEmpty | IdentityError::TokenIsInvalid | IdentityError::IssuerIsInvalid
+        | IdentityError::SignatureHasExpired | IdentityError::UserNotFound => "invalid_token",
+        _ => "server_error"
+    }
+}
+
+/**
+ * Controls that every scope of a space delimited scope string is one of the scopes the client is allowed.
+ */
+pub fn control_allowed_scopes(client : &OAuthClient, scope : &str) -> Result<(), IdentityError> {
+    let asked : BTreeSet<String> = scope.split_whitespace().map(str::to_owned).collect();
+    if !asked.is_subset(client.get_allowed_scopes()) {
+        warn!("Client {} asked for scopes it isn't allowed", client.get_client_id());
+        return Err(IdentityError::InvalidScope)
+    }
+    Ok(())
+}
+
+/**
+ * Removes duplicate whitespace out of a space delimited scope string.
+ */
+pub fn normalize_scope(scope : &str) -> String {
+    scope.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/**
+ * Code verifiers and challenges may only contain the unreserved characters of RFC 3986.
+ */
+fn is_pkce_charset(value : &str) -> bool {
+    value.bytes().all(|byte| byte.is_ascii_alphanumeric() || b"-._~".contains(&byte))
+}
+
+#[test]
+fn test_verify_code_challenge() {
+    // example of RFC 7636 appendix B
+    let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    assert!(verify_code_challenge(verifier, "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"));
+    assert!(!verify_code_challenge(verifier, "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cN"));
+    assert!(!verify_code_challenge("short", "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"));
+}
+
+#[test]
+fn test_refresh_token_of_device() {
+    use identity_dal::oauth::oauth_client::GRANT_DEVICE_CODE;
+    use identity_dal::repo::user_config::UserConfig;
+    use identity_dal::traits::t_user::UserTrait;
+    use identity_dal::user::identity_user::IdentityUser;
+
+    crate::claim::set_test_config();
+    let config = UserConfig::new_config("", "person", 100000);
+    let (db, clients, tokens) = (Store::new_db(config.clone()), ClientStore::new_db(config.clone()), TokenStore::new_db(config));
+    let jane = db.add_user(IdentityUser::new_user("jane@corp.be", "jane", "Passw0rd!").unwrap()).unwrap();
+    let (mut client, _) = OAuthClient::new_client("tv", Vec::new(), ClientType::Public).unwrap();
+    client.set_grant_types(vec![GRANT_DEVICE_CODE.to_owned()].into_iter().collect()).unwrap();
+    let client = clients.add_client(client).unwrap();
+    let refresh = |token : &str| exchange_refresh_token(TokenRequestViewModel {
+        client_id : Some(client.get_client_id().to_owned()),
+        refresh_token : Some(token.to_owned()),
+        ..TokenRequestViewModel::default()
+    }, clients.clone(), tokens.clone(), db.clone());
+
+    let device_token = issue_refresh_token(client.get_client_id(), jane.get_id(), "openid", GRANT_DEVICE_CODE, 0, &tokens).unwrap();
+    let response = refresh(&device_token).unwrap();
+    let rotated = tokens.get_refresh_token(&hash_token(response.get_refresh_token().unwrap())).unwrap();
+    assert_eq!(rotated.get_grant_type(), GRANT_DEVICE_CODE);
+
+    let code_token = issue_refresh_token(client.get_client_id(), jane.get_id(), "openid", GRANT_AUTHORIZATION_CODE, 0, &tokens).unwrap();
+    assert!(matches!(refresh(&code_token), Err(IdentityError::UnauthorizedClient)));
+}
+
+#[test]
+fn test_introspect_token_of_inactive_user() {
+    use identity_dal::repo::user_config::UserConfig;
+    use identity_dal::traits::t_user::UserTrait;
+    use identity_dal::user::identity_user::IdentityUser;
+    use identity_dal::user::user_status::UserStatus;
+
+    crate::claim::set_test_config();
+    let config = UserConfig::new_config("", "person", 100000);
+    let (db, clients, tokens) = (Store::new_db(config.clone()), ClientStore::new_db(config.clone()), TokenStore::new_db(config));
+    let mut jane = db.add_user(IdentityUser::new_user("jane@corp.be", "jane", "Passw0rd!").unwrap()).unwrap();
+    let (client, secret) = OAuthClient::new_client("api", Vec::new(), ClientType::Confidential).unwrap();
+    let client = clients.add_client(client).unwrap();
+    let access = Claim::new_oauth_claim(jane.get_id(), client.get_client_id(), "openid").unwrap().token_from_user().unwrap();
+    let refresh = issue_refresh_token(client.get_client_id(), jane.get_id(), "openid", GRANT_AUTHORIZATION_CODE, 0, &tokens).unwrap();
+    let own = Claim::new_client_claim(client.get_client_id(), "api").unwrap().token_from_user().unwrap();
+    let introspect = |token : &str| introspect_token(TokenReferenceViewModel {
+        token : Some(token.to_owned()),
+        ..TokenReferenceViewModel::default()
+    }.with_client_credentials(client.get_client_id(), secret.as_deref().unwrap()), clients.clone(), tokens.clone(), db.clone()).unwrap().is_active();
+
+    assert!(introspect(&access) && introspect(&refresh) && introspect(&own));
+    jane.set_status(UserStatus::suspended("fraud"));
+    db.update_user(jane.get_id(), &jane).unwrap();
+    assert!(!introspect(&access));
+    assert!(!introspect(&refresh));
+    assert!(introspect(&own));
+}
+
+#[test]
+fn test_authorization_code_of_allowed_scopes() {
+    use identity_dal::repo::user_config::UserConfig;
+
+    let config = UserConfig::new_config("", "person", 100000);
+    let codes = CodeStore::new_db(config);
+    let (mut client, _) = OAuthClient::new_client("app", vec!["https://app.be/callback".to_owned()], ClientType::Public).unwrap();
+    client.set_allowed_scopes(["openid", "email"].iter().map(|scope| scope.to_string()).collect());
+    let request = |scope : &str| AuthorizationRequestViewModel {
+        response_type : Some("code".to_owned()),
+        client_id : Some(client.get_client_id().to_owned()),
+        redirect_uri : Some("https://app.be/callback".to_owned()),
+        scope : Some(scope.to_owned()),
+        code_challenge : Some("E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM".to_owned()),
+        code_challenge_method : Some(CODE_CHALLENGE_METHOD.to_owned()),
+        ..AuthorizationRequestViewModel::default()
+    };
+
+    assert!(validate_authorization_request(&request("openid  email"), &client).is_ok());
+    assert!(matches!(validate_authorization_request(&request("openid admin"), &client), Err(IdentityError::InvalidScope)));
+    assert!(matches!(issue_authorization_code(&request("admin"), &client, "jane", codes.clone()), Err(IdentityError::InvalidScope)));
+    let uri = issue_authorization_code(&request("openid"), &client, "jane", codes.clone()).unwrap();
+    let code = uri.split("code=").nth(1).unwrap().split('&').next().unwrap();
+    assert_eq!(codes.take_code(code).unwrap().get_scope(), "openid");
+}
@@ -0,0 +1,73 @@
+use crate::claim::Claim;
+use crate::id_token::{ self, IdTokenClaim };
+use crate::signing_key::SigningKey;
+use crate::store::Store;
+use crate::util::hash_token;
+use identity_dal::repo::token_repo::TokenStore;
+use identity_dal::traits::t_user_manager::UserStoreTrait;
+use crate::service::person_service::get_user_info;
+use crate::viewmodels::oauth::user_info::UserInfoViewModel;
+use crate::viewmodels::oauth::discovery::DiscoveryViewModel;
+use identity_dal::oauth::authorization_code::AuthorizationCode;
+use crate::IdentityError;
+
+/**
+ * Returns true if the space delimited scope contains the given scope.
+ */
+pub fn has_scope(scope : &str, wanted : &str) -> bool {
+    scope.split_whitespace().any(|scope| scope == wanted)
+}
+
+/**
+ * Makes the signed id token that is issued together with an access token for an authorization code. The claims about the user are limited to what the granted scopes allow.
+ */
+pub fn issue_id_token(
+    access_claim : &Claim,
+    access_token : &str,
+    code : &AuthorizationCode,
+    db : &Store,
+    key : &SigningKey
+) -> Result<String, IdentityError> {
+    let person = get_user_info(code.get_user_id(), db).ok_or(IdentityError::UserNotFound)?;
+    let claim = IdTokenClaim::new(
+        access_claim,
+        access_token,
+        UserInfoViewModel::from_person_info(&person, code.get_scope()),
+        code.get_auth_time(),
+        code.get_nonce()
+    );
+    let token = key.sign(&claim)?;
+    info!("An id token has been issued to client {}", &claim.aud);
+    Ok(token)
+}
+
+/**
+ * Returns the claims of the user an access token was issued for. The token has to be granted the openid scope.
+ *
+ * An error is returned when:
+ * * the token is invalid, has expired or has been revoked
+ * * the openid scope wasn't granted
+ * * the user of the token doesn't exist anymore or isn't active
+ */
+pub fn get_user_claims(token : &str, db : &Store, tokens : &TokenStore) -> Result<UserInfoViewModel, IdentityError> {
+    let claim = Claim::decode_token(token)?.claims;
+    if tokens.is_access_token_revoked(&hash_token(token)) {
+        warn!("Userinfo has been asked with a revoked token");
+        return Err(IdentityError::TokenIsInvalid)
+    }
+    let scope = claim.scope.unwrap_or_default();
+    if !has_scope(&scope, "openid") {
+        warn!("Userinfo has been asked with a token that doesn't have the openid scope");
+        return Err(IdentityError::InsufficientScope)
+    }
+    db.get_user_by_uuid(&claim.sub).ok_or(IdentityError::UserNotFound)?.control_status()?;
+    let person = get_user_info(&claim.sub, db).ok_or(IdentityError::UserNotFound)?;
+    Ok(UserInfoViewModel::from_person_info(&person, &scope))
+}
+
+/**
+ * Returns the discovery document of the OpenID Connect provider.
+ */
+pub fn discovery_document() -> DiscoveryViewModel {
+    DiscoveryViewModel::new(id_token::issuer())
+}
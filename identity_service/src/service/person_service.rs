@@ -1,6 +1,6 @@
 use crate::claim::Claim;
 use crate::store::Store;
-use crate::store::UserDelegate;
+use crate::hooks::HookRegistry;
 use crate::viewmodels::auth::delete_user::DeleteUserViewModel;
 use crate::viewmodels::auth::login::LoginViewModel;
 use crate::viewmodels::auth::registration::RegistrationViewModel;
@@ -15,8 +15,11 @@ use identity_dal::traits::t_user_manager::UserStoreTrait;
 use identity_dal::user::identity_user::IdentityUser;
 use identity_dal::err::IdentityError;
 use std::sync::Mutex;
-use crate::service::mail_service::MailTransport;
 use crate::util::get_value_from_key;
+use crate::ldap;
+use crate::service::ldap_service;
+use identity_dal::repo::ldap_repo::LdapStore;
+use crate::service::webhook_service::WebhookStore;
 
 lazy_static! {
     static ref MIN_PASSWORD_LENGHT : usize = get_value_from_key("PWD_MIN_LEN")
@@ -26,19 +29,26 @@ lazy_static! {
 }
 
 /**
- * Function used to add an user to the sled no-sql database. The viewmodel from which the user will be added will be controlled on the fact that the password and confirmed password need to equal each other or otherwhise an error will be returned. An error will also be thrown if it couldn't add a user to the store.
+ * Controls that a new password is at least as long as the configured minimum length.
+ */
+pub fn control_password_length(password : &str) -> Result<(), IdentityError> {
+    if password.len() < *MIN_PASSWORD_LENGHT {
+        warn!("A password can't be shorter than {}", *MIN_PASSWORD_LENGHT);
+        return Err(IdentityError::CustomError("Password isn't long enough.".to_owned()))
+    }
+    Ok(())
+}
+
+/**
+ * Function used to add an user to the sled no-sql database. The viewmodel from which the user will be added will be controlled on the fact that the password and confirmed password need to equal each other or otherwhise an error will be returned. An error will also be thrown if it couldn't add a user to the store. The post registration hooks are called with the added user, the pre registration hooks are called by the registration before.
  */
 pub fn add_user(
     model: RegistrationViewModel,
     id: &str,
     db: Store,
-    user_creation_function : UserDelegate,
-    transport : &MailTransport
+    hooks : &HookRegistry
 ) -> Result<IdentityUser, IdentityError> {
-    if model.get_password().len() < MIN_PASSWORD_LENGHT.clone() {
-        warn!("A password can't be shorter than {}", MIN_PASSWORD_LENGHT.clone());
-        return Err(IdentityError::CustomError("Password isn't long enough.".to_owned()))
-    }
+    control_password_length(model.get_password())?;
     if model.get_confirmed_password() != model.get_password() {
         warn!("A password and its confirmation has to be the same");
         return Err(IdentityError::PasswordAndPasswordConfirmedNotEqual)
@@ -47,7 +57,7 @@ pub fn add_user(
         warn!("The email is already taken in the sled database");
         return Err(IdentityError::EmailIsAlreadyTaken);
     }
-    let person = match IdentityUser::new_user_with_personal_id(
+    let mut person = match IdentityUser::new_user_with_personal_id(
         id,
         model.get_email(),
         "",
@@ -58,14 +68,11 @@ pub fn add_user(
             return Err(e)
         }
     };
+    person.set_locale(model.get_locale());
     match db.add_user(person) {
         Ok(user) => {
-            if let Some(fun) = user_creation_function {
-                match fun(&user.get_id(),&db, &transport) {
-                    Ok(_) => info!("User creation function has succesfully been executed."),
-                    Err(e) => warn!("User creation function has failed. Error: {}",e)
-                }
-            }
+            let info = PersonInfoViewModel::from_identity_user(&user);
+            hooks.after("registration", |hook| hook.post_registration(&info));
             Ok(user)
         },
         Err(_) => {
@@ -82,45 +89,58 @@ pub fn add_user(
  * * new_email : updates the email of the user
  * * new_first_name : updates the first name of the user
  * * new_last_name : updates the last name of the user
+ * * new_locale : updates the locale of the mails to the user, an empty locale resets it to the default locale
+ *
+ * The pre update hooks can veto the update.
  **/
 pub fn update_user(
     token : &str,
     model: UpdateUserViewModel,
-    db: Store
+    db: Store,
+    hooks : &HookRegistry
 ) -> Result<bool, IdentityError> {
-    let mut user = match Claim::token_to_user(token, &db) {
+    let user = match Claim::token_to_user(token, &db) {
         Ok(user) => user,
         Err(e) => {
             error!("Could not map a jwt token to an user from the sled database");
             return Err(e);
         }
     };
+    let mut updated = user.clone();
     if let Some(new_email) = &model.new_email {
         if !db.is_email_taken(&new_email) {
-            user.set_email(&new_email).expect("Could not change the email of the user.");
+            updated.set_email(&new_email).expect("Could not change the email of the user.");
         }
     }
     if let Some(new_user_name) = &model.new_user_name {
-        user.set_user_name(&new_user_name);
+        updated.set_user_name(&new_user_name);
     }
-    Ok(db.update_user(user.get_id(), &user).expect("Could not update a user."))
+    if let Some(new_locale) = &model.new_locale {
+        updated.set_locale(Some(new_locale));
+    }
+    let (info, updated_info) = (PersonInfoViewModel::from_identity_user(&user), PersonInfoViewModel::from_identity_user(&updated));
+    hooks.before("update", |hook| hook.pre_update(&info, &updated_info))?;
+    let result = db.update_user(updated.get_id(), &updated).expect("Could not update a user.");
+    hooks.after("update", |hook| hook.post_update(&updated_info));
+    Ok(result)
 }
 
 /**
- * Method used to control credentials of an user. This returns a claim that can be used to be authorized as the user.
- *
- * An error is returned when the credentials are false and when the email is not found.
+ * Returns the user of the credentials. Users of an email domain of the LDAP directory are verified by the directory and synced into the local user, the others with their local password.
  */
-pub fn check_credentials(model: LoginViewModel, db: Store) -> Result<Claim, IdentityError> {
+fn authenticate(model : &LoginViewModel, db : &Store, ldap : &LdapStore, webhooks : &WebhookStore, hooks : &HookRegistry) -> Result<IdentityUser, IdentityError> {
+    if let Some(directory) = ldap::get_directory().filter(|directory| directory.handles_email(model.get_email())) {
+        let user = ldap_service::login(directory, model, db, ldap, webhooks, hooks)?;
+        user.control_status()?;
+        return Ok(user);
+    }
     if let Some(user) = db.get_user_by_email(model.get_email()) {
         if !user.check_pwd(model.get_password()) {
             warn!("The user's password is not good.");
             return Err(IdentityError::PasswordIsNotCorrect);
         }
-        return match Claim::new_read_write_claim(user.get_id()) {
-            Ok(claim) => Ok(claim),
-            Err(e) => Err(e),
-        };
+        user.control_status()?;
+        return Ok(user);
     }
     warn!(
         "The email {} doesn't exist in the sled database",
@@ -129,6 +149,20 @@ pub fn check_credentials(model: LoginViewModel, db: Store) -> Result<Claim, Iden
     Err(IdentityError::UserIsNotPresent)
 }
 
+/**
+ * Method used to control credentials of an user. This returns a claim that can be used to be authorized as the user. Users of an email domain of the LDAP directory are verified by the directory and synced into the local user, the others with their local password.
+ *
+ * An error is returned when the credentials are false, when the email is not found and when a pre login hook vetoes the login.
+ */
+pub fn check_credentials(model: LoginViewModel, db: Store, ldap: LdapStore, webhooks : &WebhookStore, hooks : &HookRegistry) -> Result<Claim, IdentityError> {
+    hooks.before("login", |hook| hook.pre_login(model.get_email()))?;
+    let user = authenticate(&model, &db, &ldap, webhooks, hooks)?;
+    let claim = Claim::new_read_write_claim(user.get_id())?;
+    let info = PersonInfoViewModel::from_identity_user(&user);
+    hooks.after("login", |hook| hook.post_login(&info));
+    Ok(claim)
+}
+
 /**
  * Method used to check an token and to return the user associated with that token's subject.
  *
@@ -139,14 +173,16 @@ pub fn check_token(token : &str, db: Store) -> Result<IdentityUser, IdentityErro
 }
 
 /**
- * Takes in a viewmodel which has a token, which is then controlled and checked.
+ * Takes in a viewmodel which has a token, which is then controlled and checked. Tokens of OAuth clients can't be exchanged.
  * 
  * A Claim is then send back.
  */
 pub fn get_new_token(token: &str, db: Store) -> Result<Claim, IdentityError> {
     match Claim::decode_token(token) {
         Ok(claim) => {
-            if db.is_id_taken(&claim.claims.sub) {
+            claim.claims.control_session()?;
+            if let Some(user) = db.get_user_by_uuid(&claim.claims.sub) {
+                user.control_status()?;
                 return Ok(Claim::new_read_write_claim(&claim.claims.sub)?)
             }
             Err(IdentityError::UserIsNotPresent)
@@ -176,11 +212,9 @@ pub fn change_password(
     token : &str,
     model: ChangePasswordViewModel,
     db: Store,
+    hooks : &HookRegistry
 ) -> Result<bool, IdentityError> {
-    if model.get_password().len() < MIN_PASSWORD_LENGHT.clone() {
-        warn!("A password can't be shorter than {}", MIN_PASSWORD_LENGHT.clone());
-        return Err(IdentityError::CustomError("Password isn't long enough.".to_owned()))
-    }
+    control_password_length(model.get_password())?;
     if token.is_empty() {
         return Err(IdentityError::TokenIsEmpty)
     }
@@ -191,30 +225,31 @@ pub fn change_password(
         return Err(IdentityError::PasswordAndPasswordConfirmedNotEqual)
     }
     let mut user: IdentityUser = Claim::token_to_user(token,&db)?;
-    match user.set_password(&model.get_password()) {
-        Ok(_) => match db.update_user(user.get_id(), &user) {
-            Ok(_) => Ok(true),
-            Err(e) => Err(e),
-        },
-        Err(e) => Err(e),
-    }
+    let info = PersonInfoViewModel::from_identity_user(&user);
+    hooks.before("password change", |hook| hook.pre_password_change(&info))?;
+    user.set_password(&model.get_password())?;
+    db.update_user(user.get_id(), &user)?;
+    hooks.after("password change", |hook| hook.post_password_change(&info));
+    Ok(true)
 }
 
 /**
- * Function used to delete a user, the viewmodel TokenHolderViewModel is used to check for authorization and to get the id of the user. The id of the user is used to check if he exists and if he exists he is deleted. An error is thrown if the token is false or if the person didn't exist.
+ * Function used to delete a user, the viewmodel TokenHolderViewModel is used to check for authorization and to get the id of the user. The id of the user is used to check if he exists and if he exists he is deleted, both his password and the delete confirmation are needed for that. The deleted user is kept until he is purged after the grace period. An error is thrown if the token is false or one of an OAuth client, the password is wrong, the deletion isn't confirmed or if the person didn't exist.
 */
-pub fn delete_user(token : &str,model: DeleteUserViewModel, db: Store) -> Result<bool, IdentityError> {
+pub fn delete_user(token : &str,model: DeleteUserViewModel, db: Store, hooks : &HookRegistry) -> Result<bool, IdentityError> {
     let claim_token = Claim::decode_token(token)?;
+    claim_token.claims.control_session()?;
     if let Some(user) = db.get_user_by_uuid(&claim_token.claims.sub) {
-        if !user.check_pwd(&model.get_password()) && !model.is_delete_confirmed() {
+        if !user.check_pwd(&model.get_password()) || !model.is_delete_confirmed() {
             warn!("The user's password or delete confirmation was not good, the user could not be deleted");
             return Err(IdentityError::UserDeleteFailed)
         }
+        let info = PersonInfoViewModel::from_identity_user(&user);
+        hooks.before("deletion", |hook| hook.pre_delete(&info))?;
         info!("User password and password confirmation was good and user is going to be deleted.");
-        return match db.delete_user(user.get_id()) {
-            Ok(_) => Ok(true),
-            Err(e) => Err(e)
-        }
+        db.delete_user(user.get_id())?;
+        hooks.after("deletion", |hook| hook.post_delete(&info));
+        return Ok(true)
     }
     warn!("Can't delete a user if he doesn't exist");
     Err(IdentityError::UserIsNotPresent)
@@ -259,21 +294,24 @@ pub fn remove_flag_of_user(
 }
 
 /**
- * Function that is used to insert a token into the token map, through the given user id. If the token has been inserted then a function that was given as a parameter will be executed that is responsible for sending the email so the user can change his password.
+ * Function that is used to insert a token into the token map, through the given user id. If the token has been inserted then the password reset request hooks are called with it, one of them is responsible for sending the email so the user can change his password.
  */
 pub fn demand_email_changing_password(
     token_map : &Mutex<HashMapTokenPasswordChange>,
     user_id : &str,
     store : Store,
-    transport : &MailTransport,
-    email_changing_function : fn(token : &str,store : &Store, token_map : &Mutex<HashMapTokenPasswordChange>, transport : &MailTransport) -> Result<(), IdentityError>
+    hooks : &HookRegistry
 ) -> Result<(),IdentityError> {
-    let token_locked_map = &mut token_map.lock()
-    .map_err(|_| IdentityError::CustomError("Could not lock the token map which gaurds tokens for changing password".to_owned()))?;
-    if let Some(token) = token_locked_map.insert_new_user_request(user_id) {
-        match email_changing_function(&token,&store, &token_map, transport) {
-            Ok(_) => info!("Managed to execute the function responsible to manage sending the email so user can change his password."),
-            Err(_) => warn!("Could not execute the functions responsible to manage sending the email so user can change his password.")
+    let token = token_map.lock()
+    .map_err(|_| IdentityError::CustomError("Could not lock the token map which gaurds tokens for changing password".to_owned()))?
+    .insert_new_user_request(user_id);
+    if let Some(token) = token {
+        match store.get_user_by_uuid(user_id) {
+            Some(user) => {
+                let info = PersonInfoViewModel::from_identity_user(&user);
+                hooks.after("password reset request", |hook| hook.post_password_reset_request(&info, &token));
+            },
+            None => warn!("A password reset has been asked for user {} who doesn't exist", user_id)
         }
     }
     Ok(())
@@ -283,6 +321,7 @@ pub fn change_forgotten_password(
     token_map : &Mutex<HashMapTokenPasswordChange>,
     token : ChangeForgottenPassword,
     store : Store,
+    hooks : &HookRegistry
 ) -> Result<(),IdentityError> {
     let token_locked_map = &mut token_map.lock()
     .map_err(|_| IdentityError::CustomError("Could not lock the token map which gaurds tokens for changing password".to_owned()))?;
@@ -294,11 +333,33 @@ pub fn change_forgotten_password(
     }
     if let Some(user_id) = token_locked_map.get_user_id_from_token(token.get_token_forgotten_pwd()) {
         if let Some(mut user) = store.get_user_by_uuid(&user_id) {
+            let info = PersonInfoViewModel::from_identity_user(&user);
+            hooks.before("password change", |hook| hook.pre_password_change(&info))?;
             user.set_password(token.get_password())?;
             if !store.update_user(&user_id , &user)? {
                 return Err(IdentityError::UserCannotBeUpdated)
             }
+            hooks.after("password change", |hook| hook.post_password_change(&info));
         }
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_oauth_token_is_no_session() {
+    use identity_dal::repo::user_config::UserConfig;
+
+    crate::claim::set_test_config();
+    let db = Store::new_db(UserConfig::new_config("", "person", 100000));
+    let jane = db.add_user(IdentityUser::new_user("jane@corp.be", "jane", "Passw0rd!").unwrap()).unwrap();
+    let session = Claim::new_read_write_claim(jane.get_id()).unwrap().token_from_user().unwrap();
+    let oauth = Claim::new_oauth_claim(jane.get_id(), "shop", "openid email").unwrap().token_from_user().unwrap();
+
+    assert_eq!(check_token(&session, db.clone()).unwrap().get_id(), jane.get_id());
+    assert!(get_new_token(&session, db.clone()).unwrap().client_id.is_none());
+    assert!(matches!(check_token(&oauth, db.clone()), Err(IdentityError::InsufficientScope)));
+    assert!(matches!(get_new_token(&oauth, db.clone()), Err(IdentityError::InsufficientScope)));
+    let confirmed : DeleteUserViewModel = serde_json::from_str(r#"{"password":"Passw0rd!","delete_confirmed":true}"#).unwrap();
+    assert!(matches!(delete_user(&oauth, confirmed, db.clone(), &HookRegistry::new()), Err(IdentityError::InsufficientScope)));
+    assert!(db.get_user_by_uuid(jane.get_id()).is_some());
+}
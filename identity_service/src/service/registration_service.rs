@@ -0,0 +1,257 @@
+use crate::hooks::HookRegistry;
+use crate::store::Store;
+use crate::service::admin_service;
+use crate::service::mail_service::MailOutbox;
+use crate::service::person_service::{ self, control_password_length };
+use crate::util::get_value_from_key;
+use crate::viewmodels::admin::registration::{ AllPendingRegistrationsViewModel, RegistrationIdViewModel };
+use crate::viewmodels::auth::person_info::PersonInfoViewModel;
+use crate::viewmodels::auth::registration::RegistrationViewModel;
+use identity_dal::onboarding::pending_registration::PendingRegistration;
+use identity_dal::repo::client_repo::ClientStore;
+use identity_dal::repo::registration_repo::RegistrationStore;
+use identity_dal::repo::token_repo::TokenStore;
+use identity_dal::traits::t_user::UserTrait;
+use identity_dal::traits::t_user_manager::UserStoreTrait;
+use identity_dal::user::identity_user::IdentityUser;
+use crate::IdentityError;
+
+lazy_static! {
+    static ref REGISTRATION_MODE : RegistrationMode = RegistrationMode::parse(
+        &get_value_from_key("PERSON_REGISTRATION_MODE").unwrap_or_else(|| "open".to_owned()),
+        &get_value_from_key("PERSON_REGISTRATION_DOMAINS").unwrap_or_default()
+    ).expect("PERSON_REGISTRATION_MODE has to be open, disabled, invite_only, domains or approval");
+}
+
+/**
+ * Function that mails an user in his locale whether his registration has been approved or rejected.
+ */
+pub type RegistrationDecisionDelegate = fn(email : &str, locale : Option<&str>, approved : bool, outbox : &MailOutbox) -> Result<(), IdentityError>;
+
+/**
+ * Stores the approval of registrations works with.
+ */
+pub struct RegistrationStores {
+    pub db : Store,
+    pub registrations : RegistrationStore
+}
+
+/**
+ * Decides who may register himself:
+ * * Open: everyone
+ * * Disabled: nobody
+ * * InviteOnly: nobody, accounts are only made through invitations
+ * * Domains: only emails of the domains
+ * * Approval: everyone, but the account is only made once the admin approves it
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistrationMode {
+    Open,
+    Disabled,
+    InviteOnly,
+    Domains(Vec<String>),
+    Approval
+}
+
+impl RegistrationMode {
+    /**
+     * Returns the mode with the name, the domains are a comma separated list that is only used for the domains mode. None is returned for an unknown mode or the domains mode without domains.
+     */
+    pub fn parse(mode : &str, domains : &str) -> Option<RegistrationMode> {
+        match mode.trim().to_lowercase().as_str() {
+            "open" => Some(RegistrationMode::Open),
+            "disabled" => Some(RegistrationMode::Disabled),
+            "invite_only" => Some(RegistrationMode::InviteOnly),
+            "approval" => Some(RegistrationMode::Approval),
+            "domains" => {
+                let domains : Vec<String> = domains.split(',')
+                    .map(|domain| domain.trim().trim_start_matches('@').to_lowercase())
+                    .filter(|domain| !domain.is_empty())
+                    .collect();
+                Some(domains).filter(|domains| !domains.is_empty()).map(RegistrationMode::Domains)
+            },
+            _ => None
+        }
+    }
+}
+
+/**
+ * Result of a registration, either the user has been added or his registration waits for approval.
+ */
+pub enum RegistrationResult {
+    Added(IdentityUser),
+    Pending(PendingRegistration)
+}
+
+/**
+ * Returns the configured registration mode.
+ */
+pub fn registration_mode() -> &'static RegistrationMode {
+    &REGISTRATION_MODE
+}
+
+/**
+ * Registers an user according to the registration mode.
+ *
+ * An error is returned when:
+ * * registration is disabled or only possible with an invitation
+ * * the domain of the email isn't allowed
+ * * the password is too short or isn't the same as its confirmation
+ * * the email is already taken or already waits for approval
+ * * a pre registration hook vetoes the registration
+ */
+pub fn register(
+    model : RegistrationViewModel,
+    id : &str,
+    db : Store,
+    registrations : RegistrationStore,
+    hooks : &HookRegistry
+) -> Result<RegistrationResult, IdentityError> {
+    control_registration_mode(registration_mode(), model.get_email())?;
+    hooks.before("registration", |hook| hook.pre_registration(model.get_email()))?;
+    if *registration_mode() == RegistrationMode::Approval {
+        control_password_length(model.get_password())?;
+        return add_pending_registration(&model, id, &db, &registrations).map(RegistrationResult::Pending)
+    }
+    person_service::add_user(model, id, db, hooks).map(RegistrationResult::Added)
+}
+
+/**
+ * Controls that the mode allows the email to register.
+ */
+pub(crate) fn control_registration_mode(mode : &RegistrationMode, email : &str) -> Result<(), IdentityError> {
+    match mode {
+        RegistrationMode::Disabled => Err(IdentityError::RegistrationIsDisabled),
+        RegistrationMode::InviteOnly => Err(IdentityError::RegistrationIsInviteOnly),
+        RegistrationMode::Domains(domains) => {
+            let domain = email.rsplit_once('@').map(|(_, domain)| domain.to_lowercase()).unwrap_or_default();
+            if domains.contains(&domain) {
+                return Ok(())
+            }
+            warn!("Registration with the domain {} is not allowed", domain);
+            Err(IdentityError::EmailDomainIsNotAllowed)
+        },
+        RegistrationMode::Open | RegistrationMode::Approval => Ok(())
+    }
+}
+
+/**
+ * Keeps the registration until the admin decides on it.
+ */
+fn add_pending_registration(model : &RegistrationViewModel, id : &str, db : &Store, registrations : &RegistrationStore) -> Result<PendingRegistration, IdentityError> {
+    if model.get_confirmed_password() != model.get_password() {
+        warn!("A password and its confirmation has to be the same");
+        return Err(IdentityError::PasswordAndPasswordConfirmedNotEqual)
+    }
+    if db.is_email_taken(model.get_email()) {
+        warn!("The email is already taken in the sled database");
+        return Err(IdentityError::EmailIsAlreadyTaken)
+    }
+    let mut user = IdentityUser::new_user_with_personal_id(id, model.get_email(), "", model.get_password())?;
+    user.set_locale(model.get_locale());
+    let registration = PendingRegistration::new(user);
+    registrations.add_registration(&registration)?;
+    info!("Registration {} waits for approval", registration.get_id());
+    Ok(registration)
+}
+
+/**
+ * Approves or rejects a registration, an approved registration becomes an user. The registration is only removed once the user has been added.
+ */
+fn decide_registration(id : &str, approved : bool, db : &Store, registrations : &RegistrationStore) -> Result<PendingRegistration, IdentityError> {
+    let registration = registrations.get_registration(id).ok_or(IdentityError::RegistrationNotFound)?;
+    if approved {
+        if db.is_email_taken(registration.get_user().get_email()) {
+            return Err(IdentityError::EmailIsAlreadyTaken)
+        }
+        db.add_user(registration.get_user().clone())?;
+    }
+    registrations.take_registration(id).ok_or(IdentityError::RegistrationNotFound)
+}
+
+/**
+ * Mails the decision to the user, a failed mail is only logged.
+ */
+fn notify_decision(registration : &PendingRegistration, approved : bool, outbox : &MailOutbox, decision_function : RegistrationDecisionDelegate) {
+    match decision_function(registration.get_user().get_email(), registration.get_user().get_locale(), approved, outbox) {
+        Ok(_) => info!("The decision on registration {} has been mailed", registration.get_id()),
+        Err(e) => warn!("The decision on registration {} could not be mailed: {}", registration.get_id(), e)
+    }
+}
+
+/**
+ * Admin function that returns the registrations that wait for approval.
+ */
+pub fn get_pending_registrations(token : &str, registrations : RegistrationStore, db : Store, clients : ClientStore, tokens : TokenStore) -> Result<AllPendingRegistrationsViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    Ok(AllPendingRegistrationsViewModel::from_registrations_vector(registrations.get_all_registrations()))
+}
+
+/**
+ * Admin function that approves a registration, the user is added and mailed that he can log in.
+ */
+pub fn approve_registration(
+    token : &str,
+    model : RegistrationIdViewModel,
+    stores : RegistrationStores,
+    clients : ClientStore,
+    tokens : TokenStore,
+    outbox : &MailOutbox,
+    decision_function : RegistrationDecisionDelegate
+) -> Result<PersonInfoViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &stores.db, &clients, &tokens)?;
+    let registration = decide_registration(model.get_id(), true, &stores.db, &stores.registrations)?;
+    info!("Registration {} has been approved", registration.get_id());
+    notify_decision(&registration, true, outbox, decision_function);
+    Ok(PersonInfoViewModel::from_identity_user(registration.get_user()))
+}
+
+/**
+ * Admin function that rejects a registration, it is removed and the user is mailed about it.
+ */
+pub fn reject_registration(
+    token : &str,
+    model : RegistrationIdViewModel,
+    stores : RegistrationStores,
+    clients : ClientStore,
+    tokens : TokenStore,
+    outbox : &MailOutbox,
+    decision_function : RegistrationDecisionDelegate
+) -> Result<(), IdentityError> {
+    admin_service::control_admin_token(token, &stores.db, &clients, &tokens)?;
+    let registration = decide_registration(model.get_id(), false, &stores.db, &stores.registrations)?;
+    info!("Registration {} has been rejected", registration.get_id());
+    notify_decision(&registration, false, outbox, decision_function);
+    Ok(())
+}
+
+#[test]
+fn test_registration_modes() {
+    use identity_dal::repo::user_config::UserConfig;
+
+    assert_eq!(RegistrationMode::parse("Domains", "@Corp.be, partner.be,"), Some(RegistrationMode::Domains(vec!["corp.be".to_owned(), "partner.be".to_owned()])));
+    assert_eq!(RegistrationMode::parse("domains", ""), None);
+    assert_eq!(RegistrationMode::parse("closed", ""), None);
+    let domains = RegistrationMode::parse("domains", "corp.be").unwrap();
+    assert!(control_registration_mode(&domains, "jane@CORP.be").is_ok());
+    assert!(matches!(control_registration_mode(&domains, "jane@corp.be.evil.com"), Err(IdentityError::EmailDomainIsNotAllowed)));
+    assert!(matches!(control_registration_mode(&RegistrationMode::InviteOnly, "jane@corp.be"), Err(IdentityError::RegistrationIsInviteOnly)));
+    assert!(matches!(control_registration_mode(&RegistrationMode::Disabled, "jane@corp.be"), Err(IdentityError::RegistrationIsDisabled)));
+
+    let config = UserConfig::new_config("", "person", 100000);
+    let db = Store::new_db(config.clone());
+    let registrations = RegistrationStore::new_db(config);
+    let model = RegistrationViewModel::new("jane@corp.be", "Passw0rd!", "Passw0rd!").with_default_locale(Some("nl-BE"));
+    add_pending_registration(&model, "jane", &db, &registrations).unwrap();
+    assert!(matches!(add_pending_registration(&model, "other", &db, &registrations), Err(IdentityError::EmailIsAwaitingApproval)));
+    add_pending_registration(&RegistrationViewModel::new("john@corp.be", "Passw0rd!", "Passw0rd!"), "john", &db, &registrations).unwrap();
+    assert!(db.get_user_by_email("jane@corp.be").is_none());
+
+    decide_registration("jane", true, &db, &registrations).unwrap();
+    assert!(db.get_user_by_uuid("jane").unwrap().check_pwd("Passw0rd!"));
+    assert_eq!(db.get_user_by_uuid("jane").unwrap().get_locale(), Some("nl-BE"));
+    decide_registration("john", false, &db, &registrations).unwrap();
+    assert!(db.get_user_by_uuid("john").is_none());
+    assert!(registrations.get_all_registrations().is_empty());
+    assert!(matches!(decide_registration("john", true, &db, &registrations), Err(IdentityError::RegistrationNotFound)));
+}
@@ -0,0 +1,191 @@
+use crate::saml::{ self, AuthnRequest, IdpCertificate, SamlAttribute };
+use crate::service::admin_service;
+use crate::signing_key::SigningKey;
+use crate::store::Store;
+use crate::viewmodels::saml::saml_response::SamlResponseViewModel;
+use crate::viewmodels::saml::service_provider::{ AllServiceProvidersViewModel, EntityIdViewModel, RegisterServiceProviderViewModel, ServiceProviderViewModel };
+use identity_dal::repo::client_repo::ClientStore;
+use identity_dal::repo::saml_repo::SamlStore;
+use identity_dal::repo::token_repo::TokenStore;
+use identity_dal::saml::service_provider::{ ServiceProvider, NAME_ID_PERSISTENT };
+use identity_dal::traits::t_user::UserTrait;
+use identity_dal::traits::t_user_manager::UserStoreTrait;
+use identity_dal::user::identity_user::IdentityUser;
+use crate::IdentityError;
+
+/**
+ * Registers a SAML service provider with its metadata, when the service provider is already registered its metadata is replaced.
+ *
+ * An error is returned when:
+ * * the token isn't one of the admin
+ * * the metadata isn't valid or has no assertion consumer service of the HTTP-POST binding
+ */
+pub fn register_service_provider(
+    token : &str,
+    model : RegisterServiceProviderViewModel,
+    saml : SamlStore,
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore
+) -> Result<ServiceProviderViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    let provider = saml::parse_service_provider_metadata(model.get_metadata())?;
+    saml.insert_service_provider(&provider)?;
+    info!("Admin has registered the SAML service provider {}", provider.get_entity_id());
+    Ok(ServiceProviderViewModel::from_provider(&provider))
+}
+
+/**
+ * Returns all registered SAML service providers.
+ */
+pub fn get_all_service_providers(token : &str, saml : SamlStore, db : Store, clients : ClientStore, tokens : TokenStore) -> Result<AllServiceProvidersViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    Ok(AllServiceProvidersViewModel::from_providers_vector(saml.get_all_service_providers()))
+}
+
+/**
+ * Removes a SAML service provider, its users can't log in with it anymore.
+ */
+pub fn remove_service_provider(
+    token : &str,
+    model : EntityIdViewModel,
+    saml : SamlStore,
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore
+) -> Result<(), IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    if !saml.remove_service_provider(model.get_entity_id()) {
+        return Err(IdentityError::ServiceProviderNotFound)
+    }
+    info!("Admin has removed the SAML service provider {}", model.get_entity_id());
+    Ok(())
+}
+
+/**
+ * Decodes the authentication request a service provider sent with the given binding and returns it together with its service provider.
+ *
+ * An error is returned when:
+ * * the request can't be decoded or isn't a valid AuthnRequest
+ * * the issuer of the request isn't a registered service provider
+ * * the requested assertion consumer service isn't one of the service provider
+ */
+pub fn receive_authn_request(binding : &str, saml_request : &str, saml : &SamlStore) -> Result<(AuthnRequest, ServiceProvider), IdentityError> {
+    let request = AuthnRequest::from_binding(binding, saml_request)?;
+    let provider = saml.get_service_provider(request.get_issuer()).ok_or_else(|| {
+        warn!("An authentication request of the unknown service provider {} has been received", request.get_issuer());
+        IdentityError::ServiceProviderNotFound
+    })?;
+    if provider.get_assertion_consumer_service(request.get_assertion_consumer_service_url()).is_none() {
+        warn!("Service provider {} requested an assertion consumer service that isn't registered", provider.get_entity_id());
+        return Err(IdentityError::SamlRequestIsInvalid("the assertion consumer service is not registered".to_owned()))
+    }
+    Ok((request, provider))
+}
+
+/**
+ * Makes the signed response to an authentication request for the user that has logged in, it has to be posted to the returned destination.
+ */
+pub fn issue_response(
+    request : &AuthnRequest,
+    provider : &ServiceProvider,
+    user_id : &str,
+    relay_state : Option<String>,
+    db : &Store,
+    key : &SigningKey,
+    certificate : &IdpCertificate
+) -> Result<SamlResponseViewModel, IdentityError> {
+    let user = db.get_user_by_uuid(user_id).ok_or(IdentityError::UserNotFound)?;
+    user.control_status()?;
+    let destination = provider.get_assertion_consumer_service(request.get_assertion_consumer_service_url())
+        .ok_or_else(|| IdentityError::SamlRequestIsInvalid("the assertion consumer service is not registered".to_owned()))?
+        .get_location()
+        .to_owned();
+    let name_id = if provider.get_name_id_format() == NAME_ID_PERSISTENT { user.get_id() } else { user.get_email() };
+    let response = saml::build_response(request, provider, &destination, name_id, &user_attributes(&user), key, certificate)?;
+    info!("User {} has logged in at SAML service provider {}", user.get_id(), provider.get_entity_id());
+    Ok(SamlResponseViewModel {
+        destination,
+        saml_response : base64::encode(&response),
+        relay_state
+    })
+}
+
+/**
+ * Returns the attributes of the user that are put in the assertion.
+ */
+fn user_attributes(user : &IdentityUser) -> Vec<SamlAttribute> {
+    vec![
+        SamlAttribute::new("uid", vec![user.get_id().to_owned()]),
+        SamlAttribute::new("email", vec![user.get_email().to_owned()]),
+        SamlAttribute::new("user_name", vec![user.get_user_name().to_owned()]),
+        SamlAttribute::new("flags", user.get_flag_list())
+    ]
+}
+
+/**
+ * Returns the metadata of the identity provider.
+ */
+pub fn get_idp_metadata(certificate : &IdpCertificate) -> String {
+    saml::idp_metadata(certificate)
+}
+
+#[test]
+fn test_saml_round_trip() {
+    use std::io::Write;
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+    use identity_dal::repo::user_config::UserConfig;
+    use identity_dal::saml::service_provider::{ BINDING_HTTP_POST, BINDING_HTTP_REDIRECT };
+    use openssl::hash::MessageDigest;
+    use openssl::sign::Verifier;
+    use openssl::x509::X509;
+
+    let config = UserConfig::new_config("", "person", 100000);
+    let db = Store::new_db(config.clone());
+    let saml = SamlStore::new_db(config);
+    let mut user = IdentityUser::new_user_with_personal_id("1", "jane@corp.be", "jane", "password").unwrap();
+    user.add_flag("sales");
+    db.add_user(user).unwrap();
+    saml.insert_service_provider(&saml::parse_service_provider_metadata(r#"<EntityDescriptor xmlns="urn:oasis:names:tc:SAML:2.0:metadata" entityID="https://sp.example.com">
+  <SPSSODescriptor protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+    <AssertionConsumerService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST" Location="https://sp.example.com/acs" index="0"/>
+  </SPSSODescriptor>
+</EntityDescriptor>"#).unwrap()).unwrap();
+    let key = SigningKey::generate().unwrap();
+    let certificate = IdpCertificate::generate(&key).unwrap();
+
+    let authn_request = format!(
+        r#"<samlp:AuthnRequest xmlns:samlp="urn:oasis:names:tc:SAML:2.0:protocol" xmlns:saml="urn:oasis:names:tc:SAML:2.0:assertion" ID="_req1" Version="2.0" IssueInstant="2026-01-01T00:00:00Z" Destination="{}" AssertionConsumerServiceURL="https://sp.example.com/acs" ProtocolBinding="{}"><saml:Issuer>https://sp.example.com</saml:Issuer></samlp:AuthnRequest>"#,
+        saml::sso_url(), BINDING_HTTP_POST
+    );
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(authn_request.as_bytes()).unwrap();
+    let redirect_request = base64::encode(encoder.finish().unwrap());
+    let (request, provider) = receive_authn_request(BINDING_HTTP_REDIRECT, &redirect_request, &saml).unwrap();
+    assert_eq!(request.get_id(), "_req1");
+    assert!(receive_authn_request(BINDING_HTTP_POST, &request.to_post_binding(), &saml).is_ok());
+    assert!(receive_authn_request(BINDING_HTTP_POST, &base64::encode(authn_request.replace("https://sp.example.com<", "https://other.example.com<")), &saml).is_err());
+    assert!(receive_authn_request(BINDING_HTTP_POST, &base64::encode(authn_request.replace("example.com/acs", "example.com/other")), &saml).is_err());
+
+    let response = issue_response(&request, &provider, "1", Some("state".to_owned()), &db, &key, &certificate).unwrap();
+    assert_eq!(response.destination, "https://sp.example.com/acs");
+    let xml = String::from_utf8(base64::decode(&response.saml_response).unwrap()).unwrap();
+    let document = roxmltree::Document::parse(&xml).unwrap();
+    let text_of = |name : &str| document.descendants().find(|node| node.tag_name().name() == name).and_then(|node| node.text()).unwrap();
+    assert_eq!(document.root_element().attribute("InResponseTo"), Some("_req1"));
+    assert_eq!(text_of("NameID"), "jane@corp.be");
+    assert_eq!(text_of("Audience"), "https://sp.example.com");
+    let flags = document.descendants().find(|node| node.attribute("Name") == Some("flags")).unwrap();
+    assert_eq!(flags.children().filter_map(|node| node.text()).collect::<Vec<_>>(), vec!["sales"]);
+
+    let assertion = &xml[xml.find("<saml:Assertion").unwrap()..xml.find("</samlp:Response>").unwrap()];
+    let signature = &assertion[assertion.find("<ds:Signature").unwrap()..assertion.find("</ds:Signature>").unwrap() + "</ds:Signature>".len()];
+    let digest = ring::digest::digest(&ring::digest::SHA256, assertion.replace(signature, "").as_bytes());
+    assert_eq!(text_of("DigestValue"), base64::encode(digest.as_ref()));
+    let signed_info = &signature[signature.find("<ds:SignedInfo>").unwrap()..signature.find("<ds:SignatureValue>").unwrap()];
+    let public_key = X509::from_der(&base64::decode(text_of("X509Certificate")).unwrap()).unwrap().public_key().unwrap();
+    let mut verifier = Verifier::new(MessageDigest::sha256(), &public_key).unwrap();
+    verifier.update(saml::canonical_signed_info(signed_info).as_bytes()).unwrap();
+    assert!(verifier.verify(&base64::decode(text_of("SignatureValue")).unwrap()).unwrap());
+}
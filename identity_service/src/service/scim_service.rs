@@ -0,0 +1,650 @@
+use std::collections::BTreeSet;
+use chrono::{ TimeZone, Utc };
+use serde::Serialize;
+use serde_json::{ json, Value };
+use crate::claim::Claim;
+use crate::hooks::HookRegistry;
+use crate::id_token;
+use crate::scim::{ self, Filter };
+use crate::service::{ admin_service, oidc_service, webhook_service };
+use crate::service::webhook_service::{ WebhookEventType, WebhookStore };
+use crate::store::Store;
+use crate::util::hash_token;
+use crate::viewmodels::auth::person_info::PersonInfoViewModel;
+use crate::viewmodels::scim::group::{ ScimGroupViewModel, ScimMemberViewModel };
+use crate::viewmodels::scim::list_response::{ ScimListResponseViewModel, ScimQueryViewModel };
+use crate::viewmodels::scim::patch::ScimPatchViewModel;
+use crate::viewmodels::scim::user::{ ScimEmailViewModel, ScimMetaViewModel, ScimUserViewModel };
+use identity_dal::group::identity_group::IdentityGroup;
+use identity_dal::repo::client_repo::ClientStore;
+use identity_dal::repo::group_repo::GroupStore;
+use identity_dal::repo::scim_repo::ScimStore;
+use identity_dal::repo::token_repo::TokenStore;
+use identity_dal::scim::scim_user::ScimUser;
+use identity_dal::traits::t_admin_manager::AdminStoreTrait;
+use identity_dal::traits::t_user::UserTrait;
+use identity_dal::traits::t_user_manager::UserStoreTrait;
+use identity_dal::user::identity_user::{ IdentityUser, RESERVED_ID };
+use identity_dal::user::user_status::UserStatus;
+use identity_dal::util::get_hash;
+use crate::IdentityError;
+
+/**
+ * Scope the access token of a provisioning client needs to use the SCIM API.
+ */
+pub static SCIM_SCOPE : &str = "scim";
+
+/**
+ * Largest page of resources a list request returns.
+ */
+const MAX_RESULTS : usize = 100;
+
+/**
+ * Stores the SCIM API works with, the lifecycle events of the provisioned users are published to the webhooks.
+ */
+pub struct ScimStores {
+    pub db : Store,
+    pub groups : GroupStore,
+    pub scim : ScimStore,
+    pub webhooks : WebhookStore
+}
+
+/**
+ * Controls that a token may be used for the SCIM API. This is the case for the access token of a provisioning client with the scim scope, which it gets through the client credentials grant, or for a token the admin functions accept.
+ */
+pub fn control_scim_token(token : &str, db : &Store, clients : &ClientStore, tokens : &TokenStore) -> Result<Claim, IdentityError> {
+    let claim = Claim::decode_token(token)?.claims;
+    if let Some(client_id) = claim.client_id.as_deref().filter(|_| claim.is_client_claim()) {
+        if oidc_service::has_scope(claim.scope.as_deref().unwrap_or_default(), SCIM_SCOPE) {
+            if tokens.is_access_token_revoked(&hash_token(token)) {
+                warn!("A revoked token of client {} has been used", client_id);
+                return Err(IdentityError::TokenIsInvalid)
+            }
+            return match clients.get_client(client_id) {
+                Some(client) if !client.is_disabled() => Ok(claim),
+                Some(_) => Err(IdentityError::ClientIsDisabled),
+                None => Err(IdentityError::ClientNotFound)
+            }
+        }
+    }
+    admin_service::control_admin_token(token, db, clients, tokens)
+}
+
+/**
+ * Returns the HTTP status and SCIM error type of an error.
+ */
+pub fn scim_error_status(error : &IdentityError) -> (u16, Option<&'static str>) {
+    match error {
+        IdentityError::UserNotFound | IdentityError::UserIsNotPresent | IdentityError::GroupNotFound => (404, None),
+        IdentityError::EmailIsAlreadyTaken | IdentityError::UserCannotBeAdded | IdentityError::IdIsAlreadyTaken
+            | IdentityError::GroupNameIsAlreadyTaken | IdentityError::UserAlreadyPresent => (409, Some("uniqueness")),
+        IdentityError::InvalidFilter(_) => (400, Some("invalidFilter")),
+        IdentityError::InvalidRequest(_) | IdentityError::EmailIsEmpty | IdentityError::EmailNotCorrectFormat
+            | IdentityError::PasswordIsEmpty | IdentityError::EmailAndPasswordIsEmpty => (400, Some("invalidValue")),
+        IdentityError::InsufficientScope | IdentityError::IdNotEqualToAdmin | IdentityError::ClientIsDisabled => (403, None),
+        IdentityError::TokenIsInvalid | IdentityError::TokenIsEmpty | IdentityError::ClientNotFound => (401, None),
+        _ => (500, None)
+    }
+}
+
+/**
+ * Returns the SCIM error message of an error.
+ */
+pub fn scim_error(error : &IdentityError) -> Value {
+    let (status, scim_type) = scim_error_status(error);
+    let mut body = json!({
+        "schemas" : [scim::SCHEMA_ERROR],
+        "status" : status.to_string(),
+        "detail" : format!("{}", error)
+    });
+    if let Some(scim_type) = scim_type {
+        body["scimType"] = json!(scim_type);
+    }
+    body
+}
+
+fn base_url() -> String {
+    format!("{}/scim/v2", id_token::issuer())
+}
+
+fn format_timestamp(timestamp : i64) -> Option<String> {
+    Utc.timestamp_opt(timestamp, 0).single().map(|time| time.to_rfc3339())
+}
+
+fn to_value<T : Serialize>(resource : &T) -> Value {
+    serde_json::to_value(resource).unwrap_or(Value::Null)
+}
+
+/**
+ * Returns a page of the resources that match the filter of the query.
+ */
+fn list<T : Serialize>(resources : Vec<T>, query : &ScimQueryViewModel) -> Result<ScimListResponseViewModel<T>, IdentityError> {
+    let filter = match query.filter.as_deref().filter(|filter| !filter.trim().is_empty()) {
+        Some(filter) => Some(Filter::parse(filter)?),
+        None => None
+    };
+    let matching : Vec<T> = resources.into_iter()
+        .filter(|resource| filter.as_ref().is_none_or(|filter| filter.matches(&to_value(resource))))
+        .collect();
+    let total_results = matching.len();
+    let start_index = query.start_index.unwrap_or(1).max(1) as usize;
+    let count = query.count.unwrap_or(MAX_RESULTS as i64).clamp(0, MAX_RESULTS as i64) as usize;
+    let resources : Vec<T> = matching.into_iter().skip(start_index - 1).take(count).collect();
+    Ok(ScimListResponseViewModel {
+        schemas : vec![scim::SCHEMA_LIST_RESPONSE.to_owned()],
+        total_results,
+        start_index,
+        items_per_page : resources.len(),
+        resources
+    })
+}
+
+fn user_resource(user : &IdentityUser, stores : &ScimStores) -> ScimUserViewModel {
+    let scim_user = stores.scim.get_user(user.get_id());
+    let location = format!("{}/Users/{}", base_url(), user.get_id());
+    ScimUserViewModel {
+        schemas : vec![scim::SCHEMA_USER.to_owned()],
+        id : Some(user.get_id().to_owned()),
+        external_id : scim_user.as_ref().and_then(|scim_user| scim_user.get_external_id()).map(str::to_owned),
+        user_name : scim_user.as_ref().and_then(|scim_user| scim_user.get_user_name()).unwrap_or(user.get_email()).to_owned(),
+        display_name : Some(user.get_user_name().to_owned()).filter(|name| !name.is_empty()),
+        name : None,
+        emails : vec![ScimEmailViewModel {
+            value : user.get_email().to_owned(),
+            email_type : Some("work".to_owned()),
+            primary : Some(true)
+        }],
+        preferred_language : user.get_locale().map(str::to_owned),
+        active : Some(user.is_active()),
+        password : None,
+        groups : stores.groups.get_groups_of_user(user.get_id()).iter().map(|group| ScimMemberViewModel {
+            value : group.get_id().to_owned(),
+            display : Some(group.get_display_name().to_owned()),
+            reference : Some(format!("{}/Groups/{}", base_url(), group.get_id()))
+        }).collect(),
+        meta : Some(ScimMetaViewModel {
+            resource_type : "User".to_owned(),
+            created : scim_user.as_ref().and_then(|scim_user| format_timestamp(scim_user.get_created_at())),
+            last_modified : scim_user.as_ref().and_then(|scim_user| format_timestamp(scim_user.get_updated_at())),
+            location
+        })
+    }
+}
+
+fn group_resource(group : &IdentityGroup, db : &Store) -> ScimGroupViewModel {
+    ScimGroupViewModel {
+        schemas : vec![scim::SCHEMA_GROUP.to_owned()],
+        id : Some(group.get_id().to_owned()),
+        external_id : group.get_external_id().map(str::to_owned),
+        display_name : group.get_display_name().to_owned(),
+        members : group.get_members().iter().map(|member| ScimMemberViewModel {
+            value : member.to_owned(),
+            display : db.get_user_by_uuid(member).map(|user| user.get_email().to_owned()),
+            reference : Some(format!("{}/Users/{}", base_url(), member))
+        }).collect(),
+        meta : Some(ScimMetaViewModel {
+            resource_type : "Group".to_owned(),
+            created : format_timestamp(group.get_created_at()),
+            last_modified : format_timestamp(group.get_updated_at()),
+            location : format!("{}/Groups/{}", base_url(), group.get_id())
+        })
+    }
+}
+
+/**
+ * Returns the user with the id, the admin isn't part of the SCIM API.
+ */
+fn find_user(id : &str, db : &Store) -> Result<IdentityUser, IdentityError> {
+    db.get_user_by_uuid(id).filter(|user| user.get_id() != RESERVED_ID).ok_or(IdentityError::UserNotFound)
+}
+
+/**
+ * Copies the attributes of the resource onto the user and returns its SCIM data. An inactive resource suspends the user, an active one reactivates him. An error is returned when the user name or email is taken by another user.
+ */
+fn apply_user_resource(user : &mut IdentityUser, model : &ScimUserViewModel, stores : &ScimStores) -> Result<(), IdentityError> {
+    if model.user_name.trim().is_empty() {
+        return Err(IdentityError::InvalidRequest("userName is required".to_owned()))
+    }
+    if stores.scim.get_user_id_by_user_name(&model.user_name).is_some_and(|id| id != user.get_id()) {
+        return Err(IdentityError::EmailIsAlreadyTaken)
+    }
+    let email = model.get_email();
+    if stores.db.get_user_by_email(email).is_some_and(|other| other.get_id() != user.get_id()) {
+        return Err(IdentityError::EmailIsAlreadyTaken)
+    }
+    user.set_email(email)?;
+    user.set_user_name(&model.get_shown_name());
+    user.set_locale(model.preferred_language.as_deref());
+    if let Some(password) = model.password.as_deref() {
+        user.set_password(password)?;
+    }
+    match model.active {
+        Some(false) if user.is_active() => user.set_status(UserStatus::suspended("deactivated through SCIM")),
+        Some(true) if !user.is_active() => user.set_status(UserStatus::Active),
+        _ => {}
+    }
+    Ok(())
+}
+
+fn create_scim_user(model : ScimUserViewModel, stores : &ScimStores, hooks : &HookRegistry) -> Result<ScimUserViewModel, IdentityError> {
+    let mut user = IdentityUser::new_user(model.get_email(), "", &get_hash(32))?;
+    user.set_hashed_password("");
+    user.set_security_stamp("");
+    apply_user_resource(&mut user, &model, stores)?;
+    hooks.before("registration", |hook| hook.pre_registration(user.get_email()))?;
+    let user = stores.db.add_user(user)?;
+    stores.scim.insert_user(&ScimUser::new(user.get_id(), Some(&model.user_name), model.external_id.as_deref()))?;
+    info!("User {} has been provisioned through SCIM", user.get_id());
+    let info = PersonInfoViewModel::from_identity_user(&user);
+    hooks.after("registration", |hook| hook.post_registration(&info));
+    webhook_service::publish(&stores.webhooks, WebhookEventType::UserRegistered, user.get_id(), &stores.db);
+    Ok(user_resource(&user, stores))
+}
+
+fn replace_scim_user(id : &str, model : ScimUserViewModel, stores : &ScimStores, hooks : &HookRegistry) -> Result<ScimUserViewModel, IdentityError> {
+    let previous = find_user(id, &stores.db)?;
+    let mut user = previous.clone();
+    apply_user_resource(&mut user, &model, stores)?;
+    let (info, updated_info) = (PersonInfoViewModel::from_identity_user(&previous), PersonInfoViewModel::from_identity_user(&user));
+    hooks.before("update", |hook| hook.pre_update(&info, &updated_info))?;
+    if model.password.is_some() {
+        hooks.before("password change", |hook| hook.pre_password_change(&info))?;
+    }
+    stores.db.update_user(user.get_id(), &user)?;
+    if model.password.is_some() {
+        hooks.after("password change", |hook| hook.post_password_change(&info));
+    }
+    hooks.after("update", |hook| hook.post_update(&updated_info));
+    webhook_service::publish_email_change(&stores.webhooks, user.get_id(), Some(previous.get_email()), &stores.db);
+    let mut scim_user = stores.scim.get_user(id).unwrap_or_else(|| ScimUser::new(id, None, None));
+    scim_user.update(Some(&model.user_name), model.external_id.as_deref());
+    stores.scim.insert_user(&scim_user)?;
+    info!("User {} has been updated through SCIM", user.get_id());
+    Ok(user_resource(&user, stores))
+}
+
+fn patch_scim_user(id : &str, model : ScimPatchViewModel, stores : &ScimStores, hooks : &HookRegistry) -> Result<ScimUserViewModel, IdentityError> {
+    let mut resource = to_value(&user_resource(&find_user(id, &stores.db)?, stores));
+    for operation in &model.operations {
+        scim::apply_patch_operation(&mut resource, &operation.op, operation.path.as_deref(), operation.value.as_ref())?;
+    }
+    let patched : ScimUserViewModel = serde_json::from_value(resource)
+        .map_err(|e| IdentityError::InvalidRequest(format!("the patched user is not valid: {}", e)))?;
+    replace_scim_user(id, patched, stores, hooks)
+}
+
+fn delete_scim_user(id : &str, stores : &ScimStores, hooks : &HookRegistry) -> Result<(), IdentityError> {
+    let user = find_user(id, &stores.db)?;
+    let info = PersonInfoViewModel::from_identity_user(&user);
+    hooks.before("deletion", |hook| hook.pre_delete(&info))?;
+    stores.groups.remove_member_from_all(user.get_id())?;
+    stores.db.delete_user(user.get_id())?;
+    stores.scim.remove_user(user.get_id());
+    info!("User {} has been deprovisioned through SCIM", user.get_id());
+    hooks.after("deletion", |hook| hook.post_delete(&info));
+    webhook_service::publish(&stores.webhooks, WebhookEventType::UserDeleted, user.get_id(), &stores.db);
+    Ok(())
+}
+
+/**
+ * Gives the name of the group as a flag to its members. The members of the old version of the group lose the old name, so members that are removed and renames are synced. A change of the flags of an user is published.
+ */
+fn sync_group_flags(old : Option<&IdentityGroup>, new : Option<&IdentityGroup>, stores : &ScimStores) -> Result<(), IdentityError> {
+    let db = &stores.db;
+    let mut user_ids : BTreeSet<&String> = BTreeSet::new();
+    user_ids.extend(old.iter().flat_map(|group| group.get_members()));
+    user_ids.extend(new.iter().flat_map(|group| group.get_members()));
+    for user_id in user_ids {
+        if let Some(mut user) = db.get_user_by_uuid(user_id) {
+            let previous_flags = user.get_flags();
+            if let Some(old) = old.filter(|old| old.has_member(user_id)) {
+                user.remove_flag(old.get_display_name());
+            }
+            if let Some(new) = new.filter(|new| new.has_member(user_id)) {
+                user.add_flag(new.get_display_name());
+            }
+            db.update_user(user_id, &user)?;
+            if user.get_flags() != previous_flags {
+                webhook_service::publish(&stores.webhooks, WebhookEventType::RolesChanged, user_id, db);
+            }
+        }
+    }
+    Ok(())
+}
+
+/**
+ * Copies the attributes of the resource onto the group, every member has to be an existing user.
+ */
+fn apply_group_resource(group : &mut IdentityGroup, model : &ScimGroupViewModel, db : &Store) -> Result<(), IdentityError> {
+    if model.display_name.trim().is_empty() {
+        return Err(IdentityError::InvalidRequest("displayName is required".to_owned()))
+    }
+    let mut members = BTreeSet::new();
+    for member in &model.members {
+        let user = find_user(&member.value, db)
+            .map_err(|_| IdentityError::InvalidRequest(format!("member {} is not an user", member.value)))?;
+        members.insert(user.get_id().to_owned());
+    }
+    group.set_display_name(model.display_name.trim());
+    group.set_external_id(model.external_id.as_deref());
+    group.set_members(members);
+    Ok(())
+}
+
+fn create_scim_group(model : ScimGroupViewModel, stores : &ScimStores) -> Result<ScimGroupViewModel, IdentityError> {
+    let mut group = IdentityGroup::new(&get_hash(21), "");
+    apply_group_resource(&mut group, &model, &stores.db)?;
+    stores.groups.add_group(&group)?;
+    sync_group_flags(None, Some(&group), stores)?;
+    info!("Group {} has been provisioned through SCIM", group.get_id());
+    Ok(group_resource(&group, &stores.db))
+}
+
+fn replace_scim_group(id : &str, model : ScimGroupViewModel, stores : &ScimStores) -> Result<ScimGroupViewModel, IdentityError> {
+    let old = stores.groups.get_group(id).ok_or(IdentityError::GroupNotFound)?;
+    let mut group = old.clone();
+    apply_group_resource(&mut group, &model, &stores.db)?;
+    stores.groups.update_group(&group)?;
+    sync_group_flags(Some(&old), Some(&group), stores)?;
+    info!("Group {} has been updated through SCIM", group.get_id());
+    Ok(group_resource(&group, &stores.db))
+}
+
+fn patch_scim_group(id : &str, model : ScimPatchViewModel, stores : &ScimStores) -> Result<ScimGroupViewModel, IdentityError> {
+    let group = stores.groups.get_group(id).ok_or(IdentityError::GroupNotFound)?;
+    let mut resource = to_value(&group_resource(&group, &stores.db));
+    for operation in &model.operations {
+        scim::apply_patch_operation(&mut resource, &operation.op, operation.path.as_deref(), operation.value.as_ref())?;
+    }
+    let patched : ScimGroupViewModel = serde_json::from_value(resource)
+        .map_err(|e| IdentityError::InvalidRequest(format!("the patched group is not valid: {}", e)))?;
+    replace_scim_group(id, patched, stores)
+}
+
+fn delete_scim_group(id : &str, stores : &ScimStores) -> Result<(), IdentityError> {
+    let group = stores.groups.remove_group(id).ok_or(IdentityError::GroupNotFound)?;
+    sync_group_flags(Some(&group), None, stores)?;
+    info!("Group {} has been deleted through SCIM", group.get_id());
+    Ok(())
+}
+
+/**
+ * Returns the user with the id.
+ */
+pub fn get_user(token : &str, id : &str, stores : &ScimStores, clients : &ClientStore, tokens : &TokenStore) -> Result<ScimUserViewModel, IdentityError> {
+    control_scim_token(token, &stores.db, clients, tokens)?;
+    Ok(user_resource(&find_user(id, &stores.db)?, stores))
+}
+
+/**
+ * Returns a page of the users that match the filter of the query.
+ */
+pub fn list_users(token : &str, query : ScimQueryViewModel, stores : &ScimStores, clients : &ClientStore, tokens : &TokenStore) -> Result<ScimListResponseViewModel<ScimUserViewModel>, IdentityError> {
+    control_scim_token(token, &stores.db, clients, tokens)?;
+    list(stores.db.get_non_admin_users().iter().map(|user| user_resource(user, stores)).collect(), &query)
+}
+
+/**
+ * Provisions an user. Without a password the user can only log in through another way, like an identity provider or a password reset.
+ *
+ * An error is returned when:
+ * * the token can't be used for the SCIM API
+ * * the userName is missing, or the user name or email is already taken
+ * * the email isn't valid
+ */
+pub fn create_user(token : &str, model : ScimUserViewModel, stores : &ScimStores, clients : &ClientStore, tokens : &TokenStore, hooks : &HookRegistry) -> Result<ScimUserViewModel, IdentityError> {
+    control_scim_token(token, &stores.db, clients, tokens)?;
+    create_scim_user(model, stores, hooks)
+}
+
+/**
+ * Replaces the attributes of an user with those of the resource.
+ */
+pub fn replace_user(token : &str, id : &str, model : ScimUserViewModel, stores : &ScimStores, clients : &ClientStore, tokens : &TokenStore, hooks : &HookRegistry) -> Result<ScimUserViewModel, IdentityError> {
+    control_scim_token(token, &stores.db, clients, tokens)?;
+    replace_scim_user(id, model, stores, hooks)
+}
+
+/**
+ * Applies the operations of a PATCH request to an user.
+ */
+pub fn patch_user(token : &str, id : &str, model : ScimPatchViewModel, stores : &ScimStores, clients : &ClientStore, tokens : &TokenStore, hooks : &HookRegistry) -> Result<ScimUserViewModel, IdentityError> {
+    control_scim_token(token, &stores.db, clients, tokens)?;
+    patch_scim_user(id, model, stores, hooks)
+}
+
+/**
+ * Deprovisions an user, he is removed from his groups.
+ */
+pub fn delete_user(token : &str, id : &str, stores : &ScimStores, clients : &ClientStore, tokens : &TokenStore, hooks : &HookRegistry) -> Result<(), IdentityError> {
+    control_scim_token(token, &stores.db, clients, tokens)?;
+    delete_scim_user(id, stores, hooks)
+}
+
+/**
+ * Returns the group with the id.
+ */
+pub fn get_group(token : &str, id : &str, stores : &ScimStores, clients : &ClientStore, tokens : &TokenStore) -> Result<ScimGroupViewModel, IdentityError> {
+    control_scim_token(token, &stores.db, clients, tokens)?;
+    let group = stores.groups.get_group(id).ok_or(IdentityError::GroupNotFound)?;
+    Ok(group_resource(&group, &stores.db))
+}
+
+/**
+ * Returns a page of the groups that match the filter of the query.
+ */
+pub fn list_groups(token : &str, query : ScimQueryViewModel, stores : &ScimStores, clients : &ClientStore, tokens : &TokenStore) -> Result<ScimListResponseViewModel<ScimGroupViewModel>, IdentityError> {
+    control_scim_token(token, &stores.db, clients, tokens)?;
+    list(stores.groups.get_all_groups().iter().map(|group| group_resource(group, &stores.db)).collect(), &query)
+}
+
+/**
+ * Makes a group, its name is given as a flag to its members.
+ */
+pub fn create_group(token : &str, model : ScimGroupViewModel, stores : &ScimStores, clients : &ClientStore, tokens : &TokenStore) -> Result<ScimGroupViewModel, IdentityError> {
+    control_scim_token(token, &stores.db, clients, tokens)?;
+    create_scim_group(model, stores)
+}
+
+/**
+ * Replaces the name and members of a group.
+ */
+pub fn replace_group(token : &str, id : &str, model : ScimGroupViewModel, stores : &ScimStores, clients : &ClientStore, tokens : &TokenStore) -> Result<ScimGroupViewModel, IdentityError> {
+    control_scim_token(token, &stores.db, clients, tokens)?;
+    replace_scim_group(id, model, stores)
+}
+
+/**
+ * Applies the operations of a PATCH request to a group.
+ */
+pub fn patch_group(token : &str, id : &str, model : ScimPatchViewModel, stores : &ScimStores, clients : &ClientStore, tokens : &TokenStore) -> Result<ScimGroupViewModel, IdentityError> {
+    control_scim_token(token, &stores.db, clients, tokens)?;
+    patch_scim_group(id, model, stores)
+}
+
+/**
+ * Deletes a group, its members lose its flag.
+ */
+pub fn delete_group(token : &str, id : &str, stores : &ScimStores, clients : &ClientStore, tokens : &TokenStore) -> Result<(), IdentityError> {
+    control_scim_token(token, &stores.db, clients, tokens)?;
+    delete_scim_group(id, stores)
+}
+
+/**
+ * Returns what the SCIM API supports.
+ */
+pub fn service_provider_config() -> Value {
+    json!({
+        "schemas" : [scim::SCHEMA_SERVICE_PROVIDER_CONFIG],
+        "patch" : { "supported" : true },
+        "bulk" : { "supported" : false, "maxOperations" : 0, "maxPayloadSize" : 0 },
+        "filter" : { "supported" : true, "maxResults" : MAX_RESULTS },
+        "changePassword" : { "supported" : true },
+        "sort" : { "supported" : false },
+        "etag" : { "supported" : false },
+        "authenticationSchemes" : [{
+            "type" : "oauthbearertoken",
+            "name" : "OAuth Bearer Token",
+            "description" : format!("Access token of the client credentials grant with the {} scope", SCIM_SCOPE),
+            "primary" : true
+        }],
+        "meta" : { "resourceType" : "ServiceProviderConfig", "location" : format!("{}/ServiceProviderConfig", base_url()) }
+    })
+}
+
+/**
+ * Returns the resource types of the SCIM API.
+ */
+pub fn resource_types() -> Vec<Value> {
+    [("User", "/Users", scim::SCHEMA_USER), ("Group", "/Groups", scim::SCHEMA_GROUP)].iter().map(|(name, endpoint, schema)| json!({
+        "schemas" : [scim::SCHEMA_RESOURCE_TYPE],
+        "id" : name,
+        "name" : name,
+        "endpoint" : endpoint,
+        "schema" : schema,
+        "meta" : { "resourceType" : "ResourceType", "location" : format!("{}/ResourceTypes/{}", base_url(), name) }
+    })).collect()
+}
+
+fn attribute(name : &str, value_type : &str, multi_valued : bool, required : bool, mutability : &str, sub_attributes : Vec<Value>) -> Value {
+    let mut attribute = json!({
+        "name" : name,
+        "type" : value_type,
+        "multiValued" : multi_valued,
+        "required" : required,
+        "caseExact" : false,
+        "mutability" : mutability,
+        "returned" : if name == "password" { "never" } else { "default" },
+        "uniqueness" : if name == "userName" { "server" } else { "none" }
+    });
+    if !sub_attributes.is_empty() {
+        attribute["subAttributes"] = Value::Array(sub_attributes);
+    }
+    attribute
+}
+
+/**
+ * Returns the schemas of the resources of the SCIM API, with the attributes that are supported.
+ */
+pub fn schemas() -> Vec<Value> {
+    let reference = |mutability : &str| vec![
+        attribute("value", "string", false, false, mutability, vec![]),
+        attribute("display", "string", false, false, "readOnly", vec![]),
+        attribute("$ref", "reference", false, false, mutability, vec![])
+    ];
+    let user = vec![
+        attribute("userName", "string", false, true, "readWrite", vec![]),
+        attribute("displayName", "string", false, false, "readWrite", vec![]),
+        attribute("name", "complex", false, false, "readWrite", vec![
+            attribute("formatted", "string", false, false, "readWrite", vec![]),
+            attribute("givenName", "string", false, false, "readWrite", vec![]),
+            attribute("familyName", "string", false, false, "readWrite", vec![])
+        ]),
+        attribute("emails", "complex", true, false, "readWrite", vec![
+            attribute("value", "string", false, false, "readWrite", vec![]),
+            attribute("type", "string", false, false, "readWrite", vec![]),
+            attribute("primary", "boolean", false, false, "readWrite", vec![])
+        ]),
+        attribute("preferredLanguage", "string", false, false, "readWrite", vec![]),
+        attribute("active", "boolean", false, false, "readWrite", vec![]),
+        attribute("password", "string", false, false, "writeOnly", vec![]),
+        attribute("groups", "complex", true, false, "readOnly", reference("readOnly"))
+    ];
+    let group = vec![
+        attribute("displayName", "string", false, true, "readWrite", vec![]),
+        attribute("members", "complex", true, false, "readWrite", reference("immutable"))
+    ];
+    [(scim::SCHEMA_USER, "User", user), (scim::SCHEMA_GROUP, "Group", group)].iter().map(|(id, name, attributes)| json!({
+        "schemas" : [scim::SCHEMA_SCHEMA],
+        "id" : id,
+        "name" : name,
+        "attributes" : attributes,
+        "meta" : { "resourceType" : "Schema", "location" : format!("{}/Schemas/{}", base_url(), id) }
+    })).collect()
+}
+
+#[test]
+fn test_scim_users_and_groups() {
+    use identity_dal::repo::user_config::UserConfig;
+    use crate::viewmodels::scim::patch::ScimPatchOperationViewModel;
+    use identity_dal::webhook::webhook_subscription::WebhookSubscription;
+
+    let config = UserConfig::new_config("", "person", 100000);
+    let stores = ScimStores { db : Store::new_db(config.clone()), groups : GroupStore::new_db(config.clone()), scim : ScimStore::new_db(config.clone()), webhooks : WebhookStore::new_db(config) };
+    stores.webhooks.save_subscription(&WebhookSubscription::new("1", "http://localhost", "secret", WebhookEventType::ALL.iter().copied().collect(), "admin")).unwrap();
+    let published = |event_type : WebhookEventType| stores.webhooks.get_deliveries_of_subscription("1", None).iter().filter(|delivery| delivery.get_event_type() == event_type).count();
+    let user_resource_of = |user_name : &str, email : &str| serde_json::from_value::<ScimUserViewModel>(json!({
+        "schemas" : [scim::SCHEMA_USER],
+        "userName" : user_name,
+        "externalId" : format!("hr-{}", user_name),
+        "name" : { "givenName" : "Jane", "familyName" : "Doe" },
+        "emails" : [{ "value" : email, "primary" : true }]
+    })).unwrap();
+
+    struct SpamGuard;
+    impl crate::hooks::LifecycleHooks for SpamGuard {
+        fn name(&self) -> &str { "spam guard" }
+
+        fn pre_registration(&self, email : &str) -> Result<(), IdentityError> {
+            if email.ends_with("@spam.com") {
+                return Err(IdentityError::EmailDomainIsNotAllowed)
+            }
+            Ok(())
+        }
+    }
+    let hooks = HookRegistry::new().register(SpamGuard);
+    assert!(matches!(create_scim_user(user_resource_of("bot", "bot@spam.com"), &stores, &hooks), Err(IdentityError::EmailDomainIsNotAllowed)));
+    assert!(stores.db.get_user_by_email("bot@spam.com").is_none());
+
+    let jane = create_scim_user(user_resource_of("jdoe", "jane@corp.be"), &stores, &hooks).unwrap();
+    let jane_id = jane.id.clone().unwrap();
+    assert_eq!(jane.user_name, "jdoe");
+    assert_eq!(jane.display_name.as_deref(), Some("Jane Doe"));
+    assert!(matches!(create_scim_user(user_resource_of("jdoe", "other@corp.be"), &stores, &hooks), Err(IdentityError::EmailIsAlreadyTaken)));
+    create_scim_user(user_resource_of("jsmith", "john@corp.be"), &stores, &hooks).unwrap();
+    assert_eq!(published(WebhookEventType::UserRegistered), 2);
+
+    let query = |filter : &str, start_index, count| ScimQueryViewModel { filter : Some(filter.to_owned()), start_index, count };
+    let page = list(stores.db.get_non_admin_users().iter().map(|user| user_resource(user, &stores)).collect(), &query(r#"userName eq "JDOE""#, None, None)).unwrap();
+    assert_eq!(page.total_results, 1);
+    assert_eq!(page.resources[0].id.as_deref(), Some(jane_id.as_str()));
+    let page = list(stores.db.get_non_admin_users().iter().map(|user| user_resource(user, &stores)).collect(), &query(r#"emails.value ew "@corp.be""#, Some(2), Some(5))).unwrap();
+    assert_eq!((page.total_results, page.start_index, page.items_per_page), (2, 2, 1));
+
+    let group = create_scim_group(serde_json::from_value(json!({ "displayName" : "sales", "members" : [{ "value" : jane_id }] })).unwrap(), &stores).unwrap();
+    let group_id = group.id.clone().unwrap();
+    assert!(stores.db.get_user_by_uuid(&jane_id).unwrap().get_flags().contains("sales"));
+    assert_eq!(published(WebhookEventType::RolesChanged), 1);
+    assert_eq!(user_resource(&stores.db.get_user_by_uuid(&jane_id).unwrap(), &stores).groups[0].value, group_id);
+
+    let patch = ScimPatchViewModel { schemas : vec![scim::SCHEMA_PATCH_OP.to_owned()], operations : vec![
+        ScimPatchOperationViewModel { op : "replace".to_owned(), path : Some("displayName".to_owned()), value : Some(json!("marketing")) }
+    ]};
+    patch_scim_group(&group_id, patch, &stores).unwrap();
+    let flags = stores.db.get_user_by_uuid(&jane_id).unwrap().get_flags();
+    assert!(flags.contains("marketing") && !flags.contains("sales"));
+    assert_eq!(published(WebhookEventType::RolesChanged), 2);
+
+    let patch = ScimPatchViewModel { schemas : vec![], operations : vec![
+        ScimPatchOperationViewModel { op : "replace".to_owned(), path : Some(r#"emails[primary eq true].value"#.to_owned()), value : Some(json!("jane.doe@corp.be")) },
+        ScimPatchOperationViewModel { op : "replace".to_owned(), path : None, value : Some(json!({ "displayName" : "Jane D.", "preferredLanguage" : "nl-BE" })) }
+    ]};
+    let patched = patch_scim_user(&jane_id, patch, &stores, &hooks).unwrap();
+    assert_eq!(patched.emails[0].value, "jane.doe@corp.be");
+    assert_eq!(patched.display_name.as_deref(), Some("Jane D."));
+    assert_eq!(stores.db.get_user_by_uuid(&jane_id).unwrap().get_locale(), Some("nl-BE"));
+    assert_eq!(patched.external_id.as_deref(), Some("hr-jdoe"));
+    assert_eq!(published(WebhookEventType::EmailChanged), 1);
+
+    let patch = ScimPatchViewModel { schemas : vec![], operations : vec![
+        ScimPatchOperationViewModel { op : "replace".to_owned(), path : Some("active".to_owned()), value : Some(json!(false)) }
+    ]};
+    assert_eq!(patch_scim_user(&jane_id, patch, &stores, &hooks).unwrap().active, Some(false));
+    assert!(matches!(stores.db.get_user_by_uuid(&jane_id).unwrap().control_status(), Err(IdentityError::UserIsSuspended)));
+
+    delete_scim_user(&jane_id, &stores, &hooks).unwrap();
+    assert_eq!(published(WebhookEventType::UserDeleted), 1);
+    assert!(stores.groups.get_group(&group_id).unwrap().get_members().is_empty());
+    assert!(matches!(delete_scim_user(&jane_id, &stores, &hooks), Err(IdentityError::UserNotFound)));
+    assert!(matches!(delete_scim_user(RESERVED_ID, &stores, &hooks), Err(IdentityError::UserNotFound)));
+}
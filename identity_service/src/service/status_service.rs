@@ -0,0 +1,182 @@
+use crate::claim::Claim;
+use crate::hooks::HookRegistry;
+use crate::store::Store;
+use crate::service::admin_service;
+use crate::util::get_value_from_key;
+use crate::viewmodels::admin::suspend_user::SuspendUserViewModel;
+use crate::viewmodels::auth::deactivate::DeactivateViewModel;
+use crate::viewmodels::auth::login::LoginViewModel;
+use crate::viewmodels::auth::person_info::PersonInfoViewModel;
+use crate::viewmodels::auth::user_id::UserIdViewModel;
+use identity_dal::repo::client_repo::ClientStore;
+use identity_dal::repo::token_repo::TokenStore;
+use identity_dal::traits::t_user::UserTrait;
+use identity_dal::traits::t_user_manager::UserStoreTrait;
+use identity_dal::user::identity_user::{ IdentityUser, RESERVED_ID };
+use identity_dal::user::user_status::UserStatus;
+use crate::IdentityError;
+
+lazy_static! {
+    static ref DEACTIVATION_GRACE_PERIOD : i64 = get_value_from_key("PERSON_DEACTIVATION_GRACE_PERIOD")
+    .unwrap_or_else(|| "2592000".to_owned())
+    .parse::<i64>()
+    .expect("PERSON_DEACTIVATION_GRACE_PERIOD has to be a number of seconds");
+}
+
+/**
+ * Returns the user with the id, the status of the admin can't be changed.
+ */
+fn find_user(id : &str, db : &Store) -> Result<IdentityUser, IdentityError> {
+    if id == RESERVED_ID {
+        warn!("The status of the admin can't be changed");
+        return Err(IdentityError::IdEqualsAdmin)
+    }
+    db.get_user_by_uuid(id).ok_or(IdentityError::UserNotFound)
+}
+
+/**
+ * Suspends the user, he can't log in or use his tokens until the admin reactivates him.
+ */
+fn suspend(id : &str, reason : &str, db : &Store) -> Result<IdentityUser, IdentityError> {
+    if reason.trim().is_empty() {
+        return Err(IdentityError::InvalidRequest("a reason is needed to suspend an user".to_owned()))
+    }
+    let mut user = find_user(id, db)?;
+    user.set_status(UserStatus::suspended(reason.trim()));
+    db.update_user(user.get_id(), &user)?;
+    info!("User {} has been suspended", user.get_id());
+    Ok(user)
+}
+
+/**
+ * Makes the user active again, whatever status he had.
+ */
+fn reactivate(id : &str, db : &Store) -> Result<IdentityUser, IdentityError> {
+    let mut user = find_user(id, db)?;
+    if !user.is_active() {
+        user.set_status(UserStatus::Active);
+        db.update_user(user.get_id(), &user)?;
+        info!("User {} has been reactivated", user.get_id());
+    }
+    Ok(user)
+}
+
+/**
+ * Deactivates the account of the user, he can undo it himself until the grace period ends.
+ */
+fn deactivate(mut user : IdentityUser, model : &DeactivateViewModel, grace_period : i64, db : &Store) -> Result<IdentityUser, IdentityError> {
+    if user.get_id() == RESERVED_ID {
+        return Err(IdentityError::IdEqualsAdmin)
+    }
+    if !user.check_pwd(model.get_password()) {
+        warn!("The password of user {} is not good, the account isn't deactivated", user.get_id());
+        return Err(IdentityError::PasswordIsNotCorrect)
+    }
+    user.set_status(UserStatus::deactivated(model.get_reason().map(str::trim).filter(|reason| !reason.is_empty()), grace_period));
+    db.update_user(user.get_id(), &user)?;
+    info!("User {} has deactivated his account", user.get_id());
+    Ok(user)
+}
+
+/**
+ * Undoes the deactivation of an user that logs in within the grace period, an active user is simply logged in.
+ */
+fn restore(model : &LoginViewModel, db : &Store) -> Result<IdentityUser, IdentityError> {
+    let mut user = db.get_user_by_email(model.get_email()).ok_or(IdentityError::UserIsNotPresent)?;
+    if !user.check_pwd(model.get_password()) {
+        warn!("The user's password is not good.");
+        return Err(IdentityError::PasswordIsNotCorrect)
+    }
+    match user.get_status() {
+        UserStatus::Active => return Ok(user),
+        status if status.is_restorable() => {},
+        UserStatus::Deactivated { .. } => return Err(IdentityError::UserIsNotRestorable),
+        status => return status.control().map(|_| user)
+    }
+    user.set_status(UserStatus::Active);
+    db.update_user(user.get_id(), &user)?;
+    info!("User {} has undone the deactivation of his account", user.get_id());
+    Ok(user)
+}
+
+/**
+ * Admin function that suspends an user with a reason.
+ *
+ * An error is returned when:
+ * * the token isn't that of the admin
+ * * the reason is empty
+ * * the user doesn't exist or is the admin
+ */
+pub fn suspend_user(
+    token : &str,
+    model : SuspendUserViewModel,
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore
+) -> Result<PersonInfoViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    suspend(model.get_user_id(), model.get_reason(), &db).map(|user| PersonInfoViewModel::from_identity_user(&user))
+}
+
+/**
+ * Admin function that reactivates a suspended, deactivated or pending user.
+ */
+pub fn reactivate_user(
+    token : &str,
+    model : UserIdViewModel,
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore
+) -> Result<PersonInfoViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    reactivate(model.get_id(), &db).map(|user| PersonInfoViewModel::from_identity_user(&user))
+}
+
+/**
+ * Deactivates the account of the user of the token, his password is asked to confirm it. His tokens can't be used anymore from then on.
+ */
+pub fn deactivate_user(token : &str, model : DeactivateViewModel, db : Store) -> Result<PersonInfoViewModel, IdentityError> {
+    let user = Claim::token_to_user(token, &db)?;
+    deactivate(user, &model, *DEACTIVATION_GRACE_PERIOD, &db).map(|user| PersonInfoViewModel::from_identity_user(&user))
+}
+
+/**
+ * Reactivates the account of an user that deactivated it himself and logs him in.
+ *
+ * An error is returned when:
+ * * the email or password isn't right
+ * * the grace period has ended
+ * * the user has been suspended or is pending
+ * * a pre login hook vetoes the login, the account stays deactivated then
+ */
+pub fn restore_user(model : LoginViewModel, db : Store, hooks : &HookRegistry) -> Result<Claim, IdentityError> {
+    hooks.before("login", |hook| hook.pre_login(model.get_email()))?;
+    let user = restore(&model, &db)?;
+    let claim = Claim::new_read_write_claim(user.get_id())?;
+    let info = PersonInfoViewModel::from_identity_user(&user);
+    hooks.after("login", |hook| hook.post_login(&info));
+    Ok(claim)
+}
+
+#[test]
+fn test_user_status_changes() {
+    use identity_dal::repo::user_config::UserConfig;
+
+    let db = Store::new_db(UserConfig::new_config("", "person", 100000));
+    let jane = db.add_user(IdentityUser::new_user("jane@corp.be", "jane", "Passw0rd!").unwrap()).unwrap();
+    assert!(matches!(suspend(jane.get_id(), " ", &db), Err(IdentityError::InvalidRequest(_))));
+    assert!(matches!(suspend(RESERVED_ID, "fraud", &db), Err(IdentityError::IdEqualsAdmin)));
+    assert_eq!(suspend(jane.get_id(), "fraud", &db).unwrap().get_status().get_reason(), Some("fraud"));
+    let login = LoginViewModel::new("jane@corp.be", "Passw0rd!");
+    assert!(matches!(restore(&login, &db), Err(IdentityError::UserIsSuspended)));
+    assert!(reactivate(jane.get_id(), &db).unwrap().is_active());
+
+    let jane = db.get_user_by_uuid(jane.get_id()).unwrap();
+    assert!(matches!(deactivate(jane.clone(), &DeactivateViewModel::new("wrong", None), 60, &db), Err(IdentityError::PasswordIsNotCorrect)));
+    deactivate(jane.clone(), &DeactivateViewModel::new("Passw0rd!", Some("leaving")), 60, &db).unwrap();
+    assert!(matches!(db.get_user_by_uuid(jane.get_id()).unwrap().control_status(), Err(IdentityError::UserIsDeactivated)));
+    assert!(restore(&login, &db).unwrap().is_active());
+
+    deactivate(jane, &DeactivateViewModel::new("Passw0rd!", None), -1, &db).unwrap();
+    assert!(matches!(restore(&login, &db), Err(IdentityError::UserIsNotRestorable)));
+}
@@ -0,0 +1,373 @@
+use crate::claim::Claim;
+use crate::store::Store;
+use crate::service::admin_service;
+use crate::util::get_value_from_key;
+use crate::viewmodels::admin::webhook::{
+    AllWebhookDeliveriesViewModel, AllWebhookSubscriptionsViewModel, CreateWebhookViewModel,
+    DeliveryIdViewModel, WebhookDeliveryViewModel, WebhookIdViewModel, WebhookSubscriptionViewModel
+};
+use identity_dal::repo::client_repo::ClientStore;
+use identity_dal::repo::token_repo::TokenStore;
+use identity_dal::traits::t_user::UserTrait;
+use identity_dal::traits::t_user_manager::UserStoreTrait;
+use identity_dal::util::get_hash;
+use identity_dal::webhook::webhook_delivery::{ DeliveryAttempt, WebhookDelivery };
+use identity_dal::webhook::webhook_subscription::WebhookSubscription;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::thread::{ self, JoinHandle };
+use std::time::Duration;
+use crate::IdentityError;
+
+pub type WebhookStore = identity_dal::repo::webhook_repo::WebhookStore;
+pub type WebhookEventType = identity_dal::webhook::webhook_subscription::WebhookEventType;
+
+/**
+ * Headers of a webhook post: the event type, the id of the delivery and the HMAC-SHA256 signature of the body with the secret of the subscription, as sha256=<hex>.
+ */
+pub static EVENT_HEADER : &str = "X-Identity-Event";
+pub static DELIVERY_HEADER : &str = "X-Identity-Delivery";
+pub static SIGNATURE_HEADER : &str = "X-Identity-Signature";
+
+/**
+ * Amount of deliveries of a subscription the delivery log returns.
+ */
+static DELIVERY_LOG_LIMIT : usize = 100;
+
+lazy_static! {
+    static ref MAX_ATTEMPTS : usize = get_value_from_key("PERSON_WEBHOOK_MAX_ATTEMPTS")
+    .unwrap_or_else(|| "8".to_owned())
+    .parse::<usize>()
+    .expect("PERSON_WEBHOOK_MAX_ATTEMPTS has to be a number");
+    static ref BACKOFF : i64 = get_value_from_key("PERSON_WEBHOOK_BACKOFF")
+    .unwrap_or_else(|| "30".to_owned())
+    .parse::<i64>()
+    .expect("PERSON_WEBHOOK_BACKOFF has to be a number of seconds");
+    static ref DELIVERY_INTERVAL : u64 = get_value_from_key("PERSON_WEBHOOK_INTERVAL")
+    .unwrap_or_else(|| "10".to_owned())
+    .parse::<u64>()
+    .expect("PERSON_WEBHOOK_INTERVAL has to be a number of seconds");
+    static ref AGENT : ureq::Agent = ureq::AgentBuilder::new()
+        .tls_connector(Arc::new(native_tls::TlsConnector::new().expect("Could not make a TLS connector")))
+        .timeout(Duration::from_secs(10))
+        .build();
+}
+
+/**
+ * Returns the signature of the body with the secret, as it is sent in the signature header. The receiver computes the same to know the post comes from this server.
+ */
+pub fn sign_payload(secret : &str, payload : &str) -> String {
+    let key = ring::hmac::SigningKey::new(&ring::digest::SHA256, secret.as_bytes());
+    let signature = ring::hmac::sign(&key, payload.as_bytes());
+    format!("sha256={}", signature.as_ref().iter().map(|byte| format!("{:02x}", byte)).collect::<String>())
+}
+
+/**
+ * Returns what the events tell about the user: his id, email, user name and flags. Of a deleted user the data he had is sent, of an user that can't be found only the id.
+ */
+fn user_data(user_id : &str, db : &Store) -> serde_json::Value {
+    match db.get_user_by_uuid(user_id).or_else(|| db.get_tombstone(user_id).map(|tombstone| tombstone.get_user().clone())) {
+        Some(user) => serde_json::json!({
+            "id" : user.get_id(),
+            "email" : user.get_email(),
+            "user_name" : user.get_user_name(),
+            "flags" : user.get_flag_list()
+        }),
+        None => serde_json::json!({ "id" : user_id })
+    }
+}
+
+/**
+ * Queues the event in the outbox for every active subscription to it, the event is posted by the delivery task. A failure to queue is only logged, it never makes the action itself fail.
+ */
+pub fn publish(webhooks : &WebhookStore, event_type : WebhookEventType, user_id : &str, db : &Store) {
+    let subscriptions = webhooks.get_subscriptions_of_event(event_type);
+    if subscriptions.is_empty() {
+        return
+    }
+    let payload = serde_json::json!({
+        "id" : get_hash(21),
+        "type" : event_type.get_name(),
+        "created_at" : chrono::Utc::now().timestamp(),
+        "data" : user_data(user_id, db)
+    }).to_string();
+    for subscription in subscriptions {
+        if let Err(e) = webhooks.enqueue(WebhookDelivery::new(subscription.get_id(), event_type, &payload).for_user(user_id)) {
+            error!("Event {} could not be queued for webhook {}: {}", event_type.get_name(), subscription.get_id(), e);
+        }
+    }
+}
+
+/**
+ * Queues an email change of the user when his email is no longer the previous one.
+ */
+pub fn publish_email_change(webhooks : &WebhookStore, user_id : &str, previous_email : Option<&str>, db : &Store) {
+    if email_of_user(user_id, db).as_deref() != previous_email {
+        publish(webhooks, WebhookEventType::EmailChanged, user_id, db);
+    }
+}
+
+/**
+ * Returns the email the user has now.
+ */
+pub fn email_of_user(user_id : &str, db : &Store) -> Option<String> {
+    db.get_user_by_uuid(user_id).map(|user| user.get_email().to_owned())
+}
+
+/**
+ * Returns the id of the user of a token, None for a token of a client or one that can't be decoded.
+ */
+pub fn user_of_token(token : &str) -> Option<String> {
+    let claim = Claim::decode_token(token).ok()?.claims;
+    if claim.is_client_claim() {
+        return None
+    }
+    Some(claim.sub)
+}
+
+/**
+ * Posts the payload of the delivery to the url of the subscription. Any 2xx response is a success.
+ */
+fn post(subscription : &WebhookSubscription, delivery : &WebhookDelivery) -> DeliveryAttempt {
+    let response = AGENT.post(subscription.get_url())
+        .set("Content-Type", "application/json")
+        .set(EVENT_HEADER, delivery.get_event_type().get_name())
+        .set(DELIVERY_HEADER, &delivery.get_id().to_string())
+        .set(SIGNATURE_HEADER, &sign_payload(subscription.get_secret(), delivery.get_payload()))
+        .send_string(delivery.get_payload());
+    match response {
+        Ok(response) => DeliveryAttempt::response(response.status()),
+        Err(ureq::Error::Status(status, _)) => DeliveryAttempt::response(status),
+        Err(e) => DeliveryAttempt::failure(&e.to_string())
+    }
+}
+
+/**
+ * Does one attempt of the delivery and stores the result. A delivery of a subscription that is removed or disabled fails right away.
+ */
+fn deliver(mut delivery : WebhookDelivery, webhooks : &WebhookStore, max_attempts : usize, backoff : i64) -> Result<WebhookDelivery, IdentityError> {
+    let attempt = match webhooks.get_subscription(delivery.get_subscription_id()) {
+        Some(subscription) if subscription.is_active() => post(&subscription, &delivery),
+        Some(_) => DeliveryAttempt::failure("the webhook is disabled"),
+        None => DeliveryAttempt::failure("the webhook has been removed")
+    };
+    if let Some(error) = attempt.get_error() {
+        warn!("Webhook delivery {} failed: {}", delivery.get_id(), error);
+    }
+    delivery.record_attempt(attempt, max_attempts, backoff);
+    webhooks.save_delivery(&delivery)?;
+    Ok(delivery)
+}
+
+/**
+ * Does an attempt of every due delivery, returns how many were delivered.
+ */
+fn deliver_due(webhooks : &WebhookStore, max_attempts : usize, backoff : i64) -> usize {
+    webhooks.get_due_deliveries(chrono::Utc::now().timestamp()).into_iter()
+        .filter_map(|delivery| deliver(delivery, webhooks, max_attempts, backoff).ok())
+        .filter(|delivery| delivery.get_attempts().last().is_some_and(DeliveryAttempt::is_success))
+        .count()
+}
+
+/**
+ * Makes a subscription with a new secret, an url that isn't http(s) or an unknown event is refused.
+ */
+fn make_subscription(model : &CreateWebhookViewModel, created_by : &str) -> Result<WebhookSubscription, IdentityError> {
+    let url = model.get_url().trim();
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        return Err(IdentityError::InvalidRequest("the url of a webhook has to be http or https".to_owned()))
+    }
+    let events = model.get_events().iter()
+        .map(|name| WebhookEventType::parse(name).ok_or_else(|| IdentityError::InvalidRequest(format!("{} is not a webhook event", name))))
+        .collect::<Result<BTreeSet<WebhookEventType>, IdentityError>>()?;
+    if events.is_empty() {
+        return Err(IdentityError::InvalidRequest("a webhook has to subscribe to an event".to_owned()))
+    }
+    Ok(WebhookSubscription::new(&get_hash(21), url, &get_hash(40), events, created_by))
+}
+
+/**
+ * Does an attempt of every due delivery, returns how many were delivered.
+ */
+pub fn deliver_due_webhooks(webhooks : &WebhookStore) -> usize {
+    deliver_due(webhooks, *MAX_ATTEMPTS, *BACKOFF)
+}
+
+/**
+ * Starts the background task that posts the due deliveries of the outbox at the configured interval.
+ */
+pub fn start_delivery_task(webhooks : WebhookStore) -> JoinHandle<()> {
+    let interval = Duration::from_secs(*DELIVERY_INTERVAL);
+    thread::spawn(move || loop {
+        let delivered = deliver_due_webhooks(&webhooks);
+        if delivered > 0 {
+            info!("{} webhook deliveries have been delivered", delivered);
+        }
+        thread::sleep(interval);
+    })
+}
+
+/**
+ * Admin function that subscribes an url to events. The secret of the signatures is only returned here.
+ */
+pub fn create_subscription(
+    token : &str,
+    model : CreateWebhookViewModel,
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore,
+    webhooks : WebhookStore
+) -> Result<WebhookSubscriptionViewModel, IdentityError> {
+    let claim = admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    let created_by = if claim.is_client_claim() { claim.client_id.unwrap_or_default() } else { claim.sub };
+    let subscription = make_subscription(&model, &created_by)?;
+    webhooks.save_subscription(&subscription)?;
+    info!("Webhook {} has been made for {}", subscription.get_id(), subscription.get_url());
+    Ok(WebhookSubscriptionViewModel::from_subscription(&subscription, true))
+}
+
+/**
+ * Admin function that returns all webhook subscriptions.
+ */
+pub fn get_subscriptions(token : &str, db : Store, clients : ClientStore, tokens : TokenStore, webhooks : WebhookStore) -> Result<AllWebhookSubscriptionsViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    Ok(AllWebhookSubscriptionsViewModel::from_subscriptions_vector(webhooks.get_all_subscriptions()))
+}
+
+/**
+ * Admin function that removes a subscription, its deliveries are removed from the outbox.
+ */
+pub fn remove_subscription(
+    token : &str,
+    model : WebhookIdViewModel,
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore,
+    webhooks : WebhookStore
+) -> Result<(), IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    let subscription = webhooks.remove_subscription(model.get_id()).ok_or_else(|| IdentityError::InvalidRequest("the webhook doesn't exist".to_owned()))?;
+    info!("Webhook {} has been removed", subscription.get_id());
+    Ok(())
+}
+
+/**
+ * Admin function that returns the delivery log of a subscription, the newest deliveries first.
+ */
+pub fn get_deliveries(
+    token : &str,
+    model : WebhookIdViewModel,
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore,
+    webhooks : WebhookStore
+) -> Result<AllWebhookDeliveriesViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    if webhooks.get_subscription(model.get_id()).is_none() {
+        return Err(IdentityError::InvalidRequest("the webhook doesn't exist".to_owned()))
+    }
+    Ok(AllWebhookDeliveriesViewModel::from_deliveries_vector(webhooks.get_deliveries_of_subscription(model.get_id(), Some(DELIVERY_LOG_LIMIT))))
+}
+
+/**
+ * Admin function that posts a delivery again right away, also one that was delivered or has failed. When the attempt fails the delivery is retried as a new pending delivery.
+ */
+pub fn redeliver(
+    token : &str,
+    model : DeliveryIdViewModel,
+    db : Store,
+    clients : ClientStore,
+    tokens : TokenStore,
+    webhooks : WebhookStore
+) -> Result<WebhookDeliveryViewModel, IdentityError> {
+    admin_service::control_admin_token(token, &db, &clients, &tokens)?;
+    let mut delivery = webhooks.get_delivery(model.get_id()).ok_or_else(|| IdentityError::InvalidRequest("the delivery doesn't exist".to_owned()))?;
+    delivery.redeliver();
+    let max_attempts = delivery.get_attempts().len() + *MAX_ATTEMPTS;
+    deliver(delivery, &webhooks, max_attempts, *BACKOFF).map(|delivery| WebhookDeliveryViewModel::from_delivery(&delivery))
+}
+
+#[cfg(test)]
+mod receiver {
+    use std::io::{ BufRead, BufReader, Read, Write };
+    use std::net::TcpListener;
+    use std::sync::{ Arc, Mutex };
+
+    /**
+     * Posts the local receiver got: the headers in lower case and the body.
+     */
+    pub type Received = Arc<Mutex<Vec<(Vec<(String, String)>, String)>>>;
+
+    /**
+     * Starts an HTTP receiver on a local port that answers the posts with the given status codes in turn, returns its url and what it received.
+     */
+    pub fn start(statuses : Vec<u16>) -> (String, Received) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let received : Received = Arc::new(Mutex::new(Vec::new()));
+        let server_received = received.clone();
+        std::thread::spawn(move || {
+            for (stream, status) in listener.incoming().zip(statuses.into_iter().cycle()) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                reader.read_line(&mut String::new()).unwrap();
+                let mut headers = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() { break }
+                    if let Some((name, value)) = line.split_once(':') {
+                        headers.push((name.trim().to_lowercase(), value.trim().to_owned()));
+                    }
+                }
+                let length = headers.iter().find(|(name, _)| name == "content-length").map_or(0, |(_, value)| value.parse().unwrap());
+                let mut body = String::new();
+                reader.by_ref().take(length).read_to_string(&mut body).unwrap();
+                server_received.lock().unwrap().push((headers, body));
+                write!(stream, "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).unwrap();
+            }
+        });
+        (url, received)
+    }
+}
+
+#[test]
+fn test_webhook_delivery() {
+    use identity_dal::repo::user_config::UserConfig;
+    use identity_dal::user::identity_user::IdentityUser;
+    use identity_dal::webhook::webhook_delivery::DeliveryStatus;
+
+    let config = UserConfig::new_config("", "person", 100000);
+    let (db, webhooks) = (Store::new_db(config.clone()), WebhookStore::new_db(config));
+    let (url, received) = receiver::start(vec![500, 200]);
+    assert!(make_subscription(&CreateWebhookViewModel::new("ftp://corp.be", &["user.registered"]), "ADMIN").is_err());
+    assert!(make_subscription(&CreateWebhookViewModel::new(&url, &["user.logged_in"]), "ADMIN").is_err());
+    let subscription = make_subscription(&CreateWebhookViewModel::new(&url, &["user.registered", "USER.EMAIL_CHANGED"]), "ADMIN").unwrap();
+    webhooks.save_subscription(&subscription).unwrap();
+
+    let jane = db.add_user(IdentityUser::new_user("jane@corp.be", "jane", "Passw0rd!").unwrap()).unwrap();
+    publish(&webhooks, WebhookEventType::RolesChanged, jane.get_id(), &db);
+    publish(&webhooks, WebhookEventType::UserRegistered, jane.get_id(), &db);
+    assert_eq!(deliver_due(&webhooks, 3, 0), 0);
+    assert_eq!(deliver_due(&webhooks, 3, 0), 1);
+    let delivery = &webhooks.get_deliveries_of_subscription(subscription.get_id(), None)[0];
+    assert_eq!((delivery.get_status(), delivery.get_attempts().len()), (&DeliveryStatus::Delivered, 2));
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+    let (headers, body) = &received[1];
+    let header = |name : &str| headers.iter().find(|(header, _)| header == &name.to_lowercase()).map(|(_, value)| value.as_str());
+    assert_eq!(header(SIGNATURE_HEADER), Some(sign_payload(subscription.get_secret(), body).as_str()));
+    assert_eq!(header(EVENT_HEADER), Some("user.registered"));
+    let payload : serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(payload["data"]["email"], "jane@corp.be");
+    drop(received);
+
+    let mut changed = jane.clone();
+    changed.set_email("jane@other.be").unwrap();
+    db.update_user(jane.get_id(), &changed).unwrap();
+    publish_email_change(&webhooks, jane.get_id(), Some("jane@other.be"), &db);
+    assert!(webhooks.get_due_deliveries(i64::MAX).is_empty());
+    publish_email_change(&webhooks, jane.get_id(), Some("jane@corp.be"), &db);
+    assert_eq!(webhooks.get_due_deliveries(i64::MAX)[0].get_event_type(), WebhookEventType::EmailChanged);
+}
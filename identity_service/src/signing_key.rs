@@ -0,0 +1,199 @@
+use jsonwebtoken::{encode, Algorithm, Header};
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MsbOption};
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::rsa::Rsa;
+use openssl::sign::Signer;
+use openssl::x509::{X509, X509NameBuilder};
+use serde::Serialize;
+use crate::store::StoreManager;
+use crate::util::get_value_from_key;
+use crate::IdentityError;
+
+/**
+ * Name under which a generated signing key is kept in the key store.
+ */
+static SIGNING_KEY_NAME : &str = "oidc_rsa";
+
+/**
+ * RSA key that is used to sign id tokens with RS256. The public part is published as a JWK so clients can verify the tokens.
+ *
+ * Attributes:
+ * * der: the PKCS#1 DER encoded private key
+ * * kid: id of the key, derived from the public key
+ * * n: base64url encoded modulus
+ * * e: base64url encoded public exponent
+ */
+#[derive(Clone)]
+pub struct SigningKey {
+    der : Vec<u8>,
+    kid : String,
+    n : String,
+    e : String
+}
+
+/**
+ * Public key in the JSON Web Key format.
+ */
+#[derive(Serialize)]
+pub struct JsonWebKey {
+    kty : String,
+    #[serde(rename = "use")]
+    key_use : String,
+    alg : String,
+    kid : String,
+    n : String,
+    e : String
+}
+
+/**
+ * Set of JSON Web Keys as published on the jwks uri.
+ */
+#[derive(Serialize)]
+pub struct JsonWebKeySet {
+    keys : Vec<JsonWebKey>
+}
+
+impl SigningKey {
+    /**
+     * Loads the signing key. When PERSON_OIDC_PRIVATE_KEY points to a PEM encoded RSA key that one is used, otherwise the key kept in the sled database is used. If there is none yet, a new key is generated and stored so tokens stay valid after a restart.
+     */
+    pub fn load(manager : &StoreManager) -> SigningKey {
+        if let Some(path) = get_value_from_key("PERSON_OIDC_PRIVATE_KEY") {
+            let pem = std::fs::read(&path).expect("Could not read the file of PERSON_OIDC_PRIVATE_KEY");
+            let rsa = Rsa::private_key_from_pem(&pem).expect("PERSON_OIDC_PRIVATE_KEY is not a PEM encoded RSA private key");
+            info!("The signing key has been loaded from {}", &path);
+            return SigningKey::from_der(&rsa.private_key_to_der().expect("Could not encode the signing key"))
+                .expect("Could not use the key of PERSON_OIDC_PRIVATE_KEY")
+        }
+        let store = manager.give_key_store();
+        if let Some(der) = store.get_key(SIGNING_KEY_NAME) {
+            return SigningKey::from_der(&der).expect("The signing key in the sled database is corrupt")
+        }
+        let key = SigningKey::generate().expect("Could not generate a signing key");
+        store.insert_key(SIGNING_KEY_NAME, &key.der).expect("Could not store the generated signing key");
+        info!("A new signing key has been generated. kid: {}", &key.kid);
+        key
+    }
+
+    /**
+     * Generates a new 2048 bit RSA key.
+     */
+    pub fn generate() -> Result<SigningKey, IdentityError> {
+        let rsa = Rsa::generate(2048).map_err(|e| IdentityError::CustomError(format!("{}", e)))?;
+        SigningKey::from_der(&rsa.private_key_to_der().map_err(|e| IdentityError::CustomError(format!("{}", e)))?)
+    }
+
+    /**
+     * Makes a signing key out of a PKCS#1 DER encoded RSA private key.
+     */
+    pub fn from_der(der : &[u8]) -> Result<SigningKey, IdentityError> {
+        let rsa = Rsa::private_key_from_der(der).map_err(|e| IdentityError::CustomError(format!("{}", e)))?;
+        let n = rsa.n().to_vec();
+        let e = rsa.e().to_vec();
+        let thumbprint = ring::digest::digest(&ring::digest::SHA256, &[n.as_slice(), e.as_slice()].concat());
+        Ok(SigningKey {
+            der : der.to_vec(),
+            kid : base64::encode_config(&thumbprint.as_ref()[..12], base64::URL_SAFE_NO_PAD),
+            n : base64::encode_config(&n, base64::URL_SAFE_NO_PAD),
+            e : base64::encode_config(&e, base64::URL_SAFE_NO_PAD)
+        })
+    }
+
+    pub fn get_kid(&self) -> &str { &self.kid }
+
+    /**
+     * Signs the claims as a RS256 JWT, the key id is put in the header.
+     */
+    pub fn sign<T: Serialize>(&self, claims : &T) -> Result<String, IdentityError> {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(self.kid.clone());
+        encode(&header, claims, &self.der).map_err(|e| {
+            warn!("Claims couldn't be signed with the signing key. Reason: {}", e);
+            IdentityError::TokenCannotBeMadeFromClaim
+        })
+    }
+
+    fn private_key(&self) -> Result<PKey<Private>, IdentityError> {
+        Rsa::private_key_from_der(&self.der)
+            .and_then(PKey::from_rsa)
+            .map_err(|e| IdentityError::CustomError(format!("{}", e)))
+    }
+
+    /**
+     * Signs the data with RSA PKCS#1 v1.5 and SHA-256, as is done for XML signatures.
+     */
+    pub fn sign_rsa_sha256(&self, data : &[u8]) -> Result<Vec<u8>, IdentityError> {
+        let key = self.private_key()?;
+        let mut signer = Signer::new(MessageDigest::sha256(), &key).map_err(|e| IdentityError::CustomError(format!("{}", e)))?;
+        signer.update(data).map_err(|e| IdentityError::CustomError(format!("{}", e)))?;
+        signer.sign_to_vec().map_err(|e| IdentityError::CustomError(format!("{}", e)))
+    }
+
+    /**
+     * Makes a self signed X.509 certificate of the key that is valid for ten years, returned DER encoded.
+     */
+    pub fn self_signed_certificate(&self, common_name : &str) -> Result<Vec<u8>, IdentityError> {
+        let to_error = |e : openssl::error::ErrorStack| IdentityError::CustomError(format!("{}", e));
+        let key = self.private_key()?;
+        let mut name = X509NameBuilder::new().map_err(to_error)?;
+        name.append_entry_by_text("CN", common_name).map_err(to_error)?;
+        let name = name.build();
+        let mut serial = BigNum::new().map_err(to_error)?;
+        serial.rand(127, MsbOption::MAYBE_ZERO, false).map_err(to_error)?;
+        let serial = serial.to_asn1_integer().map_err(to_error)?;
+        let mut builder = X509::builder().map_err(to_error)?;
+        builder.set_version(2).map_err(to_error)?;
+        builder.set_serial_number(&serial).map_err(to_error)?;
+        builder.set_subject_name(&name).map_err(to_error)?;
+        builder.set_issuer_name(&name).map_err(to_error)?;
+        builder.set_pubkey(&key).map_err(to_error)?;
+        let not_before = Asn1Time::days_from_now(0).map_err(to_error)?;
+        let not_after = Asn1Time::days_from_now(3650).map_err(to_error)?;
+        builder.set_not_before(&not_before).map_err(to_error)?;
+        builder.set_not_after(&not_after).map_err(to_error)?;
+        builder.sign(&key, MessageDigest::sha256()).map_err(to_error)?;
+        builder.build().to_der().map_err(to_error)
+    }
+
+    /**
+     * Returns true if the DER encoded certificate is one of this key.
+     */
+    pub fn is_certificate_of_key(&self, certificate : &[u8]) -> bool {
+        match (X509::from_der(certificate).and_then(|cert| cert.public_key()), self.private_key()) {
+            (Ok(public), Ok(private)) => public.public_eq(&private),
+            _ => false
+        }
+    }
+
+    /**
+     * Returns the public key as a JSON Web Key Set.
+     */
+    pub fn jwks(&self) -> JsonWebKeySet {
+        JsonWebKeySet {
+            keys : vec![JsonWebKey {
+                kty : "RSA".to_owned(),
+                key_use : "sig".to_owned(),
+                alg : "RS256".to_owned(),
+                kid : self.kid.clone(),
+                n : self.n.clone(),
+                e : self.e.clone()
+            }]
+        }
+    }
+}
+
+#[test]
+fn test_sign_and_verify() {
+    use jsonwebtoken::{decode, Validation};
+    #[derive(Serialize, serde::Deserialize)]
+    struct Test { sub : String, exp : i64 }
+
+    let key = SigningKey::generate().unwrap();
+    let token = key.sign(&Test { sub : "user".to_owned(), exp : chrono::Utc::now().timestamp() + 60 }).unwrap();
+    let public_der = Rsa::private_key_from_der(&key.der).unwrap().public_key_to_der_pkcs1().unwrap();
+    let decoded = decode::<Test>(&token, &public_der, &Validation::new(Algorithm::RS256)).unwrap();
+    assert_eq!(decoded.claims.sub, "user");
+    assert_eq!(decoded.header.kid.as_deref(), Some(key.get_kid()));
+}
@@ -1,9 +1,25 @@
 use identity_dal::repo::user_config::UserConfig;
 use identity_dal::repo::user_repo::UserStore;
+use identity_dal::repo::client_repo::ClientStore;
+use identity_dal::repo::code_repo::CodeStore;
+use identity_dal::repo::key_repo::KeyStore;
+use identity_dal::repo::token_repo::TokenStore;
+use identity_dal::repo::device_repo::DeviceStore;
+use identity_dal::repo::federation_repo::FederationStore;
+use identity_dal::repo::saml_repo::SamlStore;
+use identity_dal::repo::ldap_repo::LdapStore;
+use identity_dal::repo::group_repo::GroupStore;
+use identity_dal::repo::scim_repo::ScimStore;
+use identity_dal::repo::magic_link_repo::MagicLinkStore;
+use identity_dal::repo::invitation_repo::InvitationStore;
+use identity_dal::repo::registration_repo::RegistrationStore;
+use identity_dal::repo::audit_repo::AuditStore;
+use identity_dal::repo::webhook_repo::WebhookStore;
+use identity_dal::repo::change_feed_repo::ChangeFeedStore;
+use identity_dal::repo::mail_outbox_repo::MailOutboxStore;
 use identity_dal::traits::t_user_manager::UserStoreTrait;
 use crate::IdentityError;
 use crate::util::get_value_from_key;
-use crate::service::mail_service::MailTransport;
 /**
  * Struct used to provide user store to manage user's to those who want to. The struct has a config this will be used to give out the different stores.
 */
@@ -56,6 +72,125 @@ impl StoreManager {
         UserStore::new_db(self.0.clone())
     }
 
+    /**
+     * The store manager sends out a store of the registered OAuth clients
+     */
+    pub fn give_client_store(&self) -> ClientStore {
+        ClientStore::new_db(self.0.clone())
+    }
+
+    /**
+     * The store manager sends out a store of the pending authorization codes
+     */
+    pub fn give_code_store(&self) -> CodeStore {
+        CodeStore::new_db(self.0.clone())
+    }
+
+    /**
+     * The store manager sends out a store of the server's own keys
+     */
+    pub fn give_key_store(&self) -> KeyStore {
+        KeyStore::new_db(self.0.clone())
+    }
+
+    /**
+     * The store manager sends out a store of the refresh tokens and revoked access tokens
+     */
+    pub fn give_token_store(&self) -> TokenStore {
+        TokenStore::new_db(self.0.clone())
+    }
+
+    /**
+     * The store manager sends out a store of the pending device authorizations
+     */
+    pub fn give_device_store(&self) -> DeviceStore {
+        DeviceStore::new_db(self.0.clone())
+    }
+
+    /**
+     * The store manager sends out a store of the linked external identities and pending federated logins
+     */
+    pub fn give_federation_store(&self) -> FederationStore {
+        FederationStore::new_db(self.0.clone())
+    }
+
+    /**
+     * The store manager sends out a store of the registered SAML service providers
+     */
+    pub fn give_saml_store(&self) -> SamlStore {
+        SamlStore::new_db(self.0.clone())
+    }
+
+    /**
+     * The store manager sends out a store of the LDAP accounts of the users
+     */
+    pub fn give_ldap_store(&self) -> LdapStore {
+        LdapStore::new_db(self.0.clone())
+    }
+
+    /**
+     * The store manager sends out a store of the groups of users
+     */
+    pub fn give_group_store(&self) -> GroupStore {
+        GroupStore::new_db(self.0.clone())
+    }
+
+    /**
+     * The store manager sends out a store of the SCIM data of users
+     */
+    pub fn give_scim_store(&self) -> ScimStore {
+        ScimStore::new_db(self.0.clone())
+    }
+
+    /**
+     * The store manager sends out a store of the magic links that were mailed to users
+     */
+    pub fn give_magic_link_store(&self) -> MagicLinkStore {
+        MagicLinkStore::new_db(self.0.clone())
+    }
+
+    /**
+     * The store manager sends out a store of the invitations
+     */
+    pub fn give_invitation_store(&self) -> InvitationStore {
+        InvitationStore::new_db(self.0.clone())
+    }
+
+    /**
+     * The store manager sends out a store of the registrations that wait for approval
+     */
+    pub fn give_registration_store(&self) -> RegistrationStore {
+        RegistrationStore::new_db(self.0.clone())
+    }
+
+    /**
+     * The store manager sends out the store of the audit log
+     */
+    pub fn give_audit_store(&self) -> AuditStore {
+        AuditStore::new_db(self.0.clone())
+    }
+
+    /**
+     * The store manager sends out a store of the webhook subscriptions and the outbox of their deliveries
+     */
+    pub fn give_webhook_store(&self) -> WebhookStore {
+        WebhookStore::new_db(self.0.clone())
+    }
+
+    /**
+     * The store manager sends out a store of the change feed of the user records
+     */
+    pub fn give_change_feed_store(&self) -> ChangeFeedStore {
+        ChangeFeedStore::new_db(self.0.clone())
+    }
+
+    /**
+     * The store manager sends out the store of the outbox of the mails
+     */
+    pub fn give_mail_outbox_store(&self) -> MailOutboxStore {
+        MailOutboxStore::new_db(self.0.clone())
+    }
+
     /**
      * Uses the database and generates a string id
      */
@@ -75,5 +210,3 @@ impl StoreManager {
  * type representing the user store
  */
 pub type Store = UserStore;
-
-pub type UserDelegate = Option<fn(id : &str,store : &Store, &MailTransport) -> Result<(),IdentityError>>;
\ No newline at end of file
@@ -33,4 +33,54 @@ pub mod jwt_numeric_date {
             .single()
             .ok_or_else(|| serde::de::Error::custom("invalid Unix timestamp value"))
     }
-}
\ No newline at end of file
+}
+
+/**
+ * Percent encodes a string so it can be used as a query parameter value. Only the unreserved characters of RFC 3986 are left as they are.
+ */
+pub fn url_encode(value : &str) -> String {
+    value.bytes().map(|byte| match byte {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+        _ => format!("%{:02X}", byte)
+    }).collect()
+}
+
+/**
+ * Adds query parameters to an uri, the values are percent encoded.
+ */
+pub fn append_query(uri : &str, params : &[(&str, &str)]) -> String {
+    let query : Vec<String> = params.iter()
+        .map(|(key, value)| format!("{}={}", key, url_encode(value)))
+        .collect();
+    if query.is_empty() {
+        return uri.to_owned()
+    }
+    let separator = if uri.contains('?') { '&' } else { '?' };
+    format!("{}{}{}", uri, separator, query.join("&"))
+}
+
+/**
+ * Decodes the value of a HTTP Basic authorization header in a username and password.
+ */
+pub fn decode_basic_auth(header : &str) -> Option<(String, String)> {
+    let encoded = header.strip_prefix("Basic ")?.trim();
+    let decoded = String::from_utf8(base64::decode(encoded).ok()?).ok()?;
+    let index = decoded.find(':')?;
+    Some((decoded[..index].to_owned(), decoded[index + 1..].to_owned()))
+}
+
+/**
+ * Returns the base64url encoded SHA256 hash of a token, under which the token is stored or looked up.
+ */
+pub fn hash_token(token : &str) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, token.as_bytes());
+    base64::encode_config(digest.as_ref(), base64::URL_SAFE_NO_PAD)
+}
+
+#[test]
+fn test_query_encoding() {
+    assert_eq!(append_query("https://app.be/cb", &[("code", "a b"), ("state", "x&y")]), "https://app.be/cb?code=a%20b&state=x%26y");
+    assert_eq!(append_query("https://app.be/cb?x=1", &[("code", "c")]), "https://app.be/cb?x=1&code=c");
+    assert_eq!(decode_basic_auth("Basic Y2xpZW50OnNlY3JldA=="), Some(("client".to_owned(), "secret".to_owned())));
+    assert_eq!(decode_basic_auth("Bearer token"), None);
+}
@@ -0,0 +1,15 @@
+use crate::viewmodels::oauth::register_client::RegisteredClientViewModel;
+use identity_dal::oauth::oauth_client::OAuthClient;
+
+#[derive(serde::Serialize)]
+pub struct AllClientsViewModel {
+    pub clients : Vec<RegisteredClientViewModel>
+}
+
+impl AllClientsViewModel {
+    pub fn from_clients_vector(clients : Vec<OAuthClient>) -> Self {
+        AllClientsViewModel {
+            clients : clients.iter().map(|client| RegisteredClientViewModel::from_client(client, None)).collect()
+        }
+    }
+}
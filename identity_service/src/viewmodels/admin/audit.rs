@@ -0,0 +1,100 @@
+use identity_dal::audit::audit_event::{ AuditEvent, AuditOutcome };
+use identity_dal::audit::audit_chain::ChainVerification;
+
+/**
+ * Admin viewmodel with the conditions to query the audit log by, see AuditQuery. The event type is given by its snake case name and the times as unix timestamps.
+ */
+#[derive(serde::Deserialize, Default)]
+pub struct AuditQueryViewModel {
+    pub user : Option<String>,
+    pub actor : Option<String>,
+    pub event_type : Option<String>,
+    pub from : Option<i64>,
+    pub to : Option<i64>,
+    pub limit : Option<usize>
+}
+
+/**
+ * Viewmodel of a recorded audit event, a failed action has the reason why it failed.
+ */
+#[derive(serde::Serialize)]
+pub struct AuditEventViewModel {
+    id : u64,
+    event_type : String,
+    action : String,
+    actor : Option<String>,
+    target : Option<String>,
+    timestamp : i64,
+    ip : Option<String>,
+    success : bool,
+    reason : Option<String>
+}
+
+impl AuditEventViewModel {
+    pub fn from_event(event : &AuditEvent) -> Self {
+        AuditEventViewModel {
+            id : event.get_id(),
+            event_type : event.get_event_type().get_name().to_owned(),
+            action : event.get_action().to_owned(),
+            actor : event.get_actor().map(str::to_owned),
+            target : event.get_target().map(str::to_owned),
+            timestamp : event.get_timestamp(),
+            ip : event.get_ip().map(str::to_owned),
+            success : event.is_success(),
+            reason : match event.get_outcome() {
+                AuditOutcome::Success => None,
+                AuditOutcome::Failure(reason) => Some(reason.to_owned())
+            }
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct AllAuditEventsViewModel {
+    pub events : Vec<AuditEventViewModel>
+}
+
+impl AllAuditEventsViewModel {
+    pub fn from_events_vector(events : Vec<AuditEvent>) -> Self {
+        AllAuditEventsViewModel {
+            events : events.iter().map(AuditEventViewModel::from_event).collect()
+        }
+    }
+}
+
+/**
+ * Admin viewmodel with the result of verifying the hash chain of the audit log, broken_at is the id of the first event whose link is broken.
+ */
+#[derive(serde::Serialize)]
+pub struct AuditChainViewModel {
+    pub intact : bool,
+    pub checked : usize,
+    pub last_id : Option<u64>,
+    pub last_hash : Option<String>,
+    pub broken_at : Option<u64>,
+    pub reason : Option<String>
+}
+
+impl AuditChainViewModel {
+    pub fn from_verification(verification : ChainVerification) -> Self {
+        AuditChainViewModel {
+            intact : verification.is_intact(),
+            checked : verification.checked,
+            last_id : verification.last_id,
+            last_hash : verification.last_hash,
+            broken_at : verification.broken_at,
+            reason : verification.reason
+        }
+    }
+}
+
+/**
+ * Admin viewmodel of an exported checkpoint: the file it was written to and the last event it covers.
+ */
+#[derive(serde::Serialize)]
+pub struct AuditCheckpointViewModel {
+    pub file : String,
+    pub last_id : u64,
+    pub last_hash : String,
+    pub entries : usize
+}
@@ -0,0 +1,11 @@
+/**
+ * Admin viewmodel containing the id of an OAuth client, used to rotate the secret of the client.
+ */
+#[derive(serde::Deserialize)]
+pub struct ClientIdViewModel {
+    client_id : String
+}
+
+impl ClientIdViewModel {
+    pub fn get_client_id(&self) -> &str { &self.client_id }
+}
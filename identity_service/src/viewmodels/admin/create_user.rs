@@ -6,6 +6,8 @@ pub struct AdminCreateUserViewModel {
     email_user: String,
     password: String,
     confirm_password: String,
+    #[serde(default)]
+    locale: Option<String>
 }
 
 impl AdminCreateUserViewModel {
@@ -20,4 +22,8 @@ impl AdminCreateUserViewModel {
     pub fn get_confirmed_password(&self) -> &str {
         &self.confirm_password
     }
+
+    pub fn get_locale(&self) -> Option<&str> {
+        self.locale.as_deref()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,37 @@
+use identity_dal::user::tombstone::Tombstone;
+use identity_dal::traits::t_user::UserTrait;
+
+/**
+ * Viewmodel of a deleted user that can still be restored until he is purged.
+ */
+#[derive(serde::Serialize)]
+pub struct DeletedUserViewModel {
+    user_id : String,
+    email : String,
+    deleted_at : i64,
+    purged_after : i64
+}
+
+impl DeletedUserViewModel {
+    pub fn from_tombstone(tombstone : &Tombstone, grace_period : i64) -> Self {
+        DeletedUserViewModel {
+            user_id : tombstone.get_id().to_owned(),
+            email : tombstone.get_user().get_email().to_owned(),
+            deleted_at : tombstone.get_deleted_at(),
+            purged_after : tombstone.get_deleted_at() + grace_period
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct AllDeletedUsersViewModel {
+    pub users : Vec<DeletedUserViewModel>
+}
+
+impl AllDeletedUsersViewModel {
+    pub fn from_tombstones_vector(tombstones : Vec<Tombstone>, grace_period : i64) -> Self {
+        AllDeletedUsersViewModel {
+            users : tombstones.iter().map(|tombstone| DeletedUserViewModel::from_tombstone(tombstone, grace_period)).collect()
+        }
+    }
+}
@@ -0,0 +1,14 @@
+/**
+ * Admin viewmodel used to disable an OAuth client or to enable it again.
+ */
+#[derive(serde::Deserialize)]
+pub struct DisableClientViewModel {
+    client_id : String,
+    disabled : bool
+}
+
+impl DisableClientViewModel {
+    pub fn get_client_id(&self) -> &str { &self.client_id }
+
+    pub fn is_disabled(&self) -> bool { self.disabled }
+}
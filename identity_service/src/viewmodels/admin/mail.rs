@@ -0,0 +1,73 @@
+use identity_dal::mail::outbox_mail::{ MailAttempt, OutboxMail };
+
+/**
+ * Viewmodel with the id of a mail of the outbox.
+ */
+#[derive(serde::Deserialize)]
+pub struct MailIdViewModel {
+    id : u64
+}
+
+impl MailIdViewModel {
+    pub fn new(id : u64) -> Self {
+        MailIdViewModel { id }
+    }
+
+    pub fn get_id(&self) -> u64 { self.id }
+}
+
+#[derive(serde::Serialize)]
+pub struct MailAttemptViewModel {
+    attempted_at : i64,
+    error : Option<String>
+}
+
+impl MailAttemptViewModel {
+    pub fn from_attempt(attempt : &MailAttempt) -> Self {
+        MailAttemptViewModel {
+            attempted_at : attempt.get_attempted_at(),
+            error : attempt.get_error().map(str::to_owned)
+        }
+    }
+}
+
+/**
+ * Viewmodel of a mail of the outbox with its log of attempts. The body isn't shown since it can hold a token of the recipient.
+ */
+#[derive(serde::Serialize)]
+pub struct OutboxMailViewModel {
+    pub id : u64,
+    recipient : String,
+    subject : String,
+    pub status : String,
+    attempts : Vec<MailAttemptViewModel>,
+    next_attempt_at : Option<i64>,
+    created_at : i64
+}
+
+impl OutboxMailViewModel {
+    pub fn from_mail(mail : &OutboxMail) -> Self {
+        OutboxMailViewModel {
+            id : mail.get_id(),
+            recipient : mail.get_content().recipient.clone(),
+            subject : mail.get_content().subject.clone(),
+            status : mail.get_status().get_name().to_owned(),
+            attempts : mail.get_attempts().iter().map(MailAttemptViewModel::from_attempt).collect(),
+            next_attempt_at : if mail.is_due(i64::MAX) { Some(mail.get_next_attempt_at()) } else { None },
+            created_at : mail.get_created_at()
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct AllOutboxMailsViewModel {
+    pub mails : Vec<OutboxMailViewModel>
+}
+
+impl AllOutboxMailsViewModel {
+    pub fn from_mails_vector(mails : Vec<OutboxMail>) -> Self {
+        AllOutboxMailsViewModel {
+            mails : mails.iter().map(OutboxMailViewModel::from_mail).collect()
+        }
+    }
+}
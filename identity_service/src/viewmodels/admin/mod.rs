@@ -2,4 +2,14 @@ pub mod create_user;
 pub mod delete_user;
 pub mod update_user;
 pub mod update_user_pwd;
-pub mod all_users;
\ No newline at end of file
+pub mod all_users;
+pub mod all_clients;
+pub mod client_id;
+pub mod disable_client;
+pub mod registration;
+pub mod suspend_user;
+pub mod deleted_user;
+pub mod audit;
+pub mod webhook;
+pub mod user_change;
+pub mod mail;
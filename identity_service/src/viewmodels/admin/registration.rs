@@ -0,0 +1,51 @@
+use identity_dal::onboarding::pending_registration::PendingRegistration;
+use identity_dal::traits::t_user::UserTrait;
+
+/**
+ * Admin viewmodel containing the id of a registration that waits for approval, used to approve or reject it.
+ */
+#[derive(serde::Deserialize)]
+pub struct RegistrationIdViewModel {
+    registration_id : String
+}
+
+impl RegistrationIdViewModel {
+    pub fn new(registration_id : &str) -> Self {
+        RegistrationIdViewModel { registration_id : registration_id.to_owned() }
+    }
+
+    pub fn get_id(&self) -> &str { &self.registration_id }
+}
+
+/**
+ * Viewmodel of a registration that waits for approval.
+ */
+#[derive(serde::Serialize)]
+pub struct PendingRegistrationViewModel {
+    registration_id : String,
+    email : String,
+    requested_at : i64
+}
+
+impl PendingRegistrationViewModel {
+    pub fn from_registration(registration : &PendingRegistration) -> Self {
+        PendingRegistrationViewModel {
+            registration_id : registration.get_id().to_owned(),
+            email : registration.get_user().get_email().to_owned(),
+            requested_at : registration.get_requested_at()
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct AllPendingRegistrationsViewModel {
+    pub registrations : Vec<PendingRegistrationViewModel>
+}
+
+impl AllPendingRegistrationsViewModel {
+    pub fn from_registrations_vector(registrations : Vec<PendingRegistration>) -> Self {
+        AllPendingRegistrationsViewModel {
+            registrations : registrations.iter().map(PendingRegistrationViewModel::from_registration).collect()
+        }
+    }
+}
@@ -0,0 +1,18 @@
+/**
+ * Viewmodel used by the admin to suspend an user, the reason is kept on the user.
+ */
+#[derive(serde::Deserialize)]
+pub struct SuspendUserViewModel {
+    user_id : String,
+    reason : String
+}
+
+impl SuspendUserViewModel {
+    pub fn new(user_id : &str, reason : &str) -> Self {
+        SuspendUserViewModel { user_id : user_id.to_owned(), reason : reason.to_owned() }
+    }
+
+    pub fn get_user_id(&self) -> &str { &self.user_id }
+
+    pub fn get_reason(&self) -> &str { &self.reason }
+}
@@ -1,5 +1,5 @@
 /**
- * Viewmodel containing a token and the user id that is going to get updated, and optionally has a email, first and last name, this is needed to update an user's information.
+ * Viewmodel containing a token and the user id that is going to get updated, and optionally has a email, first and last name and the locale of the mails, this is needed to update an user's information.
  */
 #[derive(serde::Deserialize)]
 pub struct AdminUpdateUserViewModel {
@@ -7,7 +7,9 @@ pub struct AdminUpdateUserViewModel {
     #[serde(default)]
     pub new_email: Option<String>,
     #[serde(default)]
-    pub new_user_name: Option<String>
+    pub new_user_name: Option<String>,
+    #[serde(default)]
+    pub new_locale: Option<String>
 }
 
 impl AdminUpdateUserViewModel {
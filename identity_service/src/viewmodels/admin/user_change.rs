@@ -0,0 +1,45 @@
+use identity_dal::user::user_change::UserChange;
+use crate::viewmodels::auth::person_info::PersonInfoViewModel;
+
+/**
+ * Admin viewmodel with where to read the change feed from: the changes after the cursor are returned. When there are none, the request waits up to the given amount of seconds for a new change.
+ */
+#[derive(serde::Deserialize, Default)]
+pub struct ChangeFeedQueryViewModel {
+    pub cursor : Option<u64>,
+    pub limit : Option<usize>,
+    pub wait : Option<u64>
+}
+
+/**
+ * Viewmodel of a change of an user record. The user is shown as he is now, a deleted user has none.
+ */
+#[derive(serde::Serialize)]
+pub struct UserChangeViewModel {
+    sequence : u64,
+    kind : String,
+    user_id : String,
+    changed_at : i64,
+    pub user : Option<PersonInfoViewModel>
+}
+
+impl UserChangeViewModel {
+    pub fn from_change(change : &UserChange, user : Option<PersonInfoViewModel>) -> Self {
+        UserChangeViewModel {
+            sequence : change.get_sequence(),
+            kind : change.get_kind().get_name().to_owned(),
+            user_id : change.get_user_id().to_owned(),
+            changed_at : change.get_changed_at(),
+            user
+        }
+    }
+}
+
+/**
+ * Viewmodel of a page of the change feed, the cursor is the one to read the next page with.
+ */
+#[derive(serde::Serialize)]
+pub struct UserChangesViewModel {
+    pub changes : Vec<UserChangeViewModel>,
+    pub cursor : u64
+}
@@ -0,0 +1,161 @@
+use identity_dal::webhook::webhook_delivery::{ DeliveryAttempt, DeliveryStatus, WebhookDelivery };
+use identity_dal::webhook::webhook_subscription::WebhookSubscription;
+
+/**
+ * Viewmodel used by the admin to subscribe an url to events, the events are given by their names like user.registered.
+ */
+#[derive(serde::Deserialize)]
+pub struct CreateWebhookViewModel {
+    url : String,
+    events : Vec<String>
+}
+
+impl CreateWebhookViewModel {
+    pub fn new(url : &str, events : &[&str]) -> Self {
+        CreateWebhookViewModel { url : url.to_owned(), events : events.iter().map(|event| (*event).to_owned()).collect() }
+    }
+
+    pub fn get_url(&self) -> &str { &self.url }
+
+    pub fn get_events(&self) -> &[String] { &self.events }
+}
+
+/**
+ * Viewmodel with the id of a webhook subscription.
+ */
+#[derive(serde::Deserialize)]
+pub struct WebhookIdViewModel {
+    id : String
+}
+
+impl WebhookIdViewModel {
+    pub fn new(id : &str) -> Self {
+        WebhookIdViewModel { id : id.to_owned() }
+    }
+
+    pub fn get_id(&self) -> &str { &self.id }
+}
+
+/**
+ * Viewmodel with the id of a webhook delivery.
+ */
+#[derive(serde::Deserialize)]
+pub struct DeliveryIdViewModel {
+    id : u64
+}
+
+impl DeliveryIdViewModel {
+    pub fn new(id : u64) -> Self {
+        DeliveryIdViewModel { id }
+    }
+
+    pub fn get_id(&self) -> u64 { self.id }
+}
+
+/**
+ * Viewmodel of a webhook subscription. The secret is only shown when the subscription is made.
+ */
+#[derive(serde::Serialize)]
+pub struct WebhookSubscriptionViewModel {
+    pub id : String,
+    url : String,
+    events : Vec<String>,
+    active : bool,
+    created_by : String,
+    created_at : i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret : Option<String>
+}
+
+impl WebhookSubscriptionViewModel {
+    pub fn from_subscription(subscription : &WebhookSubscription, with_secret : bool) -> Self {
+        WebhookSubscriptionViewModel {
+            id : subscription.get_id().to_owned(),
+            url : subscription.get_url().to_owned(),
+            events : subscription.get_events().iter().map(|event| event.get_name().to_owned()).collect(),
+            active : subscription.is_active(),
+            created_by : subscription.get_created_by().to_owned(),
+            created_at : subscription.get_created_at(),
+            secret : if with_secret { Some(subscription.get_secret().to_owned()) } else { None }
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct AllWebhookSubscriptionsViewModel {
+    pub webhooks : Vec<WebhookSubscriptionViewModel>
+}
+
+impl AllWebhookSubscriptionsViewModel {
+    pub fn from_subscriptions_vector(subscriptions : Vec<WebhookSubscription>) -> Self {
+        AllWebhookSubscriptionsViewModel {
+            webhooks : subscriptions.iter().map(|subscription| WebhookSubscriptionViewModel::from_subscription(subscription, false)).collect()
+        }
+    }
+}
+
+/**
+ * Viewmodel of one attempt in the delivery log.
+ */
+#[derive(serde::Serialize)]
+pub struct DeliveryAttemptViewModel {
+    attempted_at : i64,
+    status_code : Option<u16>,
+    error : Option<String>
+}
+
+impl DeliveryAttemptViewModel {
+    pub fn from_attempt(attempt : &DeliveryAttempt) -> Self {
+        DeliveryAttemptViewModel {
+            attempted_at : attempt.get_attempted_at(),
+            status_code : attempt.get_status_code(),
+            error : attempt.get_error().map(str::to_owned)
+        }
+    }
+}
+
+/**
+ * Viewmodel of a webhook delivery with its log of attempts, the status is pending, delivered or failed.
+ */
+#[derive(serde::Serialize)]
+pub struct WebhookDeliveryViewModel {
+    pub id : u64,
+    subscription_id : String,
+    event_type : String,
+    pub status : String,
+    attempts : Vec<DeliveryAttemptViewModel>,
+    next_attempt_at : Option<i64>,
+    created_at : i64
+}
+
+impl WebhookDeliveryViewModel {
+    pub fn from_delivery(delivery : &WebhookDelivery) -> Self {
+        let status = match delivery.get_status() {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed"
+        };
+        WebhookDeliveryViewModel {
+            id : delivery.get_id(),
+            subscription_id : delivery.get_subscription_id().to_owned(),
+            event_type : delivery.get_event_type().get_name().to_owned(),
+            status : status.to_owned(),
+            attempts : delivery.get_attempts().iter().map(DeliveryAttemptViewModel::from_attempt).collect(),
+            next_attempt_at : if delivery.get_status() == &DeliveryStatus::Pending { Some(delivery.get_next_attempt_at()) } else { None },
+            created_at : delivery.get_created_at()
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct AllWebhookDeliveriesViewModel {
+    pub deliveries : Vec<WebhookDeliveryViewModel>
+}
+
+impl AllWebhookDeliveriesViewModel {
+    pub fn from_deliveries_vector(deliveries : Vec<WebhookDelivery>) -> Self {
+        AllWebhookDeliveriesViewModel {
+            deliveries : deliveries.iter().map(WebhookDeliveryViewModel::from_delivery).collect()
+        }
+    }
+}
@@ -0,0 +1,18 @@
+/**
+ * Viewmodel used by an user to deactivate his own account, his password re-confirms his choice.
+ */
+#[derive(serde::Deserialize)]
+pub struct DeactivateViewModel {
+    password : String,
+    reason : Option<String>
+}
+
+impl DeactivateViewModel {
+    pub fn new(password : &str, reason : Option<&str>) -> Self {
+        DeactivateViewModel { password : password.to_owned(), reason : reason.map(str::to_owned) }
+    }
+
+    pub fn get_password(&self) -> &str { &self.password }
+
+    pub fn get_reason(&self) -> Option<&str> { self.reason.as_deref() }
+}
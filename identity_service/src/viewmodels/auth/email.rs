@@ -0,0 +1,17 @@
+/**
+ * Viewmodel that holds the email of an user, it is used to ask for a magic link.
+ */
+#[derive(serde::Deserialize,serde::Serialize)]
+pub struct EmailViewModel {
+    email : String
+}
+
+impl EmailViewModel {
+    pub fn new(email : &str) -> Self {
+        EmailViewModel {
+            email : email.to_owned()
+        }
+    }
+
+    pub fn get_email(&self) -> &str { &self.email }
+}
@@ -8,6 +8,13 @@ pub struct LoginViewModel {
 }
 
 impl LoginViewModel {
+    pub fn new(email : &str, password : &str) -> Self {
+        LoginViewModel {
+            email : email.to_owned(),
+            password : password.to_owned()
+        }
+    }
+
     pub fn get_email(&self) -> &str {
         &self.email
     }
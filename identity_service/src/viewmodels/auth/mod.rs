@@ -7,4 +7,6 @@ pub mod update_pwd;
 pub mod update_user;
 pub mod change_pwd;
 pub mod flag;
-pub mod user_id;
\ No newline at end of file
+pub mod user_id;
+pub mod email;
+pub mod deactivate;
\ No newline at end of file
@@ -12,6 +12,8 @@ use serde::{Serialize,Deserialize};
  * * first name of the user
  * * last name of the user
  * * flags of the user
+ * * lifecycle status of the user and since when, with its reason
+ * * locale the mails to the user are written in, none for the default locale
  */
 #[derive(Serialize,Deserialize)]
 pub struct PersonInfoViewModel {
@@ -19,7 +21,11 @@ pub struct PersonInfoViewModel {
     email: String,
     user_name : String,
     is_admin: bool,
-    flags : Vec<String>
+    flags : Vec<String>,
+    status : String,
+    status_reason : Option<String>,
+    status_since : Option<i64>,
+    locale : Option<String>
 }
 
 impl PersonInfoViewModel {
@@ -32,13 +38,23 @@ impl PersonInfoViewModel {
             email: user.get_email().to_string(),
             user_name : user.get_user_name().to_string(),
             is_admin: user.get_id() == RESERVED_ID,
-            flags : user.get_flag_list()
+            flags : user.get_flag_list(),
+            status : user.get_status().get_name().to_owned(),
+            status_reason : user.get_status().get_reason().map(str::to_owned),
+            status_since : user.get_status().get_since(),
+            locale : user.get_locale().map(str::to_owned)
         }
     }
 
+    pub fn get_id(&self) -> &str { &self.id }
+
     pub fn get_email(&self) -> &str { &self.email }
 
     pub fn get_user_name(&self) -> &str { &self.user_name }
 
     pub fn is_admin(&self) -> bool { self.is_admin }
+
+    pub fn get_status(&self) -> &str { &self.status }
+
+    pub fn get_locale(&self) -> Option<&str> { self.locale.as_deref() }
 }
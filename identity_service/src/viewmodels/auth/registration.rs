@@ -1,5 +1,5 @@
 /**
- * Viewmodel containing attributes email and password and their confirmation for registrating a new user.
+ * Viewmodel containing attributes email and password and their confirmation for registrating a new user, and optionally the locale his mails are written in.
  *
  * The password and their confirmation needs to be equal.
  */
@@ -8,9 +8,30 @@ pub struct RegistrationViewModel {
     email: String,
     password: String,
     confirm_password: String,
+    #[serde(default)]
+    locale: Option<String>
 }
 
 impl RegistrationViewModel {
+    pub fn new(email : &str, password : &str, confirm_password : &str) -> Self {
+        RegistrationViewModel {
+            email : email.to_owned(),
+            password : password.to_owned(),
+            confirm_password : confirm_password.to_owned(),
+            locale : None
+        }
+    }
+
+    /**
+     * Returns the viewmodel with the locale when none was chosen, e.g. the locale of the Accept-Language header of the request.
+     */
+    pub fn with_default_locale(mut self, locale : Option<&str>) -> Self {
+        if self.locale.is_none() {
+            self.locale = locale.map(str::to_owned);
+        }
+        self
+    }
+
     pub fn get_email(&self) -> &str {
         &self.email
     }
@@ -22,4 +43,8 @@ impl RegistrationViewModel {
     pub fn get_confirmed_password(&self) -> &str {
         &self.confirm_password
     }
+
+    pub fn get_locale(&self) -> Option<&str> {
+        self.locale.as_deref()
+    }
 }
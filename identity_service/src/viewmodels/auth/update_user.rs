@@ -1,8 +1,9 @@
 /**
- * Viewmodel containing a token, and optionally has a email, first and last name, this is needed to update an user's information.
+ * Viewmodel containing a token, and optionally has a email, first and last name and the locale of the mails, this is needed to update an user's information. An empty locale resets it to the default locale.
  */
 #[derive(serde::Deserialize)]
 pub struct UpdateUserViewModel {
     #[serde(default)] pub new_email: Option<String>,
     #[serde(default)] pub new_user_name: Option<String>,
+    #[serde(default)] pub new_locale: Option<String>,
 }
\ No newline at end of file
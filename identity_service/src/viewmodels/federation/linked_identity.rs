@@ -0,0 +1,36 @@
+use identity_dal::federation::linked_identity::LinkedIdentity;
+
+/**
+ * Viewmodel of an external identity that is linked to an user.
+ */
+#[derive(serde::Serialize)]
+pub struct LinkedIdentityViewModel {
+    provider : String,
+    subject : String,
+    email : String,
+    linked_at : i64
+}
+
+impl LinkedIdentityViewModel {
+    pub fn from_identity(identity : &LinkedIdentity) -> Self {
+        LinkedIdentityViewModel {
+            provider : identity.get_provider().to_owned(),
+            subject : identity.get_subject().to_owned(),
+            email : identity.get_email().to_owned(),
+            linked_at : identity.get_linked_at()
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct LinkedIdentitiesViewModel {
+    pub identities : Vec<LinkedIdentityViewModel>
+}
+
+impl LinkedIdentitiesViewModel {
+    pub fn from_identities_vector(identities : Vec<LinkedIdentity>) -> Self {
+        LinkedIdentitiesViewModel {
+            identities : identities.iter().map(LinkedIdentityViewModel::from_identity).collect()
+        }
+    }
+}
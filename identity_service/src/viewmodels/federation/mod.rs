@@ -0,0 +1,3 @@
+pub mod linked_identity;
+pub mod provider;
+pub mod unlink_identity;
\ No newline at end of file
@@ -0,0 +1,21 @@
+use crate::federation::UpstreamProvider;
+
+/**
+ * Viewmodel of an identity provider users can log in with, the login uri starts the login at the provider.
+ */
+#[derive(serde::Serialize)]
+pub struct ProviderViewModel {
+    name : String,
+    display_name : String,
+    login_uri : String
+}
+
+impl ProviderViewModel {
+    pub fn from_provider(provider : &UpstreamProvider) -> Self {
+        ProviderViewModel {
+            name : provider.get_name().to_owned(),
+            display_name : provider.get_display_name().to_owned(),
+            login_uri : format!("/federation/{}/login", provider.get_name())
+        }
+    }
+}
@@ -0,0 +1,14 @@
+/**
+ * Viewmodel used to unlink an external identity, the identity is known by its provider and subject.
+ */
+#[derive(serde::Deserialize)]
+pub struct UnlinkIdentityViewModel {
+    provider : String,
+    subject : String
+}
+
+impl UnlinkIdentityViewModel {
+    pub fn get_provider(&self) -> &str { &self.provider }
+
+    pub fn get_subject(&self) -> &str { &self.subject }
+}
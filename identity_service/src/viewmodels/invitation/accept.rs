@@ -0,0 +1,30 @@
+/**
+ * Viewmodel used by the invitee to accept an invitation with the token of his link, he chooses his own name and password.
+ */
+#[derive(serde::Deserialize)]
+pub struct AcceptInvitationViewModel {
+    token : String,
+    #[serde(default)]
+    user_name : String,
+    password : String,
+    confirm_password : String
+}
+
+impl AcceptInvitationViewModel {
+    pub fn new(token : &str, user_name : &str, password : &str, confirm_password : &str) -> Self {
+        AcceptInvitationViewModel {
+            token : token.to_owned(),
+            user_name : user_name.to_owned(),
+            password : password.to_owned(),
+            confirm_password : confirm_password.to_owned()
+        }
+    }
+
+    pub fn get_token(&self) -> &str { &self.token }
+
+    pub fn get_user_name(&self) -> &str { &self.user_name }
+
+    pub fn get_password(&self) -> &str { &self.password }
+
+    pub fn get_confirmed_password(&self) -> &str { &self.confirm_password }
+}
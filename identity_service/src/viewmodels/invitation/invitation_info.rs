@@ -0,0 +1,58 @@
+use identity_dal::onboarding::invitation::{ Invitation, InvitationStatus };
+
+/**
+ * Viewmodel of an invitation, the status is pending, accepted, revoked or expired.
+ */
+#[derive(serde::Serialize)]
+pub struct InvitationViewModel {
+    id : String,
+    email : String,
+    flags : Vec<String>,
+    group_ids : Vec<String>,
+    invited_by : String,
+    status : String,
+    user_id : Option<String>,
+    created_at : i64,
+    sent_at : i64,
+    expires_at : i64
+}
+
+impl InvitationViewModel {
+    pub fn from_invitation(invitation : &Invitation) -> Self {
+        let (status, user_id) = match invitation.get_status() {
+            InvitationStatus::Accepted { user_id, .. } => ("accepted", Some(user_id.to_owned())),
+            InvitationStatus::Revoked { .. } => ("revoked", None),
+            InvitationStatus::Pending if invitation.is_expired() => ("expired", None),
+            InvitationStatus::Pending => ("pending", None)
+        };
+        InvitationViewModel {
+            id : invitation.get_id().to_owned(),
+            email : invitation.get_email().to_owned(),
+            flags : invitation.get_flags().iter().cloned().collect(),
+            group_ids : invitation.get_group_ids().iter().cloned().collect(),
+            invited_by : invitation.get_invited_by().to_owned(),
+            status : status.to_owned(),
+            user_id,
+            created_at : invitation.get_created_at(),
+            sent_at : invitation.get_sent_at(),
+            expires_at : invitation.get_expires_at()
+        }
+    }
+
+    pub fn get_id(&self) -> &str { &self.id }
+
+    pub fn get_status(&self) -> &str { &self.status }
+}
+
+#[derive(serde::Serialize)]
+pub struct AllInvitationsViewModel {
+    pub invitations : Vec<InvitationViewModel>
+}
+
+impl AllInvitationsViewModel {
+    pub fn from_invitations_vector(invitations : Vec<Invitation>) -> Self {
+        AllInvitationsViewModel {
+            invitations : invitations.iter().map(InvitationViewModel::from_invitation).collect()
+        }
+    }
+}
@@ -0,0 +1,49 @@
+/**
+ * Viewmodel used to invite an email, the account that is made gets the flags and becomes a member of the groups. The invitation is mailed in the locale, which the account also gets.
+ */
+#[derive(serde::Deserialize)]
+pub struct InviteViewModel {
+    email : String,
+    #[serde(default)]
+    flags : Vec<String>,
+    #[serde(default)]
+    group_ids : Vec<String>,
+    #[serde(default)]
+    locale : Option<String>
+}
+
+impl InviteViewModel {
+    pub fn new(email : &str, flags : &[&str], group_ids : &[&str]) -> Self {
+        InviteViewModel {
+            email : email.to_owned(),
+            flags : flags.iter().map(|flag| flag.to_string()).collect(),
+            group_ids : group_ids.iter().map(|id| id.to_string()).collect(),
+            locale : None
+        }
+    }
+
+    pub fn with_locale(mut self, locale : &str) -> Self {
+        self.locale = Some(locale.to_owned());
+        self
+    }
+
+    pub fn get_email(&self) -> &str { &self.email }
+
+    pub fn get_flags(&self) -> &[String] { &self.flags }
+
+    pub fn get_group_ids(&self) -> &[String] { &self.group_ids }
+
+    pub fn get_locale(&self) -> Option<&str> { self.locale.as_deref() }
+}
+
+/**
+ * Viewmodel containing the id of an invitation, used to resend, expire or revoke it.
+ */
+#[derive(serde::Deserialize)]
+pub struct InvitationIdViewModel {
+    invitation_id : String
+}
+
+impl InvitationIdViewModel {
+    pub fn get_id(&self) -> &str { &self.invitation_id }
+}
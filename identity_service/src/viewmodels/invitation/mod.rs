@@ -0,0 +1,3 @@
+pub mod invite;
+pub mod invitation_info;
+pub mod accept;
\ No newline at end of file
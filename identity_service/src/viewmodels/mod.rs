@@ -1,2 +1,7 @@
 pub mod admin;
 pub mod auth;
+pub mod oauth;
+pub mod federation;
+pub mod saml;
+pub mod scim;
+pub mod invitation;
\ No newline at end of file
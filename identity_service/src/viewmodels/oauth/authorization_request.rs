@@ -0,0 +1,26 @@
+/**
+ * Viewmodel containing the parameters of an OAuth authorization request, these are passed through the query of /oauth/authorize and repeated in the login and consent form.
+ */
+#[derive(serde::Deserialize, serde::Serialize, Default, Clone)]
+pub struct AuthorizationRequestViewModel {
+    #[serde(default)] pub response_type : Option<String>,
+    #[serde(default)] pub client_id : Option<String>,
+    #[serde(default)] pub redirect_uri : Option<String>,
+    #[serde(default)] pub scope : Option<String>,
+    #[serde(default)] pub state : Option<String>,
+    #[serde(default)] pub code_challenge : Option<String>,
+    #[serde(default)] pub code_challenge_method : Option<String>,
+    #[serde(default)] pub nonce : Option<String>
+}
+
+impl AuthorizationRequestViewModel {
+    pub fn get_client_id(&self) -> &str { self.client_id.as_deref().unwrap_or_default() }
+
+    pub fn get_redirect_uri(&self) -> &str { self.redirect_uri.as_deref().unwrap_or_default() }
+
+    pub fn get_scope(&self) -> &str { self.scope.as_deref().unwrap_or_default() }
+
+    pub fn get_state(&self) -> Option<&str> { self.state.as_deref() }
+
+    pub fn get_nonce(&self) -> Option<&str> { self.nonce.as_deref() }
+}
@@ -0,0 +1,66 @@
+use identity_dal::oauth::device_authorization::DeviceAuthorization;
+use chrono::Utc;
+
+/**
+ * Viewmodel containing the parameters of a request to the /oauth/device_authorization endpoint.
+ */
+#[derive(serde::Deserialize, Default)]
+pub struct DeviceAuthorizationRequestViewModel {
+    #[serde(default)] pub client_id : Option<String>,
+    #[serde(default)] pub client_secret : Option<String>,
+    #[serde(default)] pub scope : Option<String>
+}
+
+impl DeviceAuthorizationRequestViewModel {
+    /**
+     * Overrides the client id and secret with those given through HTTP Basic authentication.
+     */
+    pub fn with_client_credentials(mut self, client_id : &str, client_secret : &str) -> Self {
+        self.client_id = Some(client_id.to_owned());
+        self.client_secret = Some(client_secret.to_owned());
+        self
+    }
+
+    pub fn get_scope(&self) -> &str { self.scope.as_deref().unwrap_or_default() }
+}
+
+/**
+ * Viewmodel returned by the /oauth/device_authorization endpoint. The device shows the user code and verification uri to the user and polls the token endpoint with the device code.
+ */
+#[derive(serde::Serialize)]
+pub struct DeviceAuthorizationResponseViewModel {
+    device_code : String,
+    user_code : String,
+    verification_uri : String,
+    verification_uri_complete : String,
+    expires_in : i64,
+    interval : i64
+}
+
+impl DeviceAuthorizationResponseViewModel {
+    pub fn from_device(device : &DeviceAuthorization, verification_uri : &str) -> Self {
+        DeviceAuthorizationResponseViewModel {
+            device_code : device.get_device_code().to_owned(),
+            user_code : device.get_display_user_code(),
+            verification_uri : verification_uri.to_owned(),
+            verification_uri_complete : crate::util::append_query(verification_uri, &[("user_code", &device.get_display_user_code())]),
+            expires_in : device.get_expires_at() - Utc::now().timestamp(),
+            interval : device.get_interval()
+        }
+    }
+}
+
+/**
+ * Viewmodel used by a logged in user to approve or deny the device authorization of a user code.
+ */
+#[derive(serde::Deserialize)]
+pub struct DeviceDecisionViewModel {
+    user_code : String,
+    approve : bool
+}
+
+impl DeviceDecisionViewModel {
+    pub fn get_user_code(&self) -> &str { &self.user_code }
+
+    pub fn is_approved(&self) -> bool { self.approve }
+}
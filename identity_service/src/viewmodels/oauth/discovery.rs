@@ -0,0 +1,58 @@
+/**
+ * Scopes that are understood by the OpenID Connect provider.
+ */
+pub static SCOPES_SUPPORTED : [&str; 3] = ["openid", "profile", "email"];
+
+/**
+ * Viewmodel of the OpenID Connect discovery document that is published on /.well-known/openid-configuration.
+ */
+#[derive(serde::Serialize)]
+pub struct DiscoveryViewModel {
+    issuer : String,
+    authorization_endpoint : String,
+    token_endpoint : String,
+    userinfo_endpoint : String,
+    jwks_uri : String,
+    introspection_endpoint : String,
+    revocation_endpoint : String,
+    device_authorization_endpoint : String,
+    scopes_supported : Vec<String>,
+    response_types_supported : Vec<String>,
+    grant_types_supported : Vec<String>,
+    subject_types_supported : Vec<String>,
+    id_token_signing_alg_values_supported : Vec<String>,
+    token_endpoint_auth_methods_supported : Vec<String>,
+    code_challenge_methods_supported : Vec<String>,
+    claims_supported : Vec<String>
+}
+
+fn to_strings(values : &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+impl DiscoveryViewModel {
+    /**
+     * Makes the discovery document, all endpoints are relative to the issuer.
+     */
+    pub fn new(issuer : &str) -> Self {
+        let base = issuer.trim_end_matches('/');
+        DiscoveryViewModel {
+            issuer : issuer.to_owned(),
+            authorization_endpoint : format!("{}/oauth/authorize", base),
+            token_endpoint : format!("{}/oauth/token", base),
+            userinfo_endpoint : format!("{}/oauth/userinfo", base),
+            jwks_uri : format!("{}/.well-known/jwks.json", base),
+            introspection_endpoint : format!("{}/oauth/introspect", base),
+            revocation_endpoint : format!("{}/oauth/revoke", base),
+            device_authorization_endpoint : format!("{}/oauth/device_authorization", base),
+            scopes_supported : to_strings(&SCOPES_SUPPORTED),
+            response_types_supported : to_strings(&["code"]),
+            grant_types_supported : to_strings(&["authorization_code", "refresh_token", "client_credentials", "urn:ietf:params:oauth:grant-type:device_code"]),
+            subject_types_supported : to_strings(&["public"]),
+            id_token_signing_alg_values_supported : to_strings(&["RS256"]),
+            token_endpoint_auth_methods_supported : to_strings(&["client_secret_basic", "client_secret_post", "none"]),
+            code_challenge_methods_supported : to_strings(&["S256"]),
+            claims_supported : to_strings(&["sub", "iss", "aud", "exp", "iat", "auth_time", "nonce", "at_hash", "name", "preferred_username", "email"])
+        }
+    }
+}
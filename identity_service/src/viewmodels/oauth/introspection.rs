@@ -0,0 +1,73 @@
+use crate::claim::Claim;
+use identity_dal::oauth::refresh_token::RefreshToken;
+
+/**
+ * Viewmodel returned by the /oauth/introspect endpoint. An inactive token only has the active member, nothing is told about why it isn't active.
+ */
+#[derive(serde::Serialize)]
+pub struct IntrospectionViewModel {
+    active : bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_id : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sub : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exp : Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iat : Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iss : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token_type : Option<String>
+}
+
+impl IntrospectionViewModel {
+    pub fn inactive() -> Self {
+        IntrospectionViewModel {
+            active : false,
+            scope : None,
+            client_id : None,
+            sub : None,
+            exp : None,
+            iat : None,
+            iss : None,
+            token_type : None
+        }
+    }
+
+    /**
+     * Describes an active access token.
+     */
+    pub fn from_claim(claim : &Claim) -> Self {
+        IntrospectionViewModel {
+            active : true,
+            scope : claim.scope.clone(),
+            client_id : claim.client_id.clone(),
+            sub : Some(claim.sub.clone()),
+            exp : Some(claim.exp.timestamp()),
+            iat : Some(claim.iat.timestamp()),
+            iss : Some(claim.iss.clone()),
+            token_type : Some("Bearer".to_owned())
+        }
+    }
+
+    /**
+     * Describes an active refresh token.
+     */
+    pub fn from_refresh_token(token : &RefreshToken) -> Self {
+        IntrospectionViewModel {
+            active : true,
+            scope : Some(token.get_scope().to_owned()),
+            client_id : Some(token.get_client_id().to_owned()),
+            sub : Some(token.get_user_id().to_owned()),
+            exp : Some(token.get_expires_at()),
+            iat : None,
+            iss : None,
+            token_type : Some("refresh_token".to_owned())
+        }
+    }
+
+    pub fn is_active(&self) -> bool { self.active }
+}
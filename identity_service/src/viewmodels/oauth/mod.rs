@@ -0,0 +1,9 @@
+pub mod register_client;
+pub mod authorization_request;
+pub mod token_request;
+pub mod token_response;
+pub mod user_info;
+pub mod discovery;
+pub mod token_reference;
+pub mod introspection;
+pub mod device_authorization;
@@ -0,0 +1,60 @@
+use identity_dal::oauth::oauth_client::OAuthClient;
+
+/**
+ * Admin viewmodel used to register a new OAuth client. A client is confidential by default, public clients (SPA's, mobile apps) need to set confidential on false. When no grant types are given the client can only use the authorization code grant, the scopes are those a client may ask for with any grant.
+ */
+#[derive(serde::Deserialize)]
+pub struct RegisterClientViewModel {
+    client_name : String,
+    #[serde(default)]
+    redirect_uris : Vec<String>,
+    #[serde(default = "default_confidential")]
+    confidential : bool,
+    #[serde(default)]
+    pub grant_types : Option<Vec<String>>,
+    #[serde(default)]
+    pub scopes : Vec<String>
+}
+
+fn default_confidential() -> bool { true }
+
+impl RegisterClientViewModel {
+    pub fn get_client_name(&self) -> &str { &self.client_name }
+
+    pub fn get_redirect_uris(&self) -> &[String] { &self.redirect_uris }
+
+    pub fn is_confidential(&self) -> bool { self.confidential }
+}
+
+/**
+ * Viewmodel containing the information of a registered client. The client secret is only filled in right after registering the client or rotating its secret, it can't be seen afterwards.
+ */
+#[derive(serde::Serialize)]
+pub struct RegisteredClientViewModel {
+    client_id : String,
+    client_name : String,
+    redirect_uris : Vec<String>,
+    confidential : bool,
+    grant_types : Vec<String>,
+    scopes : Vec<String>,
+    disabled : bool,
+    created_at : i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_secret : Option<String>
+}
+
+impl RegisteredClientViewModel {
+    pub fn from_client(client : &OAuthClient, secret : Option<String>) -> Self {
+        RegisteredClientViewModel {
+            client_id : client.get_client_id().to_owned(),
+            client_name : client.get_client_name().to_owned(),
+            redirect_uris : client.get_redirect_uris().to_vec(),
+            confidential : client.is_confidential(),
+            grant_types : client.get_grant_types().iter().cloned().collect(),
+            scopes : client.get_allowed_scopes().iter().cloned().collect(),
+            disabled : client.is_disabled(),
+            created_at : client.get_created_at(),
+            client_secret : secret
+        }
+    }
+}
@@ -0,0 +1,25 @@
+/**
+ * Viewmodel containing the parameters of a request to the /oauth/introspect or /oauth/revoke endpoint. The token type hint can be access_token or refresh_token, it only decides which kind of token is looked up first.
+ */
+#[derive(serde::Deserialize, Default)]
+pub struct TokenReferenceViewModel {
+    #[serde(default)] pub token : Option<String>,
+    #[serde(default)] pub token_type_hint : Option<String>,
+    #[serde(default)] pub client_id : Option<String>,
+    #[serde(default)] pub client_secret : Option<String>
+}
+
+impl TokenReferenceViewModel {
+    /**
+     * Overrides the client id and secret with those given through HTTP Basic authentication.
+     */
+    pub fn with_client_credentials(mut self, client_id : &str, client_secret : &str) -> Self {
+        self.client_id = Some(client_id.to_owned());
+        self.client_secret = Some(client_secret.to_owned());
+        self
+    }
+
+    pub fn get_token(&self) -> &str { self.token.as_deref().unwrap_or_default() }
+
+    pub fn is_refresh_token_hint(&self) -> bool { self.token_type_hint.as_deref() == Some("refresh_token") }
+}
@@ -0,0 +1,28 @@
+/**
+ * Viewmodel containing the parameters of a request to the /oauth/token endpoint. Which parameters are needed depends on the grant type.
+ */
+#[derive(serde::Deserialize, Default)]
+pub struct TokenRequestViewModel {
+    #[serde(default)] pub grant_type : Option<String>,
+    #[serde(default)] pub code : Option<String>,
+    #[serde(default)] pub redirect_uri : Option<String>,
+    #[serde(default)] pub code_verifier : Option<String>,
+    #[serde(default)] pub client_id : Option<String>,
+    #[serde(default)] pub client_secret : Option<String>,
+    #[serde(default)] pub scope : Option<String>,
+    #[serde(default)] pub refresh_token : Option<String>,
+    #[serde(default)] pub device_code : Option<String>
+}
+
+impl TokenRequestViewModel {
+    /**
+     * Overrides the client id and secret with those given through HTTP Basic authentication.
+     */
+    pub fn with_client_credentials(mut self, client_id : &str, client_secret : &str) -> Self {
+        self.client_id = Some(client_id.to_owned());
+        self.client_secret = Some(client_secret.to_owned());
+        self
+    }
+
+    pub fn get_grant_type(&self) -> &str { self.grant_type.as_deref().unwrap_or_default() }
+}
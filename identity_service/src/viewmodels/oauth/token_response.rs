@@ -0,0 +1,54 @@
+use crate::claim::Claim;
+use crate::IdentityError;
+
+/**
+ * Viewmodel returned by the /oauth/token endpoint when a token has been issued.
+ */
+#[derive(serde::Serialize)]
+pub struct TokenResponseViewModel {
+    access_token : String,
+    token_type : String,
+    expires_in : i64,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    scope : String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refresh_token : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id_token : Option<String>
+}
+
+impl TokenResponseViewModel {
+    /**
+     * Makes a bearer token response out of a claim.
+     */
+    pub fn from_claim(claim : &Claim) -> Result<Self, IdentityError> {
+        Ok(TokenResponseViewModel {
+            access_token : claim.token_from_user()?,
+            token_type : "Bearer".to_owned(),
+            expires_in : claim.expires_in(),
+            scope : claim.scope.clone().unwrap_or_default(),
+            refresh_token : None,
+            id_token : None
+        })
+    }
+
+    /**
+     * Adds an OpenID Connect id token to the response.
+     */
+    pub fn with_id_token(mut self, id_token : String) -> Self {
+        self.id_token = Some(id_token);
+        self
+    }
+
+    /**
+     * Adds a refresh token to the response.
+     */
+    pub fn with_refresh_token(mut self, refresh_token : String) -> Self {
+        self.refresh_token = Some(refresh_token);
+        self
+    }
+
+    pub fn get_access_token(&self) -> &str { &self.access_token }
+
+    pub fn get_refresh_token(&self) -> Option<&str> { self.refresh_token.as_deref() }
+}
@@ -0,0 +1,38 @@
+use crate::viewmodels::auth::person_info::PersonInfoViewModel;
+
+/**
+ * Viewmodel containing the OpenID Connect claims of a user. Which claims are filled in depends on the granted scopes:
+ * * profile: name and preferred_username
+ * * email: email
+ */
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct UserInfoViewModel {
+    pub sub : String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_username : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email : Option<String>
+}
+
+impl UserInfoViewModel {
+    /**
+     * Maps the person info of a user onto the claims that the space delimited scope allows.
+     */
+    pub fn from_person_info(person : &PersonInfoViewModel, scope : &str) -> Self {
+        let scopes : Vec<&str> = scope.split_whitespace().collect();
+        let mut info = UserInfoViewModel {
+            sub : person.get_id().to_owned(),
+            ..UserInfoViewModel::default()
+        };
+        if scopes.contains(&"profile") && !person.get_user_name().is_empty() {
+            info.name = Some(person.get_user_name().to_owned());
+            info.preferred_username = Some(person.get_user_name().to_owned());
+        }
+        if scopes.contains(&"email") {
+            info.email = Some(person.get_email().to_owned());
+        }
+        info
+    }
+}
@@ -0,0 +1,2 @@
+pub mod service_provider;
+pub mod saml_response;
\ No newline at end of file
@@ -0,0 +1,14 @@
+/**
+ * Viewmodel of a SAML response that has to be posted to the assertion consumer service of a service provider.
+ *
+ * Attributes:
+ * * destination: url of the assertion consumer service
+ * * saml_response: base64 encoded response
+ * * relay_state: state of the service provider that has to be sent back unchanged
+ */
+#[derive(serde::Serialize)]
+pub struct SamlResponseViewModel {
+    pub destination : String,
+    pub saml_response : String,
+    pub relay_state : Option<String>
+}
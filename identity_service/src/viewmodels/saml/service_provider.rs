@@ -0,0 +1,62 @@
+use identity_dal::saml::service_provider::ServiceProvider;
+
+/**
+ * Admin viewmodel used to register a SAML service provider with its metadata XML.
+ */
+#[derive(serde::Deserialize)]
+pub struct RegisterServiceProviderViewModel {
+    metadata : String
+}
+
+impl RegisterServiceProviderViewModel {
+    pub fn get_metadata(&self) -> &str { &self.metadata }
+}
+
+/**
+ * Admin viewmodel containing the entity id of a SAML service provider, used to remove the service provider.
+ */
+#[derive(serde::Deserialize)]
+pub struct EntityIdViewModel {
+    entity_id : String
+}
+
+impl EntityIdViewModel {
+    pub fn get_entity_id(&self) -> &str { &self.entity_id }
+}
+
+/**
+ * Viewmodel of a registered SAML service provider.
+ */
+#[derive(serde::Serialize)]
+pub struct ServiceProviderViewModel {
+    entity_id : String,
+    assertion_consumer_services : Vec<String>,
+    name_id_format : String,
+    created_at : i64
+}
+
+impl ServiceProviderViewModel {
+    pub fn from_provider(provider : &ServiceProvider) -> Self {
+        ServiceProviderViewModel {
+            entity_id : provider.get_entity_id().to_owned(),
+            assertion_consumer_services : provider.get_assertion_consumer_services().iter()
+                .map(|service| service.get_location().to_owned())
+                .collect(),
+            name_id_format : provider.get_name_id_format().to_owned(),
+            created_at : provider.get_created_at()
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct AllServiceProvidersViewModel {
+    pub service_providers : Vec<ServiceProviderViewModel>
+}
+
+impl AllServiceProvidersViewModel {
+    pub fn from_providers_vector(providers : Vec<ServiceProvider>) -> Self {
+        AllServiceProvidersViewModel {
+            service_providers : providers.iter().map(ServiceProviderViewModel::from_provider).collect()
+        }
+    }
+}
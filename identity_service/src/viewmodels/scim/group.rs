@@ -0,0 +1,34 @@
+use serde::{ Deserialize, Serialize };
+use super::user::ScimMetaViewModel;
+
+/**
+ * Reference to a member of a group, or to a group an user is a member of.
+ */
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScimMemberViewModel {
+    pub value : String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display : Option<String>,
+    #[serde(default, rename = "$ref", skip_serializing_if = "Option::is_none")]
+    pub reference : Option<String>
+}
+
+/**
+ * SCIM group resource, it is both what the provisioning client sends and what it gets back.
+ */
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimGroupViewModel {
+    #[serde(default)]
+    pub schemas : Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id : Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_id : Option<String>,
+    #[serde(default)]
+    pub display_name : String,
+    #[serde(default)]
+    pub members : Vec<ScimMemberViewModel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta : Option<ScimMetaViewModel>
+}
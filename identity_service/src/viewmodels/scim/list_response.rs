@@ -0,0 +1,30 @@
+use serde::Serialize;
+
+/**
+ * Parameters of a SCIM list request.
+ *
+ * Attributes:
+ * * filter: filter the resources have to match
+ * * start_index: 1-based index of the first resource that is returned
+ * * count: maximum amount of resources that is returned
+ */
+#[derive(Default)]
+pub struct ScimQueryViewModel {
+    pub filter : Option<String>,
+    pub start_index : Option<i64>,
+    pub count : Option<i64>
+}
+
+/**
+ * Page of resources returned by a SCIM list request.
+ */
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimListResponseViewModel<T : Serialize> {
+    pub schemas : Vec<String>,
+    pub total_results : usize,
+    pub start_index : usize,
+    pub items_per_page : usize,
+    #[serde(rename = "Resources")]
+    pub resources : Vec<T>
+}
@@ -0,0 +1,4 @@
+pub mod user;
+pub mod group;
+pub mod list_response;
+pub mod patch;
\ No newline at end of file
@@ -0,0 +1,24 @@
+use serde::Deserialize;
+
+/**
+ * Operation of a SCIM PATCH request.
+ */
+#[derive(Deserialize)]
+pub struct ScimPatchOperationViewModel {
+    pub op : String,
+    #[serde(default)]
+    pub path : Option<String>,
+    #[serde(default)]
+    pub value : Option<serde_json::Value>
+}
+
+/**
+ * SCIM PATCH request, its operations are applied in order.
+ */
+#[derive(Deserialize)]
+pub struct ScimPatchViewModel {
+    #[serde(default)]
+    pub schemas : Vec<String>,
+    #[serde(rename = "Operations")]
+    pub operations : Vec<ScimPatchOperationViewModel>
+}
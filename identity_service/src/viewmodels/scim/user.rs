@@ -0,0 +1,100 @@
+use serde::{ Deserialize, Serialize };
+use super::group::ScimMemberViewModel;
+
+/**
+ * Name of a SCIM user.
+ */
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimNameViewModel {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formatted : Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub given_name : Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family_name : Option<String>
+}
+
+/**
+ * Email of a SCIM user.
+ */
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScimEmailViewModel {
+    pub value : String,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub email_type : Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary : Option<bool>
+}
+
+/**
+ * Metadata of a SCIM resource.
+ */
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimMetaViewModel {
+    pub resource_type : String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created : Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified : Option<String>,
+    pub location : String
+}
+
+/**
+ * SCIM user resource, it is both what the provisioning client sends and what it gets back. The id, groups and meta are set by the server and ignored when they are sent, the password is never sent back.
+ */
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimUserViewModel {
+    #[serde(default)]
+    pub schemas : Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id : Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_id : Option<String>,
+    #[serde(default)]
+    pub user_name : String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name : Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name : Option<ScimNameViewModel>,
+    #[serde(default)]
+    pub emails : Vec<ScimEmailViewModel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferred_language : Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active : Option<bool>,
+    #[serde(default, skip_serializing)]
+    pub password : Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups : Vec<ScimMemberViewModel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta : Option<ScimMetaViewModel>
+}
+
+impl ScimUserViewModel {
+    /**
+     * Returns the email of the user: the primary email, otherwise the first one, otherwise the user name.
+     */
+    pub fn get_email(&self) -> &str {
+        self.emails.iter().find(|email| email.primary == Some(true))
+            .or_else(|| self.emails.first())
+            .map(|email| email.value.as_str())
+            .unwrap_or(&self.user_name)
+    }
+
+    /**
+     * Returns the name the user is shown with: the display name, otherwise the formatted name or the given and family name.
+     */
+    pub fn get_shown_name(&self) -> String {
+        if let Some(display_name) = self.display_name.as_deref().filter(|name| !name.is_empty()) {
+            return display_name.to_owned()
+        }
+        match &self.name {
+            Some(ScimNameViewModel { formatted : Some(formatted), .. }) => formatted.to_owned(),
+            Some(name) => [name.given_name.as_deref(), name.family_name.as_deref()].iter().flatten().cloned().collect::<Vec<&str>>().join(" "),
+            None => String::new()
+        }
+    }
+}
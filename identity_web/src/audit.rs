@@ -0,0 +1,56 @@
+use rocket::Outcome;
+use rocket::State;
+use rocket::http::Status;
+use rocket::request::{self, Request, FromRequest};
+use identity_service::service::audit_service::{ self, AuditEvent, AuditEventType, AuditStore };
+use identity_service::store::StoreManager;
+use crate::IdentityError;
+
+/**
+ * Records the security relevant actions of a request in the audit log, together with the address of the client that did the request.
+ */
+pub struct Auditor {
+    store : AuditStore,
+    ip : Option<String>
+}
+
+impl Auditor {
+    /**
+     * Records the result of an action that the actor did on the target.
+     */
+    pub fn record<T>(&self, event_type : AuditEventType, action : &str, actor : Option<&str>, target : Option<&str>, result : &Result<T, IdentityError>) {
+        audit_service::record(&self.store, AuditEvent::new(event_type, action, audit_service::outcome_of(result))
+            .with_actor(actor)
+            .with_target(target)
+            .with_ip(self.ip.as_deref()));
+    }
+
+    /**
+     * Records the result of an action that the user or client of the token did on the target.
+     */
+    pub fn record_by_token<T>(&self, event_type : AuditEventType, action : &str, token : &str, target : Option<&str>, result : &Result<T, IdentityError>) {
+        self.record(event_type, action, audit_service::actor_of_token(token).as_deref(), target, result);
+    }
+
+    /**
+     * Records the result of an action that the user of the token did on his own account.
+     */
+    pub fn record_for_token<T>(&self, event_type : AuditEventType, action : &str, token : &str, result : &Result<T, IdentityError>) {
+        let actor = audit_service::actor_of_token(token);
+        self.record(event_type, action, actor.as_deref(), actor.as_deref(), result);
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for Auditor {
+    type Error = IdentityError;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        match request.guard::<State<StoreManager>>() {
+            Outcome::Success(sled_db) => Outcome::Success(Auditor {
+                store : sled_db.give_audit_store(),
+                ip : request.client_ip().map(|ip| ip.to_string())
+            }),
+            _ => Outcome::Failure((Status::InternalServerError, IdentityError::CustomError("The audit log is not available".to_owned())))
+        }
+    }
+}
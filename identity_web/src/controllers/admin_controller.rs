@@ -1,12 +1,38 @@
 use rocket_contrib::json::{Json,JsonValue};
 use super::error_controller;
 use identity_service::store::StoreManager;
+use identity_service::hooks::HookRegistry;
 use identity_service::viewmodels::admin::create_user::AdminCreateUserViewModel;
 use identity_service::viewmodels::admin::delete_user::DeleteUserViewModel;
 use identity_service::viewmodels::admin::update_user_pwd::AdminChangePasswordUserViewModel;
 use identity_service::viewmodels::admin::update_user::AdminUpdateUserViewModel;
+use identity_service::viewmodels::oauth::register_client::RegisterClientViewModel;
+use identity_service::viewmodels::admin::client_id::ClientIdViewModel;
+use identity_service::viewmodels::admin::disable_client::DisableClientViewModel;
+use identity_service::viewmodels::auth::user_id::UserIdViewModel;
+use identity_service::viewmodels::saml::service_provider::{ EntityIdViewModel, RegisterServiceProviderViewModel };
 use identity_service::service::admin_service;
+use identity_service::service::oauth_service;
+use identity_service::service::saml_service;
+use identity_service::service::registration_service::{ self, RegistrationStores };
+use identity_service::service::mail_service;
+use identity_service::viewmodels::admin::mail::MailIdViewModel;
+use identity_service::viewmodels::admin::registration::RegistrationIdViewModel;
+use identity_service::viewmodels::admin::suspend_user::SuspendUserViewModel;
+use identity_service::service::status_service;
+use identity_service::service::deletion_service::{ self, PurgeStores };
+use crate::delegates;
 use crate::key::ApiKey;
+use crate::audit::Auditor;
+use crate::webhooks::Webhooks;
+use identity_service::service::audit_service::{ self, AuditEventType };
+use identity_service::signing_key::SigningKey;
+use identity_service::service::webhook_service::{ self, WebhookEventType };
+use identity_service::viewmodels::admin::webhook::{ CreateWebhookViewModel, DeliveryIdViewModel, WebhookIdViewModel };
+use identity_service::viewmodels::admin::audit::AuditQueryViewModel;
+use identity_service::service::change_feed_service;
+use identity_service::viewmodels::admin::user_change::ChangeFeedQueryViewModel;
+use rocket::request::LenientForm;
 use rocket::State;
 use rocket::Route;
 
@@ -16,7 +42,34 @@ pub fn routes() -> Vec<Route> {
         delete_user, 
         change_password, 
         update_user,
-        all_users
+        all_users,
+        user_changes,
+        linked_identities,
+        suspend_user,
+        reactivate_user,
+        deleted_users,
+        restore_deleted_user,
+        purge_deleted_user,
+        audit_events,
+        verify_audit_log,
+        export_audit_checkpoint,
+        create_webhook,
+        all_webhooks,
+        remove_webhook,
+        webhook_deliveries,
+        redeliver_webhook,
+        outbox_mails,
+        retry_mail,
+        register_client,
+        all_clients,
+        rotate_client_secret,
+        disable_client,
+        register_service_provider,
+        all_service_providers,
+        remove_service_provider,
+        pending_registrations,
+        approve_registration,
+        reject_registration
     ]
 }
 
@@ -24,8 +77,13 @@ pub fn routes() -> Vec<Route> {
  * Admin function used to register a new user with the help of the viewmodel AdminCreateUserViewModel, sends a json back to notify the requester if his request was succesfull or not.
 */
 #[post("/registration", format = "application/json", data = "<model>")]
-fn register_user(key : ApiKey, model : Json<AdminCreateUserViewModel>, sled_db : State<StoreManager>) -> JsonValue {
-    match admin_service::create_user(key.get_key(),model.0, &sled_db.give_unique_id(),sled_db.give_store()) {
+fn register_user(key : ApiKey, model : Json<AdminCreateUserViewModel>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor, webhooks : Webhooks) -> JsonValue {
+    let email = model.get_email().to_owned();
+    let id = sled_db.give_unique_id();
+    let result = admin_service::create_user(key.get_key(),model.0, &id,sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store(),&hooks);
+    auditor.record_by_token(AuditEventType::AdminAction, "create_user", key.get_key(), Some(&email), &result);
+    webhooks.publish_on(WebhookEventType::UserRegistered, &id, &result);
+    match result {
         Ok(_) => {
             info!("Admin has added user has been added");
             json!({
@@ -41,8 +99,16 @@ fn register_user(key : ApiKey, model : Json<AdminCreateUserViewModel>, sled_db :
  * Admin function used to update an user's email, first and last anem with the help of the viewmodel AdminUpdateUserViewModel, sends a json back to notify the requester if his request was succesfull or not.
 */
 #[put("/update", format = "application/json", data = "<model>")]
-fn update_user(key : ApiKey, model : Json<AdminUpdateUserViewModel>, sled_db : State<StoreManager>) -> JsonValue {
-    match admin_service::update_user(key.get_key(),model.0, sled_db.give_store()) {
+fn update_user(key : ApiKey, model : Json<AdminUpdateUserViewModel>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor, webhooks : Webhooks) -> JsonValue {
+    let target = model.get_user_id().to_owned();
+    let event_type = if model.new_email.is_some() { AuditEventType::EmailChange } else { AuditEventType::AdminAction };
+    let previous_email = webhooks.email_of(&target);
+    let result = admin_service::update_user(key.get_key(),model.0, sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store(),&hooks);
+    auditor.record_by_token(event_type, "admin_update_user", key.get_key(), Some(&target), &result);
+    if result.is_ok() {
+        webhooks.publish_email_change(&target, previous_email.as_deref());
+    }
+    match result {
         Ok(_) => {
             info!("Admin has successfully been updated an user");
             json!({
@@ -57,8 +123,12 @@ fn update_user(key : ApiKey, model : Json<AdminUpdateUserViewModel>, sled_db : S
  * Admin function used to delete an user, this will use user id in the viewmodel DeleteUserViewModel. Controls if the id exists or not and delete if it does. An error is thrown whent the token is empty or the user couldn't be deleted.
 */
 #[post("/delete", format = "application/json", data = "<model>")]
-fn delete_user(key : ApiKey,model : Json<DeleteUserViewModel>, sled_db : State<StoreManager>) -> JsonValue {
-    match admin_service::delete_user(key.get_key(),model.0,sled_db.give_store()) {
+fn delete_user(key : ApiKey,model : Json<DeleteUserViewModel>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor, webhooks : Webhooks) -> JsonValue {
+    let target = model.get_user_id().to_owned();
+    let result = admin_service::delete_user(key.get_key(),model.0,sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store(),&hooks);
+    auditor.record_by_token(AuditEventType::Deletion, "admin_delete_user", key.get_key(), Some(&target), &result);
+    webhooks.publish_on(WebhookEventType::UserDeleted, &target, &result);
+    match result {
         Ok(_) => {
             info!("Admin has been deleted user has been added");
             json!({
@@ -74,8 +144,11 @@ fn delete_user(key : ApiKey,model : Json<DeleteUserViewModel>, sled_db : State<S
  * Admin function changing the password of an user with the help of the viewmodel AdminChangePasswordUserViewModel,sends a json back to notify the requester if his request was succesfull or not.
 */
 #[put("/password", format = "application/json", data = "<model>")]
-fn change_password(key : ApiKey, model : Json<AdminChangePasswordUserViewModel>, sled_db : State<StoreManager>) -> JsonValue {
-    match admin_service::update_user_pwd(key.get_key(),model.0,sled_db.give_store()) {
+fn change_password(key : ApiKey, model : Json<AdminChangePasswordUserViewModel>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor) -> JsonValue {
+    let target = model.get_id_user().to_owned();
+    let result = admin_service::update_user_pwd(key.get_key(),model.0,sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store(),&hooks);
+    auditor.record_by_token(AuditEventType::PasswordChange, "admin_change_password", key.get_key(), Some(&target), &result);
+    match result {
         Ok(_) => {
             info!("Admin has changed the password of an user has been changed.");
             json!({
@@ -92,11 +165,493 @@ fn change_password(key : ApiKey, model : Json<AdminChangePasswordUserViewModel>,
  */
 #[post("/users", format = "application/json")]
 fn all_users(key : ApiKey,sled_db : State<StoreManager>) -> JsonValue {
-    match admin_service::get_all_users(key.get_key(),sled_db.give_store()) {
+    match admin_service::get_all_users(key.get_key(),sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store()) {
         Ok(users) => {
             info!("Admin has asked a json object of all users within.");
             json!(users)
         },
         Err(e) => error_controller::return_error_json(e,false)
     }
-}
\ No newline at end of file
+}
+
+/**
+ * Where to read the change feed of the users from, see ChangeFeedQueryViewModel. The cursor is the one returned by the previous page, wait is the amount of seconds to wait for a change when there is none.
+ */
+#[derive(FromForm)]
+struct ChangeFeedForm {
+    cursor : Option<u64>,
+    limit : Option<usize>,
+    wait : Option<u64>
+}
+
+/**
+ * Returns a json object with the changes of the user records after the cursor and the cursor of the next page. When there are no changes yet the request is held open until one happens or the wait time has passed.
+ */
+#[get("/users/changes?<query..>")]
+fn user_changes(key : ApiKey, query : LenientForm<ChangeFeedForm>, sled_db : State<StoreManager>) -> JsonValue {
+    let query = query.into_inner();
+    let model = ChangeFeedQueryViewModel { cursor : query.cursor, limit : query.limit, wait : query.wait };
+    match change_feed_service::get_changes(key.get_key(),model,sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store(),sled_db.give_change_feed_store()) {
+        Ok(page) => json!({
+            "ok" : true,
+            "changes" : page.changes,
+            "cursor" : page.cursor
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Returns a json object with the external identities that are linked to the user of the viewmodel UserIdViewModel.
+ */
+#[post("/identities", format = "application/json", data = "<model>")]
+fn linked_identities(key : ApiKey, model : Json<UserIdViewModel>, sled_db : State<StoreManager>) -> JsonValue {
+    match admin_service::get_linked_identities_of_user(key.get_key(),model.0,sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store(),sled_db.give_federation_store()) {
+        Ok(identities) => {
+            info!("Admin has asked the linked identities of an user.");
+            json!({
+                "ok" : true,
+                "identities" : identities.identities
+            })
+        },
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Admin function used to register an OAuth client with the help of the viewmodel RegisterClientViewModel. The returned json object contains the client id and for confidential clients the secret, which can't be retrieved afterwards.
+ */
+#[post("/clients", format = "application/json", data = "<model>")]
+fn register_client(key : ApiKey, model : Json<RegisterClientViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let result = oauth_service::register_client(key.get_key(),model.0,sled_db.give_client_store(),sled_db.give_store(),sled_db.give_token_store());
+    auditor.record_by_token(AuditEventType::AdminAction, "register_client", key.get_key(), None, &result);
+    match result {
+        Ok(client) => {
+            info!("Admin has registered an OAuth client");
+            json!({
+                "ok" : true,
+                "client" : client
+            })
+        },
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Returns a json object where all registered OAuth clients are presented in an array, their secrets aren't part of it.
+ */
+#[get("/clients")]
+fn all_clients(key : ApiKey, sled_db : State<StoreManager>) -> JsonValue {
+    match oauth_service::get_all_clients(key.get_key(),sled_db.give_client_store(),sled_db.give_store(),sled_db.give_token_store()) {
+        Ok(clients) => {
+            info!("Admin has asked a json object of all OAuth clients.");
+            json!(clients)
+        },
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Admin function that gives a confidential OAuth client a new secret. The returned json object contains the new secret, the old one can't be used anymore.
+ */
+#[put("/clients/secret", format = "application/json", data = "<model>")]
+fn rotate_client_secret(key : ApiKey, model : Json<ClientIdViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let result = oauth_service::rotate_client_secret(key.get_key(),model.0,sled_db.give_client_store(),sled_db.give_store(),sled_db.give_token_store());
+    auditor.record_by_token(AuditEventType::AdminAction, "rotate_client_secret", key.get_key(), None, &result);
+    match result {
+        Ok(client) => {
+            info!("Admin has rotated the secret of an OAuth client");
+            json!({
+                "ok" : true,
+                "client" : client
+            })
+        },
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Admin function used to disable an OAuth client or to enable it again with the help of the viewmodel DisableClientViewModel.
+ */
+#[put("/clients/disable", format = "application/json", data = "<model>")]
+fn disable_client(key : ApiKey, model : Json<DisableClientViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let result = oauth_service::set_client_disabled(key.get_key(),model.0,sled_db.give_client_store(),sled_db.give_store(),sled_db.give_token_store());
+    auditor.record_by_token(AuditEventType::AdminAction, "disable_client", key.get_key(), None, &result);
+    match result {
+        Ok(_) => {
+            info!("Admin has changed the disabled state of an OAuth client");
+            json!({
+                "ok" : true
+            })
+        },
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Admin function used to register a SAML service provider with its metadata XML, registering it again replaces its metadata.
+ */
+#[post("/saml", format = "application/json", data = "<model>")]
+fn register_service_provider(key : ApiKey, model : Json<RegisterServiceProviderViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let result = saml_service::register_service_provider(key.get_key(),model.0,sled_db.give_saml_store(),sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store());
+    auditor.record_by_token(AuditEventType::AdminAction, "register_service_provider", key.get_key(), None, &result);
+    match result {
+        Ok(provider) => json!({
+            "ok" : true,
+            "service_provider" : provider
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Returns a json object where all registered SAML service providers are presented in an array.
+ */
+#[get("/saml")]
+fn all_service_providers(key : ApiKey, sled_db : State<StoreManager>) -> JsonValue {
+    match saml_service::get_all_service_providers(key.get_key(),sled_db.give_saml_store(),sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store()) {
+        Ok(providers) => json!(providers),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Admin function used to remove a SAML service provider with the help of the viewmodel EntityIdViewModel.
+ */
+#[delete("/saml", format = "application/json", data = "<model>")]
+fn remove_service_provider(key : ApiKey, model : Json<EntityIdViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let result = saml_service::remove_service_provider(key.get_key(),model.0,sled_db.give_saml_store(),sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store());
+    auditor.record_by_token(AuditEventType::AdminAction, "remove_service_provider", key.get_key(), None, &result);
+    match result {
+        Ok(_) => json!({
+            "ok" : true
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Returns a json object where the registrations that wait for approval are presented in an array.
+ */
+#[get("/registrations")]
+fn pending_registrations(key : ApiKey, sled_db : State<StoreManager>) -> JsonValue {
+    match registration_service::get_pending_registrations(key.get_key(),sled_db.give_registration_store(),sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store()) {
+        Ok(registrations) => json!(registrations),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Admin function used to approve a registration with the help of the viewmodel RegistrationIdViewModel, the user is added and mailed about it.
+ */
+#[post("/registrations/approve", format = "application/json", data = "<model>")]
+fn approve_registration(key : ApiKey, model : Json<RegistrationIdViewModel>, sled_db : State<StoreManager>, auditor : Auditor, webhooks : Webhooks) -> JsonValue {
+    let target = model.get_id().to_owned();
+    let result = registration_service::approve_registration(key.get_key(),model.0,RegistrationStores { db : sled_db.give_store(), registrations : sled_db.give_registration_store() },sled_db.give_client_store(),sled_db.give_token_store(),&sled_db.give_mail_outbox_store(),delegates::registration_decision);
+    auditor.record_by_token(AuditEventType::AdminAction, "approve_registration", key.get_key(), Some(&target), &result);
+    if let Ok(user) = &result {
+        webhooks.publish_on(WebhookEventType::UserRegistered, user.get_id(), &result);
+    }
+    match result {
+        Ok(user) => json!({
+            "ok" : true,
+            "user" : user
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Admin function used to reject a registration with the help of the viewmodel RegistrationIdViewModel, the user is mailed about it.
+ */
+#[post("/registrations/reject", format = "application/json", data = "<model>")]
+fn reject_registration(key : ApiKey, model : Json<RegistrationIdViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let target = model.get_id().to_owned();
+    let result = registration_service::reject_registration(key.get_key(),model.0,RegistrationStores { db : sled_db.give_store(), registrations : sled_db.give_registration_store() },sled_db.give_client_store(),sled_db.give_token_store(),&sled_db.give_mail_outbox_store(),delegates::registration_decision);
+    auditor.record_by_token(AuditEventType::AdminAction, "reject_registration", key.get_key(), Some(&target), &result);
+    match result {
+        Ok(_) => json!({
+            "ok" : true
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Admin function used to suspend an user with the help of the viewmodel SuspendUserViewModel, the user can't log in or use his tokens until he is reactivated.
+ */
+#[put("/user/suspend", format = "application/json", data = "<model>")]
+fn suspend_user(key : ApiKey, model : Json<SuspendUserViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let target = model.get_user_id().to_owned();
+    let result = status_service::suspend_user(key.get_key(),model.0,sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store());
+    auditor.record_by_token(AuditEventType::StatusChange, "suspend_user", key.get_key(), Some(&target), &result);
+    match result {
+        Ok(user) => json!({
+            "ok" : true,
+            "user" : user
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Admin function used to reactivate a suspended or deactivated user with the help of the viewmodel UserIdViewModel.
+ */
+#[put("/user/reactivate", format = "application/json", data = "<model>")]
+fn reactivate_user(key : ApiKey, model : Json<UserIdViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let target = model.get_id().to_owned();
+    let result = status_service::reactivate_user(key.get_key(),model.0,sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store());
+    auditor.record_by_token(AuditEventType::StatusChange, "reactivate_user", key.get_key(), Some(&target), &result);
+    match result {
+        Ok(user) => json!({
+            "ok" : true,
+            "user" : user
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Admin function that returns the deleted users that can still be restored, they are purged once their grace period has passed.
+ */
+#[get("/deleted_users", format = "application/json")]
+fn deleted_users(key : ApiKey, sled_db : State<StoreManager>) -> JsonValue {
+    match deletion_service::get_deleted_users(key.get_key(),sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store()) {
+        Ok(deleted_users) => json!({
+            "ok" : true,
+            "users" : deleted_users.users
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Admin function used to restore a deleted user with the help of the viewmodel UserIdViewModel, this is only possible within the grace period.
+ */
+#[post("/deleted_users/restore", format = "application/json", data = "<model>")]
+fn restore_deleted_user(key : ApiKey, model : Json<UserIdViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let target = model.get_id().to_owned();
+    let result = deletion_service::restore_deleted_user(key.get_key(),model.0,sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store());
+    auditor.record_by_token(AuditEventType::AdminAction, "restore_deleted_user", key.get_key(), Some(&target), &result);
+    match result {
+        Ok(user) => json!({
+            "ok" : true,
+            "user" : user
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Admin function used to purge a deleted user right away with the help of the viewmodel UserIdViewModel, his data is removed and his email is freed.
+ */
+#[delete("/deleted_users/purge", format = "application/json", data = "<model>")]
+fn purge_deleted_user(key : ApiKey, model : Json<UserIdViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let target = model.get_id().to_owned();
+    let result = deletion_service::purge_deleted_user(key.get_key(),model.0,purge_stores(&sled_db),sled_db.give_client_store());
+    auditor.record_by_token(AuditEventType::Deletion, "purge_deleted_user", key.get_key(), Some(&target), &result);
+    match result {
+        Ok(_) => json!({
+            "ok" : true,
+            "message" : "User has been purged"
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Returns the stores a purge removes the data of an user from.
+ */
+pub fn purge_stores(sled_db : &StoreManager) -> PurgeStores {
+    PurgeStores {
+        db : sled_db.give_store(),
+        groups : sled_db.give_group_store(),
+        scim : sled_db.give_scim_store(),
+        federation : sled_db.give_federation_store(),
+        ldap : sled_db.give_ldap_store(),
+        tokens : sled_db.give_token_store(),
+        outbox : sled_db.give_mail_outbox_store(),
+        webhooks : sled_db.give_webhook_store()
+    }
+}
+
+/**
+ * Query of the audit log, every condition is optional. The event type is given by its name, e.g. password_change, and the times as unix timestamps.
+ */
+#[derive(FromForm)]
+struct AuditQueryForm {
+    user : Option<String>,
+    actor : Option<String>,
+    event_type : Option<String>,
+    from : Option<i64>,
+    to : Option<i64>,
+    limit : Option<usize>
+}
+
+impl AuditQueryForm {
+    fn into_viewmodel(self) -> AuditQueryViewModel {
+        AuditQueryViewModel { user : self.user, actor : self.actor, event_type : self.event_type, from : self.from, to : self.to, limit : self.limit }
+    }
+}
+
+/**
+ * Returns a json object with the audit events that match the query, the newest first.
+ */
+#[get("/audit?<query..>")]
+fn audit_events(key : ApiKey, query : LenientForm<AuditQueryForm>, sled_db : State<StoreManager>) -> JsonValue {
+    match audit_service::query_events(key.get_key(),query.into_inner().into_viewmodel(),sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store(),sled_db.give_audit_store()) {
+        Ok(events) => json!({
+            "ok" : true,
+            "events" : events.events
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Returns a json object with the result of walking the hash chain of the audit log, a broken chain tells at which event it was changed.
+ */
+#[get("/audit/verify", format = "application/json")]
+fn verify_audit_log(key : ApiKey, sled_db : State<StoreManager>) -> JsonValue {
+    match audit_service::verify_audit_log(key.get_key(),sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store(),sled_db.give_audit_store()) {
+        Ok(chain) => json!({
+            "ok" : true,
+            "chain" : chain
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Admin function used to export a signed checkpoint of the audit log to the checkpoint directory right away.
+ */
+#[post("/audit/checkpoint", format = "application/json")]
+fn export_audit_checkpoint(key : ApiKey, sled_db : State<StoreManager>, signing_key : State<SigningKey>, auditor : Auditor) -> JsonValue {
+    let result = audit_service::export_checkpoint(key.get_key(),sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store(),sled_db.give_audit_store(),&signing_key);
+    auditor.record_by_token(AuditEventType::AdminAction, "export_audit_checkpoint", key.get_key(), None, &result);
+    match result {
+        Ok(checkpoint) => json!({
+            "ok" : true,
+            "checkpoint" : checkpoint
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Admin function used to subscribe an url to lifecycle events with the help of the viewmodel CreateWebhookViewModel. The secret the posts are signed with is only returned here.
+ */
+#[post("/webhooks", format = "application/json", data = "<model>")]
+fn create_webhook(key : ApiKey, model : Json<CreateWebhookViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let target = model.get_url().to_owned();
+    let result = webhook_service::create_subscription(key.get_key(),model.0,sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store(),sled_db.give_webhook_store());
+    auditor.record_by_token(AuditEventType::AdminAction, "create_webhook", key.get_key(), Some(&target), &result);
+    match result {
+        Ok(webhook) => json!({
+            "ok" : true,
+            "webhook" : webhook
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Returns a json object with all webhook subscriptions.
+ */
+#[get("/webhooks", format = "application/json")]
+fn all_webhooks(key : ApiKey, sled_db : State<StoreManager>) -> JsonValue {
+    match webhook_service::get_subscriptions(key.get_key(),sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store(),sled_db.give_webhook_store()) {
+        Ok(webhooks) => json!({
+            "ok" : true,
+            "webhooks" : webhooks.webhooks
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Admin function used to remove a webhook subscription with the help of the viewmodel WebhookIdViewModel, its deliveries are removed too.
+ */
+#[delete("/webhooks", format = "application/json", data = "<model>")]
+fn remove_webhook(key : ApiKey, model : Json<WebhookIdViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let target = model.get_id().to_owned();
+    let result = webhook_service::remove_subscription(key.get_key(),model.0,sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store(),sled_db.give_webhook_store());
+    auditor.record_by_token(AuditEventType::AdminAction, "remove_webhook", key.get_key(), Some(&target), &result);
+    match result {
+        Ok(_) => json!({
+            "ok" : true,
+            "message" : "Webhook has been removed"
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Returns a json object with the delivery log of the webhook of the viewmodel WebhookIdViewModel, the newest deliveries first.
+ */
+#[post("/webhooks/deliveries", format = "application/json", data = "<model>")]
+fn webhook_deliveries(key : ApiKey, model : Json<WebhookIdViewModel>, sled_db : State<StoreManager>) -> JsonValue {
+    match webhook_service::get_deliveries(key.get_key(),model.0,sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store(),sled_db.give_webhook_store()) {
+        Ok(deliveries) => json!({
+            "ok" : true,
+            "deliveries" : deliveries.deliveries
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Admin function used to post the delivery of the viewmodel DeliveryIdViewModel again right away.
+ */
+#[post("/webhooks/redeliver", format = "application/json", data = "<model>")]
+fn redeliver_webhook(key : ApiKey, model : Json<DeliveryIdViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let target = model.get_id().to_string();
+    let result = webhook_service::redeliver(key.get_key(),model.0,sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store(),sled_db.give_webhook_store());
+    auditor.record_by_token(AuditEventType::AdminAction, "redeliver_webhook", key.get_key(), Some(&target), &result);
+    match result {
+        Ok(delivery) => json!({
+            "ok" : true,
+            "delivery" : delivery
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Which mails of the outbox to return, the status is pending or failed. Without one all mails are returned.
+ */
+#[derive(FromForm)]
+struct MailOutboxForm {
+    status : Option<String>
+}
+
+/**
+ * Returns a json object with the mails of the outbox and their attempts, the newest first.
+ */
+#[get("/mails?<query..>")]
+fn outbox_mails(key : ApiKey, query : LenientForm<MailOutboxForm>, sled_db : State<StoreManager>) -> JsonValue {
+    match mail_service::get_outbox_mails(key.get_key(),query.status.as_deref(),sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store(),sled_db.give_mail_outbox_store()) {
+        Ok(mails) => json!({
+            "ok" : true,
+            "mails" : mails.mails
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Admin function used to send the mail of the viewmodel MailIdViewModel again, e.g. one that has failed.
+ */
+#[post("/mails/retry", format = "application/json", data = "<model>")]
+fn retry_mail(key : ApiKey, model : Json<MailIdViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let target = model.get_id().to_string();
+    let result = mail_service::retry_mail(key.get_key(),model.0,sled_db.give_store(),sled_db.give_client_store(),sled_db.give_token_store(),sled_db.give_mail_outbox_store());
+    auditor.record_by_token(AuditEventType::AdminAction, "retry_mail", key.get_key(), Some(&target), &result);
+    match result {
+        Ok(mail) => json!({
+            "ok" : true,
+            "mail" : mail
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
@@ -1,6 +1,7 @@
 use rocket_contrib::json::{Json,JsonValue};
 use super::error_controller;
 use identity_service::service::person_service;
+use identity_service::service::registration_service::{ self, RegistrationResult };
 use identity_service::store::StoreManager;
 use identity_service::viewmodels::auth::registration::RegistrationViewModel;
 use identity_service::viewmodels::auth::change_pwd::ChangeForgottenPassword;
@@ -10,11 +11,20 @@ use identity_service::viewmodels::auth::update_user::UpdateUserViewModel;
 use identity_service::viewmodels::auth::update_pwd::ChangePasswordViewModel;
 use identity_service::viewmodels::auth::delete_user::DeleteUserViewModel;
 use identity_service::viewmodels::auth::flag::FlagHolder;
-use identity_service::service::mail_service::MailTransport;
 use identity_service::map_token_pwd::TokenHolderForgottenPwd;
+use identity_service::hooks::HookRegistry;
 use identity_service::viewmodels::auth::user_id::UserIdViewModel;
+use identity_service::viewmodels::auth::email::EmailViewModel;
+use identity_service::viewmodels::auth::token::TokenHolderViewModel;
+use identity_service::service::magic_link_service;
+use identity_service::service::status_service;
+use identity_service::viewmodels::auth::deactivate::DeactivateViewModel;
 use crate::delegates;
-use crate::key::ApiKey;
+use crate::key::{ ApiKey, AcceptLanguage };
+use crate::audit::Auditor;
+use crate::webhooks::Webhooks;
+use identity_service::service::audit_service::AuditEventType;
+use identity_service::service::webhook_service::{ self, WebhookEventType };
 use rocket::State;
 use rocket::Route;
 
@@ -29,24 +39,41 @@ pub fn routes() -> Vec<Route> {
         add_flag,
         remove_flag,
         delete_user,
+        deactivate,
+        reactivate,
         send_email_forgotten_pwd,
-        change_forgotten_password
+        change_forgotten_password,
+        send_magic_link,
+        login_with_magic_link
     ]
 }
 
 /**
- * Function used to add a user through help of the viewmodel RegistrationViewModel, if it succeeds it returns a normal json object and if there are errors a json object with errors is sent.
+ * Function used to add a user through help of the viewmodel RegistrationViewModel, if it succeeds it returns a normal json object and if there are errors a json object with errors is sent. Depending on the registration mode registration can be refused, or the user is only added once the admin approves it. When the model has no locale the user gets the locale of the Accept-Language header.
  */
 #[post("/registration", format = "application/json", data = "<model>")]
-fn registration(model : Json<RegistrationViewModel>, sled_db : State<StoreManager>, transport : State<MailTransport>) -> JsonValue {
-    match person_service::add_user(model.0, &sled_db.give_unique_id(),sled_db.give_store(),Some(delegates::user_creation) ,&transport) {
-        Ok(_) => {
+fn registration(model : Json<RegistrationViewModel>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, webhooks : Webhooks, accept_language : AcceptLanguage) -> JsonValue {
+    let id = sled_db.give_unique_id();
+    let result = registration_service::register(model.0.with_default_locale(accept_language.get_locale()), &id,sled_db.give_store(),sled_db.give_registration_store(),&hooks);
+    if let Ok(RegistrationResult::Added(_)) = &result {
+        webhooks.publish_on(WebhookEventType::UserRegistered, &id, &result);
+    }
+    match result {
+        Ok(RegistrationResult::Added(_)) => {
             info!("A user has been added");
             json!({
                 "ok" : true,
                 "message" : "User has been added"
             })
         },
+        Ok(RegistrationResult::Pending(_)) => {
+            info!("A registration waits for approval");
+            json!({
+                "ok" : true,
+                "pending" : true,
+                "message" : "Registration waits for the approval of the admin"
+            })
+        },
         Err(e) => error_controller::return_error_json(e, false)
     }
 }
@@ -55,8 +82,11 @@ fn registration(model : Json<RegistrationViewModel>, sled_db : State<StoreManage
  * Function used to control the credentials and return a token in the returned json object. When the credentials aren't valid a json object that indicate the error is returned.
  */
 #[post("/login", format = "application/json", data = "<model>")]
-fn login(model : Json<LoginViewModel>, sled_db : State<StoreManager>) -> JsonValue {
-    match person_service::check_credentials(model.0,sled_db.give_store()) {
+fn login(model : Json<LoginViewModel>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor) -> JsonValue {
+    let email = model.get_email().to_owned();
+    let result = person_service::check_credentials(model.0,sled_db.give_store(),sled_db.give_ldap_store(),&sled_db.give_webhook_store(),&hooks);
+    auditor.record(AuditEventType::Login, "login", result.as_ref().ok().map(|claim| claim.sub.as_str()), Some(&email), &result);
+    match result {
         Ok(claim_of_user) => {
             info!("The given credentials are right");
             json!({
@@ -89,8 +119,18 @@ fn return_new_token(key : ApiKey, sled_db : State<StoreManager>) -> JsonValue {
  * Function used to update user throught the help of viewmodel UpdateUserViewModel, this one contains the token that after validation can be used to modify certain properties of the user. If the operations succeeds a normal json object is sent, if it doesn't a json object indicating an error is sent back.
  */
 #[put("/update", format = "application/json", data = "<model>")]
-fn update_user(key : ApiKey, model : Json<UpdateUserViewModel>, sled_db : State<StoreManager>) -> JsonValue {
-    match person_service::update_user(key.get_key(),model.0,sled_db.give_store()) {
+fn update_user(key : ApiKey, model : Json<UpdateUserViewModel>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor, webhooks : Webhooks) -> JsonValue {
+    let email_changed = model.new_email.is_some();
+    let user_id = webhook_service::user_of_token(key.get_key()).unwrap_or_default();
+    let previous_email = webhooks.email_of(&user_id);
+    let result = person_service::update_user(key.get_key(),model.0,sled_db.give_store(),&hooks);
+    if email_changed {
+        auditor.record_for_token(AuditEventType::EmailChange, "update_user", key.get_key(), &result);
+        if result.is_ok() {
+            webhooks.publish_email_change(&user_id, previous_email.as_deref());
+        }
+    }
+    match result {
         Ok(_) => {
             info!("The user has successfully been updated");
             json!({
@@ -122,8 +162,10 @@ fn get_profile(key : ApiKey, sled_db : State<StoreManager>) -> JsonValue {
  * Function used to change the password of an user. A function is used to control the token and control the password. If it succeeds a positive message passes, but if it fails a json object with the error within.
 */
 #[put("/password", format = "application/json", data = "<model>")]
-fn change_password(key : ApiKey,model : Json<ChangePasswordViewModel>, sled_db : State<StoreManager>) -> JsonValue {
-    match person_service::change_password(key.get_key(),model.0,sled_db.give_store()) {
+fn change_password(key : ApiKey,model : Json<ChangePasswordViewModel>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor) -> JsonValue {
+    let result = person_service::change_password(key.get_key(),model.0,sled_db.give_store(),&hooks);
+    auditor.record_for_token(AuditEventType::PasswordChange, "change_password", key.get_key(), &result);
+    match result {
         Ok(_) => {
             info!("The password of an user has been changed.");
             json!({
@@ -136,8 +178,11 @@ fn change_password(key : ApiKey,model : Json<ChangePasswordViewModel>, sled_db :
 }
 
 #[put("/flag/add", format = "application/json", data = "<model>")]
-fn add_flag(key : ApiKey, model : Json<FlagHolder>, sled_db : State<StoreManager>) -> JsonValue {
-    match person_service::add_flag_of_user(key.get_key(),model.0,sled_db.give_store()) {
+fn add_flag(key : ApiKey, model : Json<FlagHolder>, sled_db : State<StoreManager>, auditor : Auditor, webhooks : Webhooks) -> JsonValue {
+    let result = person_service::add_flag_of_user(key.get_key(),model.0,sled_db.give_store());
+    auditor.record_for_token(AuditEventType::FlagChange, "add_flag", key.get_key(), &result);
+    webhooks.publish_on_token(WebhookEventType::RolesChanged, key.get_key(), &result);
+    match result {
         Ok(_) => {
             info!("A flag has been added to the user.");
             json!({
@@ -150,8 +195,11 @@ fn add_flag(key : ApiKey, model : Json<FlagHolder>, sled_db : State<StoreManager
 }
 
 #[delete("/flag/remove", format = "application/json", data = "<model>")]
-fn remove_flag(key : ApiKey,model : Json<FlagHolder>, sled_db : State<StoreManager>) -> JsonValue {
-    match person_service::remove_flag_of_user(key.get_key(),model.0,sled_db.give_store()) {
+fn remove_flag(key : ApiKey,model : Json<FlagHolder>, sled_db : State<StoreManager>, auditor : Auditor, webhooks : Webhooks) -> JsonValue {
+    let result = person_service::remove_flag_of_user(key.get_key(),model.0,sled_db.give_store());
+    auditor.record_for_token(AuditEventType::FlagChange, "remove_flag", key.get_key(), &result);
+    webhooks.publish_on_token(WebhookEventType::RolesChanged, key.get_key(), &result);
+    match result {
         Ok(_) => {
             info!("A flag has been removed of the user.");
             json!({
@@ -167,8 +215,11 @@ fn remove_flag(key : ApiKey,model : Json<FlagHolder>, sled_db : State<StoreManag
  * Function used to delete an user, this will use the token to get the user id and to check  if this id exists or not and delete if it does. An error is thrown whent the token is empty or the user couldn't be deleted.
 */
 #[delete("/delete", format = "application/json", data = "<model>")]
-fn delete_user(key : ApiKey,model : Json<DeleteUserViewModel>, sled_db : State<StoreManager>) -> JsonValue {
-    match person_service::delete_user(key.get_key(),model.0,sled_db.give_store()) {
+fn delete_user(key : ApiKey,model : Json<DeleteUserViewModel>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor, webhooks : Webhooks) -> JsonValue {
+    let result = person_service::delete_user(key.get_key(),model.0,sled_db.give_store(),&hooks);
+    auditor.record_for_token(AuditEventType::Deletion, "delete_user", key.get_key(), &result);
+    webhooks.publish_on_token(WebhookEventType::UserDeleted, key.get_key(), &result);
+    match result {
         Ok(_) => {
             info!("The user has been deleted");
             json!({
@@ -180,18 +231,56 @@ fn delete_user(key : ApiKey,model : Json<DeleteUserViewModel>, sled_db : State<S
     }
 }
 
+/**
+ * Function used by an user to deactivate his own account with the help of the viewmodel DeactivateViewModel, he can undo it by reactivating within the grace period.
+ */
+#[post("/deactivate", format = "application/json", data = "<model>")]
+fn deactivate(key : ApiKey, model : Json<DeactivateViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let result = status_service::deactivate_user(key.get_key(),model.0,sled_db.give_store());
+    auditor.record_for_token(AuditEventType::StatusChange, "deactivate", key.get_key(), &result);
+    match result {
+        Ok(user) => json!({
+            "ok" : true,
+            "user" : user
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Function used to undo the deactivation of an account within the grace period, the credentials are asked and a token is returned like with a login.
+ */
+#[post("/reactivate", format = "application/json", data = "<model>")]
+fn reactivate(model : Json<LoginViewModel>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor) -> JsonValue {
+    let email = model.get_email().to_owned();
+    let result = status_service::restore_user(model.0,sled_db.give_store(),&hooks);
+    auditor.record(AuditEventType::StatusChange, "reactivate", result.as_ref().ok().map(|claim| claim.sub.as_str()), Some(&email), &result);
+    match result {
+        Ok(claim_of_user) => {
+            info!("An user has reactivated his account");
+            json!({
+                "ok" : true,
+                "token" : claim_of_user.token_from_user().unwrap()
+            })
+        },
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
 /**
  * Function that is used to send an email to change the password of an user that has forgotten password. It will also store a token that will be used to authorize the change of the password.
  */
 #[post("/forgotten_pwd", format = "application/json", data = "<model>")]
-fn send_email_forgotten_pwd(model : Json<UserIdViewModel>, sled_db : State<StoreManager>, token_map_state : State<TokenHolderForgottenPwd>, transport : State<MailTransport>) -> JsonValue {
-    match person_service::demand_email_changing_password(
+fn send_email_forgotten_pwd(model : Json<UserIdViewModel>, sled_db : State<StoreManager>, token_map_state : State<TokenHolderForgottenPwd>, hooks : State<HookRegistry>, auditor : Auditor) -> JsonValue {
+    let target = model.get_id().to_owned();
+    let result = person_service::demand_email_changing_password(
         &token_map_state,
         model.0.get_id(),
         sled_db.give_store(),
-        &transport,
-        delegates::send_email_for_forgotten_pwd
-    ) {
+        &hooks
+    );
+    auditor.record(AuditEventType::PasswordReset, "demand_password_reset", None, Some(&target), &result);
+    match result {
         Ok(_) => {
             info!("The user has succesfully demanded to change his password because he forgot it.");
             json!({
@@ -207,12 +296,15 @@ fn send_email_forgotten_pwd(model : Json<UserIdViewModel>, sled_db : State<Store
  * Will take up the token out of the viewmodel and check it. If it is okay it will continue and pass through the change.
  */
 #[post("/change_forgotten_pwd", format = "application/json", data = "<model>")]
-fn change_forgotten_password(model : Json<ChangeForgottenPassword>, sled_db : State<StoreManager>, token_map_state : State<TokenHolderForgottenPwd>) -> JsonValue {
-    match person_service::change_forgotten_password(
+fn change_forgotten_password(model : Json<ChangeForgottenPassword>, sled_db : State<StoreManager>, token_map_state : State<TokenHolderForgottenPwd>, hooks : State<HookRegistry>, auditor : Auditor) -> JsonValue {
+    let result = person_service::change_forgotten_password(
         &token_map_state,
         model.0,
-        sled_db.give_store()
-    ) {
+        sled_db.give_store(),
+        &hooks
+    );
+    auditor.record(AuditEventType::PasswordReset, "reset_password", None, None, &result);
+    match result {
         Ok(_) => {
             info!("The user has succesfully changed his password.");
             json!({
@@ -222,4 +314,40 @@ fn change_forgotten_password(model : Json<ChangeForgottenPassword>, sled_db : St
         },
         Err(e) => error_controller::return_error_json(e,false)
     }
-}
\ No newline at end of file
+}
+
+/**
+ * Function that is used to mail a magic link to the user with the email. The same answer is given when the email is unknown, so it can't be used to find out which emails have an account.
+ */
+#[post("/magic_link", format = "application/json", data = "<model>")]
+fn send_magic_link(model : Json<EmailViewModel>, sled_db : State<StoreManager>) -> JsonValue {
+    match magic_link_service::demand_magic_link(
+        model.0,
+        sled_db.give_store(),
+        sled_db.give_magic_link_store(),
+        &sled_db.give_mail_outbox_store(),
+        delegates::send_magic_link
+    ) {
+        Ok(_) => json!({
+            "ok" : true,
+            "message" : "If the email belongs to an user, a link to log in has been sent."
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Function used to exchange the token of a magic link for a token of the user, the link can only be used once.
+ */
+#[post("/magic_link/login", format = "application/json", data = "<model>")]
+fn login_with_magic_link(model : Json<TokenHolderViewModel>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor) -> JsonValue {
+    let result = magic_link_service::login_with_magic_link(model.0, sled_db.give_store(), sled_db.give_magic_link_store(), &hooks);
+    auditor.record(AuditEventType::Login, "magic_link_login", result.as_ref().ok().map(|claim| claim.sub.as_str()), result.as_ref().ok().map(|claim| claim.sub.as_str()), &result);
+    match result {
+        Ok(claim_of_user) => json!({
+            "ok" : true,
+            "token" : claim_of_user.token_from_user().unwrap()
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
@@ -1,6 +1,9 @@
 use rocket_contrib::json::JsonValue;
 use rocket::Request;
 use rocket::Catcher;
+use rocket::http::Status;
+use rocket::response::status;
+use identity_service::service::oauth_service;
 use crate::IdentityError;
 
 pub fn catches() -> Vec<Catcher> {
@@ -26,6 +29,24 @@ pub fn return_error_json(error_message : IdentityError, grave_error : bool) -> J
     })
 }
 
+/**
+ * Returns an error in the form of RFC 6749, this is used by the OAuth endpoints since OAuth clients expect the error and error_description fields. Failed client authentication and invalid tokens are answered with a 401, a token without the needed scope with a 403 and all other errors with a 400.
+ */
+pub fn return_oauth_error_json(error_message : IdentityError) -> status::Custom<JsonValue> {
+    warn!("{}",error_message);
+    let code = oauth_service::oauth_error_code(&error_message);
+    let status = match code {
+        "invalid_client" | "invalid_token" => Status::Unauthorized,
+        "insufficient_scope" => Status::Forbidden,
+        "server_error" => Status::InternalServerError,
+        _ => Status::BadRequest
+    };
+    status::Custom(status, json!({
+        "error" : code,
+        "error_description" : format!("{}",error_message)
+    }))
+}
+
 /**
  * Catches the 404 error code, this means that the path doesn't exist.
  */
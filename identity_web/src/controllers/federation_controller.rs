@@ -0,0 +1,155 @@
+use rocket_contrib::json::{Json,JsonValue};
+use rocket::response::Redirect;
+use rocket::http::{Cookie, Cookies, SameSite};
+use super::error_controller;
+use identity_service::federation;
+use identity_service::service::federation_service::{ self, FederationStores };
+use identity_service::store::StoreManager;
+use identity_service::hooks::HookRegistry;
+use identity_service::service::audit_service::AuditEventType;
+use identity_service::viewmodels::federation::unlink_identity::UnlinkIdentityViewModel;
+use crate::audit::Auditor;
+use crate::key::ApiKey;
+use rocket::State;
+use rocket::Route;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        providers,
+        login,
+        callback,
+        link,
+        linked_identities,
+        unlink
+    ]
+}
+
+/**
+ * Returns the identity providers users can log in with.
+ */
+#[get("/providers")]
+fn providers() -> JsonValue {
+    json!({
+        "ok" : true,
+        "providers" : federation_service::get_providers()
+    })
+}
+
+/**
+ * Returns the cookie that binds a login at an identity provider to the browser, it is sent back when the provider redirects to the callback.
+ */
+fn login_cookie(value : String) -> Cookie<'static> {
+    Cookie::build(federation_service::LOGIN_COOKIE, value)
+        .path("/federation")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .finish()
+}
+
+/**
+ * Starts a login at an identity provider by redirecting the user to it.
+ */
+#[get("/<provider>/login")]
+fn login(provider : String, mut cookies : Cookies, sled_db : State<StoreManager>) -> Result<Redirect, JsonValue> {
+    let provider = federation::get_provider(&provider).map_err(|e| error_controller::return_error_json(e, false))?;
+    match federation_service::start_login(provider, &sled_db.give_federation_store()) {
+        Ok((uri, cookie)) => {
+            cookies.add(login_cookie(cookie));
+            Ok(Redirect::to(uri))
+        },
+        Err(e) => Err(error_controller::return_error_json(e, true))
+    }
+}
+
+/**
+ * The identity provider sends the user back to this route. When the login succeeded a token of the local user is returned, the user is linked or provisioned when it is the first login with the identity. The login cookie has to be the one set when the login was started.
+ */
+#[get("/<provider>/callback?<code>&<state>&<error>")]
+fn callback(provider : String, code : Option<String>, state : Option<String>, error : Option<String>, mut cookies : Cookies, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor) -> JsonValue {
+    let cookie = cookies.get(federation_service::LOGIN_COOKIE).map(|cookie| cookie.value().to_owned());
+    cookies.remove(login_cookie(String::new()));
+    let provider = match federation::get_provider(&provider) {
+        Ok(provider) => provider,
+        Err(e) => return error_controller::return_error_json(e, false)
+    };
+    if let Some(error) = error {
+        return error_controller::return_error_json(identity_service::IdentityError::FederationFailed(error), false)
+    }
+    let result = federation_service::finish_login(
+        provider,
+        code.as_deref().unwrap_or_default(),
+        state.as_deref().unwrap_or_default(),
+        cookie.as_deref(),
+        FederationStores {
+            federation : sled_db.give_federation_store(),
+            db : sled_db.give_store(),
+            registrations : sled_db.give_registration_store(),
+            webhooks : sled_db.give_webhook_store()
+        },
+        &sled_db.give_unique_id(),
+        &hooks
+    );
+    auditor.record(AuditEventType::Login, "federated_login", result.as_ref().ok().map(|claim| claim.sub.as_str()), result.as_ref().ok().map(|claim| claim.sub.as_str()), &result);
+    match result {
+        Ok(claim) => {
+            info!("An user has logged in with identity provider {}", provider.get_name());
+            json!({
+                "ok" : true,
+                "token" : claim.token_from_user().unwrap()
+            })
+        },
+        Err(e) => error_controller::return_error_json(e, false)
+    }
+}
+
+/**
+ * Starts linking an identity of a provider to the logged in user. The returned uri is the one of the provider the user has to be sent to, afterwards the provider sends him back to the callback. The login cookie is set on the browser that makes this request.
+ */
+#[post("/<provider>/link")]
+fn link(provider : String, key : ApiKey, mut cookies : Cookies, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let provider = match federation::get_provider(&provider) {
+        Ok(provider) => provider,
+        Err(e) => return error_controller::return_error_json(e, false)
+    };
+    let result = federation_service::start_link(provider, key.get_key(), &sled_db.give_store(), &sled_db.give_federation_store());
+    auditor.record_for_token(AuditEventType::IdentityLink, "start_link_identity", key.get_key(), &result);
+    match result {
+        Ok((uri, cookie)) => {
+            cookies.add(login_cookie(cookie));
+            json!({
+                "ok" : true,
+                "redirect_uri" : uri
+            })
+        },
+        Err(e) => error_controller::return_error_json(e, false)
+    }
+}
+
+/**
+ * Returns the external identities linked to the user of the token.
+ */
+#[get("/identities")]
+fn linked_identities(key : ApiKey, sled_db : State<StoreManager>) -> JsonValue {
+    match federation_service::get_linked_identities(key.get_key(), &sled_db.give_store(), &sled_db.give_federation_store()) {
+        Ok(identities) => json!({
+            "ok" : true,
+            "identities" : identities.identities
+        }),
+        Err(e) => error_controller::return_error_json(e, false)
+    }
+}
+
+/**
+ * Unlinks an external identity of the user of the token with the help of the viewmodel UnlinkIdentityViewModel. The last way of an user to log in can't be unlinked.
+ */
+#[delete("/identities", format = "application/json", data = "<model>")]
+fn unlink(key : ApiKey, model : Json<UnlinkIdentityViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let result = federation_service::unlink_identity(key.get_key(), model.0, &sled_db.give_store(), &sled_db.give_federation_store());
+    auditor.record_for_token(AuditEventType::IdentityLink, "unlink_identity", key.get_key(), &result);
+    match result {
+        Ok(_) => json!({
+            "ok" : true
+        }),
+        Err(e) => error_controller::return_error_json(e, false)
+    }
+}
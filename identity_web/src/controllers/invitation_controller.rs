@@ -0,0 +1,135 @@
+use rocket_contrib::json::{Json,JsonValue};
+use super::error_controller;
+use identity_service::service::audit_service::AuditEventType;
+use identity_service::service::invitation_service::{ self, InvitationStores };
+use identity_service::store::StoreManager;
+use identity_service::hooks::HookRegistry;
+use identity_service::service::webhook_service::WebhookEventType;
+use identity_service::viewmodels::invitation::accept::AcceptInvitationViewModel;
+use identity_service::viewmodels::invitation::invite::{ InvitationIdViewModel, InviteViewModel };
+use crate::audit::Auditor;
+use crate::delegates;
+use crate::key::ApiKey;
+use crate::webhooks::Webhooks;
+use rocket::State;
+use rocket::Route;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        invite,
+        all_invitations,
+        resend_invitation,
+        expire_invitation,
+        revoke_invitation,
+        accept_invitation
+    ]
+}
+
+fn stores(sled_db : &StoreManager) -> InvitationStores {
+    InvitationStores { db : sled_db.give_store(), groups : sled_db.give_group_store(), invitations : sled_db.give_invitation_store() }
+}
+
+/**
+ * Invites an email with the flags and groups of the viewmodel InviteViewModel, this can be done by the admin or an user with the inviter flag.
+ */
+#[post("/", format = "application/json", data = "<model>")]
+fn invite(key : ApiKey, model : Json<InviteViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let email = model.get_email().to_owned();
+    let result = invitation_service::invite(key.get_key(), model.0, &stores(&sled_db), &sled_db.give_client_store(), &sled_db.give_token_store(), &sled_db.give_mail_outbox_store(), delegates::send_invitation);
+    auditor.record_by_token(AuditEventType::AdminAction, "invite", key.get_key(), Some(&email), &result);
+    match result {
+        Ok(invitation) => {
+            info!("An invitation has been made");
+            json!({
+                "ok" : true,
+                "invitation" : invitation
+            })
+        },
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Returns a json object where the invitations the token may manage are presented in an array.
+ */
+#[get("/")]
+fn all_invitations(key : ApiKey, sled_db : State<StoreManager>) -> JsonValue {
+    match invitation_service::get_invitations(key.get_key(), &stores(&sled_db), &sled_db.give_client_store(), &sled_db.give_token_store()) {
+        Ok(invitations) => json!(invitations),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Sends a new link for an invitation, the earlier links stop working.
+ */
+#[post("/resend", format = "application/json", data = "<model>")]
+fn resend_invitation(key : ApiKey, model : Json<InvitationIdViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let target = model.get_id().to_owned();
+    let result = invitation_service::resend_invitation(key.get_key(), model.0, &stores(&sled_db), &sled_db.give_client_store(), &sled_db.give_token_store(), &sled_db.give_mail_outbox_store(), delegates::send_invitation);
+    auditor.record_by_token(AuditEventType::AdminAction, "resend_invitation", key.get_key(), Some(&target), &result);
+    match result {
+        Ok(invitation) => json!({
+            "ok" : true,
+            "invitation" : invitation
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Lets an invitation expire right away.
+ */
+#[post("/expire", format = "application/json", data = "<model>")]
+fn expire_invitation(key : ApiKey, model : Json<InvitationIdViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let target = model.get_id().to_owned();
+    let result = invitation_service::expire_invitation(key.get_key(), model.0, &stores(&sled_db), &sled_db.give_client_store(), &sled_db.give_token_store());
+    auditor.record_by_token(AuditEventType::AdminAction, "expire_invitation", key.get_key(), Some(&target), &result);
+    match result {
+        Ok(invitation) => json!({
+            "ok" : true,
+            "invitation" : invitation
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Revokes an invitation that hasn't been accepted yet.
+ */
+#[delete("/", format = "application/json", data = "<model>")]
+fn revoke_invitation(key : ApiKey, model : Json<InvitationIdViewModel>, sled_db : State<StoreManager>, auditor : Auditor) -> JsonValue {
+    let target = model.get_id().to_owned();
+    let result = invitation_service::revoke_invitation(key.get_key(), model.0, &stores(&sled_db), &sled_db.give_client_store(), &sled_db.give_token_store());
+    auditor.record_by_token(AuditEventType::AdminAction, "revoke_invitation", key.get_key(), Some(&target), &result);
+    match result {
+        Ok(invitation) => json!({
+            "ok" : true,
+            "invitation" : invitation
+        }),
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
+
+/**
+ * Used by the invitee to accept his invitation with the token of his link and his own password, a token of the new account is returned.
+ */
+#[post("/accept", format = "application/json", data = "<model>")]
+fn accept_invitation(model : Json<AcceptInvitationViewModel>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor, webhooks : Webhooks) -> JsonValue {
+    let result = invitation_service::accept_invitation(model.0, &stores(&sled_db), &hooks);
+    auditor.record(AuditEventType::Login, "accept_invitation", result.as_ref().ok().map(|claim| claim.sub.as_str()), result.as_ref().ok().map(|claim| claim.sub.as_str()), &result);
+    if let Ok(claim_of_user) = &result {
+        webhooks.publish_on(WebhookEventType::UserRegistered, &claim_of_user.sub, &result);
+        webhooks.publish_on(WebhookEventType::EmailVerified, &claim_of_user.sub, &result);
+    }
+    match result {
+        Ok(claim_of_user) => {
+            info!("An invitation has been accepted");
+            json!({
+                "ok" : true,
+                "token" : claim_of_user.token_from_user().unwrap()
+            })
+        },
+        Err(e) => error_controller::return_error_json(e,false)
+    }
+}
@@ -1,4 +1,10 @@
 pub mod auth_controller;
 pub mod admin_controller;
 pub mod error_controller;
-pub mod basic_controller;
\ No newline at end of file
+pub mod basic_controller;
+pub mod oauth_controller;
+pub mod oidc_controller;
+pub mod federation_controller;
+pub mod saml_controller;
+pub mod scim_controller;
+pub mod invitation_controller;
\ No newline at end of file
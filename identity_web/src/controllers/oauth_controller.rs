@@ -0,0 +1,351 @@
+use rocket_contrib::json::{Json,JsonValue};
+use rocket::request::{Form, LenientForm};
+use rocket::response::{Redirect, status, content::Html};
+use super::error_controller;
+use identity_service::service::{ device_service, oauth_service, person_service };
+use identity_service::store::StoreManager;
+use identity_service::hooks::HookRegistry;
+use identity_service::viewmodels::auth::login::LoginViewModel;
+use identity_service::viewmodels::oauth::authorization_request::AuthorizationRequestViewModel;
+use identity_service::viewmodels::oauth::token_request::TokenRequestViewModel;
+use identity_service::viewmodels::oauth::token_reference::TokenReferenceViewModel;
+use identity_service::viewmodels::oauth::device_authorization::{ DeviceAuthorizationRequestViewModel, DeviceDecisionViewModel };
+use identity_service::service::oidc_service;
+use identity_service::signing_key::SigningKey;
+use identity_service::service::audit_service::AuditEventType;
+use crate::audit::Auditor;
+use crate::key::{ ApiKey, ClientBasicAuth, BearerToken };
+use crate::pages;
+use rocket::State;
+use rocket::Route;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        authorize_page,
+        authorize,
+        token,
+        device_authorization,
+        device_page,
+        device_decision,
+        device_decision_json,
+        introspect,
+        revoke,
+        user_info,
+        user_info_post
+    ]
+}
+
+/**
+ * Query parameters of an authorization request.
+ */
+#[derive(FromForm)]
+struct AuthorizeForm {
+    response_type : Option<String>,
+    client_id : Option<String>,
+    redirect_uri : Option<String>,
+    scope : Option<String>,
+    state : Option<String>,
+    code_challenge : Option<String>,
+    code_challenge_method : Option<String>,
+    nonce : Option<String>
+}
+
+impl AuthorizeForm {
+    fn into_viewmodel(self) -> AuthorizationRequestViewModel {
+        AuthorizationRequestViewModel {
+            response_type : self.response_type,
+            client_id : self.client_id,
+            redirect_uri : self.redirect_uri,
+            scope : self.scope,
+            state : self.state,
+            code_challenge : self.code_challenge,
+            code_challenge_method : self.code_challenge_method,
+            nonce : self.nonce
+        }
+    }
+}
+
+/**
+ * Login and consent form, it repeats the parameters of the authorization request.
+ */
+#[derive(FromForm)]
+struct AuthorizeDecisionForm {
+    response_type : Option<String>,
+    client_id : Option<String>,
+    redirect_uri : Option<String>,
+    scope : Option<String>,
+    state : Option<String>,
+    code_challenge : Option<String>,
+    code_challenge_method : Option<String>,
+    nonce : Option<String>,
+    email : Option<String>,
+    password : Option<String>,
+    decision : String
+}
+
+/**
+ * Parameters of a request to the token endpoint.
+ */
+#[derive(FromForm)]
+struct TokenForm {
+    grant_type : Option<String>,
+    code : Option<String>,
+    redirect_uri : Option<String>,
+    code_verifier : Option<String>,
+    client_id : Option<String>,
+    client_secret : Option<String>,
+    scope : Option<String>,
+    refresh_token : Option<String>,
+    device_code : Option<String>
+}
+
+/**
+ * Parameters of a request to the device authorization endpoint.
+ */
+#[derive(FromForm)]
+struct DeviceAuthorizationForm {
+    client_id : Option<String>,
+    client_secret : Option<String>,
+    scope : Option<String>
+}
+
+/**
+ * Form on which the user signs in and decides about the device of a user code.
+ */
+#[derive(FromForm)]
+struct DeviceDecisionForm {
+    user_code : String,
+    email : Option<String>,
+    password : Option<String>,
+    decision : String
+}
+
+/**
+ * Parameters of a request to the introspection or revocation endpoint.
+ */
+#[derive(FromForm)]
+struct TokenReferenceForm {
+    token : Option<String>,
+    token_type_hint : Option<String>,
+    client_id : Option<String>,
+    client_secret : Option<String>
+}
+
+impl TokenReferenceForm {
+    fn into_viewmodel(self, client : Option<ClientBasicAuth>) -> TokenReferenceViewModel {
+        let model = TokenReferenceViewModel {
+            token : self.token,
+            token_type_hint : self.token_type_hint,
+            client_id : self.client_id,
+            client_secret : self.client_secret
+        };
+        match client {
+            Some(client) => model.with_client_credentials(client.get_client_id(), client.get_client_secret()),
+            None => model
+        }
+    }
+}
+
+#[derive(Responder)]
+enum AuthorizeResponse {
+    Page(Html<String>),
+    Redirect(Redirect)
+}
+
+/**
+ * Shows the login and consent page for an authorization request. When the client or redirect uri isn't right an error page is shown, other errors are sent back to the client.
+ */
+#[get("/authorize?<request..>")]
+fn authorize_page(request : LenientForm<AuthorizeForm>, sled_db : State<StoreManager>) -> AuthorizeResponse {
+    let request = request.into_inner().into_viewmodel();
+    let client = match oauth_service::get_client_for_authorization(&request, &sled_db.give_client_store()) {
+        Ok(client) => client,
+        Err(e) => return AuthorizeResponse::Page(pages::error_page(&format!("{}", e)))
+    };
+    match oauth_service::validate_authorization_request(&request, &client) {
+        Ok(_) => AuthorizeResponse::Page(pages::authorize_page(client.get_client_name(), &request, None)),
+        Err(e) => AuthorizeResponse::Redirect(Redirect::to(oauth_service::error_redirect_uri(&request, &e)))
+    }
+}
+
+/**
+ * Handles the login and consent form. When the user allows the client an authorization code is issued and the user is sent back to the client, when the credentials are wrong the page is shown again.
+ */
+#[post("/authorize", format = "application/x-www-form-urlencoded", data = "<form>")]
+fn authorize(form : Form<AuthorizeDecisionForm>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor) -> AuthorizeResponse {
+    let form = form.into_inner();
+    let request = AuthorizationRequestViewModel {
+        response_type : form.response_type,
+        client_id : form.client_id,
+        redirect_uri : form.redirect_uri,
+        scope : form.scope,
+        state : form.state,
+        code_challenge : form.code_challenge,
+        code_challenge_method : form.code_challenge_method,
+        nonce : form.nonce
+    };
+    let client = match oauth_service::get_client_for_authorization(&request, &sled_db.give_client_store()) {
+        Ok(client) => client,
+        Err(e) => return AuthorizeResponse::Page(pages::error_page(&format!("{}", e)))
+    };
+    if form.decision != "approve" {
+        info!("The user has denied the authorization request of client {}", client.get_client_id());
+        return AuthorizeResponse::Redirect(Redirect::to(oauth_service::error_redirect_uri(&request, &identity_service::IdentityError::AccessDenied)))
+    }
+    let login = LoginViewModel::new(form.email.as_deref().unwrap_or_default(), form.password.as_deref().unwrap_or_default());
+    let result = person_service::check_credentials(login, sled_db.give_store(), sled_db.give_ldap_store(), &sled_db.give_webhook_store(), &hooks);
+    auditor.record(AuditEventType::Login, "oauth_login", result.as_ref().ok().map(|claim| claim.sub.as_str()), form.email.as_deref(), &result);
+    let claim = match result {
+        Ok(claim) => claim,
+        Err(_) => return AuthorizeResponse::Page(pages::authorize_page(client.get_client_name(), &request, Some("Email or password is not right")))
+    };
+    match oauth_service::issue_authorization_code(&request, &client, &claim.sub, sled_db.give_code_store()) {
+        Ok(uri) => AuthorizeResponse::Redirect(Redirect::to(uri)),
+        Err(e) => AuthorizeResponse::Redirect(Redirect::to(oauth_service::error_redirect_uri(&request, &e)))
+    }
+}
+
+/**
+ * Token endpoint of the OAuth server. Clients can authenticate through HTTP Basic authentication or with the client_id and client_secret parameters.
+ */
+#[post("/token", format = "application/x-www-form-urlencoded", data = "<form>")]
+fn token(form : Form<TokenForm>, client : Option<ClientBasicAuth>, sled_db : State<StoreManager>, signing_key : State<SigningKey>) -> Result<JsonValue, status::Custom<JsonValue>> {
+    let form = form.into_inner();
+    let mut model = TokenRequestViewModel {
+        grant_type : form.grant_type,
+        code : form.code,
+        redirect_uri : form.redirect_uri,
+        code_verifier : form.code_verifier,
+        client_id : form.client_id,
+        client_secret : form.client_secret,
+        scope : form.scope,
+        refresh_token : form.refresh_token,
+        device_code : form.device_code
+    };
+    if let Some(client) = client {
+        model = model.with_client_credentials(client.get_client_id(), client.get_client_secret());
+    }
+    match oauth_service::exchange_token(model, sled_db.give_client_store(), sled_db.give_code_store(), sled_db.give_token_store(), sled_db.give_device_store(), sled_db.give_store(), &signing_key) {
+        Ok(token) => {
+            info!("An OAuth token has been issued");
+            Ok(json!(token))
+        },
+        Err(e) => Err(error_controller::return_oauth_error_json(e))
+    }
+}
+
+/**
+ * Device authorization endpoint (RFC 8628), a device that can't open a browser gets a device code to poll the token endpoint with and a user code the user enters on /oauth/device.
+ */
+#[post("/device_authorization", format = "application/x-www-form-urlencoded", data = "<form>")]
+fn device_authorization(form : Form<DeviceAuthorizationForm>, client : Option<ClientBasicAuth>, sled_db : State<StoreManager>) -> Result<JsonValue, status::Custom<JsonValue>> {
+    let form = form.into_inner();
+    let mut model = DeviceAuthorizationRequestViewModel {
+        client_id : form.client_id,
+        client_secret : form.client_secret,
+        scope : form.scope
+    };
+    if let Some(client) = client {
+        model = model.with_client_credentials(client.get_client_id(), client.get_client_secret());
+    }
+    match device_service::authorize_device(model, sled_db.give_client_store(), sled_db.give_device_store()) {
+        Ok(device) => Ok(json!(device)),
+        Err(e) => Err(error_controller::return_oauth_error_json(e))
+    }
+}
+
+/**
+ * Page on which the user enters the user code of a device, when the code is given the device is shown so the user can approve it.
+ */
+#[get("/device?<user_code>")]
+fn device_page(user_code : Option<String>, sled_db : State<StoreManager>) -> Html<String> {
+    let user_code = match user_code {
+        Some(user_code) => user_code,
+        None => return pages::device_page(None, None, None)
+    };
+    match device_service::get_device_for_user_code(&user_code, &sled_db.give_device_store(), &sled_db.give_client_store()) {
+        Ok((device, client)) => pages::device_page(Some(&user_code), Some((client.get_client_name(), device.get_scope())), None),
+        Err(e) => pages::device_page(Some(&user_code), None, Some(&format!("{}", e)))
+    }
+}
+
+/**
+ * Handles the sign in and decision of the user about a device. When the credentials are wrong the page is shown again.
+ */
+#[post("/device", format = "application/x-www-form-urlencoded", data = "<form>")]
+fn device_decision(form : Form<DeviceDecisionForm>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor) -> Html<String> {
+    let form = form.into_inner();
+    let (device, client) = match device_service::get_device_for_user_code(&form.user_code, &sled_db.give_device_store(), &sled_db.give_client_store()) {
+        Ok(found) => found,
+        Err(e) => return pages::device_page(Some(&form.user_code), None, Some(&format!("{}", e)))
+    };
+    let login = LoginViewModel::new(form.email.as_deref().unwrap_or_default(), form.password.as_deref().unwrap_or_default());
+    let result = person_service::check_credentials(login, sled_db.give_store(), sled_db.give_ldap_store(), &sled_db.give_webhook_store(), &hooks);
+    auditor.record(AuditEventType::Login, "device_login", result.as_ref().ok().map(|claim| claim.sub.as_str()), form.email.as_deref(), &result);
+    let claim = match result {
+        Ok(claim) => claim,
+        Err(_) => return pages::device_page(Some(&form.user_code), Some((client.get_client_name(), device.get_scope())), Some("Email or password is not right"))
+    };
+    let approve = form.decision == "approve";
+    match device_service::decide_device_authorization(&form.user_code, &claim.sub, approve, sled_db.give_device_store(), sled_db.give_client_store()) {
+        Ok(_) => pages::device_done_page(approve),
+        Err(e) => pages::error_page(&format!("{}", e))
+    }
+}
+
+/**
+ * Lets a logged in user approve or deny the device of a user code with the help of the viewmodel DeviceDecisionViewModel.
+ */
+#[post("/device/decision", format = "application/json", data = "<model>")]
+fn device_decision_json(key : ApiKey, model : Json<DeviceDecisionViewModel>, sled_db : State<StoreManager>) -> JsonValue {
+    match device_service::decide_device_authorization_with_token(key.get_key(), model.0, sled_db.give_store(), sled_db.give_device_store(), sled_db.give_client_store()) {
+        Ok(_) => json!({
+            "ok" : true
+        }),
+        Err(e) => error_controller::return_error_json(e, false)
+    }
+}
+
+/**
+ * Token introspection endpoint (RFC 7662), tells a confidential client if an access token or refresh token is active.
+ */
+#[post("/introspect", format = "application/x-www-form-urlencoded", data = "<form>")]
+fn introspect(form : Form<TokenReferenceForm>, client : Option<ClientBasicAuth>, sled_db : State<StoreManager>) -> Result<JsonValue, status::Custom<JsonValue>> {
+    match oauth_service::introspect_token(form.into_inner().into_viewmodel(client), sled_db.give_client_store(), sled_db.give_token_store(), sled_db.give_store()) {
+        Ok(introspection) => Ok(json!(introspection)),
+        Err(e) => Err(error_controller::return_oauth_error_json(e))
+    }
+}
+
+/**
+ * Token revocation endpoint (RFC 7009), a client revokes one of its own tokens. The response is empty, also when the token was unknown.
+ */
+#[post("/revoke", format = "application/x-www-form-urlencoded", data = "<form>")]
+fn revoke(form : Form<TokenReferenceForm>, client : Option<ClientBasicAuth>, sled_db : State<StoreManager>) -> Result<JsonValue, status::Custom<JsonValue>> {
+    match oauth_service::revoke_token(form.into_inner().into_viewmodel(client), sled_db.give_client_store(), sled_db.give_token_store()) {
+        Ok(_) => Ok(json!({})),
+        Err(e) => Err(error_controller::return_oauth_error_json(e))
+    }
+}
+
+/**
+ * OpenID Connect userinfo endpoint, returns the claims of the user the bearer token was issued for.
+ */
+#[get("/userinfo")]
+fn user_info(token : BearerToken, sled_db : State<StoreManager>) -> Result<JsonValue, status::Custom<JsonValue>> {
+    match oidc_service::get_user_claims(token.get_token(), &sled_db.give_store(), &sled_db.give_token_store()) {
+        Ok(claims) => {
+            info!("Userinfo has been sent to a client");
+            Ok(json!(claims))
+        },
+        Err(e) => Err(error_controller::return_oauth_error_json(e))
+    }
+}
+
+/**
+ * The userinfo endpoint has to support POST as well.
+ */
+#[post("/userinfo")]
+fn user_info_post(token : BearerToken, sled_db : State<StoreManager>) -> Result<JsonValue, status::Custom<JsonValue>> {
+    user_info(token, sled_db)
+}
\ No newline at end of file
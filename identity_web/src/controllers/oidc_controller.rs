@@ -0,0 +1,28 @@
+use rocket_contrib::json::JsonValue;
+use identity_service::service::oidc_service;
+use identity_service::signing_key::SigningKey;
+use rocket::State;
+use rocket::Route;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        openid_configuration,
+        jwks
+    ]
+}
+
+/**
+ * Returns the OpenID Connect discovery document, so clients can find the endpoints and capabilities of the provider.
+ */
+#[get("/openid-configuration")]
+fn openid_configuration() -> JsonValue {
+    json!(oidc_service::discovery_document())
+}
+
+/**
+ * Returns the public keys with which the id tokens can be verified.
+ */
+#[get("/jwks.json")]
+fn jwks(signing_key : State<SigningKey>) -> JsonValue {
+    json!(signing_key.jwks())
+}
@@ -0,0 +1,105 @@
+use rocket::request::{Form, LenientForm};
+use rocket::response::content::{Html, Xml};
+use identity_service::saml::{ IdpCertificate, BINDING_HTTP_POST, BINDING_HTTP_REDIRECT };
+use identity_service::service::{ person_service, saml_service };
+use identity_service::signing_key::SigningKey;
+use identity_service::store::StoreManager;
+use identity_service::hooks::HookRegistry;
+use identity_service::viewmodels::auth::login::LoginViewModel;
+use identity_service::service::audit_service::AuditEventType;
+use crate::audit::Auditor;
+use crate::pages;
+use rocket::State;
+use rocket::Route;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        metadata,
+        sso_redirect,
+        sso_post,
+        login
+    ]
+}
+
+/**
+ * Authentication request of a service provider, as it is sent with the HTTP-Redirect or HTTP-POST binding.
+ */
+#[derive(FromForm)]
+struct SamlRequestForm {
+    #[form(field = "SAMLRequest")]
+    saml_request : Option<String>,
+    #[form(field = "RelayState")]
+    relay_state : Option<String>
+}
+
+/**
+ * Form of the SAML login page.
+ */
+#[derive(FromForm)]
+struct SamlLoginForm {
+    saml_request : String,
+    relay_state : Option<String>,
+    email : Option<String>,
+    password : Option<String>
+}
+
+/**
+ * Returns the metadata of the identity provider, service providers are configured with it.
+ */
+#[get("/metadata")]
+fn metadata(certificate : State<IdpCertificate>) -> Xml<String> {
+    Xml(saml_service::get_idp_metadata(&certificate))
+}
+
+/**
+ * Single sign on endpoint for requests sent with the HTTP-Redirect binding.
+ */
+#[get("/sso?<request..>")]
+fn sso_redirect(request : LenientForm<SamlRequestForm>, sled_db : State<StoreManager>) -> Html<String> {
+    show_login(BINDING_HTTP_REDIRECT, request.into_inner(), &sled_db)
+}
+
+/**
+ * Single sign on endpoint for requests sent with the HTTP-POST binding.
+ */
+#[post("/sso", format = "application/x-www-form-urlencoded", data = "<request>")]
+fn sso_post(request : LenientForm<SamlRequestForm>, sled_db : State<StoreManager>) -> Html<String> {
+    show_login(BINDING_HTTP_POST, request.into_inner(), &sled_db)
+}
+
+/**
+ * Shows the login page for a valid authentication request, otherwise an error page is shown because the service provider can't be trusted with the error.
+ */
+fn show_login(binding : &str, form : SamlRequestForm, sled_db : &StoreManager) -> Html<String> {
+    let saml_request = match form.saml_request {
+        Some(saml_request) => saml_request,
+        None => return pages::error_page("The SAMLRequest parameter is missing")
+    };
+    match saml_service::receive_authn_request(binding, &saml_request, &sled_db.give_saml_store()) {
+        Ok((request, provider)) => pages::saml_login_page(provider.get_entity_id(), &request.to_post_binding(), &form.relay_state, None),
+        Err(e) => pages::error_page(&format!("{}", e))
+    }
+}
+
+/**
+ * Handles the SAML login form. When the credentials are right a page is returned that posts the signed response to the service provider, otherwise the login page is shown again.
+ */
+#[post("/login", format = "application/x-www-form-urlencoded", data = "<form>")]
+fn login(form : Form<SamlLoginForm>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, signing_key : State<SigningKey>, certificate : State<IdpCertificate>, auditor : Auditor) -> Html<String> {
+    let form = form.into_inner();
+    let (request, provider) = match saml_service::receive_authn_request(BINDING_HTTP_POST, &form.saml_request, &sled_db.give_saml_store()) {
+        Ok(received) => received,
+        Err(e) => return pages::error_page(&format!("{}", e))
+    };
+    let login = LoginViewModel::new(form.email.as_deref().unwrap_or_default(), form.password.as_deref().unwrap_or_default());
+    let result = person_service::check_credentials(login, sled_db.give_store(), sled_db.give_ldap_store(), &sled_db.give_webhook_store(), &hooks);
+    auditor.record(AuditEventType::Login, "saml_login", result.as_ref().ok().map(|claim| claim.sub.as_str()), form.email.as_deref(), &result);
+    let claim = match result {
+        Ok(claim) => claim,
+        Err(_) => return pages::saml_login_page(provider.get_entity_id(), &form.saml_request, &form.relay_state, Some("Email or password is not right"))
+    };
+    match saml_service::issue_response(&request, &provider, &claim.sub, form.relay_state, &sled_db.give_store(), &signing_key, &certificate) {
+        Ok(response) => pages::saml_post_page(&response),
+        Err(e) => pages::error_page(&format!("{}", e))
+    }
+}
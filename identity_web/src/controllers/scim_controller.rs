@@ -0,0 +1,207 @@
+use rocket_contrib::json::{Json, JsonValue};
+use rocket::http::{ContentType, Status};
+use rocket::request::LenientForm;
+use rocket::response::content::Content;
+use rocket::response::status;
+use identity_service::service::audit_service::AuditEventType;
+use identity_service::service::scim_service::{ self, ScimStores };
+use identity_service::store::StoreManager;
+use identity_service::hooks::HookRegistry;
+use identity_service::viewmodels::scim::group::ScimGroupViewModel;
+use identity_service::viewmodels::scim::list_response::ScimQueryViewModel;
+use identity_service::viewmodels::scim::patch::ScimPatchViewModel;
+use identity_service::viewmodels::scim::user::ScimUserViewModel;
+use crate::audit::Auditor;
+use crate::key::BearerToken;
+use crate::IdentityError;
+use rocket::State;
+use rocket::Route;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        list_users,
+        create_user,
+        get_user,
+        replace_user,
+        patch_user,
+        delete_user,
+        list_groups,
+        create_group,
+        get_group,
+        replace_group,
+        patch_group,
+        delete_group,
+        service_provider_config,
+        schemas,
+        resource_types
+    ]
+}
+
+type ScimResponse = status::Custom<Content<JsonValue>>;
+
+/**
+ * Query of a list request, SCIM uses camel case for its parameters.
+ */
+#[derive(FromForm)]
+struct ScimQueryForm {
+    filter : Option<String>,
+    #[form(field = "startIndex")]
+    start_index : Option<i64>,
+    count : Option<i64>
+}
+
+impl ScimQueryForm {
+    fn into_viewmodel(self) -> ScimQueryViewModel {
+        ScimQueryViewModel { filter : self.filter, start_index : self.start_index, count : self.count }
+    }
+}
+
+fn stores(sled_db : &StoreManager) -> ScimStores {
+    ScimStores { db : sled_db.give_store(), groups : sled_db.give_group_store(), scim : sled_db.give_scim_store(), webhooks : sled_db.give_webhook_store() }
+}
+
+/**
+ * Sends a body with the SCIM media type.
+ */
+fn scim_json(status : Status, body : JsonValue) -> ScimResponse {
+    status::Custom(status, Content(ContentType::new("application", "scim+json"), body))
+}
+
+/**
+ * Sends the result of a SCIM request, an error is sent as a SCIM error message with its status.
+ */
+fn scim_result(status : Status, result : Result<JsonValue, IdentityError>) -> ScimResponse {
+    match result {
+        Ok(body) => scim_json(status, body),
+        Err(e) => {
+            let (code, _) = scim_service::scim_error_status(&e);
+            if code == 500 {
+                error!("A SCIM request failed: {}", e);
+            }
+            scim_json(Status::from_code(code).unwrap_or(Status::InternalServerError), JsonValue::from(scim_service::scim_error(&e)))
+        }
+    }
+}
+
+/**
+ * Sends an empty response for a successful DELETE request.
+ */
+fn scim_deleted(result : Result<(), IdentityError>) -> Result<status::NoContent, ScimResponse> {
+    match result {
+        Ok(()) => Ok(status::NoContent),
+        Err(e) => Err(scim_result(Status::Ok, Err(e)))
+    }
+}
+
+/**
+ * Lists the users, the query can filter them and ask for a page of them.
+ */
+#[get("/Users?<query..>")]
+fn list_users(token : BearerToken, query : LenientForm<ScimQueryForm>, sled_db : State<StoreManager>) -> ScimResponse {
+    scim_result(Status::Ok, scim_service::list_users(token.get_token(), query.into_inner().into_viewmodel(), &stores(&sled_db), &sled_db.give_client_store(), &sled_db.give_token_store()).map(|body| json!(body)))
+}
+
+/**
+ * Provisions an user.
+ */
+#[post("/Users", data = "<model>")]
+fn create_user(token : BearerToken, model : Json<ScimUserViewModel>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor) -> ScimResponse {
+    let user_name = model.user_name.clone();
+    let result = scim_service::create_user(token.get_token(), model.into_inner(), &stores(&sled_db), &sled_db.give_client_store(), &sled_db.give_token_store(), &hooks);
+    auditor.record_by_token(AuditEventType::AdminAction, "scim_create_user", token.get_token(), Some(&user_name), &result);
+    scim_result(Status::Created, result.map(|body| json!(body)))
+}
+
+#[get("/Users/<id>")]
+fn get_user(token : BearerToken, id : String, sled_db : State<StoreManager>) -> ScimResponse {
+    scim_result(Status::Ok, scim_service::get_user(token.get_token(), &id, &stores(&sled_db), &sled_db.give_client_store(), &sled_db.give_token_store()).map(|body| json!(body)))
+}
+
+#[put("/Users/<id>", data = "<model>")]
+fn replace_user(token : BearerToken, id : String, model : Json<ScimUserViewModel>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor) -> ScimResponse {
+    let result = scim_service::replace_user(token.get_token(), &id, model.into_inner(), &stores(&sled_db), &sled_db.give_client_store(), &sled_db.give_token_store(), &hooks);
+    auditor.record_by_token(AuditEventType::AdminAction, "scim_replace_user", token.get_token(), Some(&id), &result);
+    scim_result(Status::Ok, result.map(|body| json!(body)))
+}
+
+#[patch("/Users/<id>", data = "<model>")]
+fn patch_user(token : BearerToken, id : String, model : Json<ScimPatchViewModel>, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor) -> ScimResponse {
+    let result = scim_service::patch_user(token.get_token(), &id, model.into_inner(), &stores(&sled_db), &sled_db.give_client_store(), &sled_db.give_token_store(), &hooks);
+    auditor.record_by_token(AuditEventType::AdminAction, "scim_patch_user", token.get_token(), Some(&id), &result);
+    scim_result(Status::Ok, result.map(|body| json!(body)))
+}
+
+/**
+ * Deprovisions an user.
+ */
+#[delete("/Users/<id>")]
+fn delete_user(token : BearerToken, id : String, sled_db : State<StoreManager>, hooks : State<HookRegistry>, auditor : Auditor) -> Result<status::NoContent, ScimResponse> {
+    let result = scim_service::delete_user(token.get_token(), &id, &stores(&sled_db), &sled_db.give_client_store(), &sled_db.give_token_store(), &hooks);
+    auditor.record_by_token(AuditEventType::Deletion, "scim_delete_user", token.get_token(), Some(&id), &result);
+    scim_deleted(result)
+}
+
+/**
+ * Lists the groups, the query can filter them and ask for a page of them.
+ */
+#[get("/Groups?<query..>")]
+fn list_groups(token : BearerToken, query : LenientForm<ScimQueryForm>, sled_db : State<StoreManager>) -> ScimResponse {
+    scim_result(Status::Ok, scim_service::list_groups(token.get_token(), query.into_inner().into_viewmodel(), &stores(&sled_db), &sled_db.give_client_store(), &sled_db.give_token_store()).map(|body| json!(body)))
+}
+
+#[post("/Groups", data = "<model>")]
+fn create_group(token : BearerToken, model : Json<ScimGroupViewModel>, sled_db : State<StoreManager>) -> ScimResponse {
+    scim_result(Status::Created, scim_service::create_group(token.get_token(), model.into_inner(), &stores(&sled_db), &sled_db.give_client_store(), &sled_db.give_token_store()).map(|body| json!(body)))
+}
+
+#[get("/Groups/<id>")]
+fn get_group(token : BearerToken, id : String, sled_db : State<StoreManager>) -> ScimResponse {
+    scim_result(Status::Ok, scim_service::get_group(token.get_token(), &id, &stores(&sled_db), &sled_db.give_client_store(), &sled_db.give_token_store()).map(|body| json!(body)))
+}
+
+#[put("/Groups/<id>", data = "<model>")]
+fn replace_group(token : BearerToken, id : String, model : Json<ScimGroupViewModel>, sled_db : State<StoreManager>) -> ScimResponse {
+    scim_result(Status::Ok, scim_service::replace_group(token.get_token(), &id, model.into_inner(), &stores(&sled_db), &sled_db.give_client_store(), &sled_db.give_token_store()).map(|body| json!(body)))
+}
+
+#[patch("/Groups/<id>", data = "<model>")]
+fn patch_group(token : BearerToken, id : String, model : Json<ScimPatchViewModel>, sled_db : State<StoreManager>) -> ScimResponse {
+    scim_result(Status::Ok, scim_service::patch_group(token.get_token(), &id, model.into_inner(), &stores(&sled_db), &sled_db.give_client_store(), &sled_db.give_token_store()).map(|body| json!(body)))
+}
+
+#[delete("/Groups/<id>")]
+fn delete_group(token : BearerToken, id : String, sled_db : State<StoreManager>) -> Result<status::NoContent, ScimResponse> {
+    scim_deleted(scim_service::delete_group(token.get_token(), &id, &stores(&sled_db), &sled_db.give_client_store(), &sled_db.give_token_store()))
+}
+
+/**
+ * Discovery endpoint that tells provisioning clients what is supported.
+ */
+#[get("/ServiceProviderConfig")]
+fn service_provider_config() -> ScimResponse {
+    scim_json(Status::Ok, JsonValue::from(scim_service::service_provider_config()))
+}
+
+#[get("/Schemas")]
+fn schemas() -> ScimResponse {
+    let schemas = scim_service::schemas();
+    scim_json(Status::Ok, json!({
+        "schemas" : [identity_service::scim::SCHEMA_LIST_RESPONSE],
+        "totalResults" : schemas.len(),
+        "startIndex" : 1,
+        "itemsPerPage" : schemas.len(),
+        "Resources" : schemas
+    }))
+}
+
+#[get("/ResourceTypes")]
+fn resource_types() -> ScimResponse {
+    let resource_types = scim_service::resource_types();
+    scim_json(Status::Ok, json!({
+        "schemas" : [identity_service::scim::SCHEMA_LIST_RESPONSE],
+        "totalResults" : resource_types.len(),
+        "startIndex" : 1,
+        "itemsPerPage" : resource_types.len(),
+        "Resources" : resource_types
+    }))
+}
@@ -1,42 +1,104 @@
-use identity_service::store::Store;
-use identity_service::service::person_service::get_user_info;
 use identity_service::service::mail_service;
 use identity_service::mail_struct::Report;
-use identity_service::service::mail_service::MailTransport;
-use identity_service::map_token_pwd::HashMapTokenPasswordChange;
-use std::sync::Mutex;
+use identity_service::mail_template::{ self, TemplateVariables };
+use identity_service::service::mail_service::MailOutbox;
+use identity_service::hooks::LifecycleHooks;
+use identity_service::viewmodels::auth::person_info::PersonInfoViewModel;
 use crate::IdentityError;
 
 /**
- * Function that is used to send a welcome email to new users. If the smtp transport in the service library has not managed to make a real connection to the smtp server then it will return an error and a error will be logged.
+ * Mail templates that are sent by the server with the variables they get, these are validated at startup.
  */
-pub fn user_creation(id : &str, store : &Store, transport : &MailTransport) -> Result<(),IdentityError> {
-    let user_info  = get_user_info(id, &store).ok_or(IdentityError::UserIsNotPresent)?;
-    mail_service::send_email(transport,Report::new(user_info.get_email(), user_info.get_user_name(), 
-    "Welcome to rust Identity",
-    r#"
-    Welcome new user
-
-    Welcome to the rust identity server, this server is authentication backend that is written in Rust, uses JWT as authentication/authorization. It uses a embedded no-sql database named Sled. This database is very fast and efficiënt.
-
-    Kind regards
-    The admin
-    "#)?)?;
-    Ok(())
-}
-
-pub fn send_email_for_forgotten_pwd(token : &str,store : &Store, token_map : &Mutex<HashMapTokenPasswordChange>, transport : &MailTransport) -> Result<(), IdentityError> {
-    let token_locked_map = token_map.lock().expect("Could not lock the token map which gaurds tokens for changing password");
-    let user_id : String = token_locked_map.get_user_id_from_token(token).ok_or_else(|| IdentityError::CustomError("Could not get user id associated with the token.".to_owned()))?;
-    let user = get_user_info(&user_id, &store).ok_or(IdentityError::UserIsNotPresent)?;
-    mail_service::send_email(transport,Report::new(user.get_email(), user.get_user_name(), 
-    "Welcome to rust Identity",
-    &format!(r#"
-    Dear user
-
-    We recently received a notification that you forgot you're password. This token can be used to change your password. 
-
-    Token: {}
-    "#,&token))?)?;
-    Ok(())
-}
\ No newline at end of file
+pub static MAIL_TEMPLATES : [(&str, &[&str]); 6] = [
+    ("welcome", &["user_name", "email"]),
+    ("password_reset", &["user_name", "email", "token"]),
+    ("magic_link", &["user_name", "email", "link"]),
+    ("invitation", &["email", "link"]),
+    ("registration_approved", &["email"]),
+    ("registration_rejected", &["email"])
+];
+
+/**
+ * Function that makes the mail of the template in the locale of the recipient, or the default locale when he has none, and sends it to the email with the html part of the template when it has one. The logo is added when the html shows it.
+ */
+fn send_template(outbox : &MailOutbox, email : &str, alias : &str, locale : Option<&str>, template : &str, variables : &TemplateVariables) -> Result<(), IdentityError> {
+    let mail = mail_template::get_templates().render(template, locale, variables)?;
+    let mut report = Report::new(email, alias, &mail.subject, &mail.text)?;
+    if let Some(html) = &mail.html {
+        report = report.with_html(html);
+        if let Some(logo) = mail_service::get_logo().filter(|_| html.contains("cid:logo")) {
+            report = report.with_inline_image(logo.clone());
+        }
+    }
+    mail_service::send_email(outbox, report)
+}
+
+/**
+ * Hook that sends a welcome email to new users. The mail is queued in the outbox, when it can't be queued an error will be logged.
+ */
+pub struct WelcomeMail {
+    outbox : MailOutbox
+}
+
+impl WelcomeMail {
+    pub fn new(outbox : MailOutbox) -> WelcomeMail {
+        WelcomeMail { outbox }
+    }
+}
+
+impl LifecycleHooks for WelcomeMail {
+    fn name(&self) -> &str {
+        "welcome mail"
+    }
+
+    fn post_registration(&self, user : &PersonInfoViewModel) -> Result<(), IdentityError> {
+        send_template(&self.outbox, user.get_email(), user.get_user_name(), user.get_locale(), "welcome",
+        &[("user_name", user.get_user_name()), ("email", user.get_email())])
+    }
+}
+
+/**
+ * Hook that mails the token with which an user who forgot his password can choose a new one.
+ */
+pub struct ForgottenPasswordMail {
+    outbox : MailOutbox
+}
+
+impl ForgottenPasswordMail {
+    pub fn new(outbox : MailOutbox) -> ForgottenPasswordMail {
+        ForgottenPasswordMail { outbox }
+    }
+}
+
+impl LifecycleHooks for ForgottenPasswordMail {
+    fn name(&self) -> &str {
+        "forgotten password mail"
+    }
+
+    fn post_password_reset_request(&self, user : &PersonInfoViewModel, token : &str) -> Result<(), IdentityError> {
+        send_template(&self.outbox, user.get_email(), user.get_user_name(), user.get_locale(), "password_reset",
+        &[("user_name", user.get_user_name()), ("email", user.get_email()), ("token", token)])
+    }
+}
+
+/**
+ * Function that is used to mail a magic link to an user, with it he can log in once without his password.
+ */
+pub fn send_magic_link(email : &str, user_name : &str, locale : Option<&str>, link : &str, outbox : &MailOutbox) -> Result<(), IdentityError> {
+    send_template(outbox, email, user_name, locale, "magic_link", &[("user_name", user_name), ("email", email), ("link", link)])
+}
+
+/**
+ * Function that is used to mail the link of an invitation, with it the invitee can make his account and choose his own password.
+ */
+pub fn send_invitation(email : &str, locale : Option<&str>, link : &str, outbox : &MailOutbox) -> Result<(), IdentityError> {
+    send_template(outbox, email, email, locale, "invitation", &[("email", email), ("link", link)])
+}
+
+/**
+ * Function that is used to mail an user whether the admin approved or rejected his registration.
+ */
+pub fn registration_decision(email : &str, locale : Option<&str>, approved : bool, outbox : &MailOutbox) -> Result<(), IdentityError> {
+    let template = if approved { "registration_approved" } else { "registration_rejected" };
+    send_template(outbox, email, email, locale, template, &[("email", email)])
+}
@@ -22,4 +22,71 @@ impl<'a, 'r> FromRequest<'a, 'r> for ApiKey {
             None => Outcome::Failure((Status::new(400, "Token has not been given in the headers"),IdentityError::CustomError("Token has not been given in the headers".to_owned())))
         }
     }
+}
+
+/**
+ * Client id and secret that an OAuth client gives through HTTP Basic authentication.
+ */
+pub struct ClientBasicAuth(String, String);
+
+impl ClientBasicAuth {
+    pub fn get_client_id(&self) -> &str {
+        &self.0
+    }
+
+    pub fn get_client_secret(&self) -> &str {
+        &self.1
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for ClientBasicAuth {
+    type Error = IdentityError;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        match request.headers().get_one("Authorization").and_then(identity_service::util::decode_basic_auth) {
+            Some((id, secret)) => Outcome::Success(ClientBasicAuth(id, secret)),
+            None => Outcome::Failure((Status::Unauthorized, IdentityError::InvalidClient))
+        }
+    }
+}
+
+/**
+ * Locale the requester prefers most according to the Accept-Language header, none when the header isn't given.
+ */
+pub struct AcceptLanguage(Option<String>);
+
+impl AcceptLanguage {
+    pub fn get_locale(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for AcceptLanguage {
+    type Error = IdentityError;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        Outcome::Success(AcceptLanguage(request.headers().get_one("Accept-Language").and_then(identity_service::mail_template::preferred_locale)))
+    }
+}
+
+/**
+ * Access token that is given through the Authorization header with the Bearer scheme.
+ */
+pub struct BearerToken(String);
+
+impl BearerToken {
+    pub fn get_token(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for BearerToken {
+    type Error = IdentityError;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        match request.headers().get_one("Authorization").and_then(|header| header.strip_prefix("Bearer ")) {
+            Some(token) => Outcome::Success(BearerToken(token.trim().to_owned())),
+            None => Outcome::Failure((Status::Unauthorized, IdentityError::TokenIsEmpty))
+        }
+    }
 }
\ No newline at end of file
@@ -9,13 +9,23 @@ use controllers::auth_controller;
 use controllers::error_controller;
 use controllers::admin_controller;
 use controllers::basic_controller;
+use controllers::oauth_controller;
+use controllers::oidc_controller;
+use controllers::federation_controller;
+use controllers::saml_controller;
+use controllers::scim_controller;
+use controllers::invitation_controller;
 
+mod audit;
+mod webhooks;
 mod counter;
 mod adhoc;
 mod delegates;
 mod key;
+mod pages;
 
 use counter::Counter;
+use identity_service::hooks::HookRegistry;
 use std::sync::Mutex;
 
 use log::LevelFilter;
@@ -28,14 +38,35 @@ pub type IdentityError = identity_service::IdentityError;
 pub type SharedCounter = Mutex<Counter>;
 
 fn rocket() -> rocket::Rocket {
+    let store_manager = identity_service::store::StoreManager::new_with_setup();
+    let signing_key = identity_service::signing_key::SigningKey::load(&store_manager);
+    let saml_certificate = identity_service::saml::IdpCertificate::load(&store_manager, &signing_key);
+    identity_service::mail_template::get_templates().validate(&delegates::MAIL_TEMPLATES)
+        .unwrap_or_else(|e| panic!("The mail templates are not complete: {}", e));
+    identity_service::service::deletion_service::start_purge_task(admin_controller::purge_stores(&store_manager));
+    identity_service::service::change_feed_service::start_change_feed(store_manager.give_store(), store_manager.give_change_feed_store());
+    identity_service::service::webhook_service::start_delivery_task(store_manager.give_webhook_store());
+    identity_service::service::audit_service::start_checkpoint_task(store_manager.give_audit_store(), signing_key.clone());
+    identity_service::service::mail_service::start_outbox_task(store_manager.give_mail_outbox_store(), identity_service::service::mail_service::get_transport());
+    let hooks = HookRegistry::new()
+        .register(delegates::WelcomeMail::new(store_manager.give_mail_outbox_store()))
+        .register(delegates::ForgottenPasswordMail::new(store_manager.give_mail_outbox_store()));
     rocket::ignite()
         .register(error_controller::catches())
         .mount("/", basic_controller::routes())
         .mount("/user", auth_controller::routes())
         .mount("/admin", admin_controller::routes())
-        .manage(identity_service::store::StoreManager::new_with_setup())
-        .manage(identity_service::service::mail_service::get_transport())
+        .mount("/oauth", oauth_controller::routes())
+        .mount("/.well-known", oidc_controller::routes())
+        .mount("/federation", federation_controller::routes())
+        .mount("/saml", saml_controller::routes())
+        .mount("/scim/v2", scim_controller::routes())
+        .mount("/invitation", invitation_controller::routes())
+        .manage(store_manager)
+        .manage(signing_key)
+        .manage(saml_certificate)
         .manage(identity_service::map_token_pwd::get_mutext_token_forgotten_pwd_map())
+        .manage(hooks)
         .manage(Mutex::new(Counter::default()))
         .attach(adhoc::cors_handler())
         .attach(adhoc::count_handler())
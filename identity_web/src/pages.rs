@@ -0,0 +1,165 @@
+use rocket::response::content::Html;
+use identity_service::viewmodels::oauth::authorization_request::AuthorizationRequestViewModel;
+use identity_service::viewmodels::saml::saml_response::SamlResponseViewModel;
+
+/**
+ * Escapes the characters that have a meaning in html, so values can safely be put in a page.
+ */
+pub fn escape(value : &str) -> String {
+    value.chars().map(|c| match c {
+        '&' => "&amp;".to_owned(),
+        '<' => "&lt;".to_owned(),
+        '>' => "&gt;".to_owned(),
+        '"' => "&quot;".to_owned(),
+        '\'' => "&#x27;".to_owned(),
+        _ => c.to_string()
+    }).collect()
+}
+
+fn hidden_input(name : &str, value : &Option<String>) -> String {
+    match value {
+        Some(value) => format!(r#"<input type="hidden" name="{}" value="{}">"#, name, escape(value)),
+        None => String::new()
+    }
+}
+
+fn layout(title : &str, body : &str) -> Html<String> {
+    Html(format!(r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <title>{}</title>
+</head>
+<body>
+{}
+</body>
+</html>"#, escape(title), body))
+}
+
+/**
+ * Login and consent page of the OAuth authorization endpoint. The parameters of the authorization request are kept in hidden fields so they are posted back together with the credentials and the decision of the user.
+ */
+pub fn authorize_page(client_name : &str, request : &AuthorizationRequestViewModel, error : Option<&str>) -> Html<String> {
+    let scopes : String = request.get_scope()
+        .split_whitespace()
+        .map(|scope| format!("<li>{}</li>", escape(scope)))
+        .collect();
+    let hidden : String = [
+        hidden_input("response_type", &request.response_type),
+        hidden_input("client_id", &request.client_id),
+        hidden_input("redirect_uri", &request.redirect_uri),
+        hidden_input("scope", &request.scope),
+        hidden_input("state", &request.state),
+        hidden_input("code_challenge", &request.code_challenge),
+        hidden_input("code_challenge_method", &request.code_challenge_method),
+        hidden_input("nonce", &request.nonce)
+    ].concat();
+    layout("Sign in", &format!(r#"<h1>Sign in to {client}</h1>
+<p>{client} wants to access your account.</p>
+<ul>{scopes}</ul>
+{error}
+<form method="post" action="/oauth/authorize">
+    {hidden}
+    <label>Email <input type="email" name="email" required></label>
+    <label>Password <input type="password" name="password" required></label>
+    <button type="submit" name="decision" value="approve">Allow</button>
+    <button type="submit" name="decision" value="deny" formnovalidate>Deny</button>
+</form>"#,
+        client = escape(client_name),
+        scopes = scopes,
+        error = error.map(|e| format!("<p><strong>{}</strong></p>", escape(e))).unwrap_or_default(),
+        hidden = hidden
+    ))
+}
+
+/**
+ * Page on which the user enters the user code shown by a device. When the code is known, the client and its scopes are shown and the user signs in to approve or deny the device.
+ */
+pub fn device_page(user_code : Option<&str>, client : Option<(&str, &str)>, error : Option<&str>) -> Html<String> {
+    let error = error.map(|e| format!("<p><strong>{}</strong></p>", escape(e))).unwrap_or_default();
+    let (client_name, scope) = match client {
+        Some(client) => client,
+        None => return layout("Connect a device", &format!(r#"<h1>Connect a device</h1>
+<p>Enter the code that is shown on your device.</p>
+{error}
+<form method="get" action="/oauth/device">
+    <label>Code <input type="text" name="user_code" value="{code}" autocomplete="off" required></label>
+    <button type="submit">Continue</button>
+</form>"#,
+            error = error,
+            code = escape(user_code.unwrap_or_default())
+        ))
+    };
+    let scopes : String = scope
+        .split_whitespace()
+        .map(|scope| format!("<li>{}</li>", escape(scope)))
+        .collect();
+    layout("Connect a device", &format!(r#"<h1>Connect a device to {client}</h1>
+<p>A device of {client} wants to access your account, check that it shows the code {code}.</p>
+<ul>{scopes}</ul>
+{error}
+<form method="post" action="/oauth/device">
+    <input type="hidden" name="user_code" value="{code}">
+    <label>Email <input type="email" name="email" required></label>
+    <label>Password <input type="password" name="password" required></label>
+    <button type="submit" name="decision" value="approve">Allow</button>
+    <button type="submit" name="decision" value="deny" formnovalidate>Deny</button>
+</form>"#,
+        client = escape(client_name),
+        code = escape(user_code.unwrap_or_default()),
+        scopes = scopes,
+        error = error
+    ))
+}
+
+/**
+ * Page shown after the user has decided about a device.
+ */
+pub fn device_done_page(approved : bool) -> Html<String> {
+    let message = if approved { "The device has been connected, you can return to it." } else { "The device has been denied." };
+    layout("Connect a device", &format!("<h1>Connect a device</h1>\n<p>{}</p>", message))
+}
+
+/**
+ * Page shown when an authorization request can't be redirected back to the client.
+ */
+pub fn error_page(message : &str) -> Html<String> {
+    layout("Error", &format!("<h1>Authorization failed</h1>\n<p>{}</p>", escape(message)))
+}
+
+/**
+ * Login page of a SAML service provider. The authentication request is kept in its HTTP-POST binding form in a hidden field.
+ */
+pub fn saml_login_page(entity_id : &str, saml_request : &str, relay_state : &Option<String>, error : Option<&str>) -> Html<String> {
+    layout("Sign in", &format!(r#"<h1>Sign in to {provider}</h1>
+{error}
+<form method="post" action="/saml/login">
+    <input type="hidden" name="saml_request" value="{request}">
+    {relay_state}
+    <label>Email <input type="email" name="email" required></label>
+    <label>Password <input type="password" name="password" required></label>
+    <button type="submit">Sign in</button>
+</form>"#,
+        provider = escape(entity_id),
+        error = error.map(|e| format!("<p><strong>{}</strong></p>", escape(e))).unwrap_or_default(),
+        request = escape(saml_request),
+        relay_state = hidden_input("relay_state", relay_state)
+    ))
+}
+
+/**
+ * Page that posts the SAML response to the assertion consumer service of the service provider, the form is submitted as soon as the page is loaded.
+ */
+pub fn saml_post_page(response : &SamlResponseViewModel) -> Html<String> {
+    layout("Signing in", &format!(r#"<form method="post" action="{destination}" id="saml">
+    <input type="hidden" name="SAMLResponse" value="{response}">
+    {relay_state}
+    <noscript><button type="submit">Continue</button></noscript>
+</form>
+<script>document.getElementById("saml").submit();</script>"#,
+        destination = escape(&response.destination),
+        response = escape(&response.saml_response),
+        relay_state = hidden_input("RelayState", &response.relay_state)
+    ))
+}
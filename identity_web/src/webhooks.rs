@@ -0,0 +1,63 @@
+use rocket::Outcome;
+use rocket::State;
+use rocket::http::Status;
+use rocket::request::{self, Request, FromRequest};
+use identity_service::service::webhook_service::{ self, WebhookEventType, WebhookStore };
+use identity_service::store::{ Store, StoreManager };
+use crate::IdentityError;
+
+/**
+ * Publishes the lifecycle events of a request to the webhook subscriptions, the events are only queued here and posted by the delivery task.
+ */
+pub struct Webhooks {
+    store : WebhookStore,
+    db : Store
+}
+
+impl Webhooks {
+    /**
+     * Publishes the event about the user when the action succeeded.
+     */
+    pub fn publish_on<T>(&self, event_type : WebhookEventType, user_id : &str, result : &Result<T, IdentityError>) {
+        if result.is_ok() {
+            webhook_service::publish(&self.store, event_type, user_id, &self.db);
+        }
+    }
+
+    /**
+     * Publishes the event about the user of the token when the action succeeded.
+     */
+    pub fn publish_on_token<T>(&self, event_type : WebhookEventType, token : &str, result : &Result<T, IdentityError>) {
+        if let Some(user_id) = webhook_service::user_of_token(token) {
+            self.publish_on(event_type, &user_id, result);
+        }
+    }
+
+    /**
+     * Returns the email the user has before an update, to know afterwards if it changed.
+     */
+    pub fn email_of(&self, user_id : &str) -> Option<String> {
+        webhook_service::email_of_user(user_id, &self.db)
+    }
+
+    /**
+     * Publishes an email change when the email of the user is no longer the previous email.
+     */
+    pub fn publish_email_change(&self, user_id : &str, previous_email : Option<&str>) {
+        webhook_service::publish_email_change(&self.store, user_id, previous_email, &self.db);
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for Webhooks {
+    type Error = IdentityError;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        match request.guard::<State<StoreManager>>() {
+            Outcome::Success(sled_db) => Outcome::Success(Webhooks {
+                store : sled_db.give_webhook_store(),
+                db : sled_db.give_store()
+            }),
+            _ => Outcome::Failure((Status::InternalServerError, IdentityError::CustomError("The webhooks are not available".to_owned())))
+        }
+    }
+}